@@ -524,6 +524,12 @@ pub trait Handler {
     /// Fig OSCUnlock OSC
     fn osc_unlock(&mut self, _: &str) {}
 
+    /// FinalTerm semantic prompt OSC 133
+    fn semantic_prompt(&mut self, _: SemanticPromptMark) {}
+
+    /// Working directory OSC 7, with the host it was reported from
+    fn report_dir(&mut self, _host: &str, _: &Path) {}
+
     /// Unhandled `execute` fallthrough
     fn unhandled_execute(&mut self, _byte: u8) -> HandledStatus {
         HandledStatus::Unhandled
@@ -572,6 +578,70 @@ pub trait Handler {
     }
 }
 
+/// FinalTerm semantic prompt marks, sent by shells configured for other terminals.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SemanticPromptMark {
+    /// `A`/`P`, start of a prompt. Continuation prompts (`k=s`/`k=c`) are secondary.
+    PromptStart { secondary: bool },
+
+    /// `B`, end of the prompt and start of the command line.
+    CommandStart,
+
+    /// `C`, the command was submitted and its output starts.
+    CommandExecuted,
+
+    /// `D`, the command finished, with its exit code if the shell reported it.
+    CommandFinished(Option<i32>),
+}
+
+impl SemanticPromptMark {
+    fn parse(params: &[&[u8]]) -> Option<Self> {
+        let options = params.get(1..).unwrap_or_default();
+        match *params.first()? {
+            b"A" | b"P" => Some(Self::PromptStart {
+                secondary: options.iter().any(|option| matches!(*option, b"k=s" | b"k=c")),
+            }),
+            b"B" => Some(Self::CommandStart),
+            b"C" => Some(Self::CommandExecuted),
+            b"D" => Some(Self::CommandFinished(
+                options
+                    .first()
+                    .and_then(|code| str::from_utf8(code).ok())
+                    .and_then(|code| code.parse().ok()),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an OSC 7 `file://host/path` url into its host and percent decoded path.
+fn parse_file_url(url: &[u8]) -> Option<(String, String)> {
+    let rest = url
+        .strip_prefix(b"file://")
+        .or_else(|| url.strip_prefix(b"kitty-shell-cwd://"))?;
+    let path_start = rest.iter().position(|b| *b == b'/')?;
+    let (host, path) = rest.split_at(path_start);
+
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [*bytes.next()?, *bytes.next()?];
+            decoded.push(u8::from_str_radix(str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    let mut path = String::from_utf8(decoded).ok()?;
+    // `file:///C:/Users` on Windows
+    if path.len() >= 3 && path.as_bytes()[2] == b':' && path.as_bytes()[1].is_ascii_alphabetic() {
+        path.remove(0);
+    }
+
+    Some((str::from_utf8(host).ok()?.to_owned(), path))
+}
+
 /// Terminal cursor configuration.
 #[derive(Default, Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct CursorStyle {
@@ -1171,6 +1241,23 @@ where
             // Reset text cursor color.
             b"112" => self.handler.reset_color(NamedColor::Cursor as usize),
 
+            // Report working directory.
+            b"7" => {
+                if params.len() >= 2 {
+                    if let Some((host, path)) = parse_file_url(&params[1..].join(&b';')) {
+                        self.handler.report_dir(&host, Path::new(&path));
+                        return;
+                    }
+                }
+                unhandled!();
+            },
+
+            // FinalTerm semantic prompt.
+            b"133" => match SemanticPromptMark::parse(&params[1..]) {
+                Some(mark) => self.handler.semantic_prompt(mark),
+                None => unhandled!(),
+            },
+
             // feeg
             b"697" => {
                 if let Some(fig_osc) = params.get(1) {
@@ -1686,9 +1773,19 @@ mod tests {
         index: CharsetIndex,
         charset: StandardCharset,
         attr: Option<Attr>,
+        semantic_prompts: Vec<SemanticPromptMark>,
+        dir: Option<(String, std::path::PathBuf)>,
     }
 
     impl Handler for MockHandler {
+        fn semantic_prompt(&mut self, mark: SemanticPromptMark) {
+            self.semantic_prompts.push(mark);
+        }
+
+        fn report_dir(&mut self, host: &str, dir: &Path) {
+            self.dir = Some((host.to_owned(), dir.to_owned()));
+        }
+
         fn terminal_attribute(&mut self, attr: Attr) {
             self.attr = Some(attr);
         }
@@ -1713,6 +1810,8 @@ mod tests {
                 index: CharsetIndex::G0,
                 charset: StandardCharset::Ascii,
                 attr: None,
+                semantic_prompts: Vec::new(),
+                dir: None,
             }
         }
     }
@@ -1749,6 +1848,50 @@ mod tests {
         assert_eq!(handler.attr, Some(Attr::Foreground(Color::Spec(spec))));
     }
 
+    #[test]
+    fn parse_semantic_prompt() {
+        static BYTES: &[u8] =
+            b"\x1b]133;D;1\x07\x1b]133;A\x07$ \x1b]133;B\x07ls\x1b]133;C\x1b\\\x1b]133;A;k=s\x07\x1b]133;D\x07";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in BYTES {
+            parser.advance(&mut handler, *byte);
+        }
+
+        assert_eq!(handler.semantic_prompts, vec![
+            SemanticPromptMark::CommandFinished(Some(1)),
+            SemanticPromptMark::PromptStart { secondary: false },
+            SemanticPromptMark::CommandStart,
+            SemanticPromptMark::CommandExecuted,
+            SemanticPromptMark::PromptStart { secondary: true },
+            SemanticPromptMark::CommandFinished(None),
+        ]);
+    }
+
+    #[test]
+    fn parse_working_directory() {
+        static BYTES: &[u8] = b"\x1b]7;file://my-host/home/user/my%20dir\x07";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in BYTES {
+            parser.advance(&mut handler, *byte);
+        }
+
+        assert_eq!(handler.dir, Some(("my-host".into(), "/home/user/my dir".into())));
+
+        assert_eq!(parse_file_url(b"file:///tmp"), Some((String::new(), "/tmp".into())));
+        assert_eq!(
+            parse_file_url(b"file:///C:/Users"),
+            Some((String::new(), "C:/Users".into()))
+        );
+        assert_eq!(parse_file_url(b"file://host/%zz"), None);
+        assert_eq!(parse_file_url(b"http://host/tmp"), None);
+    }
+
     /// No exactly a test; useful for debugging.
     #[test]
     fn parse_zsh_startup() {
@@ -37,6 +37,7 @@ use crate::ansi::{
     Color,
     Handler,
     NamedColor,
+    SemanticPromptMark,
     StandardCharset,
 };
 use crate::event::{
@@ -157,8 +158,15 @@ impl Dimensions for SizeInfo {
     }
 }
 
+/// Most lines of output kept for a command, the end of the output is kept since that is where
+/// errors usually are
+pub const MAX_COMMAND_OUTPUT_LINES: usize = 200;
+
+/// Most bytes of output kept for a command
+pub const MAX_COMMAND_OUTPUT_LEN: usize = 16 * 1024;
+
 /// Information about the current command
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
 pub struct CommandInfo {
     pub command: Option<String>,
     pub shell: Option<String>,
@@ -169,6 +177,9 @@ pub struct CommandInfo {
     pub end_time: Option<SystemTime>,
     pub username: Option<String>,
     pub exit_code: Option<i32>,
+    /// The text the command printed to the terminal, see [MAX_COMMAND_OUTPUT_LINES] and
+    /// [MAX_COMMAND_OUTPUT_LEN] for how it is truncated
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -203,6 +214,8 @@ pub struct ShellState {
     pub preexec: bool,
     /// Position of start of cmd
     pub cmd_cursor: Option<Point>,
+    /// Position of the start of the output of the running command
+    pub output_cursor: Option<Point>,
     /// Fish suggestion color
     pub fish_suggestion_color: Option<shell_color::SuggestionColor>,
     /// Zsh autosuggestion color
@@ -219,6 +232,13 @@ pub struct ShellState {
     pub fig_log_level: Option<String>,
     /// OSC Lock
     pub osc_lock: bool,
+    /// If our own shell integration has been seen, standard OSC 133 and OSC 7 sequences are then
+    /// ignored so events are not duplicated
+    pub fig_integration: bool,
+    /// If the current OSC 133 prompt is a continuation prompt
+    pub secondary_prompt: bool,
+    /// Hostname of the local machine, used to ignore OSC 7 directories reported by remote shells
+    pub hostname: Option<String>,
 }
 
 impl ShellState {
@@ -509,6 +529,9 @@ impl<T> Term<T> {
         if let Some(ref mut cursor) = self.shell_state.cmd_cursor {
             cursor.line += lines as i32;
         }
+        if let Some(ref mut cursor) = self.shell_state.output_cursor {
+            cursor.line += lines as i32;
+        }
 
         lines = min(lines, (self.scroll_region.end - self.scroll_region.start).0 as usize);
         lines = min(lines, (self.scroll_region.end - origin).0 as usize);
@@ -530,6 +553,9 @@ impl<T> Term<T> {
         if let Some(ref mut cursor) = self.shell_state.cmd_cursor {
             cursor.line -= lines as i32;
         }
+        if let Some(ref mut cursor) = self.shell_state.output_cursor {
+            cursor.line -= lines as i32;
+        }
 
         lines = min(lines, (self.scroll_region.end - self.scroll_region.start).0 as usize);
 
@@ -678,6 +704,10 @@ impl<T> Term<T> {
         &self.shell_state
     }
 
+    pub fn shell_state_mut(&mut self) -> &mut ShellState {
+        &mut self.shell_state
+    }
+
     pub fn get_text_region(&self, rect: &Rect, start_col_offset: Column) -> Option<TextBuffer>
     where
         T: EventListener,
@@ -789,10 +819,74 @@ impl<T> Term<T> {
         }
     }
 
+    /// Saves the text between the start of the running command's output and the cursor to its
+    /// [CommandInfo], only the last [MAX_COMMAND_OUTPUT_LINES] lines are kept
+    fn capture_command_output(&mut self)
+    where
+        T: EventListener,
+    {
+        let Some(mut start) = self.shell_state.output_cursor.take() else {
+            return;
+        };
+
+        // Full screen programs draw on the alternate screen, there is no output to capture
+        if self.mode.contains(TermMode::ALT_SCREEN) {
+            return;
+        }
+
+        let Some(command_info) = &self.shell_state.command_info else {
+            return;
+        };
+        if command_info.output.is_some() {
+            return;
+        }
+
+        // The start may have scrolled out of the history
+        start.line = max(start.line, self.topmost_line());
+
+        let cursor = self.grid().cursor.point;
+        let end = if cursor.column.0 == 0 {
+            Point::new(cursor.line - 1, self.last_column())
+        } else {
+            Point::new(cursor.line, cursor.column - 1)
+        };
+
+        let output = if start <= end {
+            truncate_command_output(&self.bounds_to_string(start, end))
+        } else {
+            String::new()
+        };
+
+        if let Some(command_info) = &mut self.shell_state.command_info {
+            command_info.output = Some(output);
+        }
+    }
+
     pub fn set_windows_delay_end_prompt(&mut self, delay_end_prompt: bool) {
         self.windows_delay_end_prompt = delay_end_prompt;
     }
 
+    fn start_prompt_internal(&mut self)
+    where
+        T: EventListener,
+    {
+        trace!("Fig start prompt");
+        self.shell_state.has_seen_prompt = true;
+
+        self.capture_command_output();
+
+        self.grid.cursor.template.fig_flags.insert(FigFlags::IN_PROMPT);
+    }
+
+    fn dir_internal(&mut self, directory: &std::path::Path) {
+        trace!("Fig dir: {:?}", directory.display());
+        self.shell_state.get_mut_context().current_working_directory = Some(directory.to_path_buf());
+        match env::set_current_dir(directory) {
+            Ok(_) => {},
+            Err(err) => tracing::error!("Failed to set current dir ({}): {}", directory.display(), err),
+        }
+    }
+
     fn end_prompt_internal(&mut self, force: bool) {
         if self.windows_delay_end_prompt && !force {
             self.delayed_events.push(DelayedEvent::EndPrompt);
@@ -829,6 +923,8 @@ impl<T> Term<T> {
 
         trace!("Fig new command");
 
+        self.capture_command_output();
+
         self.shell_state.cmd_cursor = Some(self.grid().cursor.point);
         trace!("New command cursor: {:?}", self.shell_state.cmd_cursor);
 
@@ -1723,6 +1819,7 @@ impl<T: EventListener> Handler for Term<T> {
 
     #[inline]
     fn new_cmd(&mut self, session_id: &str) {
+        self.shell_state.fig_integration = true;
         self.new_cmd_internal(false, Some(session_id));
     }
 
@@ -1731,10 +1828,8 @@ impl<T: EventListener> Handler for Term<T> {
         if self.shell_state.osc_lock {
             return;
         }
-        trace!("Fig start prompt");
-        self.shell_state.has_seen_prompt = true;
-
-        self.grid.cursor.template.fig_flags.insert(FigFlags::IN_PROMPT);
+        self.shell_state.fig_integration = true;
+        self.start_prompt_internal();
     }
 
     #[inline]
@@ -1768,7 +1863,17 @@ impl<T: EventListener> Handler for Term<T> {
             username: context.username.clone(),
             exit_code: None,
             end_time: None,
+            output: None,
         });
+
+        // The output starts on the line after the command
+        let cursor = self.grid().cursor.point;
+        let line = if cursor.column.0 == 0 {
+            cursor.line
+        } else {
+            cursor.line + 1
+        };
+        self.shell_state.output_cursor = Some(Point::new(line, Column(0)));
     }
 
     #[inline]
@@ -1776,12 +1881,43 @@ impl<T: EventListener> Handler for Term<T> {
         if self.shell_state.osc_lock {
             return;
         }
-        trace!("Fig dir: {:?}", directory.display());
-        self.shell_state.get_mut_context().current_working_directory = Some(directory.to_path_buf());
-        match env::set_current_dir(directory) {
-            Ok(_) => {},
-            Err(err) => tracing::error!("Failed to set current dir ({}): {}", directory.display(), err),
+        self.shell_state.fig_integration = true;
+        self.dir_internal(directory);
+    }
+
+    #[inline]
+    fn semantic_prompt(&mut self, mark: SemanticPromptMark) {
+        if self.shell_state.fig_integration || self.shell_state.osc_lock {
+            return;
         }
+        trace!("Semantic prompt: {mark:?}");
+        match mark {
+            SemanticPromptMark::PromptStart { secondary } => {
+                self.shell_state.secondary_prompt = secondary;
+                self.start_prompt_internal();
+            },
+            SemanticPromptMark::CommandStart => {
+                self.end_prompt_internal(false);
+                if !self.shell_state.secondary_prompt {
+                    self.new_cmd_internal(false, None);
+                }
+            },
+            SemanticPromptMark::CommandExecuted => self.pre_exec(),
+            SemanticPromptMark::CommandFinished(Some(exit_code)) => self.exit_code(exit_code),
+            SemanticPromptMark::CommandFinished(None) => {},
+        }
+    }
+
+    #[inline]
+    fn report_dir(&mut self, host: &str, directory: &std::path::Path) {
+        if self.shell_state.fig_integration || self.shell_state.osc_lock {
+            return;
+        }
+        if !is_local_host(host, self.shell_state.hostname.as_deref()) {
+            trace!("Ignoring dir {:?} from remote host {host:?}", directory.display());
+            return;
+        }
+        self.dir_internal(directory);
     }
 
     #[inline]
@@ -2108,6 +2244,33 @@ pub mod test {
     }
 }
 
+/// Whether an OSC 7 host refers to this machine, hostnames are compared without their domain
+fn is_local_host(host: &str, hostname: Option<&str>) -> bool {
+    let short = |name: &str| name.split('.').next().unwrap_or_default().to_ascii_lowercase();
+    host.is_empty()
+        || host.eq_ignore_ascii_case("localhost")
+        || hostname.is_some_and(|hostname| short(hostname) == short(host))
+}
+
+/// Trims trailing whitespace and keeps the end of `output` within [MAX_COMMAND_OUTPUT_LINES] and
+/// [MAX_COMMAND_OUTPUT_LEN]
+fn truncate_command_output(output: &str) -> String {
+    let lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    let start = end.saturating_sub(MAX_COMMAND_OUTPUT_LINES);
+    let output = lines[start..end].join("\n");
+
+    if output.len() <= MAX_COMMAND_OUTPUT_LEN {
+        return output;
+    }
+
+    let mut start = output.len() - MAX_COMMAND_OUTPUT_LEN;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output[start..].to_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2335,4 +2498,104 @@ mod tests {
         assert_eq!(term.history_size(), 15);
         assert_eq!(term.grid.cursor.point, Point::new(Line(4), Column(0)));
     }
+
+    fn print_line(term: &mut Term<VoidListener>, line: &str) {
+        for c in line.chars() {
+            term.input(c);
+        }
+        term.carriage_return();
+        term.linefeed();
+    }
+
+    #[test]
+    fn command_output_is_captured() {
+        let size = SizeInfo::new(5, 20);
+        let mut term = Term::new_test(size, VoidListener, 100);
+
+        print_line(&mut term, "$ ls");
+        term.pre_exec();
+        print_line(&mut term, "a.txt   ");
+        print_line(&mut term, "b.txt");
+        print_line(&mut term, "");
+        term.exit_code(0);
+        term.start_prompt();
+
+        let command_info = term.shell_state.command_info.as_ref().unwrap();
+        assert_eq!(command_info.exit_code, Some(0));
+        assert_eq!(command_info.output.as_deref(), Some("a.txt\nb.txt"));
+    }
+
+    #[test]
+    fn command_output_follows_scrolling() {
+        let size = SizeInfo::new(3, 20);
+        let mut term = Term::new_test(size, VoidListener, 100);
+
+        print_line(&mut term, "$ seq 10");
+        term.pre_exec();
+        for i in 1..=10 {
+            print_line(&mut term, &i.to_string());
+        }
+        term.start_prompt();
+
+        let expected = (1..=10).map(|i| i.to_string()).collect::<Vec<_>>().join("\n");
+        let command_info = term.shell_state.command_info.as_ref().unwrap();
+        assert_eq!(command_info.output.as_deref(), Some(expected.as_str()));
+    }
+
+    fn advance(parser: &mut ansi::Processor, term: &mut Term<VoidListener>, bytes: &[u8]) {
+        for byte in bytes {
+            parser.advance(term, *byte);
+        }
+    }
+
+    #[test]
+    fn semantic_prompt_marks() {
+        let size = SizeInfo::new(5, 20);
+        let mut term = Term::new_test(size, VoidListener, 100);
+        let mut parser = ansi::Processor::new();
+
+        advance(&mut parser, &mut term, b"\x1b]133;A\x07$ \x1b]133;B\x07ls");
+        assert!(term.shell_state.has_seen_prompt);
+        assert_eq!(term.shell_state.cmd_cursor, Some(Point::new(Line(0), Column(2))));
+
+        advance(
+            &mut parser,
+            &mut term,
+            b"\r\n\x1b]133;C\x07a.txt\r\n\x1b]133;D;2\x07\x1b]133;A\x07",
+        );
+        let command_info = term.shell_state.command_info.as_ref().unwrap();
+        assert_eq!(command_info.command.as_deref(), Some("ls"));
+        assert_eq!(command_info.exit_code, Some(2));
+        assert_eq!(command_info.output.as_deref(), Some("a.txt"));
+
+        // Our own integration takes priority
+        term.new_cmd("");
+        term.shell_state.preexec = false;
+        advance(&mut parser, &mut term, b"\x1b]133;C\x07");
+        assert!(!term.shell_state.preexec);
+    }
+
+    #[test]
+    fn local_host() {
+        assert!(is_local_host("", None));
+        assert!(is_local_host("localhost", None));
+        assert!(is_local_host("my-mac", Some("my-mac.local")));
+        assert!(!is_local_host("remote", Some("my-mac.local")));
+        assert!(!is_local_host("remote", None));
+    }
+
+    #[test]
+    fn command_output_is_truncated() {
+        let output = (0..MAX_COMMAND_OUTPUT_LINES + 10)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let truncated = truncate_command_output(&output);
+        assert_eq!(truncated.lines().count(), MAX_COMMAND_OUTPUT_LINES);
+        assert!(truncated.starts_with("10\n"));
+
+        let truncated = truncate_command_output(&"é".repeat(MAX_COMMAND_OUTPUT_LEN));
+        assert!(truncated.len() <= MAX_COMMAND_OUTPUT_LEN);
+        assert!(truncated.chars().all(|c| c == 'é'));
+    }
 }
@@ -12,6 +12,8 @@ pub use self::ibus::{
 
 pub mod gnome_shell;
 pub mod ibus;
+pub mod notifications;
+pub mod secret_service;
 
 #[derive(Debug, Error)]
 pub enum CrateError {
@@ -25,6 +27,12 @@ pub enum CrateError {
     InvalidVersion(String),
     #[error(transparent)]
     Fdo(#[from] zbus::fdo::Error),
+    #[error("The secret service has no default collection")]
+    NoDefaultCollection,
+    #[error("The secret service prompt was dismissed")]
+    PromptDismissed,
+    #[error("Timed out waiting for the secret service prompt")]
+    PromptTimeout,
 }
 
 static SESSION_BUS: OnceLock<Connection> = OnceLock::new();
@@ -0,0 +1,52 @@
+//! # DBus interface proxy for: `org.freedesktop.Notifications`
+//!
+//! Reference: <https://specifications.freedesktop.org/notification-spec/latest/>
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::Value;
+
+use super::session_bus;
+use crate::CrateError;
+
+/// Let the notification server decide how long the notification is shown.
+const DEFAULT_EXPIRE_TIMEOUT: i32 = -1;
+
+#[proxy(
+    default_service = "org.freedesktop.Notifications",
+    interface = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Shows a desktop notification, returns the id of the notification
+pub async fn send_notification(app_name: &str, summary: &str, body: &str) -> Result<u32, CrateError> {
+    let proxy = NotificationsProxy::new(session_bus().await?).await?;
+    Ok(proxy
+        .notify(
+            app_name,
+            0,
+            "",
+            summary,
+            body,
+            &[],
+            HashMap::new(),
+            DEFAULT_EXPIRE_TIMEOUT,
+        )
+        .await?)
+}
@@ -0,0 +1,250 @@
+//! # DBus interface proxy for: `org.freedesktop.secrets`
+//!
+//! A minimal client for the freedesktop.org
+//! [Secret Service API](https://specifications.freedesktop.org/secret-service-spec/latest/), which
+//! is implemented by GNOME Keyring, KWallet, KeePassXC and others.
+//!
+//! Only the `plain` transfer algorithm is supported, the secret is still only ever sent over the
+//! session bus which is restricted to the current user.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures_lite::StreamExt;
+use tracing::{
+    debug,
+    trace,
+};
+use zbus::proxy;
+use zbus::zvariant::{
+    ObjectPath,
+    OwnedObjectPath,
+    OwnedValue,
+    Value,
+};
+
+use super::session_bus;
+use crate::CrateError;
+
+/// The alias of the collection that secrets are stored in.
+const DEFAULT_COLLECTION: &str = "default";
+
+const ITEM_LABEL_PROPERTY: &str = "org.freedesktop.Secret.Item.Label";
+const ITEM_ATTRIBUTES_PROPERTY: &str = "org.freedesktop.Secret.Item.Attributes";
+
+/// How long to wait for the user to respond to an unlock prompt.
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A secret as transferred over the bus: `(session, parameters, value, content_type)`.
+type RawSecret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[proxy(
+    default_service = "org.freedesktop.secrets",
+    interface = "org.freedesktop.Secret.Service",
+    default_path = "/org/freedesktop/secrets"
+)]
+trait Service {
+    /// OpenSession method
+    fn open_session(&self, algorithm: &str, input: &Value<'_>) -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+
+    /// SearchItems method
+    fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+
+    /// Unlock method
+    fn unlock(&self, objects: &[ObjectPath<'_>]) -> zbus::Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+
+    /// ReadAlias method
+    fn read_alias(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    default_service = "org.freedesktop.secrets",
+    interface = "org.freedesktop.Secret.Collection"
+)]
+trait Collection {
+    /// CreateItem method
+    fn create_item(
+        &self,
+        properties: HashMap<&str, Value<'_>>,
+        secret: &(ObjectPath<'_>, &[u8], &[u8], &str),
+        replace: bool,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+
+    /// Locked property
+    #[zbus(property)]
+    fn locked(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    default_service = "org.freedesktop.secrets",
+    interface = "org.freedesktop.Secret.Item"
+)]
+trait Item {
+    /// GetSecret method
+    fn get_secret(&self, session: &ObjectPath<'_>) -> zbus::Result<RawSecret>;
+
+    /// Delete method
+    fn delete(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    default_service = "org.freedesktop.secrets",
+    interface = "org.freedesktop.Secret.Prompt"
+)]
+trait Prompt {
+    /// Prompt method
+    fn prompt(&self, window_id: &str) -> zbus::Result<()>;
+
+    /// Completed signal
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: Value<'_>) -> zbus::Result<()>;
+}
+
+/// A session with the secret service running on the user's session bus.
+pub struct SecretService {
+    service: ServiceProxy<'static>,
+    session: OwnedObjectPath,
+}
+
+impl SecretService {
+    /// Connects to the secret service, returns an error if there is no session bus or no secret
+    /// service is available on it.
+    pub async fn connect() -> Result<Self, CrateError> {
+        let service = ServiceProxy::new(session_bus().await?).await?;
+        let (_, session) = service.open_session("plain", &Value::from("")).await?;
+        debug!(%session, "opened secret service session");
+        Ok(Self { service, session })
+    }
+
+    /// Returns the secret of the first item matching all of `attributes`.
+    pub async fn get(&self, attributes: &HashMap<&str, &str>) -> Result<Option<Vec<u8>>, CrateError> {
+        let Some(item) = self.find_item(attributes).await? else {
+            return Ok(None);
+        };
+        let (_, _, value, _) = self.item_proxy(item).await?.get_secret(&self.session).await?;
+        Ok(Some(value))
+    }
+
+    /// Stores `secret` in the default collection, replacing any item with the same `attributes`.
+    pub async fn set(&self, label: &str, attributes: &HashMap<&str, &str>, secret: &[u8]) -> Result<(), CrateError> {
+        let collection_path = self.service.read_alias(DEFAULT_COLLECTION).await?;
+        if collection_path.as_str() == "/" {
+            return Err(CrateError::NoDefaultCollection);
+        }
+
+        let collection = CollectionProxy::builder(self.service.inner().connection())
+            .path(collection_path.clone())?
+            .build()
+            .await?;
+        if collection.locked().await? {
+            self.unlock(vec![collection_path]).await?;
+        }
+
+        let properties = HashMap::from([
+            (ITEM_LABEL_PROPERTY, Value::from(label)),
+            (ITEM_ATTRIBUTES_PROPERTY, Value::from(attributes.clone())),
+        ]);
+        let secret = (self.session.as_ref(), &[][..], secret, "text/plain; charset=utf8");
+
+        let (item, prompt) = collection.create_item(properties, &secret, true).await?;
+        if item.as_str() == "/" {
+            self.complete_prompt(prompt).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes every item matching all of `attributes`.
+    pub async fn delete(&self, attributes: &HashMap<&str, &str>) -> Result<(), CrateError> {
+        while let Some(item) = self.find_item(attributes).await? {
+            let prompt = self.item_proxy(item).await?.delete().await?;
+            self.complete_prompt(prompt).await?;
+        }
+        Ok(())
+    }
+
+    /// Finds the first item matching `attributes`, unlocking it if required.
+    async fn find_item(&self, attributes: &HashMap<&str, &str>) -> Result<Option<OwnedObjectPath>, CrateError> {
+        let (unlocked, locked) = self.service.search_items(attributes.clone()).await?;
+        trace!(?unlocked, ?locked, "secret service search results");
+
+        if let Some(item) = unlocked.into_iter().next() {
+            return Ok(Some(item));
+        }
+
+        match locked.into_iter().next() {
+            Some(item) => {
+                self.unlock(vec![item.clone()]).await?;
+                Ok(Some(item))
+            },
+            None => Ok(None),
+        }
+    }
+
+    async fn unlock(&self, objects: Vec<OwnedObjectPath>) -> Result<(), CrateError> {
+        let objects = objects.iter().map(|o| o.as_ref()).collect::<Vec<_>>();
+        let (_, prompt) = self.service.unlock(&objects).await?;
+        self.complete_prompt(prompt).await
+    }
+
+    /// Shows the prompt at `prompt` to the user if there is one and waits for it to be completed.
+    async fn complete_prompt(&self, prompt: OwnedObjectPath) -> Result<(), CrateError> {
+        if prompt.as_str() == "/" {
+            return Ok(());
+        }
+
+        let prompt = PromptProxy::builder(self.service.inner().connection())
+            .path(prompt)?
+            .build()
+            .await?;
+        let mut completed = prompt.receive_completed().await?;
+        prompt.prompt("").await?;
+
+        let signal = tokio::time::timeout(PROMPT_TIMEOUT, completed.next())
+            .await
+            .map_err(|_err| CrateError::PromptTimeout)?
+            .ok_or(CrateError::PromptDismissed)?;
+
+        if signal.args()?.dismissed {
+            Err(CrateError::PromptDismissed)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn item_proxy(&self, item: OwnedObjectPath) -> Result<ItemProxy<'static>, CrateError> {
+        Ok(ItemProxy::builder(self.service.inner().connection())
+            .path(item)?
+            .build()
+            .await?)
+    }
+}
+
+impl std::fmt::Debug for SecretService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretService")
+            .field("session", &self.session)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "not in ci"]
+    async fn test_set_get_delete() {
+        let service = SecretService::connect().await.unwrap();
+        let attributes = HashMap::from([("service", "q-cli-test"), ("key", "test_set_get_delete")]);
+
+        service.set("q-cli test", &attributes, b"secret").await.unwrap();
+        assert_eq!(service.get(&attributes).await.unwrap().unwrap(), b"secret");
+
+        service.delete(&attributes).await.unwrap();
+        assert_eq!(service.get(&attributes).await.unwrap(), None);
+    }
+}
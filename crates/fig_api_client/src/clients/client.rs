@@ -1,5 +1,7 @@
+use std::collections::VecDeque;
 use std::sync::{
     Arc,
+    LazyLock,
     Mutex,
 };
 
@@ -24,13 +26,25 @@ use fig_aws_common::{
     app_name,
 };
 use fig_settings::State;
-use tracing::error;
+use fig_settings::telemetry_log::{
+    TelemetryLog,
+    TelemetryRecord,
+};
+use tracing::{
+    debug,
+    error,
+};
 
 use super::shared::{
     bearer_sdk_config,
     sigv4_sdk_config,
 };
-use crate::interceptor::opt_out::OptOutInterceptor;
+use crate::governor::Governor;
+use crate::interceptor::opt_out::{
+    OptOutInterceptor,
+    telemetry_category_enabled,
+    telemetry_event_category,
+};
 use crate::interceptor::session_id::SessionIdInterceptor;
 use crate::model::{
     Recommendation,
@@ -48,14 +62,32 @@ pub const FILE_CONTEXT_LEFT_FILE_CONTENT_MAX_LEN: usize = 10240;
 pub const FILE_CONTEXT_RIGHT_FILE_CONTENT_MAX_LEN: usize = 10240;
 pub const FILE_CONTEXT_FILE_NAME_MAX_LEN: usize = 1024;
 
+/// The most telemetry events kept while the endpoint is unreachable, the oldest are dropped first
+const TELEMETRY_QUEUE_MAX_LEN: usize = 100;
+
+/// Telemetry events that failed to send because the endpoint was unreachable or rate limited, they
+/// are sent after the next telemetry event that succeeds
+static TELEMETRY_QUEUE: LazyLock<Mutex<VecDeque<QueuedTelemetryEvent>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone)]
+struct QueuedTelemetryEvent {
+    telemetry_event: TelemetryEvent,
+    user_context: UserContext,
+    opt_out: OptOutPreference,
+}
+
 mod inner {
+    use std::sync::Arc;
+
     use amzn_codewhisperer_client::Client as CodewhispererClient;
     use amzn_consolas_client::Client as ConsolasClient;
 
+    use crate::governor::Governor;
+
     #[derive(Clone, Debug)]
     pub enum Inner {
-        Codewhisperer(CodewhispererClient),
-        Consolas(ConsolasClient),
+        Codewhisperer(CodewhispererClient, Arc<Governor>),
+        Consolas(ConsolasClient, Arc<Governor>),
         Mock,
     }
 }
@@ -87,7 +119,10 @@ impl Client {
             .app_name(app_name())
             .endpoint_url(endpoint.url())
             .build();
-        Self(inner::Inner::Codewhisperer(CodewhispererClient::from_conf(conf)))
+        Self(inner::Inner::Codewhisperer(
+            CodewhispererClient::from_conf(conf),
+            Governor::for_endpoint(endpoint.url()),
+        ))
     }
 
     pub async fn new_consolas_client(endpoint: &Endpoint) -> Result<Self, Error> {
@@ -98,7 +133,10 @@ impl Client {
             .app_name(app_name())
             .endpoint_url(endpoint.url())
             .build();
-        Ok(Self(inner::Inner::Consolas(ConsolasClient::from_conf(conf))))
+        Ok(Self(inner::Inner::Consolas(
+            ConsolasClient::from_conf(conf),
+            Governor::for_endpoint(endpoint.url()),
+        )))
     }
 
     pub async fn generate_recommendations(
@@ -132,8 +170,16 @@ impl Client {
         input.file_context.right_file_content = right_content;
 
         match &self.0 {
-            inner::Inner::Codewhisperer(client) => Ok(codewhisperer_generate_recommendation(client, input).await?),
-            inner::Inner::Consolas(client) => Ok(consolas_generate_recommendation(client, input).await?),
+            inner::Inner::Codewhisperer(client, governor) => {
+                governor
+                    .send(|| codewhisperer_generate_recommendation(client, input.clone()))
+                    .await
+            },
+            inner::Inner::Consolas(client, governor) => {
+                governor
+                    .send(|| consolas_generate_recommendation(client, input.clone()))
+                    .await
+            },
             inner::Inner::Mock => Ok(RecommendationsOutput {
                 recommendations: vec![Recommendation {
                     content: "Hello, world!".to_string(),
@@ -150,19 +196,31 @@ impl Client {
         let mut customizations = Vec::new();
 
         match &self.0 {
-            inner::Inner::Codewhisperer(client) => {
-                let mut paginator = client.list_available_customizations().into_paginator().send();
-                while let Some(res) = paginator.next().await {
-                    let output = res?;
-                    customizations.extend(output.customizations.into_iter().map(Into::into));
-                }
+            inner::Inner::Codewhisperer(client, governor) => {
+                customizations = governor
+                    .send(|| async {
+                        let mut customizations = Vec::new();
+                        let mut paginator = client.list_available_customizations().into_paginator().send();
+                        while let Some(res) = paginator.next().await {
+                            let output = res?;
+                            customizations.extend(output.customizations.into_iter().map(Into::into));
+                        }
+                        Ok(customizations)
+                    })
+                    .await?;
             },
-            inner::Inner::Consolas(client) => {
-                let mut pag = client.list_customizations().into_paginator().send();
-                while let Some(res) = pag.next().await {
-                    let output = res?;
-                    customizations.extend(output.customizations.into_iter().map(Into::into));
-                }
+            inner::Inner::Consolas(client, governor) => {
+                customizations = governor
+                    .send(|| async {
+                        let mut customizations = Vec::new();
+                        let mut pag = client.list_customizations().into_paginator().send();
+                        while let Some(res) = pag.next().await {
+                            let output = res?;
+                            customizations.extend(output.customizations.into_iter().map(Into::into));
+                        }
+                        Ok(customizations)
+                    })
+                    .await?;
             },
             inner::Inner::Mock => customizations.extend([
                 Customization {
@@ -191,22 +249,127 @@ impl Client {
         opt_out: OptOutPreference,
     ) -> Result<(), Error> {
         match &self.0 {
-            inner::Inner::Codewhisperer(client) => {
-                let _ = client
-                    .send_telemetry_event()
-                    .telemetry_event(telemetry_event)
-                    .user_context(user_context)
-                    .opt_out_preference(opt_out)
-                    .send()
-                    .await;
+            inner::Inner::Codewhisperer(client, governor) => {
+                let governor = &Governor::for_telemetry(governor.endpoint());
+                let event = QueuedTelemetryEvent {
+                    telemetry_event,
+                    user_context,
+                    opt_out,
+                };
+
+                match codewhisperer_send_telemetry_event(client, governor, event.clone()).await {
+                    Ok(()) => flush_telemetry_queue(client, governor).await,
+                    Err(err) if err.is_rate_limited() || !err.is_service_error() => {
+                        debug!(%err, "Failed to send telemetry event, queueing it");
+                        let mut queue = TELEMETRY_QUEUE.lock().expect("telemetry queue lock poisoned");
+                        if queue.len() >= TELEMETRY_QUEUE_MAX_LEN {
+                            queue.pop_front();
+                        }
+                        queue.push_back(event);
+                    },
+                    Err(_) => {},
+                }
                 Ok(())
             },
-            inner::Inner::Consolas(_) => Err(Error::UnsupportedConsolas("send_telemetry_event")),
+            inner::Inner::Consolas(..) => Err(Error::UnsupportedConsolas("send_telemetry_event")),
             inner::Inner::Mock => Ok(()),
         }
     }
 }
 
+/// Sends `event` and records it in the telemetry log, every event is sent through here whether it
+/// was queued or not
+async fn codewhisperer_send_telemetry_event(
+    client: &CodewhispererClient,
+    governor: &Governor,
+    event: QueuedTelemetryEvent,
+) -> Result<(), Error> {
+    // Dropped rather than left to the interceptor so denied events aren't queued for retries
+    let category = telemetry_event_category(&event.telemetry_event);
+    if !telemetry_category_enabled(category) {
+        debug!(%category, "Telemetry category is disabled, dropping telemetry event");
+        return Ok(());
+    }
+
+    governor
+        .send(|| {
+            client
+                .send_telemetry_event()
+                .telemetry_event(event.telemetry_event.clone())
+                .user_context(event.user_context.clone())
+                .opt_out_preference(event.opt_out.clone())
+                .send()
+        })
+        .await?;
+    record_telemetry_event(&TelemetryLog::new(), &event);
+    Ok(())
+}
+
+fn record_telemetry_event(log: &TelemetryLog, event: &QueuedTelemetryEvent) {
+    let record = telemetry_record(event);
+    if let Err(err) = log.insert(&record) {
+        error!(%err, name = record.name, "Failed to record telemetry event in the telemetry log");
+    }
+}
+
+/// The audit log record of a CodeWhisperer telemetry event, the event itself is kept as it is
+/// printed since the SDK types can't be serialized
+fn telemetry_record(event: &QueuedTelemetryEvent) -> TelemetryRecord {
+    let event_debug = format!("{:?}", event.telemetry_event);
+    let name = event_debug
+        .split(['(', ' ', '{'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("TelemetryEvent");
+
+    let mut fields = fig_settings::Map::new();
+    fields.insert("event".into(), event_debug.clone().into());
+    fields.insert("optOutPreference".into(), event.opt_out.as_str().into());
+    fields.insert("product".into(), event.user_context.product().into());
+    fields.insert(
+        "operatingSystem".into(),
+        event.user_context.operating_system().as_str().into(),
+    );
+    fields.insert("ideCategory".into(), event.user_context.ide_category().as_str().into());
+    if let Some(client_id) = event.user_context.client_id() {
+        fields.insert("clientId".into(), client_id.into());
+    }
+    if let Some(ide_version) = event.user_context.ide_version() {
+        fields.insert("ideVersion".into(), ide_version.into());
+    }
+
+    TelemetryRecord {
+        name: format!("codewhisperer_{name}"),
+        category: telemetry_event_category(&event.telemetry_event).to_string(),
+        fields,
+        time: std::time::SystemTime::now(),
+    }
+}
+
+/// Sends the queued telemetry events, stopping at the first failure
+async fn flush_telemetry_queue(client: &CodewhispererClient, governor: &Governor) {
+    loop {
+        let Some(event) = TELEMETRY_QUEUE
+            .lock()
+            .expect("telemetry queue lock poisoned")
+            .pop_front()
+        else {
+            return;
+        };
+
+        if let Err(err) = codewhisperer_send_telemetry_event(client, governor, event.clone()).await {
+            debug!(%err, "Failed to send queued telemetry event");
+            if err.is_rate_limited() || !err.is_service_error() {
+                TELEMETRY_QUEUE
+                    .lock()
+                    .expect("telemetry queue lock poisoned")
+                    .push_front(event);
+            }
+            return;
+        }
+    }
+}
+
 async fn codewhisperer_generate_recommendation_inner(
     client: &CodewhispererClient,
     input: RecommendationsInput,
@@ -360,6 +523,38 @@ mod tests {
         let _ = Client::new_consolas_client(&endpoint).await;
     }
 
+    #[test]
+    fn test_record_telemetry_event() {
+        let log = TelemetryLog::mock();
+        let event = QueuedTelemetryEvent {
+            telemetry_event: TelemetryEvent::ChatAddMessageEvent(
+                ChatAddMessageEvent::builder()
+                    .conversation_id("<conversation-id>")
+                    .message_id("<message-id>")
+                    .build()
+                    .unwrap(),
+            ),
+            user_context: UserContext::builder()
+                .ide_category(IdeCategory::Cli)
+                .operating_system(OperatingSystem::Linux)
+                .product("<product>")
+                .client_id("<client-id>")
+                .build()
+                .unwrap(),
+            opt_out: OptOutPreference::OptIn,
+        };
+        record_telemetry_event(&log, &event);
+
+        let records = log.search(None, Some("chatMetadata"), 10).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.name, "codewhisperer_ChatAddMessageEvent");
+        assert_eq!(record.fields["clientId"], "<client-id>");
+        assert_eq!(record.fields["product"], "<product>");
+        assert_eq!(record.fields["optOutPreference"], "OPTIN");
+        assert!(record.fields["event"].as_str().unwrap().contains("<conversation-id>"));
+    }
+
     #[tokio::test]
     async fn test_mock() {
         let client = Client::mock();
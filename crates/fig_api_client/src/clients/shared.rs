@@ -44,7 +44,8 @@ async fn base_sdk_config(region: Region, credentials_provider: impl ProvideCrede
         .region(region)
         .credentials_provider(credentials_provider)
         .timeout_config(timeout_config())
-        .retry_config(RetryConfig::adaptive())
+        // Retries are handled by the governor so they are shared across clients
+        .retry_config(RetryConfig::disabled())
         .load()
         .await
 }
@@ -18,6 +18,7 @@ use super::shared::{
     sigv4_sdk_config,
     stalled_stream_protection_config,
 };
+use crate::governor::Governor;
 use crate::interceptor::opt_out::OptOutInterceptor;
 use crate::model::{
     ChatResponseStream,
@@ -37,12 +38,13 @@ mod inner {
     use amzn_codewhisperer_streaming_client::Client as CodewhispererStreamingClient;
     use amzn_qdeveloper_streaming_client::Client as QDeveloperStreamingClient;
 
+    use crate::governor::Governor;
     use crate::model::ChatResponseStream;
 
     #[derive(Clone, Debug)]
     pub enum Inner {
-        Codewhisperer(CodewhispererStreamingClient),
-        QDeveloper(QDeveloperStreamingClient),
+        Codewhisperer(CodewhispererStreamingClient, Arc<Governor>),
+        QDeveloper(QDeveloperStreamingClient, Arc<Governor>),
         Mock(Arc<Mutex<std::vec::IntoIter<Vec<ChatResponseStream>>>>),
     }
 }
@@ -76,7 +78,10 @@ impl StreamingClient {
             .stalled_stream_protection(stalled_stream_protection_config())
             .build();
         let client = CodewhispererStreamingClient::from_conf(conf);
-        Self(inner::Inner::Codewhisperer(client))
+        Self(inner::Inner::Codewhisperer(
+            client,
+            Governor::for_endpoint(endpoint.url()),
+        ))
     }
 
     pub async fn new_qdeveloper_client(endpoint: &Endpoint) -> Result<Self, Error> {
@@ -90,7 +95,10 @@ impl StreamingClient {
             .stalled_stream_protection(stalled_stream_protection_config())
             .build();
         let client = QDeveloperStreamingClient::from_conf(conf);
-        Ok(Self(inner::Inner::QDeveloper(client)))
+        Ok(Self(inner::Inner::QDeveloper(
+            client,
+            Governor::for_endpoint(endpoint.url()),
+        )))
     }
 
     pub async fn send_message(&self, conversation_state: ConversationState) -> Result<SendMessageOutput, Error> {
@@ -103,7 +111,7 @@ impl StreamingClient {
         } = conversation_state;
 
         match &self.0 {
-            inner::Inner::Codewhisperer(client) => {
+            inner::Inner::Codewhisperer(client, governor) => {
                 let conversation_state = amzn_codewhisperer_streaming_client::types::ConversationState::builder()
                     .set_conversation_id(conversation_id)
                     .current_message(
@@ -119,25 +127,26 @@ impl StreamingClient {
                     )
                     .build()
                     .expect("building conversation_state should not fail");
-                let response = client
-                    .generate_assistant_response()
-                    .conversation_state(conversation_state)
-                    .send()
+                let response = governor
+                    .send(|| {
+                        client
+                            .generate_assistant_response()
+                            .conversation_state(conversation_state.clone())
+                            .send()
+                    })
                     .await;
 
                 match response {
                     Ok(resp) => Ok(SendMessageOutput::Codewhisperer(resp)),
-                    Err(e) => {
-                        let is_quota_breach = e.raw_response().is_some_and(|resp| resp.status().as_u16() == 429);
-                        if is_quota_breach {
-                            Err(Error::QuotaBreach("quota has reached its limit"))
-                        } else {
-                            Err(e.into())
-                        }
+                    Err(Error::CodewhispererGenerateAssistantResponse(e))
+                        if e.raw_response().is_some_and(|resp| resp.status().as_u16() == 429) =>
+                    {
+                        Err(Error::QuotaBreach("quota has reached its limit"))
                     },
+                    Err(e) => Err(e),
                 }
             },
-            inner::Inner::QDeveloper(client) => {
+            inner::Inner::QDeveloper(client, governor) => {
                 let conversation_state_builder = amzn_qdeveloper_streaming_client::types::ConversationState::builder()
                     .set_conversation_id(conversation_id)
                     .current_message(amzn_qdeveloper_streaming_client::types::ChatMessage::UserInputMessage(
@@ -150,11 +159,16 @@ impl StreamingClient {
                             .transpose()?,
                     );
 
+                let conversation_state = conversation_state_builder.build().expect("fix me");
+
                 Ok(SendMessageOutput::QDeveloper(
-                    client
-                        .send_message()
-                        .conversation_state(conversation_state_builder.build().expect("fix me"))
-                        .send()
+                    governor
+                        .send(|| {
+                            client
+                                .send_message()
+                                .conversation_state(conversation_state.clone())
+                                .send()
+                        })
                         .await?,
                 ))
             },
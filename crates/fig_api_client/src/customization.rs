@@ -1,5 +1,6 @@
 use amzn_codewhisperer_client::types::Customization as CodewhispererCustomization;
 use amzn_consolas_client::types::CustomizationSummary as ConsolasCustomization;
+use fig_auth::profile::active_scoped_key;
 use fig_settings::State;
 use serde::{
     Deserialize,
@@ -19,19 +20,19 @@ pub struct Customization {
 }
 
 impl Customization {
-    /// Load the currently selected customization from state
+    /// Load the currently selected customization for the active profile from state
     pub fn load_selected(state: &State) -> Result<Option<Self>, fig_settings::Error> {
-        state.get(CUSTOMIZATION_STATE_KEY)
+        state.get(active_scoped_key(CUSTOMIZATION_STATE_KEY))
     }
 
-    /// Save the currently selected customization to state
+    /// Save the currently selected customization for the active profile to state
     pub fn save_selected(&self, state: &State) -> Result<(), fig_settings::Error> {
-        state.set_value(CUSTOMIZATION_STATE_KEY, serde_json::to_value(self)?)
+        state.set_value(active_scoped_key(CUSTOMIZATION_STATE_KEY), serde_json::to_value(self)?)
     }
 
-    /// Delete the currently selected customization from state
+    /// Delete the currently selected customization for the active profile from state
     pub fn delete_selected(state: &State) -> Result<(), fig_settings::Error> {
-        state.remove_value(CUSTOMIZATION_STATE_KEY)
+        state.remove_value(active_scoped_key(CUSTOMIZATION_STATE_KEY))
     }
 }
 
@@ -1,7 +1,13 @@
 use std::borrow::Cow;
 
 use aws_config::Region;
+use fig_auth::profile::{
+    Profile,
+    ProfileService,
+};
+use fig_settings::State;
 use serde_json::Value;
+use tracing::error;
 
 use crate::consts::{
     PROD_CODEWHISPERER_ENDPOINT_REGION,
@@ -27,6 +33,10 @@ impl Endpoint {
     };
 
     pub fn load_codewhisperer() -> Self {
+        if let Some(endpoint) = Self::load_profile(ProfileService::Codewhisperer) {
+            return endpoint;
+        }
+
         match fig_settings::settings::get_value("api.codewhisperer.service") {
             Ok(Some(Value::Object(o))) => {
                 let endpoint = o.get("endpoint").and_then(|v| v.as_str());
@@ -45,6 +55,10 @@ impl Endpoint {
     }
 
     pub fn load_q() -> Self {
+        if let Some(endpoint) = Self::load_profile(ProfileService::Q) {
+            return endpoint;
+        }
+
         match fig_settings::settings::get_value("api.q.service") {
             Ok(Some(Value::Object(o))) => {
                 let endpoint = o.get("endpoint").and_then(|v| v.as_str());
@@ -62,6 +76,20 @@ impl Endpoint {
         }
     }
 
+    /// The active profile's endpoint override for `service`, if it has one
+    fn load_profile(service: ProfileService) -> Option<Self> {
+        match Profile::load_active(&State::new()) {
+            Ok(profile) => profile.endpoint(service).map(|endpoint| Self {
+                url: endpoint.url.clone().into(),
+                region: Region::new(endpoint.region.clone()),
+            }),
+            Err(err) => {
+                error!(%err, "Failed to load active profile");
+                None
+            },
+        }
+    }
+
     pub(crate) fn url(&self) -> &str {
         &self.url
     }
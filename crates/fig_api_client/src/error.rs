@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 use amzn_codewhisperer_client::operation::generate_completions::GenerateCompletionsError;
 use amzn_codewhisperer_client::operation::list_available_customizations::ListAvailableCustomizationsError;
+use amzn_codewhisperer_client::operation::send_telemetry_event::SendTelemetryEventError;
 use amzn_codewhisperer_streaming_client::operation::generate_assistant_response::GenerateAssistantResponseError;
 // use amzn_codewhisperer_streaming_client::operation::send_message::SendMessageError as
 // CodewhispererSendMessageError;
@@ -32,6 +35,9 @@ pub enum Error {
     #[error("{}", SdkErrorDisplay(.0))]
     ListAvailableServices(#[from] SdkError<ListCustomizationsError, HttpResponse>),
 
+    #[error("{}", SdkErrorDisplay(.0))]
+    SendTelemetryEvent(#[from] SdkError<SendTelemetryEventError, HttpResponse>),
+
     // Send message errors
     #[error("{}", SdkErrorDisplay(.0))]
     CodewhispererGenerateAssistantResponse(#[from] SdkError<GenerateAssistantResponseError, HttpResponse>),
@@ -48,6 +54,9 @@ pub enum Error {
     #[error("quota has reached its limit")]
     QuotaBreach(&'static str),
 
+    #[error("too many requests to {endpoint}, try again in {}s", .retry_after.as_secs().max(1))]
+    RateLimited { endpoint: String, retry_after: Duration },
+
     #[error(transparent)]
     SmithyBuild(#[from] aws_smithy_types::error::operation::BuildError),
 
@@ -63,6 +72,7 @@ impl Error {
             Error::GenerateRecommendations(e) => e.as_service_error().is_some_and(|e| e.is_throttling_error()),
             Error::ListAvailableCustomizations(e) => e.as_service_error().is_some_and(|e| e.is_throttling_error()),
             Error::ListAvailableServices(e) => e.as_service_error().is_some_and(|e| e.is_throttling_error()),
+            Error::SendTelemetryEvent(e) => e.as_service_error().is_some_and(|e| e.is_throttling_error()),
             Error::CodewhispererGenerateAssistantResponse(e) => {
                 e.as_service_error().is_some_and(|e| e.is_throttling_error())
             },
@@ -71,10 +81,17 @@ impl Error {
             | Error::QDeveloperChatResponseStream(_)
             | Error::SmithyBuild(_)
             | Error::UnsupportedConsolas(_)
-            | Error::QuotaBreach(_) => false,
+            | Error::QuotaBreach(_)
+            | Error::RateLimited { .. } => false,
         }
     }
 
+    /// Whether the request was not sent because the governor is holding back requests to the
+    /// endpoint
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Error::RateLimited { .. })
+    }
+
     pub fn is_service_error(&self) -> bool {
         match self {
             Error::Credentials(_) => false,
@@ -82,13 +99,15 @@ impl Error {
             Error::GenerateRecommendations(e) => e.as_service_error().is_some(),
             Error::ListAvailableCustomizations(e) => e.as_service_error().is_some(),
             Error::ListAvailableServices(e) => e.as_service_error().is_some(),
+            Error::SendTelemetryEvent(e) => e.as_service_error().is_some(),
             Error::CodewhispererGenerateAssistantResponse(e) => e.as_service_error().is_some(),
             Error::QDeveloperSendMessage(e) => e.as_service_error().is_some(),
             Error::CodewhispererChatResponseStream(_)
             | Error::QDeveloperChatResponseStream(_)
             | Error::SmithyBuild(_)
             | Error::UnsupportedConsolas(_)
-            | Error::QuotaBreach(_) => false,
+            | Error::QuotaBreach(_)
+            | Error::RateLimited { .. } => false,
         }
     }
 }
@@ -130,6 +149,10 @@ mod tests {
                 ListCustomizationsError::unhandled("<unhandled>"),
                 response(),
             )),
+            Error::SendTelemetryEvent(SdkError::service_error(
+                SendTelemetryEventError::unhandled("<unhandled>"),
+                response(),
+            )),
             Error::CodewhispererGenerateAssistantResponse(SdkError::service_error(
                 GenerateAssistantResponseError::unhandled("<unhandled>"),
                 response(),
@@ -148,6 +171,10 @@ mod tests {
             )),
             Error::SmithyBuild(aws_smithy_types::error::operation::BuildError::other("<other>")),
             Error::UnsupportedConsolas("test"),
+            Error::RateLimited {
+                endpoint: "https://example.com".into(),
+                retry_after: Duration::from_secs(30),
+            },
         ]
     }
 
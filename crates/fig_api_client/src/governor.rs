@@ -0,0 +1,775 @@
+//! # Request governor
+//!
+//! Every request made by the clients goes through the [Governor] for its endpoint, governors are
+//! shared by all clients in a process so short lived clients (e.g. inline completions creates one
+//! per request) still share their limits. Telemetry has its own governor per endpoint so it never
+//! holds back the requests a user is waiting on.
+//!
+//! A governor combines:
+//!   - A token bucket limiting the request rate, configured with `api.rateLimit.requestsPerMinute`
+//!     and `api.rateLimit.burst`
+//!   - Retries with full jitter for throttled and transient failures, waiting at least as long as
+//!     the `Retry-After` header asks. Retries are limited per request by `api.retry.maxAttempts`
+//!     and across requests by a retry budget that is refilled by successful requests
+//!   - A circuit breaker that fails requests fast after repeated failures, then lets a single probe
+//!     request through once the cooldown has passed
+//!
+//! Changes to the health of an endpoint are saved in the state so `q doctor` can report them from
+//! another process.
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::future::Future;
+use std::sync::{
+    Arc,
+    LazyLock,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
+use aws_smithy_runtime_api::client::result::SdkError;
+use fig_settings::State;
+use rand::Rng;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tracing::{
+    debug,
+    error,
+    warn,
+};
+
+use crate::Error;
+
+const STATUS_STATE_KEY: &str = "api.governor";
+
+const DEFAULT_REQUESTS_PER_MINUTE: u32 = 120;
+const DEFAULT_BURST: u32 = 20;
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The longest a request waits for a token or a `Retry-After` before failing with
+/// [Error::RateLimited]
+const MAX_WAIT: Duration = Duration::from_secs(10);
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(8);
+
+const RETRY_BUDGET: f64 = 10.0;
+const RETRY_COST: f64 = 1.0;
+const RETRY_REFUND: f64 = 0.2;
+
+const FAILURE_THRESHOLD: u32 = 5;
+const COOLDOWN_BASE: Duration = Duration::from_secs(30);
+const COOLDOWN_MAX: Duration = Duration::from_secs(5 * 60);
+
+static GOVERNORS: LazyLock<Mutex<HashMap<String, Arc<Governor>>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    requests_per_minute: u32,
+    burst: u32,
+    max_attempts: u32,
+}
+
+impl Config {
+    fn load() -> Self {
+        let get = |key: &str, default: u32| {
+            fig_settings::settings::get_int(key)
+                .ok()
+                .flatten()
+                .and_then(|i| u32::try_from(i).ok())
+                .filter(|i| *i > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            requests_per_minute: get("api.rateLimit.requestsPerMinute", DEFAULT_REQUESTS_PER_MINUTE),
+            burst: get("api.rateLimit.burst", DEFAULT_BURST),
+            max_attempts: get("api.retry.maxAttempts", DEFAULT_MAX_ATTEMPTS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Requests are sent normally
+    Closed,
+    /// Requests fail without being sent until the cooldown has passed
+    Open,
+    /// The cooldown has passed and a single probe request is allowed through
+    HalfOpen,
+}
+
+/// The health of an endpoint as last saved by a governor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GovernorStatus {
+    pub endpoint: String,
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+    /// When requests are allowed again, set while the circuit is open or the service asked to back
+    /// off with `Retry-After`
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub retry_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl GovernorStatus {
+    /// Loads the last saved status of every endpoint
+    pub fn load_all(state: &State) -> Vec<Self> {
+        match state.get::<BTreeMap<String, Self>>(STATUS_STATE_KEY) {
+            Ok(statuses) => statuses.unwrap_or_default().into_values().collect(),
+            Err(err) => {
+                error!(%err, "Failed to load api governor status");
+                vec![]
+            },
+        }
+    }
+
+    /// Whether requests to the endpoint are currently held back, an open circuit whose cooldown
+    /// has passed lets the next request through as a probe so it doesn't count
+    pub fn is_limited(&self) -> bool {
+        self.retry_at.is_some_and(|at| at > OffsetDateTime::now_utc())
+    }
+
+    /// Whether the status shows a problem, even one that no longer holds requests back
+    fn is_unhealthy(&self) -> bool {
+        self.circuit != CircuitState::Closed || self.consecutive_failures > 0 || self.retry_at.is_some()
+    }
+}
+
+/// How the result of an attempt counts toward retries and the circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Success,
+    /// The service asked us to slow down
+    Throttled {
+        retry_after: Option<Duration>,
+    },
+    /// The request failed in a way that may succeed if tried again, e.g. a 5xx or a dropped
+    /// connection
+    Failed {
+        retry_after: Option<Duration>,
+    },
+    /// The service responded but rejected the request, retrying will not help
+    Rejected,
+}
+
+impl Outcome {
+    fn classify<E>(err: &SdkError<E, HttpResponse>) -> Self {
+        match err {
+            SdkError::ConstructionFailure(_) => Self::Rejected,
+            SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => Self::Failed { retry_after: None },
+            SdkError::ResponseError(_) | SdkError::ServiceError(_) => match err.raw_response() {
+                Some(response) => {
+                    let retry_after = response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| parse_retry_after(value, OffsetDateTime::now_utc()));
+                    match response.status().as_u16() {
+                        429 => Self::Throttled { retry_after },
+                        500.. => Self::Failed { retry_after },
+                        _ => Self::Rejected,
+                    }
+                },
+                None => Self::Failed { retry_after: None },
+            },
+            _ => Self::Failed { retry_after: None },
+        }
+    }
+}
+
+/// Parses a `Retry-After` header, either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str, now: OffsetDateTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = OffsetDateTime::parse(value, &time::format_description::well_known::Rfc2822).ok()?;
+    Some((date - now).try_into().unwrap_or_default())
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: Config,
+    tokens: f64,
+    last_refill: Instant,
+    retry_budget: f64,
+    throttled_until: Option<Instant>,
+    circuit: CircuitState,
+    open_until: Option<Instant>,
+    trips: u32,
+    probe_in_flight: bool,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    /// Whether the saved status shows a problem that has to be cleared on the next success
+    unhealthy: bool,
+}
+
+impl Inner {
+    fn new(config: Config, now: Instant) -> Self {
+        Self {
+            tokens: config.burst.into(),
+            config,
+            last_refill: now,
+            retry_budget: RETRY_BUDGET,
+            throttled_until: None,
+            circuit: CircuitState::Closed,
+            open_until: None,
+            trips: 0,
+            probe_in_flight: false,
+            consecutive_failures: 0,
+            last_error: None,
+            unhealthy: false,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        let rate = f64::from(self.config.requests_per_minute) / 60.0;
+        self.tokens = (self.tokens + elapsed * rate).min(self.config.burst.into());
+        self.last_refill = now;
+    }
+
+    /// Takes a permit to send a request, returns whether it is the probe of a half open circuit or
+    /// how long to wait before trying again if there is none
+    fn try_acquire(&mut self, now: Instant) -> Result<bool, Duration> {
+        match self.circuit {
+            CircuitState::Closed => {},
+            CircuitState::Open => match self.open_until {
+                Some(until) if until > now => return Err(until - now),
+                _ => {
+                    debug!("Circuit half open, allowing a probe request");
+                    self.circuit = CircuitState::HalfOpen;
+                },
+            },
+            CircuitState::HalfOpen => {
+                if self.probe_in_flight {
+                    return Err(COOLDOWN_BASE);
+                }
+            },
+        }
+
+        if let Some(until) = self.throttled_until {
+            if until > now {
+                return Err(until - now);
+            }
+            self.throttled_until = None;
+        }
+
+        self.refill(now);
+        if self.tokens < 1.0 {
+            let rate = f64::from(self.config.requests_per_minute) / 60.0;
+            return Err(Duration::from_secs_f64((1.0 - self.tokens) / rate));
+        }
+
+        self.tokens -= 1.0;
+        let probe = self.circuit == CircuitState::HalfOpen;
+        if probe {
+            self.probe_in_flight = true;
+        }
+        Ok(probe)
+    }
+
+    /// Records the outcome of an attempt, returns whether the status changed and should be saved
+    fn record(&mut self, outcome: Outcome, error: Option<String>, now: Instant) -> bool {
+        self.probe_in_flight = false;
+
+        match outcome {
+            Outcome::Success | Outcome::Rejected => {
+                if outcome == Outcome::Success {
+                    self.retry_budget = (self.retry_budget + RETRY_REFUND).min(RETRY_BUDGET);
+                }
+                self.consecutive_failures = 0;
+                self.trips = 0;
+                self.circuit = CircuitState::Closed;
+                self.open_until = None;
+                std::mem::take(&mut self.unhealthy)
+            },
+            Outcome::Throttled { retry_after } | Outcome::Failed { retry_after } => {
+                self.consecutive_failures += 1;
+                self.last_error = error;
+                if let Some(retry_after) = retry_after {
+                    self.throttled_until = Some(now + retry_after);
+                }
+
+                let trip = self.circuit == CircuitState::HalfOpen || self.consecutive_failures >= FAILURE_THRESHOLD;
+                if trip {
+                    self.trips += 1;
+                    let cooldown = COOLDOWN_BASE
+                        .saturating_mul(2u32.saturating_pow(self.trips - 1))
+                        .min(COOLDOWN_MAX);
+                    warn!(?cooldown, failures = self.consecutive_failures, "Opening circuit");
+                    self.circuit = CircuitState::Open;
+                    self.open_until = Some(now + cooldown);
+                }
+
+                let changed = trip || retry_after.is_some() || !self.unhealthy;
+                self.unhealthy = true;
+                changed
+            },
+        }
+    }
+
+    /// Takes from the retry budget, returns false if it is exhausted
+    fn take_retry(&mut self) -> bool {
+        if self.retry_budget >= RETRY_COST {
+            self.retry_budget -= RETRY_COST;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn status(&self, endpoint: &str, now: Instant) -> GovernorStatus {
+        let retry_at = match self.circuit {
+            CircuitState::Open => self.open_until,
+            _ => self.throttled_until,
+        }
+        .filter(|at| *at > now)
+        .map(|at| OffsetDateTime::now_utc() + (at - now));
+
+        GovernorStatus {
+            endpoint: endpoint.into(),
+            circuit: self.circuit,
+            consecutive_failures: self.consecutive_failures,
+            retry_at,
+            last_error: self.last_error.clone(),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+/// Limits the requests to a single endpoint, see the [module docs](self)
+#[derive(Debug)]
+pub struct Governor {
+    endpoint: String,
+    inner: Mutex<Inner>,
+    state: State,
+}
+
+impl Governor {
+    fn new(endpoint: impl Into<String>, config: Config, state: State) -> Self {
+        let endpoint = endpoint.into();
+        let mut inner = Inner::new(config, Instant::now());
+        // Another process may have saved a problem, the next success here has to clear it
+        inner.unhealthy = GovernorStatus::load_all(&state)
+            .iter()
+            .any(|status| status.endpoint == endpoint && status.is_unhealthy());
+
+        Self {
+            endpoint,
+            inner: Mutex::new(inner),
+            state,
+        }
+    }
+
+    /// The governor shared by every client of `endpoint` in this process
+    pub fn for_endpoint(endpoint: &str) -> Arc<Self> {
+        let mut governors = GOVERNORS.lock().expect("governors lock poisoned");
+        Arc::clone(
+            governors
+                .entry(endpoint.to_owned())
+                .or_insert_with(|| Arc::new(Self::new(endpoint, Config::load(), State::new()))),
+        )
+    }
+
+    /// The governor for telemetry sent to `endpoint`, kept apart so telemetry can't use up the
+    /// limits or trip the circuit of the requests a user is waiting on
+    pub fn for_telemetry(endpoint: &str) -> Arc<Self> {
+        Self::for_endpoint(&format!("{endpoint} (telemetry)"))
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The current status of the endpoint as seen by this process
+    pub fn status(&self) -> GovernorStatus {
+        self.lock().status(&self.endpoint, Instant::now())
+    }
+
+    /// Sends a request built by `f`, retrying it while the governor allows it
+    pub(crate) async fn send<T, E, F, Fut>(&self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+        E: std::error::Error + 'static,
+        Error: From<SdkError<E, HttpResponse>>,
+    {
+        let max_attempts = self.lock().config.max_attempts;
+        let mut attempt = 1;
+        loop {
+            let permit = self.acquire().await?;
+
+            let err = match f().await {
+                Ok(output) => {
+                    self.record(Outcome::Success, None);
+                    return Ok(output);
+                },
+                Err(err) => err,
+            };
+
+            let outcome = Outcome::classify(&err);
+            self.record(outcome, Some(fig_aws_common::SdkErrorDisplay(&err).to_string()));
+            drop(permit);
+
+            let retry_after = match outcome {
+                Outcome::Throttled { retry_after } | Outcome::Failed { retry_after } => retry_after,
+                Outcome::Success | Outcome::Rejected => return Err(err.into()),
+            };
+
+            if attempt >= max_attempts
+                || retry_after.is_some_and(|retry_after| retry_after > MAX_WAIT)
+                || !self.lock().take_retry()
+            {
+                return Err(err.into());
+            }
+
+            let delay = backoff(attempt, retry_after);
+            debug!(endpoint = self.endpoint, attempt, ?delay, ?outcome, "Retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Waits for a permit to send a request, fails fast if the wait would be too long
+    async fn acquire(&self) -> Result<Permit<'_>, Error> {
+        loop {
+            let wait = match self.lock().try_acquire(Instant::now()) {
+                Ok(probe) => {
+                    return Ok(Permit { governor: self, probe });
+                },
+                Err(wait) => wait,
+            };
+
+            if wait > MAX_WAIT {
+                return Err(Error::RateLimited {
+                    endpoint: self.endpoint.clone(),
+                    retry_after: wait,
+                });
+            }
+
+            debug!(endpoint = self.endpoint, ?wait, "Waiting for rate limit");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn record(&self, outcome: Outcome, error: Option<String>) {
+        let status = {
+            let mut inner = self.lock();
+            let now = Instant::now();
+            inner
+                .record(outcome, error, now)
+                .then(|| inner.status(&self.endpoint, now))
+        };
+
+        if let Some(status) = status {
+            if let Err(err) = save_status(&self.state, status) {
+                error!(%err, "Failed to save api governor status");
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("governor lock poisoned")
+    }
+}
+
+/// Held while a request is in flight, frees the probe of a half open circuit if the request is
+/// dropped before its outcome was recorded
+struct Permit<'a> {
+    governor: &'a Governor,
+    probe: bool,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.governor.lock().probe_in_flight = false;
+        }
+    }
+}
+
+/// Exponential backoff with full jitter, never shorter than `retry_after`
+fn backoff(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let max = BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(BACKOFF_MAX);
+    let jittered = max.mul_f64(rand::thread_rng().gen_range(0.0..=1.0));
+    jittered.max(retry_after.unwrap_or_default())
+}
+
+fn save_status(state: &State, status: GovernorStatus) -> Result<(), fig_settings::Error> {
+    let mut statuses: BTreeMap<String, GovernorStatus> = state.get(STATUS_STATE_KEY)?.unwrap_or_default();
+    statuses.insert(status.endpoint.clone(), status);
+    state.set_value(STATUS_STATE_KEY, serde_json::to_value(statuses)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use amzn_codewhisperer_client::operation::generate_completions::GenerateCompletionsError;
+    use aws_smithy_runtime_api::http::Response;
+    use aws_smithy_types::body::SdkBody;
+
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            requests_per_minute: 60,
+            burst: 2,
+            max_attempts: 3,
+        }
+    }
+
+    fn service_error(status: u16, retry_after: Option<&str>) -> SdkError<GenerateCompletionsError, HttpResponse> {
+        let mut response = Response::new(status.try_into().unwrap(), SdkBody::empty());
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert("retry-after", retry_after.to_owned());
+        }
+        SdkError::service_error(GenerateCompletionsError::unhandled("<unhandled>"), response)
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut inner = Inner::new(config(), now);
+
+        assert!(inner.try_acquire(now).is_ok());
+        assert!(inner.try_acquire(now).is_ok());
+        let wait = inner.try_acquire(now).unwrap_err();
+        assert!(wait <= Duration::from_secs(1), "{wait:?}");
+
+        assert!(inner.try_acquire(now + Duration::from_secs(1)).is_ok());
+        assert!(inner.try_acquire(now + Duration::from_secs(1)).is_err());
+
+        // refills up to the burst
+        let later = now + Duration::from_secs(60);
+        assert!(inner.try_acquire(later).is_ok());
+        assert!(inner.try_acquire(later).is_ok());
+        assert!(inner.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = Instant::now();
+        let mut inner = Inner::new(config(), now);
+
+        inner.record(
+            Outcome::Throttled {
+                retry_after: Some(Duration::from_secs(5)),
+            },
+            None,
+            now,
+        );
+        assert_eq!(inner.try_acquire(now), Err(Duration::from_secs(5)));
+        assert!(inner.try_acquire(now + Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let now = Instant::now();
+        let mut inner = Inner::new(Config { burst: 100, ..config() }, now);
+        let failed = Outcome::Failed { retry_after: None };
+
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            inner.record(failed, Some("boom".into()), now);
+            assert_eq!(inner.circuit, CircuitState::Closed);
+        }
+        assert!(inner.record(failed, Some("boom".into()), now));
+        assert_eq!(inner.circuit, CircuitState::Open);
+        assert_eq!(inner.try_acquire(now), Err(COOLDOWN_BASE));
+
+        let status = inner.status("https://example.com", now);
+        assert_eq!(status.circuit, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, FAILURE_THRESHOLD);
+        assert_eq!(status.last_error.as_deref(), Some("boom"));
+        assert!(status.is_limited());
+
+        // a single probe is allowed after the cooldown, failing it doubles the cooldown
+        let after_cooldown = now + COOLDOWN_BASE;
+        assert!(inner.try_acquire(after_cooldown).is_ok());
+        assert_eq!(inner.circuit, CircuitState::HalfOpen);
+        assert!(inner.try_acquire(after_cooldown).is_err());
+        inner.record(failed, None, after_cooldown);
+        assert_eq!(inner.circuit, CircuitState::Open);
+        assert_eq!(inner.try_acquire(after_cooldown), Err(COOLDOWN_BASE * 2));
+
+        // a successful probe closes the circuit
+        let after_cooldown = after_cooldown + COOLDOWN_BASE * 2;
+        assert!(inner.try_acquire(after_cooldown).is_ok());
+        assert!(inner.record(Outcome::Success, None, after_cooldown));
+        assert_eq!(inner.circuit, CircuitState::Closed);
+        assert_eq!(inner.consecutive_failures, 0);
+        assert!(!inner.status("https://example.com", after_cooldown).is_limited());
+    }
+
+    #[test]
+    fn test_expired_open_status() {
+        let now = OffsetDateTime::now_utc();
+        let status = GovernorStatus {
+            endpoint: "https://example.com".into(),
+            circuit: CircuitState::Open,
+            consecutive_failures: FAILURE_THRESHOLD,
+            retry_at: Some(now - time::Duration::seconds(1)),
+            last_error: None,
+            updated_at: now - time::Duration::minutes(1),
+        };
+        assert!(!status.is_limited());
+        assert!(status.is_unhealthy());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe() {
+        let governor = Governor::new(
+            "https://example.com",
+            Config { burst: 100, ..config() },
+            State::new_fake(),
+        );
+        {
+            let mut inner = governor.lock();
+            inner.circuit = CircuitState::Open;
+            inner.open_until = Some(Instant::now());
+        }
+
+        let permit = governor.acquire().await.unwrap();
+        assert!(permit.probe);
+        assert!(governor.lock().try_acquire(Instant::now()).is_err());
+
+        // e.g. the user cancelled the request, the next one becomes the probe
+        drop(permit);
+        assert_eq!(governor.lock().try_acquire(Instant::now()), Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_clears_status_saved_by_another_process() {
+        let state = State::new_fake();
+        let now = Instant::now();
+        let mut inner = Inner::new(config(), now);
+        for _ in 0..FAILURE_THRESHOLD {
+            inner.record(Outcome::Failed { retry_after: None }, None, now);
+        }
+        save_status(&state, inner.status("https://example.com", now)).unwrap();
+
+        let governor = Governor::new("https://example.com", config(), state);
+        governor.record(Outcome::Success, None);
+        let statuses = GovernorStatus::load_all(&governor.state);
+        assert_eq!(statuses[0].circuit, CircuitState::Closed);
+        assert!(!statuses[0].is_unhealthy());
+    }
+
+    #[test]
+    fn test_retry_budget() {
+        let mut inner = Inner::new(config(), Instant::now());
+        for _ in 0..RETRY_BUDGET as usize {
+            assert!(inner.take_retry());
+        }
+        assert!(!inner.take_retry());
+        for _ in 0..5 {
+            inner.record(Outcome::Success, None, Instant::now());
+        }
+        assert!(inner.take_retry());
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Outcome::classify(&service_error(429, None)), Outcome::Throttled {
+            retry_after: None
+        });
+        assert_eq!(Outcome::classify(&service_error(429, Some("3"))), Outcome::Throttled {
+            retry_after: Some(Duration::from_secs(3))
+        });
+        assert_eq!(Outcome::classify(&service_error(503, None)), Outcome::Failed {
+            retry_after: None
+        });
+        assert_eq!(Outcome::classify(&service_error(400, None)), Outcome::Rejected);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = time::macros::datetime!(2015-10-21 07:28:00 UTC);
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        for attempt in 1..10 {
+            assert!(backoff(attempt, None) <= BACKOFF_MAX);
+        }
+        assert!(backoff(1, Some(Duration::from_secs(2))) >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_save_status() {
+        let state = State::new_fake();
+        let now = Instant::now();
+        let mut inner = Inner::new(config(), now);
+        inner.record(
+            Outcome::Throttled {
+                retry_after: Some(Duration::from_secs(60)),
+            },
+            Some("slow down".into()),
+            now,
+        );
+        save_status(&state, inner.status("https://a.example.com", now)).unwrap();
+        save_status(&state, Inner::new(config(), now).status("https://b.example.com", now)).unwrap();
+
+        let statuses = GovernorStatus::load_all(&state);
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses[0].is_limited());
+        assert_eq!(statuses[0].last_error.as_deref(), Some("slow down"));
+        assert!(!statuses[1].is_limited());
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let governor = Governor::new(
+            "https://example.com",
+            Config { burst: 100, ..config() },
+            State::new_fake(),
+        );
+
+        let mut attempts = 0;
+        let res: Result<(), Error> = governor
+            .send(|| {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 2 {
+                        Err(service_error(500, Some("0")))
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(attempts, 2);
+        assert_eq!(
+            GovernorStatus::load_all(&governor.state)[0].circuit,
+            CircuitState::Closed
+        );
+    }
+}
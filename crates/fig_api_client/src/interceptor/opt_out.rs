@@ -1,8 +1,14 @@
+use amzn_codewhisperer_client::operation::send_telemetry_event::SendTelemetryEventInput;
+use amzn_codewhisperer_client::types::TelemetryEvent;
 use aws_smithy_runtime_api::box_error::BoxError;
 use aws_smithy_runtime_api::client::interceptors::Intercept;
-use aws_smithy_runtime_api::client::interceptors::context::BeforeTransmitInterceptorContextMut;
+use aws_smithy_runtime_api::client::interceptors::context::{
+    BeforeSerializationInterceptorContextRef,
+    BeforeTransmitInterceptorContextMut,
+};
 use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
 use aws_smithy_types::config_bag::ConfigBag;
+use fig_telemetry_core::TelemetryCategory;
 
 use crate::consts::{
     SHARE_CODEWHISPERER_CONTENT_SETTINGS_KEY,
@@ -13,9 +19,29 @@ fn is_codewhisperer_content_optout() -> bool {
     !fig_settings::settings::get_bool_or(SHARE_CODEWHISPERER_CONTENT_SETTINGS_KEY, true)
 }
 
+/// Whether the user allows sending telemetry events of `category`
+pub fn telemetry_category_enabled(category: TelemetryCategory) -> bool {
+    fig_settings::settings::get_bool_or(category.setting_key(), true)
+}
+
+/// The category a CodeWhisperer telemetry event can be opted out of with
+pub fn telemetry_event_category(event: &TelemetryEvent) -> TelemetryCategory {
+    match event {
+        TelemetryEvent::ChatAddMessageEvent(_)
+        | TelemetryEvent::ChatInteractWithMessageEvent(_)
+        | TelemetryEvent::ChatUserModificationEvent(_)
+        | TelemetryEvent::InlineChatEvent(_) => TelemetryCategory::ChatMetadata,
+        TelemetryEvent::CodeScanFailedEvent(_) | TelemetryEvent::MetricData(_) => TelemetryCategory::Diagnostics,
+        _ => TelemetryCategory::FeatureUsage,
+    }
+}
+
+/// Sets the content opt out header on every request and fails `SendTelemetryEvent` requests whose
+/// event category the user denied
 #[derive(Debug, Clone)]
 pub struct OptOutInterceptor {
     override_value: Option<bool>,
+    override_denied_categories: Option<Vec<TelemetryCategory>>,
     _inner: (),
 }
 
@@ -23,9 +49,17 @@ impl OptOutInterceptor {
     pub const fn new() -> Self {
         Self {
             override_value: None,
+            override_denied_categories: None,
             _inner: (),
         }
     }
+
+    fn category_enabled(&self, category: TelemetryCategory) -> bool {
+        match &self.override_denied_categories {
+            Some(denied) => !denied.contains(&category),
+            None => telemetry_category_enabled(category),
+        }
+    }
 }
 
 impl Intercept for OptOutInterceptor {
@@ -33,6 +67,24 @@ impl Intercept for OptOutInterceptor {
         "OptOutInterceptor"
     }
 
+    fn read_before_execution(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(event) = context
+            .input()
+            .downcast_ref::<SendTelemetryEventInput>()
+            .and_then(|input| input.telemetry_event())
+        {
+            let category = telemetry_event_category(event);
+            if !self.category_enabled(category) {
+                return Err(format!("telemetry category {category} is disabled").into());
+            }
+        }
+        Ok(())
+    }
+
     fn modify_before_signing(
         &self,
         context: &mut BeforeTransmitInterceptorContextMut<'_>,
@@ -50,6 +102,10 @@ impl Intercept for OptOutInterceptor {
 
 #[cfg(test)]
 mod tests {
+    use amzn_codewhisperer_client::types::{
+        ChatAddMessageEvent,
+        TerminalUserInteractionEvent,
+    };
     use amzn_consolas_client::config::RuntimeComponentsBuilder;
     use amzn_consolas_client::config::interceptors::InterceptorContext;
     use aws_smithy_runtime_api::client::interceptors::context::Input;
@@ -86,4 +142,40 @@ mod tests {
         let val = context.request().headers().get(X_AMZN_CODEWHISPERER_OPT_OUT_HEADER);
         assert_eq!(val, Some("true"));
     }
+
+    #[test]
+    fn test_denied_telemetry_category() {
+        let mut cfg = ConfigBag::base();
+        let interceptor = OptOutInterceptor {
+            override_denied_categories: Some(vec![TelemetryCategory::ChatMetadata]),
+            ..OptOutInterceptor::new()
+        };
+
+        let send = |event: TelemetryEvent, cfg: &mut ConfigBag| {
+            let input = SendTelemetryEventInput::builder()
+                .telemetry_event(event)
+                .build()
+                .unwrap();
+            let context = InterceptorContext::new(Input::erase(input));
+            interceptor.read_before_execution(&BeforeSerializationInterceptorContextRef::from(&context), cfg)
+        };
+
+        let chat = ChatAddMessageEvent::builder()
+            .conversation_id("conversation")
+            .message_id("message")
+            .build()
+            .unwrap();
+        assert!(send(TelemetryEvent::ChatAddMessageEvent(chat), &mut cfg).is_err());
+
+        let terminal = TerminalUserInteractionEvent::builder().build();
+        assert!(send(TelemetryEvent::TerminalUserInteractionEvent(terminal), &mut cfg).is_ok());
+
+        // Other operations are never blocked
+        let context = InterceptorContext::new(Input::erase(()));
+        assert!(
+            interceptor
+                .read_before_execution(&BeforeSerializationInterceptorContextRef::from(&context), &mut cfg)
+                .is_ok()
+        );
+    }
 }
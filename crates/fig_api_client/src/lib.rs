@@ -4,6 +4,7 @@ pub(crate) mod credentials;
 mod customization;
 mod endpoints;
 mod error;
+pub mod governor;
 pub(crate) mod interceptor;
 pub mod model;
 
@@ -14,3 +15,7 @@ pub use clients::{
 pub use customization::Customization;
 pub use endpoints::Endpoint;
 pub use error::Error;
+pub use interceptor::opt_out::{
+    telemetry_category_enabled,
+    telemetry_event_category,
+};
@@ -574,6 +574,8 @@ pub struct ShellHistoryEntry {
     pub command: String,
     pub directory: Option<String>,
     pub exit_code: Option<i32>,
+    /// The output of the command, the terminal does not separate stdout from stderr
+    pub stdout: Option<String>,
 }
 
 impl From<ShellHistoryEntry> for amzn_codewhisperer_streaming_client::types::ShellHistoryEntry {
@@ -582,6 +584,7 @@ impl From<ShellHistoryEntry> for amzn_codewhisperer_streaming_client::types::She
             .command(value.command)
             .set_directory(value.directory)
             .set_exit_code(value.exit_code)
+            .set_stdout(value.stdout)
             .build()
             .expect("Failed to build ShellHistoryEntry")
     }
@@ -593,6 +596,7 @@ impl From<ShellHistoryEntry> for amzn_qdeveloper_streaming_client::types::ShellH
             .command(value.command)
             .set_directory(value.directory)
             .set_exit_code(value.exit_code)
+            .set_stdout(value.stdout)
             .build()
             .expect("Failed to build ShellHistoryEntry")
     }
@@ -732,6 +736,7 @@ mod tests {
                         command: "test command".to_string(),
                         directory: Some("test directory".to_string()),
                         exit_code: Some(0),
+                        stdout: Some("test output".to_string()),
                     }]),
                 }),
                 env_state: Some(EnvState {
@@ -805,6 +810,7 @@ mod tests {
                 command: "test command".to_string(),
                 directory: Some("test directory".to_string()),
                 exit_code: Some(0),
+                stdout: Some("test output".to_string()),
             }]),
         };
 
@@ -825,6 +831,7 @@ mod tests {
             command: "test command".to_string(),
             directory: None,
             exit_code: None,
+            stdout: None,
         };
 
         let codewhisper_shell_history_entry =
@@ -42,7 +42,10 @@ use aws_smithy_runtime_api::client::identity::{
 use aws_smithy_types::error::display::DisplayErrorContext;
 use aws_types::region::Region;
 use aws_types::request_id::RequestId;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use fig_aws_common::app_name;
+use fig_settings::State;
 use fig_telemetry_core::{
     Event,
     EventType,
@@ -56,6 +59,11 @@ use tracing::{
 };
 
 use crate::consts::*;
+use crate::profile::{
+    Profile,
+    active_name,
+    active_scoped_key,
+};
 use crate::scope::is_scopes;
 use crate::secret_store::{
     Secret,
@@ -118,7 +126,7 @@ pub struct DeviceRegistration {
 }
 
 impl DeviceRegistration {
-    const SECRET_KEY: &'static str = "codewhisperer:odic:device-registration";
+    pub(crate) const SECRET_KEY: &'static str = "codewhisperer:odic:device-registration";
 
     pub fn from_output(
         output: RegisterClientOutput,
@@ -138,7 +146,7 @@ impl DeviceRegistration {
 
     /// Loads the OIDC registered client from the secret store, deleting it if it is expired.
     async fn load_from_secret_store(secret_store: &SecretStore, region: &Region) -> Result<Option<Self>> {
-        let device_registration = secret_store.get(Self::SECRET_KEY).await?;
+        let device_registration = secret_store.get(&active_scoped_key(Self::SECRET_KEY)).await?;
 
         if let Some(device_registration) = device_registration {
             // check that the data is not expired, assume it is invalid if not present
@@ -152,7 +160,7 @@ impl DeviceRegistration {
         }
 
         // delete the data if its expired or invalid
-        if let Err(err) = secret_store.delete(Self::SECRET_KEY).await {
+        if let Err(err) = secret_store.delete(&active_scoped_key(Self::SECRET_KEY)).await {
             error!(?err, "Failed to delete device registration from keychain");
         }
 
@@ -205,7 +213,7 @@ impl DeviceRegistration {
     /// Saves to the passed secret store.
     pub async fn save(&self, secret_store: &SecretStore) -> Result<()> {
         secret_store
-            .set(Self::SECRET_KEY, &serde_json::to_string(&self)?)
+            .set(&active_scoped_key(Self::SECRET_KEY), &serde_json::to_string(&self)?)
             .await?;
         Ok(())
     }
@@ -283,7 +291,7 @@ pub struct BuilderIdToken {
 }
 
 impl BuilderIdToken {
-    const SECRET_KEY: &'static str = "codewhisperer:odic:token";
+    pub(crate) const SECRET_KEY: &'static str = "codewhisperer:odic:token";
 
     #[cfg(test)]
     fn test() -> Self {
@@ -300,7 +308,7 @@ impl BuilderIdToken {
 
     /// Load the token from the keychain, refresh the token if it is expired and return it
     pub async fn load(secret_store: &SecretStore, force_refresh: bool) -> Result<Option<Self>> {
-        match secret_store.get(Self::SECRET_KEY).await {
+        match secret_store.get(&active_scoped_key(Self::SECRET_KEY)).await {
             Ok(Some(secret)) => {
                 let token: Option<Self> = serde_json::from_str(&secret.0)?;
                 match token {
@@ -435,14 +443,14 @@ impl BuilderIdToken {
     /// Save the token to the keychain
     pub async fn save(&self, secret_store: &SecretStore) -> Result<()> {
         secret_store
-            .set(Self::SECRET_KEY, &serde_json::to_string(self)?)
+            .set(&active_scoped_key(Self::SECRET_KEY), &serde_json::to_string(self)?)
             .await?;
         Ok(())
     }
 
     /// Delete the token from the keychain
     pub async fn delete(&self, secret_store: &SecretStore) -> Result<()> {
-        secret_store.delete(Self::SECRET_KEY).await?;
+        secret_store.delete(&active_scoped_key(Self::SECRET_KEY)).await?;
         Ok(())
     }
 
@@ -479,6 +487,67 @@ impl BuilderIdToken {
     }
 }
 
+/// A token and the client registration needed to refresh it
+///
+/// Used to move a login from a machine with a browser to a headless one with
+/// `q user export-token` and `q user import-token`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TokenExport {
+    pub token: BuilderIdToken,
+    pub registration: DeviceRegistration,
+}
+
+impl TokenExport {
+    const PREFIX: &'static str = "qtoken1:";
+
+    /// Loads the token and registration of the active profile, refreshing the token if it is
+    /// expired
+    pub async fn load(secret_store: &SecretStore) -> Result<Option<Self>> {
+        let Some(token) = BuilderIdToken::load(secret_store, false).await? else {
+            return Ok(None);
+        };
+
+        let region = token.region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
+        let Some(registration) = DeviceRegistration::load_from_secret_store(secret_store, &region).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self { token, registration }))
+    }
+
+    /// Saves the token and registration to the active profile
+    pub async fn save(&self, secret_store: &SecretStore) -> Result<()> {
+        if self.registration.oauth_flow != self.token.oauth_flow {
+            return Err(Error::InvalidTokenExport(
+                "registration and token are for different oauth flows".into(),
+            ));
+        }
+
+        self.registration.save(secret_store).await?;
+        self.token.save(secret_store).await
+    }
+
+    /// Encodes the export as a single line of text that is easy to copy between terminals
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!(
+            "{}{}",
+            Self::PREFIX,
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?)
+        ))
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let encoded = encoded
+            .trim()
+            .strip_prefix(Self::PREFIX)
+            .ok_or_else(|| Error::InvalidTokenExport("missing prefix".into()))?;
+        let json = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|err| Error::InvalidTokenExport(err.to_string()))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
 pub enum PollCreateToken {
     Pending,
     Complete(BuilderIdToken),
@@ -555,14 +624,20 @@ pub async fn is_logged_in() -> bool {
 }
 
 pub async fn logout() -> Result<()> {
+    logout_profile(&active_name(&State::new())).await
+}
+
+/// Logs out of the profile with `name`, which doesn't have to be the active profile
+pub async fn logout_profile(name: &str) -> Result<()> {
     let Ok(secret_store) = SecretStore::new().await else {
         return Ok(());
     };
 
-    let (builder_res, device_res) = tokio::join!(
-        secret_store.delete(BuilderIdToken::SECRET_KEY),
-        secret_store.delete(DeviceRegistration::SECRET_KEY),
-    );
+    let profile = Profile::new(name);
+    let token_key = profile.scoped_key(BuilderIdToken::SECRET_KEY);
+    let registration_key = profile.scoped_key(DeviceRegistration::SECRET_KEY);
+    let (builder_res, device_res) =
+        tokio::join!(secret_store.delete(&token_key), secret_store.delete(&registration_key),);
 
     builder_res?;
     device_res?;
@@ -652,6 +727,40 @@ mod tests {
         assert!(token.is_amzn_user());
     }
 
+    #[test]
+    fn test_token_export_encode_decode() {
+        let export = TokenExport {
+            token: BuilderIdToken::test(),
+            registration: DeviceRegistration {
+                client_id: "test_client_id".into(),
+                client_secret: Secret("test_client_secret".into()),
+                client_secret_expires_at: None,
+                region: OIDC_BUILDER_ID_REGION.to_string(),
+                oauth_flow: OAuthFlow::DeviceCode,
+                scopes: None,
+            },
+        };
+
+        let encoded = export.encode().unwrap();
+        assert!(encoded.starts_with(TokenExport::PREFIX));
+        assert!(!encoded.contains(char::is_whitespace));
+
+        let decoded = TokenExport::decode(&format!("  {encoded}\n")).unwrap();
+        assert_eq!(decoded.token.access_token, export.token.access_token);
+        assert_eq!(decoded.token.refresh_token, export.token.refresh_token);
+        assert_eq!(decoded.registration.client_id, export.registration.client_id);
+        assert_eq!(decoded.registration.client_secret, export.registration.client_secret);
+
+        assert!(matches!(
+            TokenExport::decode("not a token"),
+            Err(Error::InvalidTokenExport(_))
+        ));
+        assert!(matches!(
+            TokenExport::decode("qtoken1:!!!"),
+            Err(Error::InvalidTokenExport(_))
+        ));
+    }
+
     #[ignore = "not in ci"]
     #[tokio::test]
     async fn logout_test() {
@@ -47,6 +47,23 @@ pub enum Error {
     OAuthMissingCode,
     #[error("OAuth error: {0}")]
     OAuthCustomError(String),
+    #[error("Invalid exported token: {0}")]
+    InvalidTokenExport(String),
+    #[error("Profile not found: {0}")]
+    ProfileNotFound(String),
+    #[error("Profile already exists: {0}")]
+    ProfileAlreadyExists(String),
+    #[error("Invalid profile name: {0}, only letters, numbers, '-' and '_' are allowed")]
+    ProfileInvalidName(String),
+    #[error("The default profile can not be removed")]
+    ProfileDefaultRemove,
+    #[error("Profile override already set to {0}")]
+    ProfileOverrideAlreadySet(String),
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    SecretService(#[from] dbus::CrateError),
 }
 
 impl Error {
@@ -2,6 +2,7 @@ pub mod builder_id;
 mod consts;
 mod error;
 pub mod pkce;
+pub mod profile;
 mod scope;
 pub mod secret_store;
 
@@ -10,6 +11,7 @@ pub use builder_id::{
     is_amzn_user,
     is_logged_in,
     logout,
+    logout_profile,
     refresh_token,
 };
 pub use consts::{
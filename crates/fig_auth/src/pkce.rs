@@ -12,6 +12,9 @@
 //!      - Code: [PkceRegistration::register]
 //!   2. Host a local HTTP server to handle the redirect
 //!      - Code: [PkceRegistration::finish]
+//!      - If the browser is on another machine the redirect can't reach the server, instead the
+//!        user pastes the redirect URL into the terminal:
+//!        [PkceRegistration::finish_with_callback_url]
 //!   3. Open the [PkceRegistration::url] in the browser, and approve the request.
 //!   4. Exchange the code for access and refresh tokens.
 //!      - This completes the future returned by [PkceRegistration::finish].
@@ -41,6 +44,7 @@ use hyper::{
 use hyper_util::rt::TokioIo;
 use percent_encoding::{
     NON_ALPHANUMERIC,
+    percent_decode_str,
     utf8_percent_encode,
 };
 use rand::Rng;
@@ -226,7 +230,7 @@ impl PkceRegistration {
     /// Only the first connection will be served.
     pub async fn finish<C: PkceClient>(self, client: &C, secret_store: Option<&SecretStore>) -> Result<()> {
         let code = tokio::select! {
-            code = Self::recv_code(self.listener, self.state) => {
+            code = Self::recv_code(&self.listener, &self.state) => {
                 code?
             },
             _ = tokio::time::sleep(self.timeout) => {
@@ -234,6 +238,36 @@ impl PkceRegistration {
             }
         };
 
+        self.exchange_code(client, code, secret_store).await
+    }
+
+    /// Finishes the flow with the URL the browser was redirected to instead of hosting a local
+    /// HTTP server. This is used when the browser is on another machine, in which case the
+    /// redirect fails and the user copies the URL from the browser's address bar.
+    pub async fn finish_with_callback_url<C: PkceClient>(
+        self,
+        client: &C,
+        callback_url: &str,
+        secret_store: Option<&SecretStore>,
+    ) -> Result<()> {
+        let (code, state) = parse_callback_url(callback_url)?;
+        if state != self.state {
+            return Err(Error::OAuthStateMismatch {
+                actual: state,
+                expected: self.state,
+            });
+        }
+
+        self.exchange_code(client, code, secret_store).await
+    }
+
+    /// Exchanges the authorization code for an access token.
+    async fn exchange_code<C: PkceClient>(
+        self,
+        client: &C,
+        code: String,
+        secret_store: Option<&SecretStore>,
+    ) -> Result<()> {
         let response = client
             .create_token(CreateTokenArgs {
                 client_id: self.registered_client.client_id().to_string(),
@@ -277,7 +311,7 @@ impl PkceRegistration {
         Ok(())
     }
 
-    async fn recv_code(listener: TcpListener, expected_state: String) -> Result<String> {
+    async fn recv_code(listener: &TcpListener, expected_state: &str) -> Result<String> {
         let (code_tx, mut code_rx) = tokio::sync::mpsc::channel::<Result<(String, String)>>(1);
         let (stream, _) = listener.accept().await?;
         let stream = TokioIo::new(stream); // Wrapper to implement Hyper IO traits for Tokio types.
@@ -299,7 +333,7 @@ impl PkceRegistration {
                 if state != expected_state {
                     return Err(Error::OAuthStateMismatch {
                         actual: state,
-                        expected: expected_state,
+                        expected: expected_state.to_owned(),
                     });
                 }
                 // Give time for the user to be redirected to index.html.
@@ -451,6 +485,37 @@ impl PkceQueryParams {
     }
 }
 
+/// Parses the code and state from the URL the browser was redirected to, e.g.
+/// `http://127.0.0.1:49153/oauth/callback?code=...&state=...`
+fn parse_callback_url(callback_url: &str) -> Result<(String, String)> {
+    let query = callback_url
+        .trim()
+        .split_once('?')
+        .map(|(_, query)| query.split_once('#').map_or(query, |(query, _)| query))
+        .ok_or(Error::OAuthInvalidQueryParams("query parameters are missing".into()))?;
+
+    let query_params = query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k, percent_decode_str(v).decode_utf8_lossy().into_owned()))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    if let Some(error) = query_params.get("error") {
+        let error_description = query_params.get("error_description").map_or("", |s| s.as_str());
+        return Err(Error::OAuthCustomError(format!(
+            "error occurred during authorization: {:?}, {:?}",
+            error, error_description
+        )));
+    }
+
+    match (query_params.get("code"), query_params.get("state")) {
+        (Some(code), Some(state)) => Ok((code.clone(), state.clone())),
+        _ => Err(Error::OAuthInvalidQueryParams(
+            "missing code and/or state in the query parameters".into(),
+        )),
+    }
+}
+
 /// Generates a random 43-octet URL safe string according to the RFC recommendation.
 ///
 /// Reference: https://datatracker.ietf.org/doc/html/rfc7636#section-4.1
@@ -610,6 +675,57 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_pkce_flow_with_callback_url() {
+        let region = Region::new("us-east-1");
+        let issuer_url = START_URL.into();
+        let client = TestPkceClient {};
+
+        let registration = PkceRegistration::register(&client, region.clone(), issuer_url, None)
+            .await
+            .unwrap();
+        let callback_url = format!("{}?code=code&state={}", registration.redirect_uri, registration.state);
+        registration
+            .finish_with_callback_url(&client, &callback_url, None)
+            .await
+            .unwrap();
+
+        let registration = PkceRegistration::register(&client, region, START_URL.into(), None)
+            .await
+            .unwrap();
+        let callback_url = format!("{}?code=code&state=not_my_state", registration.redirect_uri);
+        assert!(matches!(
+            registration
+                .finish_with_callback_url(&client, &callback_url, None)
+                .await,
+            Err(Error::OAuthStateMismatch { actual: _, expected: _ })
+        ));
+    }
+
+    #[test]
+    fn test_parse_callback_url() {
+        assert_eq!(
+            parse_callback_url(" http://127.0.0.1:1234/oauth/callback?code=a%2Fb&state=xyz\n").unwrap(),
+            ("a/b".into(), "xyz".into())
+        );
+        assert_eq!(
+            parse_callback_url("http://127.0.0.1:1234/oauth/callback?state=xyz&code=abc#fragment").unwrap(),
+            ("abc".into(), "xyz".into())
+        );
+        assert!(matches!(
+            parse_callback_url("http://127.0.0.1:1234/oauth/callback"),
+            Err(Error::OAuthInvalidQueryParams(_))
+        ));
+        assert!(matches!(
+            parse_callback_url("http://127.0.0.1:1234/oauth/callback?code=abc"),
+            Err(Error::OAuthInvalidQueryParams(_))
+        ));
+        assert!(matches!(
+            parse_callback_url("http://127.0.0.1:1234/oauth/callback?error=access_denied&error_description=denied"),
+            Err(Error::OAuthCustomError(_))
+        ));
+    }
+
     #[tokio::test]
     async fn verify_gen_code_challenge() {
         let code_verifier = generate_code_verifier();
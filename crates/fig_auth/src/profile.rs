@@ -0,0 +1,348 @@
+//! # Profiles
+//!
+//! Named auth profiles allow a user to be logged in with multiple accounts (e.g. Builder ID and
+//! an IAM Identity Center start URL) at the same time and switch between them without logging
+//! out.
+//!
+//! Every profile has its own token and client registration in the secret store and its own
+//! selected customization. The `default` profile uses the same secret store keys as before
+//! profiles existed so existing logins keep working.
+//!
+//! The active profile is resolved in order from:
+//!   1. The per-invocation override set with [set_override] (e.g. `q --profile work chat`)
+//!   2. The [PROFILE_ENV_VAR] environment variable
+//!   3. The profile selected with `q user profile use`
+//!   4. The [DEFAULT_PROFILE]
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use fig_settings::State;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::builder_id::{
+    BuilderIdToken,
+    DeviceRegistration,
+};
+use crate::secret_store::SecretStore;
+use crate::{
+    Error,
+    Result,
+};
+
+/// The name of the profile that always exists and uses the legacy secret store keys
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Environment variable that selects the active profile for a process and its children
+pub const PROFILE_ENV_VAR: &str = "Q_PROFILE";
+
+const PROFILES_STATE_KEY: &str = "auth.profiles";
+const ACTIVE_PROFILE_STATE_KEY: &str = "auth.profile";
+
+static PROFILE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// The APIs a profile can override the endpoint of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileService {
+    /// Overrides the `api.codewhisperer.service` setting
+    Codewhisperer,
+    /// Overrides the `api.q.service` setting
+    Q,
+}
+
+/// An API endpoint override for a profile, takes precedence over the `api.*.service` settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileEndpoint {
+    pub url: String,
+    pub region: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    /// The IAM Identity Center start URL, `None` for Builder ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_url: Option<String>,
+    /// The IAM Identity Center region, `None` for Builder ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Endpoint overrides, each service is a separate API so they are set separately
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub endpoints: BTreeMap<ProfileService, ProfileEndpoint>,
+}
+
+impl Profile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            start_url: None,
+            region: None,
+            endpoints: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// Scopes a secret store key or state key to this profile
+    pub fn scoped_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        scoped_key(&self.name, key)
+    }
+
+    /// Loads every profile, the [DEFAULT_PROFILE] is always first
+    pub fn list(state: &State) -> Result<Vec<Self>> {
+        let mut profiles = load_profiles(state)?;
+        let default = profiles
+            .remove(DEFAULT_PROFILE)
+            .unwrap_or_else(|| Self::new(DEFAULT_PROFILE));
+        Ok(std::iter::once(default).chain(profiles.into_values()).collect())
+    }
+
+    /// Loads the profile with `name`
+    pub fn load(state: &State, name: &str) -> Result<Option<Self>> {
+        Ok(Self::list(state)?.into_iter().find(|profile| profile.name == name))
+    }
+
+    /// The endpoint override for `service`, if the profile has one
+    pub fn endpoint(&self, service: ProfileService) -> Option<&ProfileEndpoint> {
+        self.endpoints.get(&service)
+    }
+
+    /// Loads the active profile, fails if it has not been added
+    pub fn load_active(state: &State) -> Result<Self> {
+        let name = active_name(state);
+        match Self::load(state, &name)? {
+            Some(profile) => Ok(profile),
+            None => Err(Error::ProfileNotFound(name)),
+        }
+    }
+
+    /// Adds a new profile, fails if a profile with the same name exists
+    pub fn add(&self, state: &State) -> Result<()> {
+        validate_name(&self.name)?;
+
+        let mut profiles = load_profiles(state)?;
+        if self.is_default() || profiles.contains_key(&self.name) {
+            return Err(Error::ProfileAlreadyExists(self.name.clone()));
+        }
+
+        profiles.insert(self.name.clone(), self.clone());
+        save_profiles(state, &profiles)
+    }
+
+    /// Saves changes to an existing profile, or adds it if it does not exist
+    pub fn save(&self, state: &State) -> Result<()> {
+        validate_name(&self.name)?;
+
+        let mut profiles = load_profiles(state)?;
+        profiles.insert(self.name.clone(), self.clone());
+        save_profiles(state, &profiles)
+    }
+
+    /// Sets the profile used when there is no per-invocation override
+    pub fn select(state: &State, name: &str) -> Result<()> {
+        if Self::load(state, name)?.is_none() {
+            return Err(Error::ProfileNotFound(name.into()));
+        }
+
+        if name == DEFAULT_PROFILE {
+            state.remove_value(ACTIVE_PROFILE_STATE_KEY)?;
+        } else {
+            state.set_value(ACTIVE_PROFILE_STATE_KEY, name)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the profile with `name` and the secrets stored for it, the [DEFAULT_PROFILE] can not
+    /// be removed
+    pub async fn remove(state: &State, secret_store: &SecretStore, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE {
+            return Err(Error::ProfileDefaultRemove);
+        }
+
+        let mut profiles = load_profiles(state)?;
+        if profiles.remove(name).is_none() {
+            return Err(Error::ProfileNotFound(name.into()));
+        }
+
+        let token_key = scoped_key(name, BuilderIdToken::SECRET_KEY);
+        let registration_key = scoped_key(name, DeviceRegistration::SECRET_KEY);
+        let (token_res, registration_res) =
+            tokio::join!(secret_store.delete(&token_key), secret_store.delete(&registration_key),);
+        token_res?;
+        registration_res?;
+
+        save_profiles(state, &profiles)?;
+
+        if state.get_string(ACTIVE_PROFILE_STATE_KEY)?.as_deref() == Some(name) {
+            state.remove_value(ACTIVE_PROFILE_STATE_KEY)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Overrides the active profile for the rest of the process, this can only be set once
+pub fn set_override(name: impl Into<String>) -> Result<()> {
+    let name = name.into();
+    validate_name(&name)?;
+    PROFILE_OVERRIDE.set(name).map_err(Error::ProfileOverrideAlreadySet)
+}
+
+/// Fails if the profile picked with [set_override] or [PROFILE_ENV_VAR] has not been added, so a
+/// typo doesn't silently fall back to another account
+pub fn validate_active(state: &State) -> Result<()> {
+    let explicit = PROFILE_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| std::env::var(PROFILE_ENV_VAR).ok().filter(|name| !name.is_empty()));
+    validate_exists(state, explicit.as_deref())
+}
+
+fn validate_exists(state: &State, name: Option<&str>) -> Result<()> {
+    match name {
+        Some(name) if Profile::load(state, name)?.is_none() => Err(Error::ProfileNotFound(name.into())),
+        _ => Ok(()),
+    }
+}
+
+/// The name of the active profile, see the [module docs](self) for how it is resolved
+pub fn active_name(state: &State) -> String {
+    if let Some(name) = PROFILE_OVERRIDE.get() {
+        return name.clone();
+    }
+
+    if let Ok(name) = std::env::var(PROFILE_ENV_VAR) {
+        if !name.is_empty() {
+            return name;
+        }
+    }
+
+    state
+        .get_string(ACTIVE_PROFILE_STATE_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_PROFILE.into())
+}
+
+/// Scopes `key` to the active profile, keys for the [DEFAULT_PROFILE] are unchanged
+pub fn active_scoped_key(key: &str) -> Cow<'_, str> {
+    scoped_key(&active_name(&State::new()), key)
+}
+
+fn scoped_key<'a>(name: &str, key: &'a str) -> Cow<'a, str> {
+    if name == DEFAULT_PROFILE {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(format!("{key}:{name}"))
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid =
+        !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::ProfileInvalidName(name.into()))
+    }
+}
+
+fn load_profiles(state: &State) -> Result<BTreeMap<String, Profile>> {
+    Ok(state.get(PROFILES_STATE_KEY)?.unwrap_or_default())
+}
+
+fn save_profiles(state: &State, profiles: &BTreeMap<String, Profile>) -> Result<()> {
+    Ok(state.set_value(PROFILES_STATE_KEY, serde_json::to_value(profiles)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_key() {
+        assert_eq!(
+            scoped_key(DEFAULT_PROFILE, "codewhisperer:odic:token"),
+            "codewhisperer:odic:token"
+        );
+        assert_eq!(
+            scoped_key("work", "codewhisperer:odic:token"),
+            "codewhisperer:odic:token:work"
+        );
+        assert_eq!(Profile::new("work").scoped_key("key"), "key:work");
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("my_work-2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("with space").is_err());
+        assert!(validate_name("a:b").is_err());
+        assert!(validate_name(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_add_list_select() {
+        let state = State::new_fake();
+
+        assert_eq!(Profile::list(&state).unwrap(), vec![Profile::new(DEFAULT_PROFILE)]);
+        assert_eq!(active_name(&state), DEFAULT_PROFILE);
+
+        let work = Profile {
+            name: "work".into(),
+            start_url: Some("https://example.awsapps.com/start".into()),
+            region: Some("us-west-2".into()),
+            endpoints: BTreeMap::from([(ProfileService::Q, ProfileEndpoint {
+                url: "https://q.example.com".into(),
+                region: "us-west-2".into(),
+            })]),
+        };
+        work.add(&state).unwrap();
+        assert!(matches!(work.add(&state), Err(Error::ProfileAlreadyExists(_))));
+        assert!(matches!(
+            Profile::new(DEFAULT_PROFILE).add(&state),
+            Err(Error::ProfileAlreadyExists(_))
+        ));
+
+        assert_eq!(Profile::list(&state).unwrap(), vec![
+            Profile::new(DEFAULT_PROFILE),
+            work.clone()
+        ]);
+
+        let loaded = Profile::load(&state, "work").unwrap().unwrap();
+        assert_eq!(loaded.endpoint(ProfileService::Q).unwrap().url, "https://q.example.com");
+        assert_eq!(loaded.endpoint(ProfileService::Codewhisperer), None);
+
+        Profile::select(&state, "work").unwrap();
+        assert_eq!(
+            state.get_string(ACTIVE_PROFILE_STATE_KEY).unwrap().as_deref(),
+            Some("work")
+        );
+        assert!(matches!(
+            Profile::select(&state, "missing"),
+            Err(Error::ProfileNotFound(_))
+        ));
+
+        Profile::select(&state, DEFAULT_PROFILE).unwrap();
+        assert_eq!(state.get_string(ACTIVE_PROFILE_STATE_KEY).unwrap(), None);
+
+        assert!(validate_exists(&state, None).is_ok());
+        assert!(validate_exists(&state, Some(DEFAULT_PROFILE)).is_ok());
+        assert!(validate_exists(&state, Some("work")).is_ok());
+        assert!(matches!(
+            validate_exists(&state, Some("wrok")),
+            Err(Error::ProfileNotFound(_))
+        ));
+    }
+}
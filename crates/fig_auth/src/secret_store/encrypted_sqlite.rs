@@ -0,0 +1,239 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use fig_settings::sqlite::{
+    Db,
+    database,
+};
+use fig_util::directories::fig_data_dir;
+use ring::aead::{
+    AES_256_GCM,
+    Aad,
+    LessSafeKey,
+    NONCE_LEN,
+    Nonce,
+    UnboundKey,
+};
+use ring::hkdf::{
+    HKDF_SHA256,
+    Salt,
+};
+use ring::rand::{
+    SecureRandom,
+    SystemRandom,
+};
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::Secret;
+use crate::{
+    Error,
+    Result,
+};
+
+/// Prefix of values encrypted by this store, values without it are legacy plaintext values
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+const KEYFILE_NAME: &str = "secret_store.key";
+const KEYFILE_LEN: usize = 32;
+
+const HKDF_SALT: &[u8] = b"q-cli secret store";
+const HKDF_INFO: &[u8] = b"auth_kv aes-256-gcm v1";
+
+/// A secret store that keeps values in the `auth_kv` table encrypted with AES-256-GCM
+///
+/// The key is derived from a random keyfile in the data directory that is only readable by the
+/// current user. Plaintext values written by
+/// [`SqliteSecretStore`](super::sqlite::SqliteSecretStore) are transparently re-encrypted the first
+/// time they are read.
+pub struct EncryptedSqliteSecretStore {
+    db: &'static Db,
+    key: LessSafeKey,
+}
+
+impl EncryptedSqliteSecretStore {
+    pub async fn new() -> Result<Self> {
+        let keyfile = Self::keyfile_path()?;
+        let key = tokio::task::spawn_blocking(move || load_or_create_keyfile(&keyfile))
+            .await
+            .map_err(|err| Error::Crypto(err.to_string()))??;
+
+        Ok(Self {
+            db: database()?,
+            key: derive_key(&key)?,
+        })
+    }
+
+    /// A store backed by an in-memory database with a fixed key
+    #[cfg(test)]
+    pub(crate) fn mock() -> Self {
+        let db = Db::mock();
+        db.migrate().expect("Failed to migrate database");
+        Self {
+            db: Box::leak(Box::new(db)),
+            key: derive_key(&[7; KEYFILE_LEN]).unwrap(),
+        }
+    }
+
+    fn keyfile_path() -> Result<PathBuf> {
+        Ok(fig_data_dir()?.join(KEYFILE_NAME))
+    }
+
+    pub async fn set(&self, key: &str, password: &str) -> Result<()> {
+        let value = encrypt(&self.key, key, password)?;
+        Ok(self.db.set_auth_value(key, value)?)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Secret>> {
+        let Some(value) = self.db.get_auth_value(key)? else {
+            return Ok(None);
+        };
+
+        match value.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(encrypted) => Ok(Some(decrypt(&self.key, key, encrypted)?.into())),
+            None => {
+                debug!(key, "encrypting legacy plaintext secret");
+                if let Err(err) = self.set(key, &value).await {
+                    warn!(?err, key, "failed to encrypt legacy plaintext secret");
+                }
+                Ok(Some(value.into()))
+            },
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        Ok(self.db.unset_auth_value(key)?)
+    }
+}
+
+fn load_or_create_keyfile(path: &Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(key) if key.len() == KEYFILE_LEN => return Ok(key),
+        Ok(_) => return Err(Error::Crypto(format!("keyfile {} is corrupt", path.display()))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+        Err(err) => return Err(err.into()),
+    }
+
+    let mut key = vec![0; KEYFILE_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_err| Error::Crypto("failed to generate keyfile".into()))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(&key)?;
+            file.sync_all()?;
+            Ok(key)
+        },
+        // Another process created the keyfile first, use theirs
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(std::fs::read(path)?),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn derive_key(keyfile: &[u8]) -> Result<LessSafeKey> {
+    let prk = Salt::new(HKDF_SHA256, HKDF_SALT).extract(keyfile);
+    let okm = prk
+        .expand(&[HKDF_INFO], &AES_256_GCM)
+        .map_err(|_err| Error::Crypto("failed to derive key".into()))?;
+    Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+/// Encrypts `plaintext`, the `key` is used as associated data so values can't be swapped between
+/// keys
+fn encrypt(cipher: &LessSafeKey, key: &str, plaintext: &str) -> Result<String> {
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_err| Error::Crypto("failed to generate nonce".into()))?;
+
+    let mut in_out = plaintext.as_bytes().to_vec();
+    cipher
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(key), &mut in_out)
+        .map_err(|_err| Error::Crypto("failed to encrypt secret".into()))?;
+
+    let mut data = nonce.to_vec();
+    data.extend(in_out);
+    Ok(format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(data)))
+}
+
+fn decrypt(cipher: &LessSafeKey, key: &str, encrypted: &str) -> Result<String> {
+    let data = STANDARD
+        .decode(encrypted)
+        .map_err(|err| Error::Crypto(err.to_string()))?;
+    if data.len() < NONCE_LEN {
+        return Err(Error::Crypto("encrypted secret is truncated".into()));
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_err| Error::Crypto("invalid nonce".into()))?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = cipher
+        .open_in_place(nonce, Aad::from(key), &mut in_out)
+        .map_err(|_err| Error::Crypto("failed to decrypt secret".into()))?;
+
+    Ok(String::from_utf8(plaintext.to_vec())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let cipher = derive_key(&[7; KEYFILE_LEN]).unwrap();
+
+        let encrypted = encrypt(&cipher, "key", "password").unwrap();
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert!(!encrypted.contains("password"));
+
+        let encrypted = encrypted.strip_prefix(ENCRYPTED_PREFIX).unwrap();
+        assert_eq!(decrypt(&cipher, "key", encrypted).unwrap(), "password");
+
+        // the value is bound to the key it was stored under
+        assert!(decrypt(&cipher, "other_key", encrypted).is_err());
+
+        // and to the keyfile
+        let other_cipher = derive_key(&[8; KEYFILE_LEN]).unwrap();
+        assert!(decrypt(&other_cipher, "key", encrypted).is_err());
+    }
+
+    #[test]
+    fn test_keyfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYFILE_NAME);
+
+        let key = load_or_create_keyfile(&path).unwrap();
+        assert_eq!(key.len(), KEYFILE_LEN);
+        assert_eq!(load_or_create_keyfile(&path).unwrap(), key);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::write(&path, b"short").unwrap();
+        assert!(load_or_create_keyfile(&path).is_err());
+    }
+}
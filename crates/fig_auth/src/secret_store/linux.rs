@@ -1,27 +1,198 @@
+use std::collections::HashMap;
+
+use dbus::CrateError;
+use dbus::secret_service::SecretService;
+use tracing::{
+    debug,
+    warn,
+};
+
 use super::Secret;
-use super::sqlite::SqliteSecretStore;
+use super::encrypted_sqlite::EncryptedSqliteSecretStore;
 use crate::Result;
 
-pub struct SecretStoreImpl {
-    inner: SqliteSecretStore,
+/// The `service` attribute set on every item we store in the secret service
+const SERVICE_ATTRIBUTE: &str = "q-cli";
+
+/// Label shown for the items in tools like Seahorse
+const LABEL: &str = "Amazon Q Developer for command line";
+
+/// The parts of the secret service the store uses, so tests can stand in for a locked keyring
+pub(super) trait Keyring {
+    async fn get(&self, attributes: &HashMap<&str, &str>) -> Result<Option<Vec<u8>>, CrateError>;
+    async fn set(&self, label: &str, attributes: &HashMap<&str, &str>, secret: &[u8]) -> Result<(), CrateError>;
+    async fn delete(&self, attributes: &HashMap<&str, &str>) -> Result<(), CrateError>;
+}
+
+impl Keyring for SecretService {
+    async fn get(&self, attributes: &HashMap<&str, &str>) -> Result<Option<Vec<u8>>, CrateError> {
+        SecretService::get(self, attributes).await
+    }
+
+    async fn set(&self, label: &str, attributes: &HashMap<&str, &str>, secret: &[u8]) -> Result<(), CrateError> {
+        SecretService::set(self, label, attributes, secret).await
+    }
+
+    async fn delete(&self, attributes: &HashMap<&str, &str>) -> Result<(), CrateError> {
+        SecretService::delete(self, attributes).await
+    }
+}
+
+pub struct SecretStoreImpl<K = SecretService> {
+    backend: Backend<K>,
+}
+
+enum Backend<K> {
+    /// The `org.freedesktop.secrets` service on the session bus
+    SecretService {
+        service: K,
+        /// Holds secrets written before the secret service was available or while it was failing,
+        /// anything in it is newer than the secret service's copy
+        fallback: EncryptedSqliteSecretStore,
+    },
+    /// Used when no secret service is running, e.g. headless machines
+    EncryptedSqlite(EncryptedSqliteSecretStore),
 }
 
 impl SecretStoreImpl {
     pub async fn new() -> Result<Self> {
-        Ok(Self {
-            inner: SqliteSecretStore::new().await?,
-        })
+        let fallback = EncryptedSqliteSecretStore::new().await?;
+
+        let backend = match SecretService::connect().await {
+            Ok(service) => Backend::SecretService { service, fallback },
+            Err(err) => {
+                debug!(%err, "secret service unavailable, using encrypted sqlite store");
+                Backend::EncryptedSqlite(fallback)
+            },
+        };
+
+        Ok(Self { backend })
     }
+}
 
+impl<K: Keyring> SecretStoreImpl<K> {
     pub async fn set(&self, key: &str, password: &str) -> Result<()> {
-        self.inner.set(key, password).await
+        match &self.backend {
+            Backend::SecretService { service, fallback } => {
+                match service.set(LABEL, &attributes(key), password.as_bytes()).await {
+                    // Make sure a stale copy doesn't get migrated over the new value
+                    Ok(()) => fallback.delete(key).await,
+                    Err(err) => {
+                        // e.g. the keyring is locked and the unlock prompt was dismissed
+                        warn!(%err, key, "failed to set secret in the secret service, using encrypted sqlite store");
+                        fallback.set(key, password).await
+                    },
+                }
+            },
+            Backend::EncryptedSqlite(store) => store.set(key, password).await,
+        }
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<Secret>> {
-        self.inner.get(key).await
+        match &self.backend {
+            Backend::SecretService { service, fallback } => {
+                // Migrate secrets written before the secret service was available or while it failed
+                if let Some(secret) = fallback.get(key).await? {
+                    debug!(key, "migrating secret to the secret service");
+                    match service.set(LABEL, &attributes(key), secret.0.as_bytes()).await {
+                        Ok(()) => fallback.delete(key).await?,
+                        Err(err) => warn!(%err, key, "failed to migrate secret to the secret service"),
+                    }
+                    return Ok(Some(secret));
+                }
+
+                match service.get(&attributes(key)).await? {
+                    Some(secret) => Ok(Some(String::from_utf8(secret)?.into())),
+                    None => Ok(None),
+                }
+            },
+            Backend::EncryptedSqlite(store) => store.get(key).await,
+        }
     }
 
     pub async fn delete(&self, key: &str) -> Result<()> {
-        self.inner.delete(key).await
+        match &self.backend {
+            Backend::SecretService { service, fallback } => {
+                fallback.delete(key).await?;
+                Ok(service.delete(&attributes(key)).await?)
+            },
+            Backend::EncryptedSqlite(store) => store.delete(key).await,
+        }
+    }
+}
+
+fn attributes(key: &str) -> HashMap<&str, &str> {
+    HashMap::from([("service", SERVICE_ATTRIBUTE), ("key", key)])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{
+        AtomicBool,
+        Ordering,
+    };
+
+    use super::*;
+
+    /// A keyring whose writes fail while it is locked
+    #[derive(Default)]
+    struct FakeKeyring {
+        items: Mutex<HashMap<String, Vec<u8>>>,
+        locked: AtomicBool,
+    }
+
+    impl Keyring for FakeKeyring {
+        async fn get(&self, attributes: &HashMap<&str, &str>) -> Result<Option<Vec<u8>>, CrateError> {
+            Ok(self.items.lock().unwrap().get(attributes["key"]).cloned())
+        }
+
+        async fn set(&self, _label: &str, attributes: &HashMap<&str, &str>, secret: &[u8]) -> Result<(), CrateError> {
+            if self.locked.load(Ordering::SeqCst) {
+                return Err(CrateError::PromptDismissed);
+            }
+            self.items
+                .lock()
+                .unwrap()
+                .insert(attributes["key"].into(), secret.to_vec());
+            Ok(())
+        }
+
+        async fn delete(&self, attributes: &HashMap<&str, &str>) -> Result<(), CrateError> {
+            self.items.lock().unwrap().remove(attributes["key"]);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_falls_back_when_secret_service_fails() {
+        let store = SecretStoreImpl {
+            backend: Backend::SecretService {
+                service: FakeKeyring::default(),
+                fallback: EncryptedSqliteSecretStore::mock(),
+            },
+        };
+        let Backend::SecretService { service, fallback } = &store.backend else {
+            unreachable!()
+        };
+
+        store.set("token", "old").await.unwrap();
+        assert_eq!(service.items.lock().unwrap()["token"], b"old");
+
+        // A locked keyring keeps the new value in the fallback, which wins over the stale copy
+        service.locked.store(true, Ordering::SeqCst);
+        store.set("token", "new").await.unwrap();
+        assert_eq!(service.items.lock().unwrap()["token"], b"old");
+        assert_eq!(store.get("token").await.unwrap(), Some("new".into()));
+        assert_eq!(fallback.get("token").await.unwrap(), Some("new".into()));
+
+        // Once it is unlocked the value is migrated over
+        service.locked.store(false, Ordering::SeqCst);
+        assert_eq!(store.get("token").await.unwrap(), Some("new".into()));
+        assert_eq!(service.items.lock().unwrap()["token"], b"new");
+        assert_eq!(fallback.get("token").await.unwrap(), None);
+
+        store.delete("token").await.unwrap();
+        assert_eq!(store.get("token").await.unwrap(), None);
     }
 }
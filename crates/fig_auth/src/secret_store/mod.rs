@@ -1,4 +1,6 @@
 #[cfg(target_os = "linux")]
+mod encrypted_sqlite;
+#[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
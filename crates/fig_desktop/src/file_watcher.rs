@@ -1,4 +1,6 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use fig_proto::fig::notification::Type as NotificationEnum;
 use fig_proto::fig::{
@@ -10,6 +12,7 @@ use fig_util::directories;
 use notify::event::ModifyKind;
 use notify::{
     EventKind,
+    RecommendedWatcher,
     RecursiveMode,
     Watcher,
 };
@@ -17,15 +20,30 @@ use serde_json::{
     Map,
     Value,
 };
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::{
     debug,
     error,
+    info,
     trace,
 };
 
-use crate::EventLoopProxy;
+use crate::event::WindowEvent;
 use crate::notification_bus::NOTIFICATION_BUS;
+use crate::protocol::spec::clear_index_cache;
+use crate::webview::AUTOCOMPLETE_ID;
 use crate::webview::notification::WebviewNotificationsState;
+use crate::{
+    Event,
+    EventLoopProxy,
+};
+
+const DEV_MODE_KEY: &str = "autocomplete.developerMode";
+const DEV_COMPLETIONS_FOLDER_KEY: &str = "autocomplete.devCompletionsFolder";
+
+/// How long to wait for more changes to specs before reloading them, building specs writes many
+/// files at once
+const SPEC_RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
 
 pub async fn setup_listeners(notifications_state: Arc<WebviewNotificationsState>, proxy: EventLoopProxy) {
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
@@ -86,8 +104,33 @@ pub async fn setup_listeners(notifications_state: Arc<WebviewNotificationsState>
         },
     };
 
+    let local_specs_dir = match directories::autocomplete_specs_dir() {
+        Ok(specs_dir) => {
+            if let Err(err) = std::fs::create_dir_all(&specs_dir) {
+                error!(%err, "failed to create local specs dir");
+            }
+
+            match watcher.watch(&specs_dir, RecursiveMode::Recursive) {
+                Ok(()) => {
+                    trace!("watching local specs at {specs_dir:?}");
+                    Some(specs_dir)
+                },
+                Err(err) => {
+                    error!(%err, "failed to watch local specs dir");
+                    None
+                },
+            }
+        },
+        Err(err) => {
+            error!(%err, "failed to get local specs dir");
+            None
+        },
+    };
+
+    let reload_specs_tx = spawn_spec_reloader(proxy.clone());
+
     tokio::spawn(async move {
-        let _watcher = watcher;
+        let mut watcher = watcher;
 
         let mut prev_settings = match fig_settings::OldSettings::load_from_file() {
             Ok(map) => map,
@@ -97,11 +140,11 @@ pub async fn setup_listeners(notifications_state: Arc<WebviewNotificationsState>
             },
         };
 
+        let mut dev_specs_dir = None;
+        watch_dev_specs_dir(&mut watcher, &mut dev_specs_dir, &prev_settings);
+
         #[cfg(target_os = "linux")]
         {
-            use crate::Event;
-            use crate::event::WindowEvent;
-            use crate::webview::AUTOCOMPLETE_ID;
             proxy
                 .send_event(Event::WindowEvent {
                     window_id: AUTOCOMPLETE_ID,
@@ -159,6 +202,7 @@ pub async fn setup_listeners(notifications_state: Arc<WebviewNotificationsState>
                                 );
 
                                 prev_settings = settings;
+                                watch_dev_specs_dir(&mut watcher, &mut dev_specs_dir, &prev_settings);
                             },
                             Err(err) => error!(%err, "Failed to get settings"),
                         }
@@ -178,8 +222,85 @@ pub async fn setup_listeners(notifications_state: Arc<WebviewNotificationsState>
                     NOTIFICATION_BUS.send_midway();
                 }
             }
+
+            let spec_dirs = [&local_specs_dir, &dev_specs_dir];
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) && event
+                .paths
+                .iter()
+                .any(|path| spec_dirs.iter().copied().flatten().any(|dir| path.starts_with(dir)))
+            {
+                trace!(paths = ?event.paths, "Spec changed");
+                reload_specs_tx.send(()).ok();
+            }
+        }
+    });
+}
+
+/// Watches the dev mode specs folder when dev mode is enabled, the folder is rewatched when the
+/// settings change
+fn watch_dev_specs_dir(
+    watcher: &mut RecommendedWatcher,
+    dev_specs_dir: &mut Option<PathBuf>,
+    settings: &Map<String, Value>,
+) {
+    let new_dir = dev_specs_dir_setting(settings);
+    if new_dir == *dev_specs_dir {
+        return;
+    }
+
+    if let Some(old_dir) = dev_specs_dir.take() {
+        if let Err(err) = watcher.unwatch(&old_dir) {
+            error!(%err, "failed to unwatch dev specs dir");
+        }
+    }
+
+    if let Some(new_dir) = new_dir {
+        match watcher.watch(&new_dir, RecursiveMode::Recursive) {
+            Ok(()) => {
+                debug!("watching dev specs at {new_dir:?}");
+                *dev_specs_dir = Some(new_dir);
+            },
+            Err(err) => error!(%err, "failed to watch dev specs dir {new_dir:?}"),
+        }
+    }
+}
+
+fn dev_specs_dir_setting(settings: &Map<String, Value>) -> Option<PathBuf> {
+    if !settings.get(DEV_MODE_KEY).and_then(Value::as_bool).unwrap_or(false) {
+        return None;
+    }
+
+    let folder = settings.get(DEV_COMPLETIONS_FOLDER_KEY)?.as_str()?;
+    let folder = PathBuf::from(shellexpand::tilde(folder).into_owned());
+    folder.is_absolute().then_some(folder)
+}
+
+/// Reloads the specs in the autocomplete window once changes to the spec directories settle
+fn spawn_spec_reloader(proxy: EventLoopProxy) -> UnboundedSender<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            while let Ok(Some(())) = tokio::time::timeout(SPEC_RELOAD_DEBOUNCE, rx.recv()).await {}
+
+            info!("specs changed, clearing autocomplete cache");
+            clear_index_cache().await;
+            proxy
+                .send_event(Event::WindowEvent {
+                    window_id: AUTOCOMPLETE_ID,
+                    window_event: WindowEvent::Event {
+                        event_name: "clear-cache".into(),
+                        payload: None,
+                    },
+                })
+                .ok();
         }
     });
+
+    tx
 }
 
 // Diffs the old and new settings and calls the appropriate callbacks
@@ -16,6 +16,8 @@ use fig_proto::local::{
     DumpStateResponse,
     LogLevelCommand,
     LogLevelResponse,
+    LoginCommand,
+    LogoutCommand,
     OpenBrowserCommand,
     OpenUiElementCommand,
     QuitCommand,
@@ -207,7 +209,18 @@ pub fn log_level(LogLevelCommand { level }: LogLevelCommand) -> LocalResult {
     ))))
 }
 
-pub async fn login(proxy: &EventLoopProxy) -> LocalResult {
+/// Whether `profile` is the profile the app is showing, commands without one are for the active
+/// profile
+fn is_active_profile(profile: Option<&str>) -> bool {
+    profile.is_none_or(|profile| profile == fig_auth::profile::active_name(&fig_settings::State::new()))
+}
+
+pub async fn login(command: LoginCommand, proxy: &EventLoopProxy) -> LocalResult {
+    // Logging in to another profile doesn't change what the app shows
+    if !is_active_profile(command.profile.as_deref()) {
+        return Ok(LocalResponse::Success(None));
+    }
+
     proxy
         .send_event(Event::WindowEvent {
             window_id: DASHBOARD_ID,
@@ -234,8 +247,15 @@ pub async fn login(proxy: &EventLoopProxy) -> LocalResult {
     Ok(LocalResponse::Success(None))
 }
 
-pub async fn logout(proxy: &EventLoopProxy) -> LocalResult {
-    fig_auth::logout().await.ok();
+pub async fn logout(command: LogoutCommand, proxy: &EventLoopProxy) -> LocalResult {
+    match &command.profile {
+        Some(profile) => fig_auth::logout_profile(profile).await.ok(),
+        None => fig_auth::logout().await.ok(),
+    };
+
+    if !is_active_profile(command.profile.as_deref()) {
+        return Ok(LocalResponse::Success(None));
+    }
 
     proxy
         .send_event(Event::WindowEvent {
@@ -16,6 +16,7 @@ use crate::event::{
     WindowPosition,
 };
 use crate::platform::PlatformState;
+use crate::protocol::spec::clear_index_cache;
 use crate::webview::WindowId;
 use crate::{
     AUTOCOMPLETE_ID,
@@ -101,6 +102,7 @@ pub async fn event(hook: EventHook, proxy: &EventLoopProxy) -> Result<()> {
 }
 
 pub async fn clear_autocomplete_cache(hook: ClearAutocompleteCacheHook, proxy: &EventLoopProxy) -> Result<()> {
+    clear_index_cache().await;
     proxy.send_event(Event::WindowEvent {
         window_id: AUTOCOMPLETE_ID,
         window_event: WindowEvent::Event {
@@ -196,8 +196,8 @@ async fn handle_local_ipc<Ctx>(
                             OpenBrowser(command) => commands::open_browser(command).await,
                             PromptAccessibility(_) => commands::prompt_for_accessibility_permission(&ctx).await,
                             LogLevel(command) => commands::log_level(command),
-                            Login(_) => commands::login(&proxy).await,
-                            Logout(_) => commands::logout(&proxy).await,
+                            Login(command) => commands::login(command, &proxy).await,
+                            Logout(command) => commands::logout(command, &proxy).await,
                             DumpState(command) => commands::dump_state(
                                 command,
                                 &figterm_state,
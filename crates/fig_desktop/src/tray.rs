@@ -101,6 +101,7 @@ fn tray_update(proxy: &EventLoopProxy) {
                 ignore_rollout: true,
                 interactive: true,
                 relaunch_dashboard: true,
+                release_url: None,
             },
         )
         .await;
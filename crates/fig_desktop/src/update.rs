@@ -101,6 +101,7 @@ pub async fn check_for_update(show_webview: bool, relaunch_dashboard: bool) -> b
             ignore_rollout: false,
             interactive: show_webview,
             relaunch_dashboard,
+            release_url: None,
         })
         .await
         {
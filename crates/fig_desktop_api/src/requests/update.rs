@@ -20,6 +20,7 @@ pub async fn update_application(request: UpdateApplicationRequest) -> RequestRes
             ignore_rollout: request.ignore_rollout.unwrap_or(true),
             interactive: request.interactive.unwrap_or(true),
             relaunch_dashboard: request.relaunch_dashboard.unwrap_or(true),
+            release_url: None,
         },
     ));
     RequestResult::success()
@@ -1,24 +1,60 @@
 use std::borrow::BorrowMut as _;
-use std::path::Path;
+use std::path::{
+    Path,
+    PathBuf,
+};
 use std::time::Duration;
 
+use bytes::Bytes;
 use hex::encode;
-use reqwest::IntoUrl;
-use tokio::io::AsyncWriteExt as _;
+use tokio::io::{
+    AsyncReadExt as _,
+    AsyncWriteExt as _,
+};
 use tokio::sync::mpsc::Sender;
+use url::Url;
 
 use crate::{
     Error,
     UpdateStatus,
 };
 
+/// Converts a `file://` url from a local mirror to a path, [Option::None] for any other scheme
+fn local_path(url: &Url) -> Option<PathBuf> {
+    match url.scheme() {
+        "file" => url.to_file_path().ok(),
+        _ => None,
+    }
+}
+
+/// Reads the whole file at `src`, which may be on a local mirror
+pub(crate) async fn fetch_bytes(src: &Url) -> Result<Bytes, Error> {
+    if let Some(path) = local_path(src) {
+        return Ok(tokio::fs::read(path).await?.into());
+    }
+
+    Ok(fig_request::client()
+        .expect("Unable to create HTTP client")
+        .get(src.clone())
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?)
+}
+
 #[allow(dead_code)]
 pub(crate) async fn download_file(
-    src: impl IntoUrl,
+    src: Url,
     dst: impl AsRef<Path>,
     size: u64,
     tx: Option<Sender<UpdateStatus>>,
 ) -> Result<String, Error> {
+    if let Some(path) = local_path(&src) {
+        return copy_file(&path, dst.as_ref(), tx).await;
+    }
+
     let client = fig_request::client().expect("fig_request client must be instantiated on first request");
     let mut response = client.get(src).timeout(Duration::from_secs(30 * 60)).send().await?;
 
@@ -55,3 +91,52 @@ pub(crate) async fn download_file(
     let hex_digest = encode(ctx.finish());
     Ok(hex_digest)
 }
+
+/// Copies a package from a local mirror, returning the same digest as [download_file]
+async fn copy_file(src: &Path, dst: &Path, tx: Option<Sender<UpdateStatus>>) -> Result<String, Error> {
+    if let Some(tx) = &tx {
+        tx.send(UpdateStatus::Message("Copying from mirror...".into()))
+            .await
+            .ok();
+    }
+
+    let mut src = tokio::fs::File::open(src).await?;
+    let mut file = tokio::fs::File::create(dst).await?;
+    let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = src.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        ctx.update(&buf[..n]);
+        file.write_all(&buf[..n]).await?;
+    }
+    file.flush().await?;
+
+    if let Some(tx) = &tx {
+        tx.send(UpdateStatus::Percent(100.0)).await.ok();
+    }
+
+    Ok(encode(ctx.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("q-x86_64-linux.tar.zst");
+        let dst = dir.path().join("download");
+        std::fs::write(&src, b"package").unwrap();
+
+        let url = Url::from_file_path(&src).unwrap();
+        assert_eq!(&fetch_bytes(&url).await.unwrap()[..], b"package");
+
+        let hash = download_file(url, &dst, 7, None).await.unwrap();
+        assert_eq!(hash, encode(ring::digest::digest(&ring::digest::SHA256, b"package")));
+        assert_eq!(std::fs::read(&dst).unwrap(), b"package");
+    }
+}
@@ -11,6 +11,7 @@ use std::time::{
 
 use cfg_if::cfg_if;
 use fig_os_shim::Context;
+use fig_settings::State;
 use fig_util::manifest::{
     Channel,
     FileType,
@@ -36,7 +37,14 @@ use tracing::{
 };
 use url::Url;
 
-use crate::Error;
+use crate::{
+    Error,
+    download,
+    signature,
+};
+
+/// The serial of the newest index seen, see [`Index::check_fresh`]
+const INDEX_SERIAL_KEY: &str = "updates.indexSerial";
 
 const DEFAULT_RELEASE_URL: &str = "https://desktop-release.q.us-east-1.amazonaws.com";
 
@@ -44,6 +52,8 @@ const DEFAULT_RELEASE_URL: &str = "https://desktop-release.q.us-east-1.amazonaws
 /// - The env var `Q_DESKTOP_RELEASE_URL`
 /// - The setting `install.releaseUrl`
 /// - Falls back to the default or the build time env var `Q_BUILD_DESKTOP_RELEASE_URL`
+///
+/// A `file://` url points at a local mirror, a directory laid out like the release bucket
 static RELEASE_URL: LazyLock<Url> = LazyLock::new(|| {
     match std::env::var("Q_DESKTOP_RELEASE_URL") {
         Ok(s) => Url::parse(&s),
@@ -55,6 +65,35 @@ static RELEASE_URL: LazyLock<Url> = LazyLock::new(|| {
     .unwrap()
 });
 
+/// The configured url the index and packages are served from
+pub fn release_url() -> &'static Url {
+    &RELEASE_URL
+}
+
+/// Resolves `path`, a plain relative path like `1.0.0/q.zip`, relative to `release_url`, keeping
+/// any path the release url already has so mirrors can be served from a subdirectory or a local
+/// folder
+///
+/// Paths come from the index, anything that could point outside of the release url like `..`, an
+/// absolute path or another url is rejected.
+fn release_path(release_url: &Url, path: &str) -> Result<Url, Error> {
+    let plain = path
+        .split('/')
+        .all(|segment| !matches!(segment, "" | "." | "..") && !segment.contains(['\\', ':', '?', '#', '%']));
+    if !plain {
+        return Err(Error::UpdateFailed(format!(
+            "Invalid release path {path}, it must be a plain relative path"
+        )));
+    }
+
+    let mut base = release_url.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path)
+        .map_err(|err| Error::UpdateFailed(format!("Invalid release path {path}: {err}")))
+}
+
 fn deser_enum_other<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -87,9 +126,36 @@ where
 pub struct Index {
     supported: Vec<Support>,
     versions: Vec<RemoteVersion>,
+    /// Increases with every published index, an index with a lower serial than one already seen is
+    /// an older index being replayed
+    #[serde(default)]
+    serial: Option<u64>,
+    /// Unix time after which the index is stale and no longer trusted
+    #[serde(default)]
+    expires: Option<u64>,
+    /// Where the index was pulled from, packages are downloaded relative to it
+    #[serde(skip)]
+    release_url: Option<Url>,
 }
 
 impl Index {
+    /// Rejects an index that expired or is older than the last one seen, so a mirror can't keep
+    /// serving a signed index after a fix is released
+    fn check_fresh(&self, now: u64, last_serial: Option<u64>) -> Result<(), Error> {
+        let (Some(serial), Some(expires)) = (self.serial, self.expires) else {
+            return Err(Error::SignatureVerification("the index has no serial or expiry".into()));
+        };
+        if expires <= now {
+            return Err(Error::SignatureVerification(format!("the index expired at {expires}")));
+        }
+        if let Some(last_serial) = last_serial.filter(|last_serial| serial < *last_serial) {
+            return Err(Error::SignatureVerification(format!(
+                "the index is older than one already seen, serial {serial} is below {last_serial}"
+            )));
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub(crate) fn latest(&self) -> Option<&RemoteVersion> {
         self.versions.iter().max_by(|a, b| a.version.cmp(&b.version))
@@ -228,7 +294,7 @@ impl Index {
 
         Ok(Some(UpdatePackage {
             version: chosen.version.clone(),
-            download_url: package.download_url(),
+            download_url: package.download_url(self.release_url.as_ref().unwrap_or(&RELEASE_URL))?,
             sha256: package.sha256.clone(),
             size: package.size,
             cli_path: package.cli_path.clone(),
@@ -285,10 +351,8 @@ pub struct Package {
 }
 
 impl Package {
-    pub(crate) fn download_url(&self) -> Url {
-        let mut url = RELEASE_URL.clone();
-        url.set_path(&self.download);
-        url
+    pub(crate) fn download_url(&self, release_url: &Url) -> Result<Url, Error> {
+        release_path(release_url, &self.download)
     }
 }
 
@@ -338,23 +402,46 @@ impl PackageArchitecture {
     }
 }
 
-fn index_endpoint(_channel: &Channel) -> Url {
-    let mut url = RELEASE_URL.clone();
-    url.set_path("index.json");
-    url
+fn index_endpoint(release_url: &Url, _channel: &Channel) -> Result<Url, Error> {
+    release_path(release_url, "index.json")
 }
 
+/// Downloads the index for `channel`, verifying its signature before parsing it
 pub async fn pull(channel: &Channel) -> Result<Index, Error> {
-    let response = fig_request::client()
-        .expect("Unable to create HTTP client")
-        .get(index_endpoint(channel))
-        .send()
-        .await?;
-    let index = response.json().await?;
+    pull_from(&RELEASE_URL, channel).await
+}
+
+/// Like [pull], but from a mirror of the release bucket rather than the configured release url
+pub async fn pull_from(release_url: &Url, channel: &Channel) -> Result<Index, Error> {
+    pull_verified(release_url, channel, signature::pinned_keys(), &State::new()).await
+}
+
+/// Pulls the index and verifies it with `keys`, the serial of the newest index is kept in `state`
+async fn pull_verified(release_url: &Url, channel: &Channel, keys: &[Vec<u8>], state: &State) -> Result<Index, Error> {
+    let url = index_endpoint(release_url, channel)?;
+    let bytes = download::fetch_bytes(&url).await?;
+    signature::verify_index(keys, &url, &bytes).await?;
+    let mut index: Index = serde_json::from_slice(&bytes)?;
+
+    // Without keys the index can't be trusted to be fresh either, see [`signature`]
+    if !keys.is_empty() {
+        let last_serial = state
+            .get_int(INDEX_SERIAL_KEY)
+            .ok()
+            .flatten()
+            .and_then(|serial| u64::try_from(serial).ok());
+        index.check_fresh(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(), last_serial)?;
+        if let Some(serial) = index.serial.and_then(|serial| i64::try_from(serial).ok()) {
+            state.set_value(INDEX_SERIAL_KEY, serial).ok();
+        }
+    }
+
+    index.release_url = Some(release_url.clone());
     Ok(index)
 }
 
 pub async fn check_for_updates(
+    release_url: &Url,
     channel: Channel,
     target_triple: &TargetTriple,
     variant: &Variant,
@@ -362,9 +449,14 @@ pub async fn check_for_updates(
     ignore_rollout: bool,
 ) -> Result<Option<UpdatePackage>, Error> {
     const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
-    pull(&channel)
-        .await?
-        .find_next_version(target_triple, variant, file_type, CURRENT_VERSION, ignore_rollout, None)
+    pull_from(release_url, &channel).await?.find_next_version(
+        target_triple,
+        variant,
+        file_type,
+        CURRENT_VERSION,
+        ignore_rollout,
+        None,
+    )
 }
 
 pub(crate) async fn get_file_type(ctx: &Context, variant: &Variant) -> Result<FileType, Error> {
@@ -392,6 +484,7 @@ mod tests {
     };
 
     use super::*;
+    use crate::signature::test_signer::TestSigner;
 
     macro_rules! test_ser_deser {
         ($ty:ident, $variant:expr, $text:expr) => {
@@ -440,6 +533,137 @@ mod tests {
         println!("{:#?}", *RELEASE_URL);
     }
 
+    #[test]
+    fn test_release_path() {
+        for (release_url, path, expected) in [
+            ("https://example.com", "index.json", "https://example.com/index.json"),
+            (
+                "https://example.com/",
+                "1.0.0/Amazon Q.dmg",
+                "https://example.com/1.0.0/Amazon%20Q.dmg",
+            ),
+            (
+                "https://example.com/q/releases",
+                "1.0.0/q.zip",
+                "https://example.com/q/releases/1.0.0/q.zip",
+            ),
+            ("file:///mnt/mirror/", "index.json", "file:///mnt/mirror/index.json"),
+        ] {
+            assert_eq!(
+                release_path(&Url::parse(release_url).unwrap(), path).unwrap().as_str(),
+                expected
+            );
+        }
+
+        let release_url = Url::parse("file:///mnt/mirror/").unwrap();
+        for path in [
+            "",
+            "/etc/passwd",
+            "//example.com/q.zip",
+            "../q.zip",
+            "1.0.0/../../q.zip",
+            "1.0.0/./q.zip",
+            "1.0.0//q.zip",
+            "%2e%2e/q.zip",
+            "..\\q.zip",
+            "https://example.com/q.zip",
+            "file:///etc/passwd",
+            "q.zip?x=1",
+        ] {
+            assert!(release_path(&release_url, path).is_err(), "{path}");
+        }
+    }
+
+    /// The test index with the `serial` and `expires` a published index has
+    fn fresh_test_index(serial: u64) -> Vec<u8> {
+        let mut index: serde_json::Value = serde_json::from_str(include_str!("../test_files/test-index.json")).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        index["serial"] = serial.into();
+        index["expires"] = (now + 3600).into();
+        serde_json::to_vec(&index).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pull_from_local_mirror() {
+        let mirror = tempfile::tempdir().unwrap();
+        let index_path = mirror.path().join("index.json");
+        let release_url = Url::from_directory_path(mirror.path()).unwrap();
+        let signer = TestSigner::new();
+        let keys = signer.keys();
+        let state = State::new_fake();
+
+        signer.write_signed(&index_path, &fresh_test_index(2));
+        let next = pull_verified(&release_url, &Channel::Stable, &keys, &state)
+            .await
+            .unwrap()
+            .find_next_version(
+                &TargetTriple::AArch64UnknownLinuxMusl,
+                &Variant::Minimal,
+                Some(&FileType::TarZst),
+                "1.2.0",
+                true,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.download_url.scheme(), "file");
+        assert!(next.download_url.path().starts_with(release_url.path()));
+        assert_eq!(state.get_int(INDEX_SERIAL_KEY).unwrap(), Some(2));
+
+        // An older index signed with the same key is a replay
+        signer.write_signed(&index_path, &fresh_test_index(1));
+        assert!(matches!(
+            pull_verified(&release_url, &Channel::Stable, &keys, &state).await,
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Signed by another key
+        TestSigner::new().write_signed(&index_path, &fresh_test_index(3));
+        assert!(matches!(
+            pull_verified(&release_url, &Channel::Stable, &keys, &state).await,
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Changed after it was signed
+        std::fs::write(&index_path, fresh_test_index(4)).unwrap();
+        assert!(matches!(
+            pull_verified(&release_url, &Channel::Stable, &keys, &state).await,
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Not signed at all
+        std::fs::remove_file(mirror.path().join("index.json.sig")).unwrap();
+        assert!(matches!(
+            pull_verified(&release_url, &Channel::Stable, &keys, &state).await,
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Builds without keys only check hashes
+        assert!(pull_verified(&release_url, &Channel::Stable, &[], &state).await.is_ok());
+    }
+
+    #[test]
+    fn test_check_fresh() {
+        let index = |serial, expires| Index {
+            supported: vec![],
+            versions: vec![],
+            serial,
+            expires,
+            release_url: None,
+        };
+
+        assert!(index(Some(5), Some(200)).check_fresh(100, None).is_ok());
+        assert!(index(Some(5), Some(200)).check_fresh(100, Some(5)).is_ok());
+        assert!(index(Some(6), Some(200)).check_fresh(100, Some(5)).is_ok());
+        // Replayed
+        assert!(index(Some(4), Some(200)).check_fresh(100, Some(5)).is_err());
+        // Expired
+        assert!(index(Some(5), Some(100)).check_fresh(100, None).is_err());
+        // Indexes without either can't be checked
+        assert!(index(None, Some(200)).check_fresh(100, None).is_err());
+        assert!(index(Some(5), None).check_fresh(100, None).is_err());
+    }
+
     #[test]
     fn index_serde_test() {
         let old_cli_name = OLD_CLI_BINARY_NAMES[0];
@@ -6,6 +6,9 @@ pub mod index;
 mod linux;
 #[cfg(target_os = "macos")]
 pub mod macos;
+pub mod signature;
+#[cfg(target_os = "linux")]
+mod versions;
 #[cfg(windows)]
 mod windows;
 
@@ -42,15 +45,18 @@ use tracing::{
     error,
     info,
 };
+use url::Url;
 #[cfg(windows)]
 use windows as os;
 
 mod common;
+mod mirror;
 pub use common::{
     InstallComponents,
     install,
     uninstall,
 };
+pub use mirror::Mirror;
 
 pub const UNINSTALL_URL: &str = "https://pulse.aws/survey/QYFVDA5H";
 
@@ -101,6 +107,10 @@ pub enum Error {
     BundleMetadataNotFound,
     #[error("unsupported variant: {0}")]
     UnsupportedVariant(String),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("failed to verify update signature: {0}")]
+    SignatureVerification(String),
 }
 
 impl From<fig_util::directories::DirectoryError> for Error {
@@ -145,6 +155,11 @@ pub fn get_max_channel() -> Channel {
 }
 
 pub async fn check_for_updates(ignore_rollout: bool) -> Result<Option<UpdatePackage>, Error> {
+    check_for_updates_from(index::release_url(), ignore_rollout).await
+}
+
+/// Like [check_for_updates], but from a mirror of the release bucket, see [Mirror]
+pub async fn check_for_updates_from(release_url: &Url, ignore_rollout: bool) -> Result<Option<UpdatePackage>, Error> {
     let manifest = manifest();
     let ctx = Context::new();
     let file_type = match (&manifest.variant, ctx.platform().os()) {
@@ -155,6 +170,7 @@ pub async fn check_for_updates(ignore_rollout: bool) -> Result<Option<UpdatePack
         _ => Some(index::get_file_type(&Context::new(), &manifest.variant).await?),
     };
     index::check_for_updates(
+        release_url,
         get_channel()?,
         &manifest.target_triple,
         &manifest.variant,
@@ -164,6 +180,51 @@ pub async fn check_for_updates(ignore_rollout: bool) -> Result<Option<UpdatePack
     .await
 }
 
+/// Downloads the next update and verifies its hash and signature without installing it, returning
+/// [Option::None] if there is no update
+pub async fn verify_update(release_url: &Url, ignore_rollout: bool) -> Result<Option<UpdatePackage>, Error> {
+    let Some(update) = check_for_updates_from(release_url, ignore_rollout).await? else {
+        return Ok(None);
+    };
+
+    let temp_dir = tempfile::tempdir()?;
+    let file_name = update
+        .download_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or(PRODUCT_NAME);
+    let real_hash = download::download_file(
+        update.download_url.clone(),
+        temp_dir.path().join(file_name),
+        update.size,
+        None,
+    )
+    .await?;
+    if real_hash != update.sha256 {
+        return Err(Error::UpdateFailed(format!(
+            "file hash mismatch. Expected: {}, Actual: {real_hash}",
+            update.sha256
+        )));
+    }
+    signature::verify_package(&update.download_url, &real_hash).await?;
+
+    Ok(Some(update))
+}
+
+/// Switches back to the version that was installed before the last update, returning it
+pub fn rollback() -> Result<String, Error> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            match &manifest().variant {
+                Variant::Minimal => versions::Versions::new()?.rollback(),
+                variant => Err(Error::UnsupportedVariant(variant.to_string())),
+            }
+        } else {
+            Err(Error::UnsupportedPlatform)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum UpdateStatus {
     Percent(f32),
@@ -180,6 +241,8 @@ pub struct UpdateOptions {
     pub interactive: bool,
     /// If to relaunch into dashboard after update (false will launch in background)
     pub relaunch_dashboard: bool,
+    /// Update from this mirror instead of the configured release url
+    pub release_url: Option<Url>,
 }
 
 /// Attempt to update if there is a newer version of Fig
@@ -190,10 +253,12 @@ pub async fn update(
         ignore_rollout,
         interactive,
         relaunch_dashboard,
+        release_url,
     }: UpdateOptions,
 ) -> Result<bool, Error> {
     info!("Checking for updates...");
-    if let Some(update) = check_for_updates(ignore_rollout).await? {
+    let release_url = release_url.as_ref().unwrap_or(index::release_url());
+    if let Some(update) = check_for_updates_from(release_url, ignore_rollout).await? {
         info!("Found update: {}", update.version);
         debug!("Update info: {:?}", update);
 
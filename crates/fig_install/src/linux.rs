@@ -11,8 +11,10 @@ use fig_integrations::gnome_extension::GnomeExtensionIntegration;
 use fig_os_shim::Context;
 use fig_util::directories::{
     fig_data_dir_ctx,
+    figterm_socket_path,
     local_webview_data_dir,
 };
+use fig_util::env_var::QTERM_SESSION_ID;
 use fig_util::manifest::manifest;
 use fig_util::{
     CLI_BINARY_NAME,
@@ -28,9 +30,11 @@ use url::Url;
 
 use crate::download::download_file;
 use crate::index::UpdatePackage;
+use crate::versions::Versions;
 use crate::{
     Error,
     UpdateStatus,
+    signature,
 };
 
 macro_rules! bail {
@@ -93,26 +97,6 @@ fn extract_archive(archive_path: &Path, tempdir: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-/// find binaries in the `bin_dir`` and move them to `$HOME/.local/bin`
-async fn replace_bins(bin_dir: &Path) -> Result<(), Error> {
-    let local_bin = fig_util::directories::home_local_bin()?;
-
-    let mut res = Ok(());
-
-    let mut read_bin_dir = tokio::fs::read_dir(bin_dir).await?;
-    while let Ok(Some(bin)) = read_bin_dir.next_entry().await {
-        let installed_bin_path = local_bin.join(bin.file_name());
-
-        let _ = tokio::fs::remove_file(&installed_bin_path).await;
-        if let Err(err) = tokio::fs::copy(bin.path(), installed_bin_path).await {
-            if res.is_ok() {
-                res = Err(err.into());
-            }
-        }
-    }
-    res
-}
-
 pub(crate) async fn update(
     update_package: UpdatePackage,
     tx: Sender<UpdateStatus>,
@@ -130,6 +114,7 @@ pub(crate) async fn update(
 
 pub(crate) async fn update_minimal(
     UpdatePackage {
+        version,
         download_url,
         sha256,
         size,
@@ -150,7 +135,9 @@ pub(crate) async fn update_minimal(
     // canonicalize to handle if the home dir is a symlink (like on Dev Desktops)
     let local_bin = fig_util::directories::home_local_bin()?.canonicalize()?;
 
-    if exe_parent != local_bin {
+    // after the first update the installed binary is a symlink into the kept versions
+    let installed_exe = local_bin.join(CLI_BINARY_NAME).canonicalize().ok();
+    if exe_parent != local_bin && installed_exe.as_ref() != Some(&exe_path) {
         bail!(
             "Update is only supported for binaries installed in {local_bin:?}, the current executable is in {exe_parent:?}"
         );
@@ -174,14 +161,38 @@ pub(crate) async fn update_minimal(
             archive.file_name
         )));
     }
+    signature::verify_package(&download_url, &real_hash).await?;
 
     let tempdir_path = tempdir.path().to_owned();
     tokio::task::spawn_blocking(move || extract_archive(&archive_path, &tempdir_path))
         .await
         .map_err(|err| Error::UpdateFailed(format!("Failed to extract {}: {err}", archive.file_name)))??;
 
+    tx.send(UpdateStatus::Message("Installing update...".into())).await.ok();
+    let version = version.to_string();
     let bin_dir = tempdir.path().join(archive.name).join("bin");
-    replace_bins(&bin_dir).await?;
+    let versions = Versions::new()?;
+    let versions = tokio::task::spawn_blocking({
+        let version = version.clone();
+        move || {
+            versions
+                .install(&bin_dir, &version, env!("CARGO_PKG_VERSION"))
+                .map(|()| versions)
+        }
+    })
+    .await
+    .map_err(|err| Error::UpdateFailed(format!("Failed to install {version}: {err}")))??;
+
+    tx.send(UpdateStatus::Message("Checking update...".into())).await.ok();
+    let session_id = std::env::var(QTERM_SESSION_ID)
+        .ok()
+        .filter(|session_id| figterm_socket_path(session_id).is_ok_and(|path| path.exists()));
+    if let Err(err) = versions.health_check(&version, session_id.as_deref()).await {
+        error!(%err, "Update failed its health check, rolling back");
+        let previous = versions.rollback()?;
+        bail!("{err}, rolled back to {previous}");
+    }
+    versions.prune();
 
     Ok(())
 }
@@ -237,13 +248,14 @@ async fn update_full_ctx(
         .await?;
 
     debug!(?file_name, "Downloading update file");
-    let real_hash = download_file(download_url, &download_path, size, Some(tx.clone())).await?;
+    let real_hash = download_file(download_url.clone(), &download_path, size, Some(tx.clone())).await?;
 
     if real_hash != expected_hash {
         return Err(Error::UpdateFailed(format!(
             "file hash mismatch. Expected: {expected_hash}, Actual: {real_hash}"
         )));
     }
+    signature::verify_package(&download_url, &real_hash).await?;
 
     tx.send(UpdateStatus::Message("Installing update...".into())).await.ok();
 
@@ -358,9 +370,6 @@ mod tests {
 
         extract_archive(&archive_path, tempdir.path()).unwrap();
         print_tree(tempdir_path);
-
-        let bin_dir = tempdir.path().join(CLI_BINARY_NAME).join("bin");
-        replace_bins(&bin_dir).await.unwrap();
     }
 
     #[tokio::test]
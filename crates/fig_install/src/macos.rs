@@ -45,6 +45,7 @@ use crate::index::UpdatePackage;
 use crate::{
     Error,
     UpdateStatus,
+    signature,
 };
 
 pub(crate) async fn update(
@@ -74,7 +75,7 @@ pub(crate) async fn update(
 
     debug!(?dmg_path, "downloading dmg");
 
-    let real_hash = download_file(update.download_url, &dmg_path, update.size, Some(tx.clone())).await?;
+    let real_hash = download_file(update.download_url.clone(), &dmg_path, update.size, Some(tx.clone())).await?;
 
     // validate the dmg hash
     let expected_hash = update.sha256;
@@ -83,6 +84,7 @@ pub(crate) async fn update(
             "dmg hash mismatch. Expected: {expected_hash}, Actual: {real_hash}"
         )));
     }
+    signature::verify_package(&update.download_url, &real_hash).await?;
 
     tx.send(UpdateStatus::Message("Unpacking update...".into())).await.ok();
 
@@ -532,7 +534,7 @@ mod test {
 
         let temp_dir = TempDir::new().unwrap();
         let dmg_path = temp_dir.path().join("CodeWhisperer.dmg");
-        let real_hash = download_file(dmg_pkg.download.clone(), dmg_path, 0, None)
+        let real_hash = download_file(dmg_pkg.download_url(crate::index::release_url())?, dmg_path, 0, None)
             .await
             .unwrap();
         println!("{real_hash}");
@@ -0,0 +1,252 @@
+//! Updating from a mirror of the release bucket rather than the configured release url, so releases
+//! can be staged internally and hosts without internet access can still update
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use tempfile::TempDir;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    Error,
+    signature,
+};
+
+const INDEX_FILE: &str = "index.json";
+
+/// A copy of the release bucket, with `index.json` (and its signature) at the root and packages at
+/// the same paths the index lists them under
+#[derive(Debug)]
+pub struct Mirror {
+    release_url: Url,
+    /// Keeps an unpacked bundle around for as long as the mirror is in use
+    _bundle: Option<TempDir>,
+}
+
+impl Mirror {
+    /// Opens `location`, which is either the url of a mirror, a mirror directory, or a bundle which
+    /// is a mirror directory packed as a `.tar.zst` archive (Linux only)
+    ///
+    /// Mirrors are only trusted as far as their signatures, so builds without pinned keys refuse
+    /// to open one. Bundles are unpacked to a temporary directory, so this blocks until that is
+    /// done
+    pub fn open(location: &str) -> Result<Self, Error> {
+        Self::open_with_keys(location, signature::pinned_keys())
+    }
+
+    /// Local mirrors have their index checked against `keys` up front, remote ones when the index
+    /// is pulled
+    fn open_with_keys(location: &str, keys: &[Vec<u8>]) -> Result<Self, Error> {
+        if keys.is_empty() {
+            return Err(Error::SignatureVerification(
+                "this build can't verify signatures, so it can't update from a mirror".into(),
+            ));
+        }
+
+        let mirror = Self::open_unverified(location)?;
+        if let Ok(root) = mirror.release_url.to_file_path() {
+            let index = std::fs::read(root.join(INDEX_FILE))?;
+            let signature = std::fs::read_to_string(root.join(format!("{INDEX_FILE}.sig"))).map_err(|err| {
+                Error::SignatureVerification(format!("unable to read the signature of the mirror index: {err}"))
+            })?;
+            signature::verify_signature(keys, location, &index, &signature)?;
+        }
+        Ok(mirror)
+    }
+
+    fn open_unverified(location: &str) -> Result<Self, Error> {
+        let path = match Url::parse(location) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                return Ok(Self {
+                    release_url: url,
+                    _bundle: None,
+                });
+            },
+            Ok(url) if url.scheme() == "file" => url
+                .to_file_path()
+                .map_err(|()| Error::UpdateFailed(format!("Invalid mirror url: {location}")))?,
+            _ => PathBuf::from(location),
+        };
+
+        if path.is_dir() {
+            Ok(Self {
+                release_url: directory_url(&path)?,
+                _bundle: None,
+            })
+        } else if path.is_file() {
+            let bundle = tempfile::tempdir()?;
+            unpack_bundle(&path, bundle.path())?;
+            Ok(Self {
+                release_url: directory_url(&bundle_root(bundle.path())?)?,
+                _bundle: Some(bundle),
+            })
+        } else {
+            Err(Error::UpdateFailed(format!(
+                "{location} is not a mirror url, directory or bundle"
+            )))
+        }
+    }
+
+    /// The url to use in place of the configured release url
+    pub fn release_url(&self) -> &Url {
+        &self.release_url
+    }
+}
+
+fn directory_url(dir: &Path) -> Result<Url, Error> {
+    if !dir.join(INDEX_FILE).is_file() {
+        return Err(Error::UpdateFailed(format!(
+            "{} is not a mirror, it has no {INDEX_FILE}",
+            dir.display()
+        )));
+    }
+    Url::from_directory_path(dir.canonicalize()?)
+        .map_err(|()| Error::UpdateFailed(format!("Invalid mirror directory: {}", dir.display())))
+}
+
+#[cfg(target_os = "linux")]
+fn unpack_bundle(bundle: &Path, dst: &Path) -> Result<(), Error> {
+    debug!(?bundle, ?dst, "unpacking mirror bundle");
+    let decoder = zstd::Decoder::new(std::fs::File::open(bundle)?)?;
+    tar::Archive::new(decoder).unpack(dst)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unpack_bundle(bundle: &Path, _dst: &Path) -> Result<(), Error> {
+    Err(Error::UpdateFailed(format!(
+        "{} is a bundle, bundles are only supported on Linux",
+        bundle.display()
+    )))
+}
+
+/// Bundles may either have the index at their root or be a single directory with the index in it
+fn bundle_root(unpacked: &Path) -> Result<PathBuf, Error> {
+    if unpacked.join(INDEX_FILE).is_file() {
+        return Ok(unpacked.to_owned());
+    }
+
+    let mut entries = std::fs::read_dir(unpacked)?.collect::<Result<Vec<_>, _>>()?;
+    match (entries.pop(), entries.is_empty()) {
+        (Some(entry), true) if entry.path().join(INDEX_FILE).is_file() => Ok(entry.path()),
+        _ => Err(Error::UpdateFailed(format!("The bundle has no {INDEX_FILE}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::test_signer::TestSigner;
+
+    fn write_mirror(dir: &Path) {
+        std::fs::create_dir_all(dir.join("1.0.0")).unwrap();
+        std::fs::write(dir.join(INDEX_FILE), "{}").unwrap();
+        std::fs::write(dir.join("1.0.0/q-x86_64-linux.tar.zst"), "package").unwrap();
+    }
+
+    fn write_signed_mirror(dir: &Path, signer: &TestSigner) {
+        write_mirror(dir);
+        signer.write_signed(&dir.join(INDEX_FILE), b"{}");
+    }
+
+    #[cfg(target_os = "linux")]
+    fn write_bundle(src: &Path, prefix: &str, bundle: &Path) {
+        let encoder = zstd::Encoder::new(std::fs::File::create(bundle).unwrap(), 0).unwrap();
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_dir_all(prefix, src).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_open_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().to_str().unwrap();
+        let signer = TestSigner::new();
+        let keys = signer.keys();
+        write_signed_mirror(dir.path(), &signer);
+
+        let mirror = Mirror::open_with_keys(location, &keys).unwrap();
+        assert_eq!(mirror.release_url().scheme(), "file");
+
+        // Builds without keys can't verify any mirror
+        assert!(matches!(
+            Mirror::open_with_keys(location, &[]),
+            Err(Error::SignatureVerification(_))
+        ));
+        assert!(matches!(
+            Mirror::open_with_keys("https://mirror.example.com/q/", &[]),
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Signed by another key
+        assert!(matches!(
+            Mirror::open_with_keys(location, &TestSigner::new().keys()),
+            Err(Error::SignatureVerification(_))
+        ));
+
+        // Changed after it was signed
+        std::fs::write(dir.path().join(INDEX_FILE), "{\"versions\":[]}").unwrap();
+        assert!(matches!(
+            Mirror::open_with_keys(location, &keys),
+            Err(Error::SignatureVerification(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_unsigned() {
+        let dir = tempfile::tempdir().unwrap();
+        write_mirror(dir.path());
+        assert!(matches!(
+            Mirror::open_with_keys(dir.path().to_str().unwrap(), &TestSigner::new().keys()),
+            Err(Error::SignatureVerification(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_url() {
+        let mirror = Mirror::open_unverified("https://mirror.example.com/q/").unwrap();
+        assert_eq!(mirror.release_url().as_str(), "https://mirror.example.com/q/");
+    }
+
+    #[test]
+    fn test_open_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        write_mirror(dir.path());
+
+        let mirror = Mirror::open_unverified(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(mirror.release_url().scheme(), "file");
+        assert!(mirror.release_url().path().ends_with('/'));
+
+        let url = Url::from_directory_path(dir.path()).unwrap();
+        assert!(Mirror::open_unverified(url.as_str()).is_ok());
+
+        std::fs::remove_file(dir.path().join(INDEX_FILE)).unwrap();
+        assert!(Mirror::open_unverified(dir.path().to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("mirror");
+        let signer = TestSigner::new();
+        write_signed_mirror(&src, &signer);
+
+        for prefix in [".", "mirror"] {
+            let bundle = dir.path().join("bundle.tar.zst");
+            write_bundle(&src, prefix, &bundle);
+
+            let mirror = Mirror::open_with_keys(bundle.to_str().unwrap(), &signer.keys()).unwrap();
+            let root = mirror.release_url().to_file_path().unwrap();
+            assert_eq!(
+                std::fs::read_to_string(root.join("1.0.0/q-x86_64-linux.tar.zst")).unwrap(),
+                "package"
+            );
+        }
+
+        assert!(Mirror::open_unverified(dir.path().join("missing").to_str().unwrap()).is_err());
+    }
+}
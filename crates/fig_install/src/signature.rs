@@ -0,0 +1,208 @@
+//! Detached Ed25519 signatures for the update index and packages
+//!
+//! Signatures are published next to the file they sign with a `.sig` suffix and contain the base64
+//! encoded signature. The index is signed as served, packages are signed over the raw SHA-256
+//! digest of the archive so they can be verified without reading the whole archive into memory.
+//!
+//! Verification is rolled out by pinning keys: until signed indexes and packages are published the
+//! key list is empty and updates are only checked against the SHA-256 in the index. Once keys are
+//! pinned every index and package must be signed, and an index must be fresh, see
+//! [`Index`](crate::index::Index).
+
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::signature::{
+    ED25519,
+    UnparsedPublicKey,
+};
+use tracing::{
+    error,
+    warn,
+};
+use url::Url;
+
+use crate::{
+    Error,
+    download,
+};
+
+/// The base64 encoded Ed25519 public keys updates must be signed by, a new key is added here a
+/// release before it starts signing so keys can be rotated without breaking older clients
+///
+/// Keys are only added once the published index is signed and has a `serial` and `expires`,
+/// verification is enforced from then on.
+const PUBLIC_KEYS: &[&str] = &[];
+
+static PINNED_KEYS: LazyLock<Vec<Vec<u8>>> = LazyLock::new(|| {
+    PUBLIC_KEYS
+        .iter()
+        .filter_map(|key| match STANDARD.decode(key) {
+            Ok(key) => Some(key),
+            Err(err) => {
+                error!(%err, %key, "Invalid pinned update signing key");
+                None
+            },
+        })
+        .collect()
+});
+
+/// Whether this build has keys to verify update signatures with, and so enforces them
+pub fn keys_pinned() -> bool {
+    !PINNED_KEYS.is_empty()
+}
+
+pub(crate) fn pinned_keys() -> &'static [Vec<u8>] {
+    &PINNED_KEYS
+}
+
+/// The url of the detached signature for the file at `url`
+pub(crate) fn signature_url(url: &Url) -> Url {
+    let mut signature_url = url.clone();
+    signature_url.set_path(&format!("{}.sig", url.path()));
+    signature_url
+}
+
+fn decode_signature(text: &str) -> Result<Vec<u8>, Error> {
+    STANDARD
+        .decode(text.trim())
+        .map_err(|err| Error::SignatureVerification(format!("malformed signature: {err}")))
+}
+
+fn verify_with_keys(keys: &[Vec<u8>], message: &[u8], signature: &[u8]) -> bool {
+    keys.iter()
+        .any(|key| UnparsedPublicKey::new(&ED25519, key).verify(message, signature).is_ok())
+}
+
+/// Verifies `message` against `signature`, the contents of the `.sig` file of `name`
+pub(crate) fn verify_signature(
+    keys: &[Vec<u8>],
+    name: impl Display,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), Error> {
+    if verify_with_keys(keys, message, &decode_signature(signature)?) {
+        Ok(())
+    } else {
+        Err(Error::SignatureVerification(format!(
+            "{name} is not signed by a trusted key"
+        )))
+    }
+}
+
+async fn fetch_signature(url: &Url) -> Result<String, Error> {
+    let signature_url = signature_url(url);
+    let bytes = download::fetch_bytes(&signature_url)
+        .await
+        .map_err(|err| Error::SignatureVerification(format!("unable to fetch {signature_url}: {err}")))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+async fn verify(keys: &[Vec<u8>], url: &Url, message: &[u8]) -> Result<(), Error> {
+    if keys.is_empty() {
+        warn!(%url, "No update signing keys are pinned in this build, skipping signature verification");
+        return Ok(());
+    }
+
+    let signature = fetch_signature(url).await?;
+    verify_signature(keys, url, message, &signature)
+}
+
+/// Verifies the raw bytes of the index downloaded from `url` with `keys`
+pub(crate) async fn verify_index(keys: &[Vec<u8>], url: &Url, bytes: &[u8]) -> Result<(), Error> {
+    verify(keys, url, bytes).await
+}
+
+/// Verifies the package downloaded from `url`, given the hex encoded SHA-256 digest of the archive
+pub(crate) async fn verify_package(url: &Url, sha256: &str) -> Result<(), Error> {
+    let digest = hex::decode(sha256)
+        .map_err(|err| Error::SignatureVerification(format!("invalid package digest {sha256}: {err}")))?;
+    verify(pinned_keys(), url, &digest).await
+}
+
+/// Signs files like the release pipeline does, for tests of the pinned key path
+#[cfg(test)]
+pub(crate) mod test_signer {
+    use ring::rand::SystemRandom;
+    use ring::signature::{
+        Ed25519KeyPair,
+        KeyPair,
+    };
+
+    use super::*;
+
+    pub(crate) struct TestSigner(Ed25519KeyPair);
+
+    impl TestSigner {
+        pub(crate) fn new() -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            Self(Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap())
+        }
+
+        pub(crate) fn keys(&self) -> Vec<Vec<u8>> {
+            vec![self.0.public_key().as_ref().to_vec()]
+        }
+
+        /// Writes `contents` to `path` and its signature next to it
+        pub(crate) fn write_signed(&self, path: &std::path::Path, contents: &[u8]) {
+            std::fs::write(path, contents).unwrap();
+            std::fs::write(
+                format!("{}.sig", path.display()),
+                STANDARD.encode(self.0.sign(contents)),
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::rand::SystemRandom;
+    use ring::signature::{
+        Ed25519KeyPair,
+        KeyPair,
+    };
+
+    use super::*;
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_signature_url() {
+        let url = Url::parse("https://example.com/1.0.0/q-x86_64-linux.tar.zst").unwrap();
+        assert_eq!(
+            signature_url(&url).as_str(),
+            "https://example.com/1.0.0/q-x86_64-linux.tar.zst.sig"
+        );
+    }
+
+    #[test]
+    fn test_verify_with_keys() {
+        let old = key_pair();
+        let new = key_pair();
+        let untrusted = key_pair();
+        let keys = vec![old.public_key().as_ref().to_vec(), new.public_key().as_ref().to_vec()];
+
+        let message = b"{\"versions\":[]}";
+        let signature = STANDARD.encode(new.sign(message));
+        let signature = decode_signature(&format!("{signature}\n")).unwrap();
+
+        assert!(verify_with_keys(&keys, message, &signature));
+        assert!(verify_with_keys(&keys, message, old.sign(message).as_ref()));
+        assert!(!verify_with_keys(&keys, b"{\"versions\":[1]}", &signature));
+        assert!(!verify_with_keys(&keys, message, untrusted.sign(message).as_ref()));
+        assert!(!verify_with_keys(&[], message, &signature));
+        assert!(decode_signature("not base64!").is_err());
+
+        assert!(verify_signature(&keys, "index.json", message, &STANDARD.encode(new.sign(message))).is_ok());
+        assert!(matches!(
+            verify_signature(&keys, "index.json", message, &STANDARD.encode(untrusted.sign(message))),
+            Err(Error::SignatureVerification(_))
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+//! Side-by-side installs of the minimal variant, so an update can be switched to atomically and
+//! rolled back if the new binaries don't work
+//!
+//! Each version is kept in `<data dir>/versions/<version>/bin`, with `current` and `previous`
+//! symlinks next to them. The binaries in `~/.local/bin` are symlinks into `current/bin`, so
+//! switching version is a single rename of the `current` symlink.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+use fig_os_shim::{
+    Command,
+    Context,
+    Process,
+};
+use fig_util::directories::{
+    fig_data_dir,
+    home_local_bin,
+};
+use fig_util::{
+    CLI_BINARY_NAME,
+    PTY_BINARY_NAME,
+};
+use tracing::{
+    debug,
+    warn,
+};
+
+use crate::Error;
+
+const CURRENT: &str = "current";
+const PREVIOUS: &str = "previous";
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub(crate) struct Versions {
+    /// Where each version is kept
+    root: PathBuf,
+    /// Where the binaries are linked to, e.g. `~/.local/bin`
+    local_bin: PathBuf,
+    /// Runs the binaries for the health check
+    process: Process,
+}
+
+impl Versions {
+    pub(crate) fn new() -> Result<Self, Error> {
+        Ok(Self {
+            root: fig_data_dir()?.join("versions"),
+            local_bin: home_local_bin()?,
+            process: Context::new().process().clone(),
+        })
+    }
+
+    /// The version a link points to
+    fn link(&self, name: &str) -> Option<String> {
+        std::fs::read_link(self.root.join(name))
+            .ok()?
+            .file_name()?
+            .to_str()
+            .map(Into::into)
+    }
+
+    fn bin_dir(&self, version: &str) -> PathBuf {
+        self.root.join(version).join("bin")
+    }
+
+    /// Installs the binaries in `bin_dir` as `version` and switches to it, keeping the currently
+    /// installed binaries as the previous version
+    pub(crate) fn install(&self, bin_dir: &Path, version: &str, installed_version: &str) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.root)?;
+        let names = bin_names(bin_dir)?;
+
+        let previous = match self.link(CURRENT) {
+            Some(current) => Some(current),
+            None => self.adopt(&names, installed_version)?,
+        };
+        if previous.as_deref() == Some(version) {
+            return Err(Error::UpdateFailed(format!("{version} is already installed")));
+        }
+
+        // Stage next to the final location so moving it into place is a rename
+        let staging = self.root.join(format!(".{version}.staging"));
+        remove_dir_if_exists(&staging)?;
+        std::fs::create_dir_all(staging.join("bin"))?;
+        for name in &names {
+            let dst = staging.join("bin").join(name);
+            std::fs::copy(bin_dir.join(name), &dst)?;
+            std::fs::set_permissions(&dst, std::fs::Permissions::from_mode(0o755))?;
+        }
+        remove_dir_if_exists(&self.root.join(version))?;
+        std::fs::rename(&staging, self.root.join(version))?;
+
+        self.switch(version, previous.as_deref())?;
+        self.link_bins(&names)
+    }
+
+    /// Moves binaries that were installed before versions were kept side-by-side into their own
+    /// version, so there is something to roll back to
+    fn adopt(&self, names: &[String], installed_version: &str) -> Result<Option<String>, Error> {
+        let installed = names
+            .iter()
+            .filter(|name| self.local_bin.join(name).is_file())
+            .collect::<Vec<_>>();
+        if installed.is_empty() {
+            return Ok(None);
+        }
+
+        let bin_dir = self.bin_dir(installed_version);
+        std::fs::create_dir_all(&bin_dir)?;
+        for name in installed {
+            debug!(%name, %installed_version, "keeping installed binary");
+            std::fs::copy(self.local_bin.join(name), bin_dir.join(name))?;
+        }
+        replace_link(&self.root.join(CURRENT), Path::new(installed_version))?;
+        Ok(Some(installed_version.into()))
+    }
+
+    fn switch(&self, current: &str, previous: Option<&str>) -> Result<(), Error> {
+        replace_link(&self.root.join(CURRENT), Path::new(current))?;
+        if let Some(previous) = previous {
+            replace_link(&self.root.join(PREVIOUS), Path::new(previous))?;
+        }
+        Ok(())
+    }
+
+    /// Links `names` into the current version, and removes links to binaries the current version
+    /// doesn't have, e.g. after rolling back to a version from before a binary was added
+    fn link_bins(&self, names: &[String]) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.local_bin)?;
+        let current_bin = self.root.join(CURRENT).join("bin");
+        for name in names {
+            let target = current_bin.join(name);
+            let link = self.local_bin.join(name);
+            if std::fs::read_link(&link).ok().as_ref() != Some(&target) {
+                replace_link(&link, &target)?;
+            }
+        }
+
+        for entry in std::fs::read_dir(&self.local_bin)?.flatten() {
+            let Ok(target) = std::fs::read_link(entry.path()) else {
+                continue;
+            };
+            if target.starts_with(&current_bin) && !target.exists() {
+                debug!(link = ?entry.path(), "removing link to a binary the current version doesn't have");
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches back to the previous version, returning it
+    pub(crate) fn rollback(&self) -> Result<String, Error> {
+        let Some(previous) = self.link(PREVIOUS) else {
+            return Err(Error::UpdateFailed(
+                "There is no previous version to roll back to".into(),
+            ));
+        };
+        let current = self.link(CURRENT);
+
+        self.switch(&previous, current.as_deref())?;
+        self.link_bins(&bin_names(&self.bin_dir(&previous))?)?;
+        Ok(previous)
+    }
+
+    /// Checks the binaries of the current version start and report `version`, and when running in a
+    /// qterm session that the new CLI can talk to it
+    pub(crate) async fn health_check(&self, version: &str, session_id: Option<&str>) -> Result<(), Error> {
+        for name in [CLI_BINARY_NAME, PTY_BINARY_NAME] {
+            let bin = self.local_bin.join(name);
+            if bin.exists() {
+                let stdout = self.run(&bin, &["--version"]).await?;
+                if reported_version(&stdout) != Some(version) {
+                    return Err(Error::UpdateFailed(format!(
+                        "`{name} --version` reported {:?}, expected {version}",
+                        stdout.trim()
+                    )));
+                }
+            }
+        }
+
+        if let Some(session_id) = session_id {
+            self.run(&self.local_bin.join(CLI_BINARY_NAME), &[
+                "_",
+                "ipc",
+                "--figterm",
+                session_id,
+                "--json",
+                r#"{"diagnostics":{}}"#,
+                "--recv",
+            ])
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn run(&self, bin: &Path, args: &[&str]) -> Result<String, Error> {
+        let command = format!("{} {}", bin.display(), args.join(" "));
+        let output = tokio::time::timeout(
+            HEALTH_CHECK_TIMEOUT,
+            self.process.output(&Command::new(bin).args(args).kill_on_drop()),
+        )
+        .await
+        .map_err(|_elapsed| Error::UpdateFailed(format!("`{command}` timed out")))?
+        .map_err(|err| Error::UpdateFailed(format!("`{command}` failed to start: {err}")))?;
+
+        if !output.success() {
+            let status = match output.exit_code {
+                Some(code) => format!("exit code {code}"),
+                None => "a signal".into(),
+            };
+            return Err(Error::UpdateFailed(format!(
+                "`{command}` exited with {status}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Removes every version other than the current and previous one
+    pub(crate) fn prune(&self) {
+        let keep = [self.link(CURRENT), self.link(PREVIOUS)];
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == CURRENT || name == PREVIOUS || keep.iter().flatten().any(|keep| *keep == name) {
+                continue;
+            }
+            if let Err(err) = std::fs::remove_dir_all(entry.path()) {
+                warn!(%err, %name, "Failed to remove old version");
+            }
+        }
+    }
+}
+
+fn bin_names(bin_dir: &Path) -> Result<Vec<String>, Error> {
+    let mut names = std::fs::read_dir(bin_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+fn remove_dir_if_exists(path: &Path) -> Result<(), Error> {
+    match std::fs::remove_dir_all(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Points `link` at `target` with a rename, so anything resolving `link` sees either the old or the
+/// new target and never a missing file
+fn replace_link(link: &Path, target: &Path) -> Result<(), Error> {
+    let Some(name) = link.file_name() else {
+        return Err(Error::UpdateFailed(format!("Invalid link path: {link:?}")));
+    };
+    let tmp = link.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    let _ = std::fs::remove_file(&tmp);
+    std::os::unix::fs::symlink(target, &tmp)?;
+    std::fs::rename(&tmp, link)?;
+    Ok(())
+}
+
+/// The version in the output of `--version`, e.g. `1.2.0` in `q 1.2.0`
+fn reported_version(stdout: &str) -> Option<&str> {
+    stdout.lines().next()?.split_whitespace().last()
+}
+
+#[cfg(test)]
+mod tests {
+    use fig_os_shim::Output;
+
+    use super::*;
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        versions: Versions,
+        downloads: PathBuf,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let versions = Versions {
+            root: dir.path().join("versions"),
+            local_bin: dir.path().join("bin"),
+            process: Process::new_fake(),
+        };
+        std::fs::create_dir_all(&versions.local_bin).unwrap();
+        Fixture {
+            downloads: dir.path().join("downloads"),
+            versions,
+            _dir: dir,
+        }
+    }
+
+    /// A bin dir like the one in an update archive with `names`, each binary contains `version`
+    fn download(fixture: &Fixture, version: &str, names: &[&str]) -> PathBuf {
+        let bin_dir = fixture.downloads.join(version);
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        for name in names {
+            std::fs::write(bin_dir.join(name), version).unwrap();
+        }
+        bin_dir
+    }
+
+    fn installed(fixture: &Fixture, name: &str) -> Option<String> {
+        std::fs::read_to_string(fixture.versions.local_bin.join(name)).ok()
+    }
+
+    /// Scripts the output of `name --version`
+    fn reports(fixture: &Fixture, name: &str, output: Output) {
+        fixture
+            .versions
+            .process
+            .push_output(fixture.versions.local_bin.join(name), output);
+    }
+
+    #[test]
+    fn test_reported_version() {
+        assert_eq!(reported_version("q 1.2.0\n"), Some("1.2.0"));
+        assert_eq!(reported_version("1.2.0"), Some("1.2.0"));
+        assert_eq!(reported_version("q 1.2.0-beta\nmore\n"), Some("1.2.0-beta"));
+        assert_eq!(reported_version(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_install_and_rollback() {
+        let fixture = fixture();
+        let versions = &fixture.versions;
+
+        // A binary installed before versions were kept side-by-side
+        std::fs::write(versions.local_bin.join(CLI_BINARY_NAME), "1.0.0").unwrap();
+
+        versions
+            .install(&download(&fixture, "1.1.0", &[CLI_BINARY_NAME]), "1.1.0", "1.0.0")
+            .unwrap();
+        assert_eq!(versions.link(CURRENT).as_deref(), Some("1.1.0"));
+        assert_eq!(versions.link(PREVIOUS).as_deref(), Some("1.0.0"));
+        assert_eq!(installed(&fixture, CLI_BINARY_NAME).as_deref(), Some("1.1.0"));
+
+        reports(
+            &fixture,
+            CLI_BINARY_NAME,
+            Output::succeeded(format!("{CLI_BINARY_NAME} 1.1.0\n")),
+        );
+        versions.health_check("1.1.0", None).await.unwrap();
+        let invocations = versions.process.invocations();
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(invocations[0].get_args(), ["--version"]);
+
+        // 1.2.0 adds qterm, which 1.1.0 doesn't have
+        versions
+            .install(
+                &download(&fixture, "1.2.0", &[CLI_BINARY_NAME, PTY_BINARY_NAME]),
+                "1.2.0",
+                "1.1.0",
+            )
+            .unwrap();
+        versions.prune();
+        assert!(!versions.root.join("1.0.0").exists());
+        assert_eq!(installed(&fixture, CLI_BINARY_NAME).as_deref(), Some("1.2.0"));
+        assert_eq!(installed(&fixture, PTY_BINARY_NAME).as_deref(), Some("1.2.0"));
+
+        assert_eq!(versions.rollback().unwrap(), "1.1.0");
+        assert_eq!(installed(&fixture, CLI_BINARY_NAME).as_deref(), Some("1.1.0"));
+        assert_eq!(versions.link(PREVIOUS).as_deref(), Some("1.2.0"));
+        assert!(
+            std::fs::symlink_metadata(versions.local_bin.join(PTY_BINARY_NAME)).is_err(),
+            "the link to a binary 1.1.0 doesn't have is removed"
+        );
+
+        // The rolled back version passes its own health check
+        reports(
+            &fixture,
+            CLI_BINARY_NAME,
+            Output::succeeded(format!("{CLI_BINARY_NAME} 1.1.0\n")),
+        );
+        versions.health_check("1.1.0", None).await.unwrap();
+
+        // Rolling back again swaps back
+        assert_eq!(versions.rollback().unwrap(), "1.2.0");
+        assert_eq!(installed(&fixture, CLI_BINARY_NAME).as_deref(), Some("1.2.0"));
+        assert_eq!(installed(&fixture, PTY_BINARY_NAME).as_deref(), Some("1.2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_fails() {
+        let fixture = fixture();
+        let versions = &fixture.versions;
+
+        versions
+            .install(
+                &download(&fixture, "1.1.0", &[CLI_BINARY_NAME, PTY_BINARY_NAME]),
+                "1.1.0",
+                "1.0.0",
+            )
+            .unwrap();
+
+        // Reporting another version, even one containing the expected version
+        for reported in ["1.0.9", "1.1.0-beta", "11.1.0", "1.1.0.1"] {
+            reports(
+                &fixture,
+                CLI_BINARY_NAME,
+                Output::succeeded(format!("{CLI_BINARY_NAME} {reported}\n")),
+            );
+            assert!(versions.health_check("1.1.0", None).await.is_err(), "{reported}");
+        }
+
+        // The CLI is fine but qterm isn't
+        reports(
+            &fixture,
+            CLI_BINARY_NAME,
+            Output::succeeded(format!("{CLI_BINARY_NAME} 1.1.0\n")),
+        );
+        reports(&fixture, PTY_BINARY_NAME, Output::failed(1, "error"));
+        assert!(versions.health_check("1.1.0", None).await.is_err());
+
+        // Failing to start at all
+        assert!(versions.health_check("1.1.0", None).await.is_err());
+
+        // The CLI can't talk to the qterm session
+        reports(
+            &fixture,
+            CLI_BINARY_NAME,
+            Output::succeeded(format!("{CLI_BINARY_NAME} 1.1.0\n")),
+        );
+        reports(
+            &fixture,
+            PTY_BINARY_NAME,
+            Output::succeeded(format!("{PTY_BINARY_NAME} 1.1.0\n")),
+        );
+        reports(&fixture, CLI_BINARY_NAME, Output::failed(1, "no session"));
+        assert!(versions.health_check("1.1.0", Some("session")).await.is_err());
+
+        reports(
+            &fixture,
+            CLI_BINARY_NAME,
+            Output::succeeded(format!("{CLI_BINARY_NAME} 1.1.0\n")),
+        );
+        reports(
+            &fixture,
+            PTY_BINARY_NAME,
+            Output::succeeded(format!("{PTY_BINARY_NAME} 1.1.0\n")),
+        );
+        reports(&fixture, CLI_BINARY_NAME, Output::succeeded("{}"));
+        versions.health_check("1.1.0", Some("session")).await.unwrap();
+    }
+
+    #[test]
+    fn test_rollback_without_previous() {
+        let fixture = fixture();
+        assert!(fixture.versions.rollback().is_err());
+    }
+}
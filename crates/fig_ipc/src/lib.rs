@@ -23,6 +23,7 @@ pub use send_recv_message::SendRecvMessage;
 pub use unix_socket::{
     BufferedUnixStream,
     socket_connect,
+    socket_connect_or_remove_stale,
     socket_connect_timeout,
     validate_socket,
 };
@@ -136,13 +136,13 @@ pub async fn quit_command() -> Result<()> {
     send_command_to_socket(command).await
 }
 
-pub async fn login_command() -> Result<()> {
-    let command = command::Command::Login(LoginCommand {});
+pub async fn login_command(profile: Option<String>) -> Result<()> {
+    let command = command::Command::Login(LoginCommand { profile });
     send_command_to_socket(command).await
 }
 
-pub async fn logout_command() -> Result<()> {
-    let command = command::Command::Logout(LogoutCommand {});
+pub async fn logout_command(profile: Option<String>) -> Result<()> {
+    let command = command::Command::Logout(LogoutCommand { profile });
     send_command_to_socket(command).await
 }
 
@@ -110,6 +110,23 @@ pub async fn socket_connect_timeout(socket: impl AsRef<Path>, timeout: Duration)
     }
 }
 
+/// Like [`socket_connect_timeout`], but a socket file left behind by a process that exited is
+/// removed so it isn't mistaken for a live socket again
+pub async fn socket_connect_or_remove_stale(
+    socket: impl AsRef<Path>,
+    timeout: Duration,
+) -> Result<UnixStream, ConnectError> {
+    let socket = socket.as_ref();
+    let result = socket_connect_timeout(socket, timeout).await;
+    if let Err(ConnectError::Io(err)) = &result {
+        if err.kind() == std::io::ErrorKind::ConnectionRefused {
+            debug!(?socket, "Removing stale socket");
+            tokio::fs::remove_file(socket).await.ok();
+        }
+    }
+    result
+}
+
 /// Checks all the lower bits of a permission with the mask 0o777
 fn validate_mode_bits(left: u32, right: u32) -> bool {
     left & 0o777 == right & 0o777
@@ -162,6 +179,37 @@ mod tests {
         socket_thread.abort();
     }
 
+    #[tokio::test]
+    async fn test_remove_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("socket.sock");
+        #[cfg(unix)]
+        {
+            use std::fs::Permissions;
+            use std::os::unix::fs::PermissionsExt;
+            tokio::fs::set_permissions(&dir, Permissions::from_mode(0o700))
+                .await
+                .unwrap();
+        }
+
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        assert!(
+            socket_connect_or_remove_stale(&socket_path, Duration::from_secs(1))
+                .await
+                .is_ok()
+        );
+
+        // The socket file outlives the listener
+        drop(listener);
+        assert!(socket_path.exists());
+        assert!(
+            socket_connect_or_remove_stale(&socket_path, Duration::from_secs(1))
+                .await
+                .is_err()
+        );
+        assert!(!socket_path.exists());
+    }
+
     /// If this test fails, we need to reevaluate the permissions model design around our sockets
     /// and double check with security
     #[test]
@@ -1,8 +1,16 @@
-use std::fs::File;
+mod rotation;
+
 use std::path::Path;
 use std::sync::Mutex;
 
-use fig_util::env_var::Q_LOG_LEVEL;
+use fig_util::env_var::{
+    Q_LOG_FORMAT,
+    Q_LOG_LEVEL,
+};
+pub use rotation::{
+    Rotation,
+    rotated_path,
+};
 use thiserror::Error;
 use tracing::info;
 use tracing::level_filters::LevelFilter;
@@ -15,7 +23,6 @@ use tracing_subscriber::{
     fmt,
 };
 
-const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 const DEFAULT_FILTER: LevelFilter = LevelFilter::ERROR;
 
 static Q_LOG_LEVEL_GLOBAL: Mutex<Option<String>> = Mutex::new(None);
@@ -32,6 +39,26 @@ pub enum Error {
     TracingReload(#[from] tracing_subscriber::reload::Error),
 }
 
+/// The format log files are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, including the fields of the spans the event was in
+    Json,
+}
+
+impl LogFormat {
+    /// The format set with `Q_LOG_FORMAT`, defaulting to [LogFormat::Text]
+    pub fn from_env() -> Self {
+        match std::env::var(Q_LOG_FORMAT) {
+            Ok(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
 /// Arguments to the initialize_logging function
 #[derive(Debug)]
 pub struct LogArgs<T: AsRef<Path>> {
@@ -76,34 +103,24 @@ pub fn initialize_logging<T: AsRef<Path>>(args: LogArgs<T>) -> Result<LogGuard,
                 std::fs::create_dir_all(parent)?;
             }
 
-            // We delete the old log file when requested each time the logger is initialized, otherwise we only
-            // delete the file when it has grown too large.
+            // We delete the old log file when requested each time the logger is initialized, otherwise it is
+            // rotated once it grows too large or old.
             if args.delete_old_log_file {
                 std::fs::remove_file(log_path).ok();
-            } else if log_path.exists() && std::fs::metadata(log_path)?.len() > MAX_FILE_SIZE {
-                std::fs::remove_file(log_path)?;
-            }
-
-            // Create the new log file or append to the existing one.
-            let file = if args.delete_old_log_file {
-                File::create(log_path)?
-            } else {
-                File::options().append(true).create(true).open(log_path)?
-            };
-
-            // On posix-like systems, we modify permissions so that only the owner has access.
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Ok(metadata) = file.metadata() {
-                    let mut permissions = metadata.permissions();
-                    permissions.set_mode(0o600);
-                    file.set_permissions(permissions).ok();
-                }
             }
+            let file = rotation::RotatingFile::open(log_path, Rotation::default())?;
 
             let (non_blocking, guard) = tracing_appender::non_blocking(file);
-            let file_layer = fmt::layer().with_line_number(true).with_writer(non_blocking);
+            let file_layer = match LogFormat::from_env() {
+                LogFormat::Text => fmt::layer().with_line_number(true).with_writer(non_blocking).boxed(),
+                LogFormat::Json => fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_line_number(true)
+                    .with_writer(non_blocking)
+                    .boxed(),
+            };
 
             (Some(file_layer), Some(guard))
         },
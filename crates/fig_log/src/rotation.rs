@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{
+    self,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+/// When to rotate a log file and how many rotated files to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+    /// Rotate once the file grows past this many bytes
+    pub max_size: u64,
+    /// Rotate once the file was started this long ago
+    pub max_age: Duration,
+    /// How many rotated files to keep, as `<name>.1` (newest) to `<name>.<max_files>` (oldest)
+    pub max_files: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+            max_age: Duration::from_secs(60 * 60 * 24),
+            max_files: 3,
+        }
+    }
+}
+
+/// A log file that rotates itself as it is written to
+#[derive(Debug)]
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    started: SystemTime,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, rotating it first if it is already due
+    pub(crate) fn open(path: impl Into<PathBuf>, rotation: Rotation) -> io::Result<Self> {
+        let path = path.into();
+        let (file, size, started) = open_append(&path)?;
+        let mut this = Self {
+            path,
+            rotation,
+            file,
+            size,
+            started,
+        };
+        if this.due() {
+            this.rotate()?;
+        }
+        Ok(this)
+    }
+
+    fn due(&self) -> bool {
+        self.size >= self.rotation.max_size
+            || self
+                .started
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= self.rotation.max_age)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        rotate_files(&self.path, self.rotation.max_files)?;
+        let (file, size, started) = open_append(&self.path)?;
+        self.file = file;
+        self.size = size;
+        self.started = started;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.due() {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The path of the `n`th rotated file of `path`
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{n}"));
+    path.with_file_name(name)
+}
+
+/// Shifts `path` to `<path>.1`, `<path>.1` to `<path>.2` and so on, dropping the oldest
+fn rotate_files(path: &Path, max_files: usize) -> io::Result<()> {
+    if max_files == 0 {
+        return remove_if_exists(path);
+    }
+    remove_if_exists(&rotated_path(path, max_files))?;
+    for n in (1..max_files).rev() {
+        rename_if_exists(&rotated_path(path, n), &rotated_path(path, n + 1))?;
+    }
+    rename_if_exists(path, &rotated_path(path, 1))
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Opens `path` for appending, returning its size and when it was started
+fn open_append(path: &Path) -> io::Result<(File, u64, SystemTime)> {
+    let file = File::options().append(true).create(true).open(path)?;
+    let metadata = file.metadata()?;
+
+    // On posix-like systems, we modify permissions so that only the owner has access.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = metadata.permissions();
+        permissions.set_mode(0o600);
+        file.set_permissions(permissions).ok();
+    }
+
+    let started = if metadata.len() == 0 {
+        SystemTime::now()
+    } else {
+        metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now())
+    };
+    Ok((file, metadata.len(), started))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.log");
+        let rotation = Rotation {
+            max_size: 10,
+            max_age: Duration::from_secs(60),
+            max_files: 2,
+        };
+
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        for line in ["first line\n", "second line\n", "third line\n", "fourth line\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "fourth line\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "third line\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "second line\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        // A file that is already due is rotated when opened
+        drop(file);
+        RotatingFile::open(&path, rotation).unwrap();
+        assert_eq!(read(&path), "");
+        assert_eq!(read(&rotated_path(&path, 1)), "fourth line\n");
+
+        // Time based rotation
+        std::fs::write(&path, "old\n").unwrap();
+        let mut file = RotatingFile::open(&path, Rotation {
+            max_age: Duration::ZERO,
+            ..rotation
+        })
+        .unwrap();
+        file.write_all(b"new\n").unwrap();
+        assert_eq!(read(&path), "new\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "old\n");
+    }
+}
@@ -1,6 +1,7 @@
 mod env;
 mod fs;
 mod platform;
+mod process;
 pub mod process_info;
 mod providers;
 mod sysinfo;
@@ -13,6 +14,11 @@ pub use platform::{
     Os,
     Platform,
 };
+pub use process::{
+    Command,
+    Output,
+    Process,
+};
 use process_info::FakePid;
 pub use process_info::ProcessInfo;
 pub use providers::{
@@ -21,6 +27,7 @@ pub use providers::{
     EnvProvider,
     FsProvider,
     PlatformProvider,
+    ProcessProvider,
     SysInfoProvider,
 };
 pub use sysinfo::SysInfo;
@@ -41,6 +48,7 @@ pub struct Context {
     fs: Fs,
     env: Env,
     platform: Platform,
+    process: Process,
     process_info: ProcessInfo,
     sysinfo: SysInfo,
 }
@@ -52,6 +60,7 @@ impl Context {
             fs: Default::default(),
             env: Default::default(),
             platform: Default::default(),
+            process: Default::default(),
             process_info: ProcessInfo::new(ctx.clone()),
             sysinfo: SysInfo::default(),
         })
@@ -62,6 +71,7 @@ impl Context {
             fs: Fs::new_fake(),
             env: Env::new_fake(),
             platform: Platform::new_fake(Os::current()),
+            process: Process::new_fake(),
             process_info: ProcessInfo::new_fake(FakePid::default()),
             sysinfo: SysInfo::new_fake(),
         })
@@ -83,6 +93,10 @@ impl Context {
         &self.platform
     }
 
+    pub fn process(&self) -> &Process {
+        &self.process
+    }
+
     pub fn process_info(&self) -> &ProcessInfo {
         &self.process_info
     }
@@ -97,6 +111,7 @@ pub struct ContextBuilder {
     fs: Option<Fs>,
     env: Option<Env>,
     platform: Option<Platform>,
+    process: Option<Process>,
     process_info: Option<ProcessInfo>,
     sysinfo: Option<SysInfo>,
 }
@@ -111,11 +126,13 @@ impl ContextBuilder {
         let fs = self.fs.unwrap_or_default();
         let env = self.env.unwrap_or_default();
         let platform = self.platform.unwrap_or_default();
+        let process = self.process.unwrap_or_default();
         let sysinfo = self.sysinfo.unwrap_or_default();
         Arc::new_cyclic(|ctx| Context {
             fs,
             env,
             platform,
+            process,
             process_info: if let Some(process_info) = self.process_info {
                 process_info
             } else {
@@ -130,11 +147,13 @@ impl ContextBuilder {
         let fs = self.fs.unwrap_or(Fs::new_fake());
         let env = self.env.unwrap_or(Env::new_fake());
         let platform = self.platform.unwrap_or(Platform::new_fake(Os::Mac));
+        let process = self.process.unwrap_or(Process::new_fake());
         let sysinfo = self.sysinfo.unwrap_or(SysInfo::new_fake());
         Arc::new_cyclic(|ctx| Context {
             fs,
             env,
             platform,
+            process,
             process_info: if let Some(process_info) = self.process_info {
                 process_info
             } else {
@@ -159,6 +178,11 @@ impl ContextBuilder {
         self
     }
 
+    pub fn with_process(mut self, process: Process) -> Self {
+        self.process = Some(process);
+        self
+    }
+
     pub fn with_process_info(mut self, process_info: ProcessInfo) -> Self {
         self.process_info = Some(process_info);
         self
@@ -212,6 +236,7 @@ mod tests {
         let ctx = ContextBuilder::new().build();
         assert!(ctx.fs().is_real());
         assert!(ctx.env().is_real());
+        assert!(ctx.process().is_real());
         assert!(ctx.process_info().is_real());
         assert!(ctx.platform().is_real());
         assert!(ctx.sysinfo().is_real());
@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::ffi::{
+    OsStr,
+    OsString,
+};
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Stdio;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use crate::Shim;
+
+/// A subprocess to run through [Process].
+///
+/// By default stdin is null and stdout and stderr are captured, like
+/// [std::process::Command::output].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Command {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    current_dir: Option<PathBuf>,
+    inherit_stdin: bool,
+    inherit_output: bool,
+    kill_on_drop: bool,
+}
+
+impl Command {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.envs.push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.current_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Lets the subprocess read from the parent's stdin.
+    pub fn inherit_stdin(mut self) -> Self {
+        self.inherit_stdin = true;
+        self
+    }
+
+    /// Writes stdout and stderr to the parent's instead of capturing them, the returned [Output]
+    /// then has empty `stdout` and `stderr`.
+    pub fn inherit_output(mut self) -> Self {
+        self.inherit_output = true;
+        self
+    }
+
+    /// Kills the subprocess if the future running it is dropped, e.g. when it times out.
+    pub fn kill_on_drop(mut self) -> Self {
+        self.kill_on_drop = true;
+        self
+    }
+
+    pub fn get_program(&self) -> &OsStr {
+        &self.program
+    }
+
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    pub fn get_envs(&self) -> &[(OsString, OsString)] {
+        &self.envs
+    }
+
+    pub fn get_current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    fn to_tokio(&self) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .kill_on_drop(self.kill_on_drop);
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+        command.stdin(if self.inherit_stdin {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        });
+        if self.inherit_output {
+            command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        } else {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        command
+    }
+}
+
+/// The result of a subprocess that ran to completion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Output {
+    /// [None] if the process was terminated by a signal.
+    pub exit_code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl Output {
+    /// A successful exit with `stdout`.
+    pub fn succeeded(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            exit_code: Some(0),
+            stdout: stdout.into(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// A failed exit with `exit_code` and `stderr`.
+    pub fn failed(exit_code: i32, stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            exit_code: Some(exit_code),
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+impl From<std::process::Output> for Output {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            exit_code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+/// Runs subprocesses.
+///
+/// The fake implementation replies to each invocation with the next response scripted for its
+/// program and records every invocation, programs without a scripted response fail to spawn with
+/// [io::ErrorKind::NotFound] as if they weren't installed.
+#[derive(Debug, Clone, Default)]
+pub struct Process(inner::Inner);
+
+mod inner {
+    use std::collections::{
+        HashMap,
+        VecDeque,
+    };
+    use std::ffi::OsString;
+    use std::io;
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+
+    use super::{
+        Command,
+        Output,
+    };
+
+    #[derive(Debug, Clone, Default)]
+    pub enum Inner {
+        #[default]
+        Real,
+        Fake(Arc<Mutex<Fake>>),
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Fake {
+        pub responses: HashMap<OsString, VecDeque<Result<Output, io::ErrorKind>>>,
+        pub invocations: Vec<Command>,
+    }
+}
+
+impl Process {
+    pub fn new_fake() -> Self {
+        Self(inner::Inner::Fake(Arc::new(Mutex::new(inner::Fake::default()))))
+    }
+
+    /// Runs `command` to completion, returning its output.
+    pub async fn output(&self, command: &Command) -> io::Result<Output> {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => Ok(command.to_tokio().spawn()?.wait_with_output().await?.into()),
+            Inner::Fake(fake) => {
+                let mut fake = fake.lock().unwrap();
+                fake.invocations.push(command.clone());
+                match fake
+                    .responses
+                    .get_mut(command.get_program())
+                    .and_then(VecDeque::pop_front)
+                {
+                    Some(Ok(output)) => Ok(output),
+                    Some(Err(kind)) => Err(io::Error::new(kind, "scripted error")),
+                    None => Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} not found", command.get_program().to_string_lossy()),
+                    )),
+                }
+            },
+        }
+    }
+
+    /// Queues `output` as the reply to the next invocation of `program`.
+    pub fn push_output(&self, program: impl AsRef<OsStr>, output: Output) {
+        self.push_response(program, Ok(output));
+    }
+
+    /// Queues a failure to spawn as the reply to the next invocation of `program`.
+    pub fn push_error(&self, program: impl AsRef<OsStr>, kind: io::ErrorKind) {
+        self.push_response(program, Err(kind));
+    }
+
+    fn push_response(&self, program: impl AsRef<OsStr>, response: Result<Output, io::ErrorKind>) {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => panic!("unimplemented"),
+            Inner::Fake(fake) => fake
+                .lock()
+                .unwrap()
+                .responses
+                .entry(program.as_ref().to_owned())
+                .or_default()
+                .push_back(response),
+        }
+    }
+
+    /// Every command run so far, in order.
+    pub fn invocations(&self) -> Vec<Command> {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => panic!("unimplemented"),
+            Inner::Fake(fake) => fake.lock().unwrap().invocations.clone(),
+        }
+    }
+}
+
+impl Shim for Process {
+    fn is_real(&self) -> bool {
+        matches!(self.0, inner::Inner::Real)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_real_output() {
+        let process = Process::default();
+        let output = process
+            .output(
+                &Command::new("sh")
+                    .args(["-c", "echo $GREETING; echo oops >&2; exit 3"])
+                    .env("GREETING", "hi"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success());
+        assert_eq!(output.stdout, b"hi\n");
+        assert_eq!(output.stderr, b"oops\n");
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let output = process
+            .output(&Command::new("pwd").current_dir(tempdir.path()))
+            .await
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(
+            PathBuf::from(String::from_utf8(output.stdout).unwrap().trim())
+                .canonicalize()
+                .unwrap(),
+            tempdir.path().canonicalize().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_output() {
+        let process = Process::new_fake();
+        assert!(!process.is_real());
+        process.push_output("git", Output::succeeded("## main"));
+        process.push_output("git", Output::failed(128, "not a git repository"));
+        process.push_error("aws", io::ErrorKind::PermissionDenied);
+
+        let status = Command::new("git")
+            .args(["status", "--porcelain=v1"])
+            .current_dir("/repo");
+        assert_eq!(process.output(&status).await.unwrap().stdout, b"## main");
+        assert_eq!(process.output(&status).await.unwrap().exit_code, Some(128));
+        assert_eq!(
+            process.output(&status).await.unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            process.output(&Command::new("aws")).await.unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        let invocations = process.invocations();
+        assert_eq!(invocations.len(), 4);
+        assert_eq!(invocations[0], status);
+        assert_eq!(invocations[0].get_args(), ["status", "--porcelain=v1"]);
+        assert_eq!(invocations[0].get_current_dir(), Some(Path::new("/repo")));
+        assert_eq!(invocations[3].get_program(), "aws");
+    }
+}
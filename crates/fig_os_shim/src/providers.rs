@@ -5,6 +5,7 @@ use crate::{
     Env,
     Fs,
     Platform,
+    Process,
     SysInfo,
 };
 
@@ -94,6 +95,25 @@ where
     }
 }
 
+pub trait ProcessProvider {
+    fn process(&self) -> &Process;
+}
+
+impl ProcessProvider for Process {
+    fn process(&self) -> &Process {
+        self
+    }
+}
+
+impl<T> ProcessProvider for T
+where
+    T: ContextProvider,
+{
+    fn process(&self) -> &Process {
+        self.context().process()
+    }
+}
+
 pub trait SysInfoProvider {
     fn sysinfo(&self) -> &SysInfo;
 }
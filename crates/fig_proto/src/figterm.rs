@@ -1,7 +1,29 @@
 use std::iter::repeat;
 
+use fig_util::shell_words::AcceptKind;
+
 pub use crate::proto::figterm::*;
 
+impl From<AcceptKind> for InlineShellCompletionAcceptKind {
+    fn from(kind: AcceptKind) -> Self {
+        match kind {
+            AcceptKind::Full => Self::Full,
+            AcceptKind::Word => Self::Word,
+            AcceptKind::Argument => Self::Argument,
+        }
+    }
+}
+
+impl From<InlineShellCompletionAcceptKind> for AcceptKind {
+    fn from(kind: InlineShellCompletionAcceptKind) -> Self {
+        match kind {
+            InlineShellCompletionAcceptKind::Full => Self::Full,
+            InlineShellCompletionAcceptKind::Word => Self::Word,
+            InlineShellCompletionAcceptKind::Argument => Self::Argument,
+        }
+    }
+}
+
 impl InsertTextRequest {
     pub fn to_term_string(&self) -> String {
         let mut out = String::new();
@@ -22,7 +22,7 @@ use crate::sqlite::{
     database,
 };
 
-const ALL_COLUMNS: &str = "id, command, shell, pid, session_id, cwd, start_time, duration, hostname, exit_code";
+const ALL_COLUMNS: &str = "id, command, shell, pid, session_id, cwd, start_time, duration, hostname, exit_code, output";
 
 fn escape_string(s: impl AsRef<str>) -> String {
     s.as_ref()
@@ -46,6 +46,8 @@ pub struct CommandInfo {
     pub end_time: Option<SystemTime>,
     pub hostname: Option<String>,
     pub exit_code: Option<i32>,
+    /// The text the command printed to the terminal, ANSI escapes are already removed
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -92,8 +94,8 @@ impl History {
             if !command.is_empty() {
                 self.conn()?.execute(
                     "INSERT INTO history 
-                        (command, shell, pid, session_id, cwd, start_time, end_time, duration, hostname, exit_code)
-                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        (command, shell, pid, session_id, cwd, start_time, end_time, duration, hostname, exit_code, output)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                     params![
                         &command_info.command,
                         &command_info.shell,
@@ -123,6 +125,7 @@ impl History {
                             .and_then(|duration| i64::try_from(duration).ok()),
                         &command_info.hostname,
                         &command_info.exit_code,
+                        &command_info.output,
                     ],
                 )?;
             }
@@ -281,6 +284,7 @@ fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CommandInfo> {
         end_time,
         hostname: row.get(8)?,
         exit_code: row.get(9)?,
+        output: row.get(10)?,
     })
 }
 
@@ -295,6 +299,7 @@ pub enum HistoryColumn {
     Duration,
     Hostname,
     ExitCode,
+    Output,
 }
 
 impl std::fmt::Display for HistoryColumn {
@@ -310,6 +315,7 @@ impl std::fmt::Display for HistoryColumn {
             HistoryColumn::Duration => f.write_str("duration"),
             HistoryColumn::Hostname => f.write_str("hostname"),
             HistoryColumn::ExitCode => f.write_str("exit_code"),
+            HistoryColumn::Output => f.write_str("output"),
         }
     }
 }
@@ -429,6 +435,7 @@ mod tests {
                     end_time: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(124)),
                     hostname: Some("laptop".into()),
                     exit_code: Some(0),
+                    output: None,
                 },
                 false,
             )
@@ -446,6 +453,7 @@ mod tests {
                     end_time: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(125)),
                     hostname: Some("laptop".into()),
                     exit_code: Some(0),
+                    output: Some("test result: ok".into()),
                 },
                 false,
             )
@@ -463,6 +471,7 @@ mod tests {
                     end_time: None,
                     hostname: Some("laptop".into()),
                     exit_code: None,
+                    output: None,
                 },
                 false,
             )
@@ -486,6 +495,7 @@ mod tests {
         );
         assert_eq!(rows[0].hostname, Some("laptop".into()));
         assert_eq!(rows[0].exit_code, Some(0));
+        assert_eq!(rows[0].output, None);
 
         assert_eq!(rows[1].command, Some("cargo test".into()));
         assert_eq!(rows[1].shell, Some("zsh".into()));
@@ -502,6 +512,7 @@ mod tests {
         );
         assert_eq!(rows[1].hostname, Some("laptop".into()));
         assert_eq!(rows[1].exit_code, Some(0));
+        assert_eq!(rows[1].output, Some("test result: ok".into()));
 
         assert_eq!(rows[2].command, Some("cargo run".into()));
         assert_eq!(rows[2].shell, Some("zsh".into()));
@@ -552,6 +563,7 @@ mod tests {
                 "hostname": "laptop",
                 "exit_code": 0,
                 "duration": 1000,
+                "output": null,
             })
             .as_object()
             .unwrap()
@@ -571,6 +583,7 @@ mod tests {
                 "hostname": "laptop",
                 "exit_code": 0,
                 "duration": 1000,
+                "output": "test result: ok",
             })
             .as_object()
             .unwrap()
@@ -590,6 +603,7 @@ mod tests {
                 "hostname": "laptop",
                 "exit_code": null,
                 "duration": null,
+                "output": null,
             })
             .as_object()
             .unwrap()
@@ -4,6 +4,8 @@ pub mod keybindings;
 pub mod settings;
 pub mod sqlite;
 pub mod state;
+pub mod telemetry_log;
+pub mod translate_history;
 
 use std::fs::{
     self,
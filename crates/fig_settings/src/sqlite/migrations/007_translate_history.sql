@@ -0,0 +1,9 @@
+CREATE TABLE translate_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    question TEXT NOT NULL,
+    refinements TEXT,
+    command TEXT NOT NULL,
+    action TEXT,
+    cwd TEXT,
+    time INTEGER NOT NULL
+);
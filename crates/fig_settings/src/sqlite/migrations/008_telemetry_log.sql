@@ -0,0 +1,7 @@
+CREATE TABLE telemetry_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL,
+    fields TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
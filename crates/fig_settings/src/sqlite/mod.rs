@@ -65,7 +65,10 @@ const MIGRATIONS: &[Migration] = migrations![
     "002_drop_history_in_ssh_docker",
     "003_improved_history_timing",
     "004_state_table",
-    "005_auth_table"
+    "005_auth_table",
+    "006_history_output",
+    "007_translate_history",
+    "008_telemetry_log"
 ];
 
 #[derive(Debug, Clone)]
@@ -109,7 +112,7 @@ impl Db {
         Ok(Self { pool })
     }
 
-    pub(crate) fn mock() -> Self {
+    pub fn mock() -> Self {
         let conn = SqliteConnectionManager::memory();
         let pool = Pool::builder().build(conn).unwrap();
         Self { pool }
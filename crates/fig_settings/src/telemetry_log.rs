@@ -0,0 +1,197 @@
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use inner::Inner;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use tracing::trace;
+
+use crate::sqlite::{
+    Db,
+    database,
+};
+use crate::{
+    Map,
+    Result,
+};
+
+/// How many records are kept, the oldest are dropped first
+const MAX_RECORDS: i64 = 1000;
+
+/// A telemetry event as it was sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryRecord {
+    /// The name of the metric, e.g. `codewhispererterminal_cliSubcommandExecuted`
+    pub name: String,
+    /// The category the event was allowed under, e.g. `featureUsage`
+    pub category: String,
+    /// Every field that was sent along with the event
+    pub fields: Map,
+    pub time: SystemTime,
+}
+
+/// A local audit log of the telemetry that left this machine
+#[derive(Debug, Default)]
+pub struct TelemetryLog(inner::Inner);
+
+mod inner {
+    use crate::sqlite::Db;
+
+    #[derive(Debug, Default)]
+    pub enum Inner {
+        #[default]
+        Global,
+        Owned(Db),
+    }
+}
+
+impl TelemetryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mock() -> Self {
+        let db = Db::mock();
+        db.migrate().expect("Failed to migrate database");
+        Self(inner::Inner::Owned(db))
+    }
+
+    fn db(&self) -> Result<&Db> {
+        match &self.0 {
+            Inner::Owned(db) => Ok(db),
+            Inner::Global => Ok(database()?),
+        }
+    }
+
+    fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
+        Ok(self.db()?.pool.get()?)
+    }
+
+    pub fn insert(&self, record: &TelemetryRecord) -> Result<()> {
+        trace!("Inserting telemetry record: {:?}", record);
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO telemetry_log (name, category, fields, time) VALUES (?, ?, ?, ?)",
+            params![
+                &record.name,
+                &record.category,
+                serde_json::to_string(&record.fields)?,
+                record
+                    .time
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .and_then(|d| i64::try_from(d.as_millis()).ok())
+                    .unwrap_or_default(),
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM telemetry_log WHERE id <= (SELECT MAX(id) FROM telemetry_log) - ?",
+            params![MAX_RECORDS],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent records first, only including those whose name contains `name` and
+    /// whose category is `category` when given
+    pub fn search(&self, name: Option<&str>, category: Option<&str>, limit: usize) -> Result<Vec<TelemetryRecord>> {
+        let conn = self.conn()?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+        let mut stmt = conn.prepare(
+            "SELECT name, category, fields, time FROM telemetry_log
+                WHERE (?1 IS NULL OR name LIKE ?1 ESCAPE '\\')
+                    AND (?2 IS NULL OR category = ?2)
+                ORDER BY time DESC, id DESC
+                LIMIT ?3",
+        )?;
+
+        let pattern = name.map(|name| {
+            let escaped = name.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+
+        let rows = stmt.query_map(params![pattern, category, limit], map_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Deletes every record
+    pub fn clear(&self) -> Result<()> {
+        self.conn()?.execute("DELETE FROM telemetry_log", [])?;
+        Ok(())
+    }
+}
+
+fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TelemetryRecord> {
+    let fields = row
+        .get::<_, String>(2)
+        .ok()
+        .and_then(|fields| serde_json::from_str(&fields).ok())
+        .unwrap_or_default();
+
+    let time = row
+        .get::<_, i64>(3)
+        .ok()
+        .and_then(|t| UNIX_EPOCH.checked_add(Duration::from_millis(u64::try_from(t).ok()?)))
+        .unwrap_or(UNIX_EPOCH);
+
+    Ok(TelemetryRecord {
+        name: row.get(0)?,
+        category: row.get(1)?,
+        fields,
+        time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(name: &str, category: &str, millis: u64) -> TelemetryRecord {
+        let mut fields = Map::new();
+        fields.insert("result".into(), json!("Succeeded"));
+        TelemetryRecord {
+            name: name.into(),
+            category: category.into(),
+            fields,
+            time: UNIX_EPOCH + Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn insert_search() {
+        let log = TelemetryLog::mock();
+
+        let login = record("codewhispererterminal_userLoggedIn", "account", 100);
+        let chat = record("amazonq_startChat", "chatMetadata", 200);
+        log.insert(&login).unwrap();
+        log.insert(&chat).unwrap();
+
+        assert_eq!(log.search(None, None, 10).unwrap(), vec![chat.clone(), login.clone()]);
+        assert_eq!(log.search(None, None, 1).unwrap(), vec![chat.clone()]);
+        assert_eq!(log.search(Some("LOGGED"), None, 10).unwrap(), vec![login.clone()]);
+        assert_eq!(log.search(None, Some("chatMetadata"), 10).unwrap(), vec![chat.clone()]);
+        assert_eq!(log.search(Some("_start"), Some("account"), 10).unwrap(), vec![]);
+
+        log.clear().unwrap();
+        assert_eq!(log.search(None, None, 10).unwrap(), vec![]);
+
+        for i in 0..MAX_RECORDS + 5 {
+            log.insert(&record(
+                "codewhispererterminal_menuBarActioned",
+                "featureUsage",
+                i as u64,
+            ))
+            .unwrap();
+        }
+        let records = log.search(None, None, usize::MAX).unwrap();
+        assert_eq!(records.len() as i64, MAX_RECORDS);
+        assert_eq!(records.last().unwrap().time, UNIX_EPOCH + Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use inner::Inner;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use tracing::trace;
+
+use crate::Result;
+use crate::sqlite::{
+    Db,
+    database,
+};
+
+/// A command generated by `translate`, along with how it was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    pub question: String,
+    /// The follow up requests that refined the first suggestion, in the order they were asked
+    pub refinements: Vec<String>,
+    pub command: String,
+    /// What the user did with the command, e.g. `execute` or `copy`
+    pub action: Option<String>,
+    pub cwd: Option<String>,
+    pub time: SystemTime,
+}
+
+#[derive(Debug, Default)]
+pub struct TranslateHistory(inner::Inner);
+
+mod inner {
+    use crate::sqlite::Db;
+
+    #[derive(Debug, Default)]
+    pub enum Inner {
+        #[default]
+        Global,
+        Owned(Db),
+    }
+}
+
+impl TranslateHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mock() -> Self {
+        let db = Db::mock();
+        db.migrate().expect("Failed to migrate database");
+        Self(inner::Inner::Owned(db))
+    }
+
+    fn db(&self) -> Result<&Db> {
+        match &self.0 {
+            Inner::Owned(db) => Ok(db),
+            Inner::Global => Ok(database()?),
+        }
+    }
+
+    fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
+        Ok(self.db()?.pool.get()?)
+    }
+
+    pub fn insert(&self, translation: &Translation) -> Result<()> {
+        trace!("Inserting translation into history: {:?}", translation);
+        let refinements = if translation.refinements.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&translation.refinements)?)
+        };
+
+        self.conn()?.execute(
+            "INSERT INTO translate_history (question, refinements, command, action, cwd, time)
+                VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                &translation.question,
+                refinements,
+                &translation.command,
+                &translation.action,
+                &translation.cwd,
+                translation
+                    .time
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .and_then(|d| i64::try_from(d.as_secs()).ok())
+                    .unwrap_or_default(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent translations first, only including those whose question, refinements
+    /// or command contain `query` when one is given
+    pub fn search(&self, query: Option<&str>, limit: usize) -> Result<Vec<Translation>> {
+        let conn = self.conn()?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+        let mut stmt = conn.prepare(
+            "SELECT question, refinements, command, action, cwd, time FROM translate_history
+                WHERE ?1 IS NULL
+                    OR question LIKE ?1 ESCAPE '\\'
+                    OR refinements LIKE ?1 ESCAPE '\\'
+                    OR command LIKE ?1 ESCAPE '\\'
+                ORDER BY time DESC, id DESC
+                LIMIT ?2",
+        )?;
+
+        let pattern = query.map(|query| {
+            let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+
+        let rows = stmt.query_map(params![pattern, limit], map_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Translation> {
+    let refinements = row
+        .get::<_, Option<String>>(1)?
+        .and_then(|refinements| serde_json::from_str(&refinements).ok())
+        .unwrap_or_default();
+
+    let time = row
+        .get::<_, i64>(5)
+        .ok()
+        .and_then(|t| UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(t).ok()?)))
+        .unwrap_or(UNIX_EPOCH);
+
+    Ok(Translation {
+        question: row.get(0)?,
+        refinements,
+        command: row.get(2)?,
+        action: row.get(3)?,
+        cwd: row.get(4)?,
+        time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(question: &str, refinements: &[&str], command: &str, secs: u64) -> Translation {
+        Translation {
+            question: question.into(),
+            refinements: refinements.iter().map(|r| (*r).into()).collect(),
+            command: command.into(),
+            action: Some("execute".into()),
+            cwd: Some("/home/user".into()),
+            time: UNIX_EPOCH + Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn insert_search() {
+        let history = TranslateHistory::mock();
+
+        let find = translation(
+            "find large files",
+            &["make it recursive", "only .rs files"],
+            "find . -name '*.rs' -size +1M",
+            100,
+        );
+        let list = translation("list 100% of files", &[], "ls -a", 200);
+        history.insert(&find).unwrap();
+        history.insert(&list).unwrap();
+
+        assert_eq!(history.search(None, 10).unwrap(), vec![list.clone(), find.clone()]);
+        assert_eq!(history.search(None, 1).unwrap(), vec![list.clone()]);
+        assert_eq!(history.search(Some("RECURSIVE"), 10).unwrap(), vec![find.clone()]);
+        assert_eq!(history.search(Some("ls -a"), 10).unwrap(), vec![list.clone()]);
+        assert_eq!(history.search(Some("100%"), 10).unwrap(), vec![list]);
+        assert_eq!(history.search(Some("_"), 10).unwrap(), vec![]);
+    }
+}
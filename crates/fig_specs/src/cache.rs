@@ -0,0 +1,347 @@
+//! The on disk cache of files downloaded from the spec CDNs
+//!
+//! Every file is stored under a directory named after its source with its metadata in a
+//! `.meta.json` file next to it. Cached files are used as is until they are older than the max age,
+//! then they are revalidated with their etag. If the source can't be reached the cached file is
+//! used no matter its age, and pinned files are never revalidated.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+use fig_request::reqwest::Client;
+use fig_util::directories;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tracing::{
+    debug,
+    warn,
+};
+
+use crate::cdn::{
+    CdnSource,
+    Fetched,
+};
+use crate::{
+    Result,
+    join_relative,
+};
+
+const META_SUFFIX: &str = ".meta.json";
+
+/// How long a cached file is used before it is revalidated
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refresh {
+    /// Revalidate the cached file if it is older than the max age
+    IfStale,
+    /// Always revalidate the cached file unless it is pinned
+    Always,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMeta {
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+    #[serde(with = "time::serde::timestamp")]
+    pub fetched_at: OffsetDateTime,
+    /// Pinned files are kept at the cached version
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedFile {
+    pub meta: CacheMeta,
+    pub bytes: Vec<u8>,
+}
+
+/// A file in the cache, see [`SpecCache::list`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    /// The [name](CdnSource::name) of the source the file is from
+    pub source: String,
+    /// The path of the file on the source
+    pub path: String,
+    pub size: u64,
+    #[serde(flatten)]
+    pub meta: CacheMeta,
+}
+
+impl CacheEntry {
+    /// The name of the spec if the file is one
+    pub fn spec_name(&self) -> Option<&str> {
+        self.path.strip_suffix(".js")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpecCache {
+    dir: PathBuf,
+    max_age: Duration,
+}
+
+impl SpecCache {
+    /// The cache in [`directories::autocomplete_spec_cache_dir`]
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_dir(directories::autocomplete_spec_cache_dir()?))
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age: MAX_AGE,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn file_path(&self, source: &str, path: &str) -> Result<PathBuf> {
+        join_relative(&join_relative(&self.dir, source)?, path)
+    }
+
+    fn is_stale(&self, meta: &CacheMeta, now: OffsetDateTime) -> bool {
+        now - meta.fetched_at >= self.max_age
+    }
+
+    /// Reads a file from the cache without checking if it is current
+    pub async fn read(&self, source: &str, path: &str) -> Result<Option<CachedFile>> {
+        let file_path = self.file_path(source, path)?;
+
+        let meta = match tokio::fs::read(meta_path(&file_path)).await {
+            Ok(meta) => serde_json::from_slice(&meta)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        match tokio::fs::read(&file_path).await {
+            Ok(bytes) => Ok(Some(CachedFile { meta, bytes })),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn write(&self, source: &str, path: &str, file: &CachedFile) -> Result<()> {
+        let file_path = self.file_path(source, path)?;
+        if let Some(parent) = file_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        write_atomic(&file_path, &file.bytes).await?;
+        self.write_meta(&file_path, &file.meta).await
+    }
+
+    async fn write_meta(&self, file_path: &Path, meta: &CacheMeta) -> Result<()> {
+        write_atomic(&meta_path(file_path), &serde_json::to_vec_pretty(meta)?).await
+    }
+
+    /// Gets `path` from `source`, using the cached file if it is current
+    ///
+    /// If the source can't be reached a cached file is returned no matter how old it is.
+    pub async fn get(&self, client: &Client, source: &CdnSource, path: &str, refresh: Refresh) -> Result<CachedFile> {
+        let cached = self.read(source.name(), path).await?;
+        let now = OffsetDateTime::now_utc();
+
+        if let Some(cached) = &cached {
+            let revalidate = match refresh {
+                Refresh::IfStale => self.is_stale(&cached.meta, now),
+                Refresh::Always => true,
+            };
+            if cached.meta.pinned || !revalidate {
+                return Ok(cached.clone());
+            }
+        }
+
+        let etag = cached.as_ref().and_then(|cached| cached.meta.etag.as_deref());
+        match (source.fetch(client, path, etag).await, cached) {
+            (Ok(Fetched::NotModified), Some(mut cached)) => {
+                debug!(source = source.name(), path, "Cached spec file is current");
+                cached.meta.fetched_at = now;
+                self.write_meta(&self.file_path(source.name(), path)?, &cached.meta)
+                    .await?;
+                Ok(cached)
+            },
+            (
+                Ok(Fetched::Modified {
+                    bytes,
+                    etag,
+                    content_type,
+                }),
+                _,
+            ) => {
+                debug!(source = source.name(), path, "Caching spec file");
+                let file = CachedFile {
+                    meta: CacheMeta {
+                        etag,
+                        content_type,
+                        fetched_at: now,
+                        pinned: false,
+                    },
+                    bytes,
+                };
+                self.write(source.name(), path, &file).await?;
+                Ok(file)
+            },
+            (Ok(Fetched::NotModified), None) => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{path} is not modified but not cached"),
+            )
+            .into()),
+            (Err(err), Some(cached)) => {
+                warn!(%err, source = source.name(), path, "Failed to revalidate spec file, using the cached one");
+                Ok(cached)
+            },
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    /// Pins or unpins a cached file, returns false if the file is not cached
+    pub async fn set_pinned(&self, source: &str, path: &str, pinned: bool) -> Result<bool> {
+        let Some(mut cached) = self.read(source, path).await? else {
+            return Ok(false);
+        };
+
+        cached.meta.pinned = pinned;
+        self.write_meta(&self.file_path(source, path)?, &cached.meta).await?;
+        Ok(true)
+    }
+
+    /// Every file in the cache, sorted by source and path
+    pub async fn list(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = vec![];
+        let mut dirs = vec![self.dir.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            let mut read_dir = match tokio::fs::read_dir(&dir).await {
+                Ok(read_dir) => read_dir,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+
+                let Some(file_path) = path
+                    .to_str()
+                    .and_then(|path| path.strip_suffix(META_SUFFIX))
+                    .map(PathBuf::from)
+                else {
+                    continue;
+                };
+
+                let Ok(relative) = file_path.strip_prefix(&self.dir) else {
+                    continue;
+                };
+                let mut components = relative.iter().filter_map(|component| component.to_str());
+                let Some(source) = components.next() else {
+                    continue;
+                };
+                let path = components.collect::<Vec<_>>().join("/");
+
+                let (Ok(meta), Ok(metadata)) = (
+                    tokio::fs::read(entry.path()).await,
+                    tokio::fs::metadata(&file_path).await,
+                ) else {
+                    continue;
+                };
+                let Ok(meta) = serde_json::from_slice(&meta) else {
+                    continue;
+                };
+
+                entries.push(CacheEntry {
+                    source: source.to_owned(),
+                    path,
+                    size: metadata.len(),
+                    meta,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| (&a.source, &a.path).cmp(&(&b.source, &b.path)));
+        Ok(entries)
+    }
+}
+
+fn meta_path(file_path: &Path) -> PathBuf {
+    let mut path = file_path.as_os_str().to_owned();
+    path.push(META_SUFFIX);
+    path.into()
+}
+
+/// Writes through a temporary file so the desktop app and the cli never read a partial file
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(fetched_at: OffsetDateTime) -> CacheMeta {
+        CacheMeta {
+            etag: Some("\"abc\"".into()),
+            content_type: Some("application/javascript".into()),
+            fetched_at,
+            pinned: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SpecCache::with_dir(dir.path());
+        assert!(cache.read("cdn", "git.js").await.unwrap().is_none());
+
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let file = CachedFile {
+            meta: meta(now),
+            bytes: b"export default {}".to_vec(),
+        };
+        cache.write("cdn", "git.js", &file).await.unwrap();
+        cache.write("cdn", "aws/index.js", &file).await.unwrap();
+
+        let read = cache.read("cdn", "git.js").await.unwrap().unwrap();
+        assert_eq!(read.meta, file.meta);
+        assert_eq!(read.bytes, file.bytes);
+
+        assert!(cache.set_pinned("cdn", "git.js", true).await.unwrap());
+        assert!(!cache.set_pinned("cdn", "npm.js", true).await.unwrap());
+
+        let entries = cache.list().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "aws/index.js");
+        assert_eq!(entries[1].spec_name(), Some("git"));
+        assert_eq!(entries[1].size, 17);
+        assert!(entries[1].meta.pinned);
+
+        assert!(cache.read("cdn", "../git.js").await.is_err());
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let cache = SpecCache::with_dir("/cache");
+        let now = OffsetDateTime::now_utc();
+        assert!(!cache.is_stale(&meta(now), now));
+        assert!(!cache.is_stale(&meta(now - Duration::from_secs(60)), now));
+        assert!(cache.is_stale(&meta(now - MAX_AGE), now));
+    }
+}
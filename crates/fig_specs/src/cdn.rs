@@ -0,0 +1,248 @@
+//! The CDNs completion specs are published to
+
+use std::sync::LazyLock;
+
+use fig_auth::builder_id_token;
+use fig_request::reqwest::header::{
+    CONTENT_TYPE,
+    ETAG,
+    IF_NONE_MATCH,
+};
+use fig_request::reqwest::{
+    Client,
+    StatusCode,
+};
+use futures::future;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::error;
+use url::Url;
+
+use crate::cache::{
+    Refresh,
+    SpecCache,
+};
+use crate::{
+    INDEX_PATH,
+    Result,
+};
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthType {
+    None,
+    Midway,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdnSource {
+    pub url: Url,
+    pub auth_type: AuthType,
+}
+
+pub static CDNS: LazyLock<Vec<CdnSource>> = LazyLock::new(|| {
+    vec![
+        // Public cdn
+        CdnSource {
+            url: "https://specs.q.us-east-1.amazonaws.com".try_into().unwrap(),
+            auth_type: AuthType::None,
+        },
+        // Internal Amazon spec cdn
+        CdnSource {
+            url: "https://prod.us-east-1.shellspecs.jupiter.ai.aws.dev"
+                .try_into()
+                .unwrap(),
+            auth_type: AuthType::Midway,
+        },
+    ]
+});
+
+/// The response to a request for a file on a [`CdnSource`]
+#[derive(Debug)]
+pub(crate) enum Fetched {
+    /// The cached file with the etag that was sent is still current
+    NotModified,
+    Modified {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+        content_type: Option<String>,
+    },
+}
+
+impl CdnSource {
+    /// The name of the source, files from it are cached in a directory of this name
+    pub fn name(&self) -> &str {
+        self.url.host_str().unwrap_or_default()
+    }
+
+    /// If the current user can access the source, the internal cdn is only available to Amazon
+    /// users
+    pub async fn is_available(&self) -> bool {
+        match self.auth_type {
+            AuthType::Midway => match builder_id_token().await {
+                Ok(token) => token.is_some_and(|token| token.is_amzn_user()),
+                Err(err) => {
+                    error!(%err, "Failed to load auth");
+                    false
+                },
+            },
+            AuthType::None => true,
+        }
+    }
+
+    /// Requests `path` from the source, a request with an `etag` is answered with
+    /// [`Fetched::NotModified`] if the file didn't change
+    pub(crate) async fn fetch(&self, client: &Client, path: &str, etag: Option<&str>) -> Result<Fetched> {
+        let mut url = self.url.clone();
+        url.set_path(path);
+
+        let response = match self.auth_type {
+            // Requests through midway can't be conditional, the file is always downloaded
+            AuthType::Midway => fig_request::midway::midway_request(url).await?,
+            AuthType::None => {
+                let mut request = client.get(url);
+                if let Some(etag) = etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                request.send().await?
+            },
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
+
+        let response = response.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let etag = header(ETAG);
+        let content_type = header(CONTENT_TYPE);
+
+        Ok(Fetched::Modified {
+            bytes: response.bytes().await?.to_vec(),
+            etag,
+            content_type,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecIndex {
+    pub completions: Vec<String>,
+    pub diff_versioned_completions: Vec<String>,
+}
+
+impl SpecIndex {
+    pub fn contains(&self, name: &str) -> bool {
+        self.completions.iter().any(|completion| completion == name)
+    }
+
+    /// Merges the indexes into one with every spec listed once, in sorted order
+    pub fn merge<'a>(indexes: impl IntoIterator<Item = &'a SpecIndex>) -> SpecIndex {
+        let mut merged = SpecIndex::default();
+        for index in indexes {
+            merged.completions.extend(index.completions.iter().cloned());
+            merged
+                .diff_versioned_completions
+                .extend(index.diff_versioned_completions.iter().cloned());
+        }
+
+        for list in [&mut merged.completions, &mut merged.diff_versioned_completions] {
+            list.sort();
+            list.dedup();
+        }
+
+        merged
+    }
+}
+
+/// The index of the specs published to a source
+#[derive(Debug, Clone)]
+pub struct SourceIndex {
+    pub source: CdnSource,
+    pub index: SpecIndex,
+}
+
+/// Loads the index of every source available to the current user through the cache
+pub async fn indexes(cache: &SpecCache, client: &Client, refresh: Refresh) -> Vec<Result<SourceIndex>> {
+    future::join_all(CDNS.iter().map(|source| async move {
+        if !source.is_available().await {
+            return None;
+        }
+
+        let index = async {
+            let file = cache.get(client, source, INDEX_PATH, refresh).await?;
+            Ok(SourceIndex {
+                source: source.clone(),
+                index: serde_json::from_slice(&file.bytes)?,
+            })
+        }
+        .await;
+
+        if let Err(err) = &index {
+            error!(%err, source = source.name(), "Failed to load spec index");
+        }
+
+        Some(index)
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The source to load the spec `name` from, the first source listing it in its index or the public
+/// cdn if none do
+pub fn source_for<'a>(indexes: impl IntoIterator<Item = &'a SourceIndex>, name: &str) -> &'a CdnSource {
+    indexes
+        .into_iter()
+        .find(|index| index.index.contains(name))
+        .map_or(&CDNS[0], |index| &index.source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(completions: &[&str]) -> SpecIndex {
+        SpecIndex {
+            completions: completions.iter().map(|s| (*s).to_owned()).collect(),
+            diff_versioned_completions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let merged = SpecIndex::merge([&index(&["git", "aws"]), &index(&["cargo", "git"])]);
+        assert_eq!(merged.completions, vec!["aws", "cargo", "git"]);
+        assert!(merged.contains("cargo"));
+        assert!(!merged.contains("npm"));
+    }
+
+    #[test]
+    fn test_source_for() {
+        let indexes = vec![
+            SourceIndex {
+                source: CDNS[0].clone(),
+                index: index(&["git"]),
+            },
+            SourceIndex {
+                source: CDNS[1].clone(),
+                index: index(&["git", "brazil"]),
+            },
+        ];
+
+        assert_eq!(source_for(&indexes, "git"), &CDNS[0]);
+        assert_eq!(source_for(&indexes, "brazil"), &CDNS[1]);
+        assert_eq!(source_for(&indexes, "npm"), &CDNS[0]);
+        assert_eq!(CDNS[0].name(), "specs.q.us-east-1.amazonaws.com");
+    }
+}
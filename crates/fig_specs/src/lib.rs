@@ -0,0 +1,96 @@
+//! Completion specs for autocomplete
+//!
+//! Specs are published to the spec CDNs and cached on disk so autocomplete keeps working offline,
+//! specs authored locally in [`autocomplete_specs_dir`] take precedence over the published ones.
+//!
+//! [`autocomplete_specs_dir`]: fig_util::directories::autocomplete_specs_dir
+
+pub mod cache;
+pub mod cdn;
+pub mod local;
+pub mod module;
+pub mod spec;
+
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+
+pub use cache::{
+    CacheEntry,
+    CacheMeta,
+    CachedFile,
+    Refresh,
+    SpecCache,
+};
+pub use cdn::{
+    AuthType,
+    CDNS,
+    CdnSource,
+    SourceIndex,
+    SpecIndex,
+};
+use thiserror::Error;
+
+/// The path of the spec index on every source
+pub const INDEX_PATH: &str = "index.json";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Request(#[from] fig_request::Error),
+    #[error(transparent)]
+    Reqwest(#[from] fig_request::reqwest::Error),
+    #[error(transparent)]
+    Directory(#[from] fig_util::directories::DirectoryError),
+    #[error("invalid spec path: {0}")]
+    InvalidPath(String),
+    #[error("the module does not export a spec")]
+    NoExport,
+    #[error("the spec can only be completed by the desktop app, {0} can't be read")]
+    Unsupported(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The path of the spec `name` relative to the root of a source
+pub fn spec_path(name: &str) -> String {
+    format!("{name}.js")
+}
+
+/// Joins `path` onto `dir`, paths that could escape `dir` are rejected
+pub(crate) fn join_relative(dir: &Path, path: &str) -> Result<PathBuf> {
+    let path = path.trim_start_matches('/');
+    let relative = Path::new(path);
+
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(Error::InvalidPath(path.into()));
+    }
+
+    Ok(dir.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_relative() {
+        let dir = Path::new("/cache");
+        assert_eq!(join_relative(dir, "git.js").unwrap(), dir.join("git.js"));
+        assert_eq!(join_relative(dir, "/aws/s3.js").unwrap(), dir.join("aws/s3.js"));
+        assert!(join_relative(dir, "../git.js").is_err());
+        assert!(join_relative(dir, "aws/../../git.js").is_err());
+        assert!(join_relative(dir, "./git.js").is_err());
+        assert!(join_relative(dir, "").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Specs authored locally, the compiled `.js` output of a spec is placed in the specs directory
+
+use std::path::Path;
+
+use crate::{
+    Result,
+    SpecIndex,
+    join_relative,
+};
+
+/// The index of the specs in `dir`, every `.js` file at the top of the directory is a spec
+pub async fn index(dir: &Path) -> Result<SpecIndex> {
+    let mut index = SpecIndex::default();
+
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(index),
+        Err(err) => return Err(err.into()),
+    };
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        if !entry.file_type().await?.is_file() {
+            continue;
+        }
+
+        if let Some(name) = entry.file_name().to_str().and_then(|name| name.strip_suffix(".js")) {
+            index.completions.push(name.to_owned());
+        }
+    }
+
+    index.completions.sort();
+    Ok(index)
+}
+
+/// Reads `path` from `dir`, returns `None` if there is no such file
+pub async fn read(dir: &Path, path: &str) -> Result<Option<Vec<u8>>> {
+    match tokio::fs::read(join_relative(dir, path)?).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_specs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(index(&dir.path().join("missing")).await.unwrap(), SpecIndex::default());
+
+        std::fs::write(dir.path().join("mytool.js"), "export default {}").unwrap();
+        std::fs::write(dir.path().join("deploy.js"), "export default {}").unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        std::fs::create_dir(dir.path().join("other.js")).unwrap();
+
+        assert_eq!(index(dir.path()).await.unwrap().completions, vec!["deploy", "mytool"]);
+        assert_eq!(
+            read(dir.path(), "/mytool.js").await.unwrap(),
+            Some(b"export default {}".to_vec())
+        );
+        assert_eq!(read(dir.path(), "/missing.js").await.unwrap(), None);
+        assert!(read(dir.path(), "/../mytool.js").await.is_err());
+    }
+}
@@ -0,0 +1,720 @@
+//! Reads the static parts of a compiled spec module
+//!
+//! Specs are javascript modules that export the spec object, either written by hand, generated by
+//! `q specs generate` or minified by esbuild on the CDN. This is not a javascript engine, only
+//! literals, objects, arrays and references to top level variables are evaluated. Specs with any
+//! part that can't be evaluated are rejected with [`Error::Unsupported`] rather than read with that
+//! part missing, which covers all of the dynamic parts of a spec:
+//!   - `generators`, e.g. the branches suggested for `git checkout`
+//!   - `script`s and `postProcess`, `custom` and other functions
+//!   - `generateSpec` and `loadSpec`, specs that build their subcommands at runtime
+//!   - computed keys and values, like template literals with substitutions
+//!
+//! Only the desktop app can complete those specs, a spec read here is complete.
+
+use std::collections::HashMap;
+
+use serde_json::{
+    Map,
+    Number,
+    Value,
+};
+
+use crate::spec::Subcommand;
+use crate::{
+    Error,
+    Result,
+};
+
+/// Keys of a spec that are only used by the desktop app, even when their value is a literal
+const DYNAMIC_KEYS: &[&str] = &["generators", "generateSpec", "loadSpec"];
+
+/// Parses the spec exported by the module `source`
+pub fn parse(source: &str) -> Result<Subcommand> {
+    let export = evaluate(source).ok_or(Error::NoExport)?;
+    if let Some(dropped) = export.dropped {
+        return Err(Error::Unsupported(dropped));
+    }
+    Ok(serde_json::from_value(export.value)?)
+}
+
+/// The value of the default export of the module `source`, without the parts that couldn't be
+/// evaluated
+pub fn export(source: &str) -> Option<Value> {
+    evaluate(source).map(|export| export.value)
+}
+
+fn evaluate(source: &str) -> Option<Evaluated> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        pos: 0,
+        bindings: HashMap::new(),
+        dropped: None,
+    };
+    parser.module()
+}
+
+/// A value and the first part of it that couldn't be evaluated
+#[derive(Debug, Clone)]
+struct Evaluated {
+    value: Value,
+    dropped: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Punct(char),
+    Spread,
+    /// A token that can't be evaluated, a regex or a template literal with substitutions
+    Other,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            },
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            },
+            '/' if regex_allowed(tokens.last()) => {
+                i = skip_regex(&chars, i);
+                tokens.push(Token::Other);
+            },
+            '"' | '\'' => {
+                let (string, end) = read_string(&chars, i);
+                tokens.push(Token::Str(string));
+                i = end;
+            },
+            '`' => {
+                let (token, end) = read_template(&chars, i);
+                tokens.push(token);
+                i = end;
+            },
+            '.' if chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') => {
+                tokens.push(Token::Spread);
+                i += 3;
+            },
+            c if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                tokens.push(text.parse().map_or(Token::Other, Token::Num));
+            },
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            },
+            c => {
+                tokens.push(Token::Punct(c));
+                i += 1;
+            },
+        }
+    }
+
+    tokens
+}
+
+/// If a `/` after `last` starts a regex rather than being a division
+fn regex_allowed(last: Option<&Token>) -> bool {
+    match last {
+        None => true,
+        Some(Token::Punct(c)) => !matches!(c, ')' | ']' | '}'),
+        Some(Token::Ident(ident)) => matches!(ident.as_str(), "return" | "typeof" | "case" | "in" | "of"),
+        Some(_) => false,
+    }
+}
+
+fn skip_regex(chars: &[char], start: usize) -> usize {
+    let mut i = start + 1;
+    let mut in_class = false;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => break,
+            '\n' => return i,
+            _ => {},
+        }
+        i += 1;
+    }
+
+    i += 1;
+    while i < chars.len() && chars[i].is_ascii_alphabetic() {
+        i += 1;
+    }
+    i
+}
+
+fn read_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let mut string = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() && chars[i] != quote {
+        if chars[i] == '\\' {
+            i += 1;
+            let Some(&escaped) = chars.get(i) else {
+                break;
+            };
+            match escaped {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'b' => string.push('\u{8}'),
+                'f' => string.push('\u{c}'),
+                'v' => string.push('\u{b}'),
+                '0' => string.push('\0'),
+                'x' | 'u' => {
+                    let digits = if escaped == 'x' { 2 } else { 4 };
+                    let (hex, len) = if escaped == 'u' && chars.get(i + 1) == Some(&'{') {
+                        let len = chars[i + 2..].iter().take_while(|c| **c != '}').count();
+                        (chars[i + 2..i + 2 + len].iter().collect::<String>(), len + 2)
+                    } else {
+                        let end = (i + 1 + digits).min(chars.len());
+                        (chars[i + 1..end].iter().collect::<String>(), digits)
+                    };
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        string.push(c);
+                    }
+                    i += len;
+                },
+                // A line continuation
+                '\n' => {},
+                c => string.push(c),
+            }
+        } else {
+            string.push(chars[i]);
+        }
+        i += 1;
+    }
+
+    (string, i + 1)
+}
+
+/// Template literals without substitutions are plain strings
+fn read_template(chars: &[char], start: usize) -> (Token, usize) {
+    let mut string = String::new();
+    let mut substitution = false;
+    let mut i = start + 1;
+
+    while i < chars.len() && chars[i] != '`' {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                if let Some(&c) = chars.get(i) {
+                    string.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        c => c,
+                    });
+                }
+            },
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                substitution = true;
+                let mut depth = 0;
+                while i < chars.len() {
+                    match chars[i] {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        },
+                        _ => {},
+                    }
+                    i += 1;
+                }
+            },
+            c => string.push(c),
+        }
+        i += 1;
+    }
+
+    let token = if substitution { Token::Other } else { Token::Str(string) };
+    (token, i + 1)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    bindings: HashMap<String, Evaluated>,
+    /// The first part of the value being evaluated that couldn't be
+    dropped: Option<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(i)) if i == ident)
+    }
+
+    fn drop(&mut self, what: impl Into<String>) {
+        self.dropped.get_or_insert_with(|| what.into());
+    }
+
+    /// Evaluates a whole value, keeping track of what was dropped from it on its own
+    fn evaluated(&mut self) -> Option<Evaluated> {
+        let outer = self.dropped.take();
+        let value = self.value();
+        let dropped = std::mem::replace(&mut self.dropped, outer);
+        value.map(|value| Evaluated { value, dropped })
+    }
+
+    fn module(&mut self) -> Option<Evaluated> {
+        let mut export = None;
+
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Ident(ident) if matches!(ident.as_str(), "var" | "let" | "const") => {
+                    self.pos += 1;
+                    self.declarations();
+                },
+                Token::Ident(ident) if ident == "export" => {
+                    self.pos += 1;
+                    if let Some(value) = self.export() {
+                        export = Some(value);
+                    }
+                },
+                _ => self.skip_statement(),
+            }
+        }
+
+        export
+    }
+
+    /// `a = 1, b = {}` after `var`, `let` or `const`
+    fn declarations(&mut self) {
+        loop {
+            let Some(Token::Ident(name)) = self.next() else {
+                self.skip_statement();
+                return;
+            };
+
+            if self.eat_punct('=') {
+                if let Some(evaluated) = self.evaluated() {
+                    self.bindings.insert(name, evaluated);
+                }
+            }
+
+            if !self.eat_punct(',') {
+                self.eat_punct(';');
+                return;
+            }
+        }
+    }
+
+    /// `export default value` or `export { name as default }`
+    fn export(&mut self) -> Option<Evaluated> {
+        if self.is_ident("default") {
+            self.pos += 1;
+            let evaluated = self.evaluated();
+            self.eat_punct(';');
+            return evaluated;
+        }
+
+        if !self.eat_punct('{') {
+            self.skip_statement();
+            return None;
+        }
+
+        let mut export = None;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Punct('}') => break,
+                Token::Ident(name) if self.is_ident("as") => {
+                    self.pos += 1;
+                    if self.is_ident("default") {
+                        self.pos += 1;
+                        export = self.bindings.get(&name).cloned();
+                    }
+                },
+                _ => {},
+            }
+        }
+        self.eat_punct(';');
+        export
+    }
+
+    /// Skips to the end of a statement, the next `;` or declaration
+    fn skip_statement(&mut self) {
+        let start = self.pos;
+        let mut depth = 0_usize;
+
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct('(' | '[' | '{') => depth += 1,
+                Token::Punct(')' | ']' | '}') => depth = depth.saturating_sub(1),
+                Token::Punct(';') if depth == 0 => {
+                    self.pos += 1;
+                    return;
+                },
+                Token::Ident(ident)
+                    if depth == 0
+                        && self.pos != start
+                        && matches!(ident.as_str(), "var" | "let" | "const" | "export" | "function") =>
+                {
+                    return;
+                },
+                _ => {},
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skips the rest of an expression, up to the `,`, `;` or closing bracket after it
+    fn skip_expression(&mut self) {
+        let mut depth = 0_usize;
+
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct('(' | '[' | '{') => depth += 1,
+                Token::Punct(')' | ']' | '}') if depth == 0 => return,
+                Token::Punct(')' | ']' | '}') => depth -= 1,
+                Token::Punct(',' | ';') if depth == 0 => return,
+                _ => {},
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn at_expression_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Punct(',' | ';' | ')' | ']' | '}')))
+    }
+
+    /// The value a top level variable was bound to
+    fn binding(&mut self, name: &str) -> Option<Value> {
+        let Evaluated { value, dropped } = self.bindings.get(name)?.clone();
+        if let Some(dropped) = dropped {
+            self.drop(dropped);
+        }
+        Some(value)
+    }
+
+    /// Evaluates an expression, `None` if it isn't a literal
+    fn value(&mut self) -> Option<Value> {
+        let value = self.primary();
+        if self.at_expression_end() {
+            value
+        } else {
+            self.skip_expression();
+            None
+        }
+    }
+
+    fn primary(&mut self) -> Option<Value> {
+        let value = match self.next()? {
+            Token::Punct('{') => self.object(),
+            Token::Punct('[') => self.array(),
+            Token::Str(string) => Value::String(string),
+            Token::Num(number) => number_value(number)?,
+            Token::Punct('-') => match self.next()? {
+                Token::Num(number) => number_value(-number)?,
+                _ => return None,
+            },
+            // Minifiers write `true` and `false` as `!0` and `!1`
+            Token::Punct('!') => match self.next()? {
+                Token::Num(number) => Value::Bool(number == 0.0),
+                _ => return None,
+            },
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ if self.at_expression_end() => self.binding(&ident)?,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// The rest of an object after its `{`
+    fn object(&mut self) -> Value {
+        let mut map = Map::new();
+
+        loop {
+            let key = match self.next() {
+                None | Some(Token::Punct('}')) => break,
+                Some(Token::Spread) => {
+                    match self.value() {
+                        Some(Value::Object(spread)) => map.extend(spread),
+                        _ => self.drop("a spread object"),
+                    }
+                    self.eat_punct(',');
+                    continue;
+                },
+                Some(Token::Ident(key) | Token::Str(key)) => key,
+                Some(Token::Num(number)) => number.to_string(),
+                // Computed keys and anything else
+                Some(_) => {
+                    self.drop("a computed key");
+                    self.skip_expression();
+                    self.eat_punct(',');
+                    continue;
+                },
+            };
+
+            match self.peek() {
+                Some(Token::Punct(':')) => {
+                    self.pos += 1;
+                    match self.value() {
+                        Some(_) if DYNAMIC_KEYS.contains(&key.as_str()) => self.drop(key),
+                        Some(value) => {
+                            map.insert(key, value);
+                        },
+                        None => self.drop(key),
+                    }
+                },
+                // Shorthand `{ name }`
+                Some(Token::Punct(',' | '}')) => match self.binding(&key) {
+                    Some(value) => {
+                        map.insert(key, value);
+                    },
+                    None => self.drop(key),
+                },
+                // Methods and accessors
+                _ => {
+                    self.drop(key);
+                    self.skip_expression();
+                },
+            }
+
+            if self.eat_punct(',') {
+                continue;
+            }
+            if !self.eat_punct('}') {
+                // Not a well formed object, stop at the end of the expression
+                self.drop("an object");
+                self.skip_expression();
+                self.eat_punct('}');
+            }
+            break;
+        }
+
+        Value::Object(map)
+    }
+
+    /// The rest of an array after its `[`
+    fn array(&mut self) -> Value {
+        let mut values = vec![];
+
+        loop {
+            match self.peek() {
+                None => break,
+                Some(Token::Punct(']')) => {
+                    self.pos += 1;
+                    break;
+                },
+                Some(Token::Punct(',')) => {
+                    self.pos += 1;
+                    continue;
+                },
+                Some(Token::Spread) => {
+                    self.pos += 1;
+                    match self.value() {
+                        Some(Value::Array(spread)) => values.extend(spread),
+                        _ => self.drop("a spread array"),
+                    }
+                },
+                Some(_) => match self.value() {
+                    Some(value) => values.push(value),
+                    None => self.drop("an array element"),
+                },
+            }
+
+            if !self.eat_punct(',') {
+                if !self.eat_punct(']') {
+                    self.drop("an array");
+                    self.skip_expression();
+                    self.eat_punct(']');
+                }
+                break;
+            }
+        }
+
+        Value::Array(values)
+    }
+}
+
+fn number_value(number: f64) -> Option<Value> {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Some(Value::Number((number as i64).into()))
+    } else {
+        Number::from_f64(number).map(Value::Number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::spec::Template;
+
+    #[test]
+    fn test_generated_module() {
+        let module = Subcommand {
+            name: "tool".into(),
+            description: Some("A \"quoted\" tool".into()),
+            ..Default::default()
+        }
+        .to_module()
+        .unwrap();
+
+        assert_eq!(
+            export(&module),
+            Some(json!({ "name": "tool", "description": "A \"quoted\" tool" }))
+        );
+    }
+
+    #[test]
+    fn test_minified_module() {
+        let source = r#"var n={name:"branch",suggestions:["main"]},o=[{name:["-v","--verbose"],isRepeatable:!0}],e={name:"git",description:'The \'stupid\' content tracker',subcommands:[{name:"checkout",args:n,options:[...o,{name:"-b",args:{name:"new-branch"}}]},{name:"status",hidden:!1,options:o}],options:[{name:"--version"},{name:`--help`,description:`Show help`}],parserDirectives:{flagsArePosixNoncompliant:!0}},i=e;export{i as default};"#;
+
+        let spec = parse(source).unwrap();
+        assert_eq!(spec.name.first(), Some("git"));
+        assert_eq!(spec.description.as_deref(), Some("The 'stupid' content tracker"));
+        assert_eq!(spec.subcommands.len(), 2);
+        assert_eq!(spec.subcommands[0].args[0].name, "branch");
+        assert_eq!(spec.subcommands[0].args[0].suggestions, vec!["main"]);
+        assert_eq!(spec.subcommands[0].options.len(), 2);
+        assert!(spec.subcommands[0].options[0].is_repeatable);
+        assert_eq!(spec.subcommands[1].options.len(), 1);
+        assert_eq!(spec.options[1].name.first(), Some("--help"));
+        assert_eq!(spec.options[1].description.as_deref(), Some("Show help"));
+    }
+
+    #[test]
+    fn test_handwritten_module() {
+        let source = r#"
+            // A spec written by hand
+            const pathArg = { name: "path", template: ["filepaths", "folders"] };
+
+            function helper() {
+                return { name: "unused" };
+            }
+
+            const completionSpec = {
+                name: "tool",
+                args: pathArg,
+                options: [
+                    { name: "--count", args: { name: "n", suggestions: ["1", "2"], default: -1 } },
+                    { name: "--skip", priority: 1.5 },
+                ],
+            };
+            export default completionSpec;
+        "#;
+
+        let spec = parse(source).unwrap();
+        assert_eq!(spec.args[0].template, Some(Template::Filepaths));
+        assert_eq!(spec.options.len(), 2);
+        assert_eq!(spec.options[0].args[0].suggestions, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let unsupported = |spec: &str| match parse(&format!("const a = {{ name: \"tool\" }};\nexport default {spec};"))
+        {
+            Err(Error::Unsupported(dropped)) => dropped,
+            other => panic!("{spec} was read: {other:?}"),
+        };
+
+        // Generators, even without functions
+        assert_eq!(
+            unsupported(r#"{ name: "git", args: { name: "branch", generators: { template: "folders" } } }"#),
+            "generators"
+        );
+        assert_eq!(
+            unsupported(
+                r#"{ name: "git", args: { generators: { script: ["git", "branch"], postProcess: (out) => out.split("\n") } } }"#
+            ),
+            "postProcess"
+        );
+        assert_eq!(
+            unsupported(r#"{ name: "aws", subcommands: [{ name: "s3", loadSpec: "aws/s3" }] }"#),
+            "loadSpec"
+        );
+        assert_eq!(
+            unsupported(r#"{ name: "tool", generateSpec: async function () { return {}; } }"#),
+            "generateSpec"
+        );
+        // Methods, calls, computed keys and values
+        assert_eq!(unsupported(r#"{ name: "tool", custom() { return []; } }"#), "custom");
+        assert_eq!(
+            unsupported(r#"{ name: "tool", options: [helper()] }"#),
+            "an array element"
+        );
+        assert_eq!(unsupported(r#"{ name: "tool", ["a" + "b"]: 1 }"#), "a computed key");
+        assert_eq!(
+            unsupported(r#"{ name: "tool", description: `Run ${name}` }"#),
+            "description"
+        );
+        assert_eq!(
+            unsupported(r#"{ name: "tool", options: [...helper()] }"#),
+            "a spread array"
+        );
+        assert_eq!(unsupported(r#"{ name: "tool", args: missing }"#), "args");
+
+        // Through a variable, which is only a problem once it is used
+        let source = r#"const gen = { script: () => [] }, unused = { postProcess: (out) => out };
+            const completionSpec = { name: "tool", args: { name: "x", generators: gen } };
+            export default completionSpec;"#;
+        assert!(matches!(parse(source), Err(Error::Unsupported(dropped)) if dropped == "script"));
+        let source = r#"const unused = { postProcess: (out) => out };
+            export default { name: "tool" };"#;
+        assert!(parse(source).is_ok());
+
+        // What can be evaluated is still exported
+        assert_eq!(
+            export(r#"export default { name: "git", custom() { return []; }, hidden: true };"#),
+            Some(json!({ "name": "git", "hidden": true }))
+        );
+    }
+
+    #[test]
+    fn test_no_export() {
+        assert!(matches!(parse("const a = 1;"), Err(Error::NoExport)));
+    }
+}
@@ -0,0 +1,256 @@
+//! The completion spec format read by autocomplete, see `@withfig/autocomplete-types`
+//!
+//! Only the static parts of a spec are modeled, specs with generators or other functions are
+//! rejected by [`crate::module::parse`].
+
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+
+/// One or more names, serialized as a string when there is only one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Names(pub Vec<String>);
+
+impl Names {
+    pub fn first(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|n| n == name)
+    }
+}
+
+impl<S: Into<String>> From<S> for Names {
+    fn from(name: S) -> Self {
+        Names(vec![name.into()])
+    }
+}
+
+impl Serialize for Names {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [name] => serializer.serialize_str(name),
+            names => names.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Names {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Names(one_or_many(deserializer)?))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subcommand {
+    pub name: Names,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub subcommands: Vec<Subcommand>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub options: Vec<Opt>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub args: Vec<Arg>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Opt {
+    pub name: Names,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub args: Vec<Arg>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub is_required: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub is_repeatable: bool,
+    /// The option is also available to every subcommand
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub is_persistent: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Arg {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub is_optional: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", deserialize_with = "flag")]
+    pub is_variadic: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "suggestions")]
+    pub suggestions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "template")]
+    pub template: Option<Template>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Template {
+    Filepaths,
+    Folders,
+}
+
+impl Arg {
+    /// An arg named after its placeholder, `FILE` or `<dir>`, args that look like paths get a
+    /// template so autocomplete suggests paths for them
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let lower = name.to_lowercase();
+        let template = if ["dir", "folder"].iter().any(|s| lower.contains(s)) {
+            Some(Template::Folders)
+        } else if ["file", "path"].iter().any(|s| lower.contains(s)) {
+            Some(Template::Filepaths)
+        } else {
+            None
+        };
+
+        Arg {
+            name,
+            template,
+            ..Default::default()
+        }
+    }
+}
+
+impl Subcommand {
+    /// Renders the spec as the compiled javascript module autocomplete loads
+    pub fn to_module(&self) -> serde_json::Result<String> {
+        Ok(format!(
+            "const completionSpec = {};\nexport default completionSpec;\n",
+            serde_json::to_string_pretty(self)?
+        ))
+    }
+}
+
+/// A value or a list of values, specs may give a single arg or name without a list
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(values) => values,
+        OneOrMany::One(value) => vec![value],
+    })
+}
+
+/// A boolean, `isRepeatable` may also be the number of times an option can be repeated
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(value) => value,
+        serde_json::Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0),
+        _ => false,
+    })
+}
+
+/// The names of the suggestions, suggestions are either names or objects with a name
+fn suggestions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Suggestion {
+        Name(String),
+        Object { name: Names },
+    }
+
+    Ok(one_or_many::<_, Suggestion>(deserializer)?
+        .into_iter()
+        .filter_map(|suggestion| match suggestion {
+            Suggestion::Name(name) => Some(name),
+            Suggestion::Object { name } => name.0.into_iter().next(),
+        })
+        .collect())
+}
+
+/// The template of an arg, of a list of templates paths are preferred over folders and templates
+/// other than those two are ignored
+fn template<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Template>, D::Error> {
+    let templates: Vec<String> = one_or_many(deserializer)?;
+    Ok(if templates.iter().any(|t| t == "filepaths") {
+        Some(Template::Filepaths)
+    } else if templates.iter().any(|t| t == "folders") {
+        Some(Template::Folders)
+    } else {
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let spec = Subcommand {
+            name: "tool".into(),
+            options: vec![Opt {
+                name: Names(vec!["-o".into(), "--output".into()]),
+                args: vec![Arg::new("FILE")],
+                ..Default::default()
+            }],
+            args: vec![Arg {
+                is_variadic: true,
+                ..Arg::new("dir")
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::json!({
+                "name": "tool",
+                "options": [{
+                    "name": ["-o", "--output"],
+                    "args": [{ "name": "FILE", "template": "filepaths" }],
+                }],
+                "args": [{ "name": "dir", "isVariadic": true, "template": "folders" }],
+            })
+        );
+        assert!(
+            spec.to_module()
+                .unwrap()
+                .ends_with("};\nexport default completionSpec;\n")
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let spec: Subcommand = serde_json::from_value(serde_json::json!({
+            "name": ["checkout", "co"],
+            "args": { "name": "branch", "isOptional": true, "suggestions": ["main", { "name": ["dev"] }] },
+            "options": [{ "name": "-q", "isRepeatable": 2 }, { "name": "--file", "args": { "template": ["filepaths", "folders"] } }],
+        }))
+        .unwrap();
+
+        assert_eq!(spec.name, Names(vec!["checkout".into(), "co".into()]));
+        assert_eq!(spec.args, vec![Arg {
+            name: "branch".into(),
+            is_optional: true,
+            suggestions: vec!["main".into(), "dev".into()],
+            ..Default::default()
+        }]);
+        assert!(spec.options[0].is_repeatable);
+        assert_eq!(spec.options[1].args[0].template, Some(Template::Filepaths));
+    }
+}
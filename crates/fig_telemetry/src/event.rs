@@ -69,6 +69,7 @@ pub(crate) mod tests {
         SuggestionState,
         TelemetryResult,
     };
+    use fig_util::shell_words::AcceptKind;
 
     use super::*;
 
@@ -102,6 +103,8 @@ pub(crate) mod tests {
             suggestion_state: SuggestionState::Accept,
             edit_buffer_len: Some(123),
             suggested_chars_len: 42,
+            accepted_chars_len: Some(42),
+            accept_kind: Some(AcceptKind::Full),
             number_of_recommendations: 3,
             latency: Duration::from_millis(500),
             terminal: Some("vscode".into()),
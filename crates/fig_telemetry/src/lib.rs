@@ -50,15 +50,22 @@ pub use event::{
     InlineShellCompletionActionedOptions,
 };
 use fig_api_client::Client as CodewhispererClient;
+pub use fig_api_client::telemetry_category_enabled;
 use fig_aws_common::app_name;
 use fig_settings::State;
+use fig_settings::telemetry_log::{
+    TelemetryLog,
+    TelemetryRecord,
+};
 use fig_telemetry_core::{
     Event,
+    MetricDatum,
     TelemetryEmitter,
 };
 pub use fig_telemetry_core::{
     EventType,
     SuggestionState,
+    TelemetryCategory,
 };
 use fig_util::Shell;
 use fig_util::system_info::os_version;
@@ -184,6 +191,29 @@ pub async fn finish_telemetry_unwrap() {
     }
 }
 
+/// The audit log record of a metric, with the metric's metadata as its fields
+fn telemetry_record(category: TelemetryCategory, metric_datum: &MetricDatum) -> TelemetryRecord {
+    let mut fields = fig_settings::Map::new();
+    fields.insert("value".into(), metric_datum.value().into());
+    fields.insert("unit".into(), metric_datum.unit().as_str().into());
+    fields.insert("passive".into(), metric_datum.passive().into());
+    for entry in metric_datum.metadata() {
+        if let (Some(key), Some(value)) = (entry.key(), entry.value()) {
+            fields.insert(key.into(), value.into());
+        }
+    }
+
+    TelemetryRecord {
+        name: metric_datum.metric_name().into(),
+        category: category.to_string(),
+        fields,
+        time: u64::try_from(metric_datum.epoch_timestamp())
+            .ok()
+            .and_then(|millis| SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(millis)))
+            .unwrap_or_else(SystemTime::now),
+    }
+}
+
 fn opt_out_preference() -> OptOutPreference {
     if telemetry_is_disabled() {
         OptOutPreference::OptOut
@@ -267,6 +297,11 @@ impl Client {
         if telemetry_is_disabled() {
             return;
         }
+        let category = event.ty.category();
+        if !telemetry_category_enabled(category) {
+            debug!(%category, "Telemetry category is disabled, not posting metric");
+            return;
+        }
         let Some(toolkit_telemetry_client) = self.toolkit_telemetry_client.clone() else {
             return;
         };
@@ -284,6 +319,15 @@ impl Client {
                 let os_architecture = std::env::consts::ARCH;
                 let os_version = os_version().map(|v| v.to_string()).unwrap_or_default();
                 let metric_name = metric_datum.metric_name().to_owned();
+                let mut record = telemetry_record(category, &metric_datum);
+                record.fields.extend([
+                    ("awsProduct".into(), product.as_str().into()),
+                    ("awsProductVersion".into(), product_version.into()),
+                    ("clientId".into(), client_id.to_string().into()),
+                    ("os".into(), os.into()),
+                    ("osArchitecture".into(), os_architecture.into()),
+                    ("osVersion".into(), os_version.clone().into()),
+                ]);
 
                 debug!(?product, ?metric_datum, "Posting metrics");
                 if let Err(err) = toolkit_telemetry_client
@@ -300,12 +344,17 @@ impl Client {
                     .map_err(DisplayErrorContext)
                 {
                     error!(%err, ?metric_name, "Failed to post metric");
+                } else if let Err(err) = TelemetryLog::new().insert(&record) {
+                    error!(%err, ?metric_name, "Failed to record metric in the telemetry log");
                 }
             }
         });
     }
 
     async fn send_cw_telemetry_event(&self, event: &AppTelemetryEvent) {
+        if !telemetry_category_enabled(event.ty.category()) {
+            return;
+        }
         match &event.ty {
             EventType::TranslationActioned {
                 latency,
@@ -735,6 +784,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_telemetry_record() {
+        let created_time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let event = Event {
+            created_time: Some(created_time),
+            credential_start_url: None,
+            ty: EventType::CliSubcommandExecuted {
+                subcommand: "doctor".into(),
+                terminal: Some("iTerm".into()),
+                terminal_version: None,
+                shell: None,
+                shell_version: None,
+            },
+        };
+        let category = event.ty.category();
+        let record = telemetry_record(category, &event.into_metric_datum().unwrap());
+
+        assert_eq!(record.name, "codewhispererterminal_cliSubcommandExecuted");
+        assert_eq!(record.category, "featureUsage");
+        assert_eq!(record.time, created_time);
+        assert_eq!(record.fields["codewhispererterminal_subcommand"], "doctor");
+        assert_eq!(record.fields["codewhispererterminal_terminal"], "iTerm");
+    }
+
     #[tracing_test::traced_test]
     #[tokio::test]
     #[ignore = "needs auth which is not in CI"]
@@ -33,8 +33,10 @@ use aws_toolkit_telemetry_definitions::types::{
     CodewhispererterminalUserInputId,
     CodewhispererterminalUtteranceId,
 };
+use fig_util::shell_words::AcceptKind;
 use strum::{
     Display,
+    EnumIter,
     EnumString,
 };
 
@@ -156,6 +158,8 @@ impl Event {
                 suggestion_state,
                 edit_buffer_len,
                 suggested_chars_len,
+                accepted_chars_len,
+                accept_kind,
                 ..
             } => Some(
                 CodewhispererterminalInlineShellActioned {
@@ -166,6 +170,8 @@ impl Event {
                     codewhispererterminal_accepted: Some(suggestion_state.is_accepted().into()),
                     codewhispererterminal_typed_count: edit_buffer_len.map(Into::into),
                     codewhispererterminal_suggested_count: Some(Into::into(suggested_chars_len as i64)),
+                    codewhispererterminal_accepted_count: accepted_chars_len.map(|len| (len as i64).into()),
+                    codewhispererterminal_accept_type: accept_kind.map(|kind| kind.as_str().to_owned().into()),
                     codewhispererterminal_terminal: terminal.map(Into::into),
                     codewhispererterminal_terminal_version: terminal_version.map(Into::into),
                     codewhispererterminal_shell: shell.map(Into::into),
@@ -354,6 +360,11 @@ pub enum EventType {
         suggestion_state: SuggestionState,
         edit_buffer_len: Option<i64>,
         suggested_chars_len: i32,
+        /// How many characters of the suggestion were accepted, partial accepts add up
+        accepted_chars_len: Option<i32>,
+        /// The last way the suggestion was accepted, [`AcceptKind::Full`] only if it was accepted
+        /// all at once
+        accept_kind: Option<AcceptKind>,
         number_of_recommendations: i32,
         latency: Duration,
         terminal: Option<String>,
@@ -415,6 +426,53 @@ pub enum EventType {
     },
 }
 
+impl EventType {
+    /// The category the event can be opted out of with
+    pub fn category(&self) -> TelemetryCategory {
+        match self {
+            EventType::UserLoggedIn {}
+            | EventType::RefreshCredentials { .. }
+            | EventType::FigUserMigrated {}
+            | EventType::MigrateClientId { .. } => TelemetryCategory::Account,
+            EventType::CompletionInserted { .. }
+            | EventType::InlineShellCompletionActioned { .. }
+            | EventType::TranslationActioned { .. }
+            | EventType::CliSubcommandExecuted { .. }
+            | EventType::DashboardPageViewed { .. }
+            | EventType::MenuBarActioned { .. } => TelemetryCategory::FeatureUsage,
+            EventType::ChatStart { .. }
+            | EventType::ChatEnd { .. }
+            | EventType::ChatAddedMessage { .. }
+            | EventType::ToolUseSuggested { .. } => TelemetryCategory::ChatMetadata,
+            EventType::DoctorCheckFailed { .. } => TelemetryCategory::Diagnostics,
+        }
+    }
+}
+
+/// Groups of telemetry events that can be allowed or denied separately from `telemetry.enabled`
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Hash, EnumString, Display, EnumIter, serde::Serialize, serde::Deserialize,
+)]
+#[strum(serialize_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub enum TelemetryCategory {
+    /// Sign ins, credential refreshes and client id migrations
+    Account,
+    /// Which features are used, e.g. completions, translations and cli subcommands
+    FeatureUsage,
+    /// Chat conversations and tool uses, never including their content
+    ChatMetadata,
+    /// Failed doctor checks and other errors
+    Diagnostics,
+}
+
+impl TelemetryCategory {
+    /// The setting that denies the category when set to `false`
+    pub fn setting_key(&self) -> String {
+        format!("telemetry.{self}.enabled")
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum SuggestionState {
     Accept,
@@ -502,4 +560,24 @@ mod tests {
     async fn test_no_global_telemetry_emitter() {
         assert!(send_event(Event::new(EventType::UserLoggedIn {})).await.is_none());
     }
+
+    #[test]
+    fn test_telemetry_category() {
+        assert_eq!(
+            EventType::ChatEnd {
+                conversation_id: "abc".into()
+            }
+            .category(),
+            TelemetryCategory::ChatMetadata
+        );
+        assert_eq!(TelemetryCategory::FeatureUsage.to_string(), "featureUsage");
+        assert_eq!(
+            "chatMetadata".parse::<TelemetryCategory>().unwrap(),
+            TelemetryCategory::ChatMetadata
+        );
+        assert_eq!(
+            TelemetryCategory::ChatMetadata.setting_key(),
+            "telemetry.chatMetadata.enabled"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+//! The [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) format used for terminal
+//! session recordings
+//!
+//! A recording is a header line followed by one event per line, each event is a JSON array of
+//! `[time, code, data]` where time is the number of seconds since the start of the recording.
+
+use std::collections::HashMap;
+
+use serde::de::Error as _;
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u8,
+    pub width: usize,
+    pub height: usize,
+    /// Unix timestamp of the start of the recording
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl Header {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            version: 2,
+            width,
+            height,
+            timestamp: None,
+            title: None,
+            env: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCode {
+    /// Data written to the terminal
+    Output,
+    /// Data read from the keyboard
+    Input,
+    /// The terminal was resized, the data is `{columns}x{rows}`
+    Resize,
+    /// A named point in the recording, we use these to mark the start of each command
+    Marker,
+}
+
+impl EventCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventCode::Output => "o",
+            EventCode::Input => "i",
+            EventCode::Resize => "r",
+            EventCode::Marker => "m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Seconds since the start of the recording
+    pub time: f64,
+    pub code: EventCode,
+    pub data: String,
+}
+
+impl Event {
+    /// The size of a [`EventCode::Resize`] event as `(columns, rows)`
+    pub fn size(&self) -> Option<(usize, usize)> {
+        if self.code != EventCode::Resize {
+            return None;
+        }
+        let (columns, rows) = self.data.split_once('x')?;
+        Some((columns.parse().ok()?, rows.parse().ok()?))
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Round to microseconds to keep the recording small
+        let time = (self.time * 1_000_000.0).round() / 1_000_000.0;
+        (time, self.code.as_str(), &self.data).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (time, code, data) = <(f64, String, String)>::deserialize(deserializer)?;
+        let code = match code.as_str() {
+            "o" => EventCode::Output,
+            "i" => EventCode::Input,
+            "r" => EventCode::Resize,
+            "m" => EventCode::Marker,
+            other => return Err(D::Error::custom(format!("unknown event code `{other}`"))),
+        };
+        Ok(Self { time, code, data })
+    }
+}
+
+/// Decodes bytes into UTF-8, holding back incomplete sequences that are split across reads
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let mut decoded = String::new();
+        let mut rest = self.pending.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    rest = &[];
+                    break;
+                },
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    decoded.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match err.error_len() {
+                        Some(len) => {
+                            decoded.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        },
+                        // Incomplete sequence at the end, wait for more bytes
+                        None => {
+                            rest = after;
+                            break;
+                        },
+                    }
+                },
+            }
+        }
+
+        self.pending = rest.to_vec();
+        decoded
+    }
+}
+
+/// Parses a recording into its header and events
+pub fn parse(recording: &str) -> Result<(Header, Vec<Event>), serde_json::Error> {
+    let mut lines = recording.lines().filter(|line| !line.trim().is_empty());
+    let header: Header = serde_json::from_str(lines.next().unwrap_or_default())?;
+    let events = lines.map(serde_json::from_str).collect::<Result<_, _>>()?;
+    Ok((header, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serde() {
+        let event = Event {
+            time: 1.234_567_89,
+            code: EventCode::Output,
+            data: "hello\r\n".into(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"[1.234568,"o","hello\r\n"]"#);
+
+        let parsed: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.code, EventCode::Output);
+        assert_eq!(parsed.data, "hello\r\n");
+
+        assert!(serde_json::from_str::<Event>(r#"[1.0,"x",""]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let recording = concat!(
+            r#"{"version":2,"width":80,"height":24}"#,
+            "\n",
+            r#"[0.5,"o","$ "]"#,
+            "\n",
+            r#"[1.0,"r","100x30"]"#,
+            "\n",
+            r#"[1.5,"m","ls"]"#,
+            "\n",
+        );
+        let (header, events) = parse(recording).unwrap();
+        assert_eq!(header, Header::new(80, 24));
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1].size(), Some((100, 30)));
+        assert_eq!(events[2].code, EventCode::Marker);
+        assert_eq!(events[0].size(), None);
+    }
+
+    #[test]
+    fn test_utf8_decoder() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "é✓".as_bytes();
+        assert_eq!(decoder.decode(&bytes[..1]), "");
+        assert_eq!(decoder.decode(&bytes[1..3]), "é");
+        assert_eq!(decoder.decode(&bytes[3..]), "✓");
+        assert_eq!(decoder.decode(b"a\xffb"), "a\u{fffd}b");
+    }
+}
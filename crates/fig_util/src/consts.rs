@@ -113,6 +113,9 @@ pub mod env_var {
         /// Sets the current log level
         Q_LOG_LEVEL = "Q_LOG_LEVEL",
 
+        /// Set to `json` to write log files as JSON lines
+        Q_LOG_FORMAT = "Q_LOG_FORMAT",
+
         /// Overrides the ZDOTDIR environment variable
         Q_ZDOTDIR = "Q_ZDOTDIR",
 
@@ -129,7 +132,13 @@ pub mod env_var {
         Q_USING_ZSH_AUTOSUGGESTIONS = "Q_USING_ZSH_AUTOSUGGESTIONS",
 
         /// Overrides the path to the bundle metadata released with certain desktop builds.
-        Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH"
+        Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH",
+
+        /// Path qterm records the session to in the asciicast format
+        Q_TERM_RECORD = "Q_TERM_RECORD",
+
+        /// Set to `1` to also record keyboard input with [`Q_TERM_RECORD`]
+        Q_TERM_RECORD_INPUT = "Q_TERM_RECORD_INPUT"
     }
 }
 
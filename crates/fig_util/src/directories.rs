@@ -281,11 +281,17 @@ pub fn autocomplete_dir() -> Result<PathBuf> {
     Ok(fig_data_dir()?.join("autocomplete"))
 }
 
-/// The autocomplete specs directory
+/// The autocomplete specs directory, locally authored specs placed here are loaded before the ones
+/// from the spec CDNs
 pub fn autocomplete_specs_dir() -> Result<PathBuf> {
     Ok(autocomplete_dir()?.join("specs"))
 }
 
+/// The directory specs downloaded from the spec CDNs are cached in
+pub fn autocomplete_spec_cache_dir() -> Result<PathBuf> {
+    Ok(autocomplete_dir()?.join("spec-cache"))
+}
+
 /// The directory to all the fig logs
 /// - Linux: `/tmp/fig/$USER/logs`
 /// - MacOS: `$TMPDIR/logs`
@@ -333,7 +339,8 @@ pub fn desktop_socket_path() -> Result<PathBuf> {
 }
 
 /// The path to remote socket
-// - Linux/MacOS on ssh: At the value of `Q_PARENT`
+// - Linux/MacOS on ssh: At the value of `Q_PARENT`, or the local remote socket when a multiplexer started by `q remote
+//   attach` created it
 // - Linux/MacOS not on ssh:
 /// - MacOS: `$TMPDIR/cwrun/remote.sock`
 /// - Linux: `$XDG_RUNTIME_DIR/cwrun/remote.sock`
@@ -344,7 +351,10 @@ pub fn remote_socket_path() -> Result<PathBuf> {
         if let Some(parent_socket) = std::env::var_os(Q_PARENT) {
             Ok(PathBuf::from(parent_socket))
         } else {
-            Err(DirectoryError::QParentNotSet)
+            match local_remote_socket_path()? {
+                socket if socket.exists() => Ok(socket),
+                _ => Err(DirectoryError::QParentNotSet),
+            }
         }
     } else {
         local_remote_socket_path()
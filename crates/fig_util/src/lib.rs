@@ -1,8 +1,11 @@
+pub mod asciicast;
+pub mod command_risk;
 pub mod directories;
 pub mod manifest;
 mod open;
 pub mod process_info;
 mod shell;
+pub mod shell_words;
 pub mod system_info;
 pub mod terminal;
 
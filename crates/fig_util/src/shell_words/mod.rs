@@ -0,0 +1,251 @@
+//! Splitting a shell command line into words, arguments and commands
+//!
+//! Inline suggestions are accepted a word or an argument at a time, both need to know where the
+//! shell would split the line so a quoted string or an escaped space is never cut in half.
+//! [`parse`] goes further and splits a whole command line into the commands it runs.
+
+mod parse;
+
+use clap::ValueEnum;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+pub use self::parse::{
+    Command,
+    Pipeline,
+    Redirect,
+    Script,
+    Word,
+    parse,
+};
+
+/// How much of an inline suggestion to accept
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum AcceptKind {
+    /// The entire suggestion
+    Full,
+    /// Up to the end of the next word, words are split on blanks, slashes and operators
+    Word,
+    /// Up to the end of the next argument as the shell would parse it
+    Argument,
+}
+
+impl AcceptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AcceptKind::Full => "full",
+            AcceptKind::Word => "word",
+            AcceptKind::Argument => "argument",
+        }
+    }
+
+    pub fn is_partial(&self) -> bool {
+        !matches!(self, AcceptKind::Full)
+    }
+
+    /// The start of `suggestion` that is accepted when it is shown after `buffer`
+    pub fn accepted<'a>(&self, buffer: &str, suggestion: &'a str) -> &'a str {
+        match self {
+            AcceptKind::Full => suggestion,
+            AcceptKind::Word => next_word(buffer, suggestion),
+            AcceptKind::Argument => next_argument(buffer, suggestion),
+        }
+    }
+}
+
+impl std::fmt::Display for AcceptKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The start of `suggestion` up to the end of its next word, including any separators before it
+///
+/// An escaped character is always part of a word, so `my\ file` is a single word.
+pub fn next_word<'a>(buffer: &str, suggestion: &'a str) -> &'a str {
+    let mut escaped = buffer.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1;
+    let mut started = false;
+
+    for (i, c) in suggestion.char_indices() {
+        let word = escaped || !is_word_separator(c);
+        escaped = !escaped && c == '\\';
+
+        if word {
+            started = true;
+        } else if started {
+            return &suggestion[..i];
+        }
+    }
+
+    suggestion
+}
+
+/// The start of `suggestion` up to the end of its next argument, including any blanks before it
+///
+/// `buffer` is parsed first so a suggestion that continues an open quote ends where the quote is
+/// closed. Quoted strings, escaped characters and `$(..)`/`${..}` are kept whole, a run of
+/// operators like `&&` or `>>` is an argument of its own.
+pub fn next_argument<'a>(buffer: &str, suggestion: &'a str) -> &'a str {
+    let mut scanner = Scanner::default();
+    for c in buffer.chars() {
+        scanner.next(c);
+    }
+
+    let mut current = None;
+    for (i, c) in suggestion.char_indices() {
+        match (current, scanner.next(c)) {
+            (None, Token::Blank) => {},
+            (None, token) => current = Some(token),
+            (Some(Token::Word), Token::Word) | (Some(Token::Operator), Token::Operator) => {},
+            (Some(_), _) => return &suggestion[..i],
+        }
+    }
+
+    suggestion
+}
+
+fn is_word_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '/' | '=' | ':' | ',') || is_operator(c)
+}
+
+fn is_operator(c: char) -> bool {
+    matches!(c, '|' | '&' | ';' | '<' | '>' | '(' | ')')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Blank,
+    Operator,
+    Word,
+}
+
+/// Tracks quoting one character at a time, following the POSIX shell rules shared by bash, zsh
+/// and fish closely enough to find argument boundaries
+#[derive(Debug, Default)]
+struct Scanner {
+    quote: Option<char>,
+    escaped: bool,
+    /// How many `$(` or `${` are open
+    depth: usize,
+    last: Option<char>,
+}
+
+impl Scanner {
+    fn next(&mut self, c: char) -> Token {
+        let last = self.last.replace(c);
+
+        if self.escaped {
+            self.escaped = false;
+            return Token::Word;
+        }
+
+        match self.quote {
+            Some('\'') => {
+                if c == '\'' {
+                    self.quote = None;
+                }
+                return Token::Word;
+            },
+            Some(quote) => {
+                if c == '\\' {
+                    self.escaped = true;
+                } else if c == quote {
+                    self.quote = None;
+                }
+                return Token::Word;
+            },
+            None => {},
+        }
+
+        match c {
+            '\\' => {
+                self.escaped = true;
+                Token::Word
+            },
+            '\'' | '"' => {
+                self.quote = Some(c);
+                Token::Word
+            },
+            '(' | '{' if last == Some('$') => {
+                self.depth += 1;
+                Token::Word
+            },
+            ')' | '}' if self.depth > 0 => {
+                self.depth -= 1;
+                Token::Word
+            },
+            _ if self.depth > 0 => Token::Word,
+            c if c.is_whitespace() => Token::Blank,
+            c if is_operator(c) => Token::Operator,
+            _ => Token::Word,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_word() {
+        assert_eq!(next_word("git ", "commit -m 'fix'"), "commit");
+        assert_eq!(next_word("git", " commit -m 'fix'"), " commit");
+        assert_eq!(next_word("git comm", "it -m 'fix'"), "it");
+        assert_eq!(next_word("cd ", "/usr/local/bin"), "/usr");
+        assert_eq!(next_word("cd /usr", "/local/bin"), "/local");
+        assert_eq!(next_word("git push ", "--force origin"), "--force");
+        assert_eq!(next_word("cat ", "my\\ file.txt | wc"), "my\\ file.txt");
+        assert_eq!(next_word("cat my\\", " file.txt | wc"), " file.txt");
+        assert_eq!(next_word("ls", " && echo"), " && echo");
+        assert_eq!(next_word("ls", ""), "");
+        assert_eq!(next_word("ls", "  "), "  ");
+    }
+
+    #[test]
+    fn test_next_argument() {
+        assert_eq!(next_argument("git ", "commit -m 'fix'"), "commit");
+        assert_eq!(next_argument("git commit", " -m 'fix the bug'"), " -m");
+        assert_eq!(
+            next_argument("git commit -m", " 'fix the bug' --amend"),
+            " 'fix the bug'"
+        );
+        assert_eq!(
+            next_argument("git commit -m", r#" "say \"hi there\"" --amend"#),
+            r#" "say \"hi there\"""#
+        );
+        assert_eq!(next_argument("cd ", "/usr/local/bin && ls"), "/usr/local/bin");
+        assert_eq!(next_argument("cat ", "my\\ file.txt | wc"), "my\\ file.txt");
+        assert_eq!(next_argument("echo ", "$(date +%s) done"), "$(date +%s)");
+        assert_eq!(next_argument("echo ", "${HOME} done"), "${HOME}");
+        assert_eq!(next_argument("echo a", "b>>out"), "b");
+        assert_eq!(next_argument("echo ab", ">>out"), ">>");
+        assert_eq!(next_argument("ls", " && echo"), " &&");
+        assert_eq!(next_argument("ls", ""), "");
+    }
+
+    #[test]
+    fn test_next_argument_continues_buffer() {
+        // The buffer opened a quote the suggestion closes
+        assert_eq!(next_argument("echo 'hello", " world' foo"), " world'");
+        assert_eq!(next_argument("echo \"a ", "b c\" d"), "b c\"");
+        // The buffer ends with an escaped space
+        assert_eq!(next_argument("cat my\\ ", "file.txt | wc"), "file.txt");
+        assert_eq!(next_argument("echo $(date", " +%s) done"), " +%s)");
+    }
+
+    #[test]
+    fn test_accept_kind() {
+        let buffer = "git commit -m";
+        let suggestion = " 'fix the bug'";
+        assert_eq!(AcceptKind::Full.accepted(buffer, suggestion), suggestion);
+        assert_eq!(AcceptKind::Word.accepted(buffer, suggestion), " 'fix");
+        assert_eq!(AcceptKind::Argument.accepted(buffer, suggestion), suggestion);
+
+        assert!(!AcceptKind::Full.is_partial());
+        assert!(AcceptKind::Word.is_partial());
+        assert_eq!(AcceptKind::from_str("argument", true), Ok(AcceptKind::Argument));
+    }
+}
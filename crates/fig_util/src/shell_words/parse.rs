@@ -0,0 +1,826 @@
+//! A parser for the parts of the shell grammar that decide what a command line runs
+//!
+//! Commands are split into lists, pipelines, subshells, groups and function definitions, the
+//! commands inside `$(...)`, backticks and process substitutions are parsed as scripts of their
+//! own. Keywords of compound commands like `if` and `for` are skipped, so the commands in their
+//! bodies end up in the enclosing script.
+
+/// A list of pipelines separated by `;`, `&`, `&&`, `||` or newlines
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub pipelines: Vec<Pipeline>,
+    /// A quote, substitution or heredoc was never closed
+    pub incomplete: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    /// The pipeline is run in the background with `&`
+    pub background: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Simple {
+        /// The `NAME=value` words before the command
+        assignments: Vec<Word>,
+        words: Vec<Word>,
+        redirects: Vec<Redirect>,
+    },
+    /// A `( ... )` subshell or `{ ...; }` group
+    Compound {
+        body: Script,
+        redirects: Vec<Redirect>,
+    },
+    Function {
+        name: String,
+        body: Script,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    /// The word with quotes and escapes removed, expansions are kept as they were written
+    pub text: String,
+    /// The word contains an unquoted or double quoted expansion, so its value is only known when
+    /// it is run
+    pub dynamic: bool,
+    /// The commands in the substitutions of the word
+    pub substitutions: Vec<Script>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub op: &'static str,
+    pub fd: Option<u32>,
+    pub target: Word,
+}
+
+impl Redirect {
+    /// If the redirect writes to its target
+    pub fn writes(&self) -> bool {
+        match self.op {
+            ">" | ">>" | ">|" | "&>" | "&>>" | "<>" => true,
+            // `>&1` duplicates a file descriptor, `>& file` writes both outputs to the file
+            ">&" => !self.target.text.chars().all(|c| c.is_ascii_digit() || c == '-'),
+            _ => false,
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Script {
+    let (tokens, incomplete) = Lexer::new(source).lex();
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        case_depth: 0,
+    };
+    let mut script = parser.script(None);
+    script.incomplete |= incomplete;
+    script
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(Word),
+    Op(&'static str),
+    Redirect { op: &'static str, fd: Option<u32> },
+}
+
+const OPS: &[&str] = &[
+    "&>>", "<<<", "<<-", "||", "|&", "&&", "&>", ";;", "<<", "<>", "<&", ">>", ">&", ">|", "|", "&", ";", "(", ")",
+    "<", ">", "\n",
+];
+
+const REDIRECTS: &[&str] = &["&>>", "<<<", "<<-", "&>", "<<", "<>", "<&", ">>", ">&", ">|", "<", ">"];
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    incomplete: bool,
+    /// The delimiters of heredocs whose bodies start after the next newline
+    heredocs: Vec<(String, bool)>,
+}
+
+impl Lexer {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            incomplete: false,
+            heredocs: vec![],
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn lex(mut self) -> (Vec<Token>, bool) {
+        let mut tokens = vec![];
+
+        while let Some(c) = self.peek(0) {
+            match c {
+                ' ' | '\t' => self.pos += 1,
+                '\\' if self.peek(1) == Some('\n') => self.pos += 2,
+                '#' => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                },
+                // Process substitutions are words
+                '<' | '>' if self.peek(1) == Some('(') => tokens.push(Token::Word(self.word())),
+                _ => match OPS.iter().find(|op| self.starts_with(op)) {
+                    Some(op) => {
+                        self.pos += op.chars().count();
+                        if *op == "\n" {
+                            self.heredoc_bodies();
+                        }
+
+                        let token = if REDIRECTS.contains(op) {
+                            // `2>` redirects the file descriptor written right before it
+                            let fd = match tokens.last() {
+                                Some(Token::Word(word))
+                                    if !word.text.is_empty()
+                                        && word.text.chars().all(|c| c.is_ascii_digit())
+                                        && !self.chars[self.pos - op.chars().count() - 1].is_whitespace() =>
+                                {
+                                    let fd = word.text.parse().ok();
+                                    tokens.pop();
+                                    fd
+                                },
+                                _ => None,
+                            };
+                            Token::Redirect { op, fd }
+                        } else {
+                            Token::Op(op)
+                        };
+                        let heredoc = matches!(token, Token::Redirect { op: "<<" | "<<-", .. });
+                        tokens.push(token);
+
+                        if heredoc {
+                            while matches!(self.peek(0), Some(' ' | '\t')) {
+                                self.pos += 1;
+                            }
+                            let delimiter = self.word();
+                            self.heredocs.push((delimiter.text.clone(), *op == "<<-"));
+                            tokens.push(Token::Word(delimiter));
+                        }
+                    },
+                    None => tokens.push(Token::Word(self.word())),
+                },
+            }
+        }
+
+        if !self.heredocs.is_empty() {
+            self.incomplete = true;
+        }
+        (tokens, self.incomplete)
+    }
+
+    /// Skips the bodies of the heredocs started on the line that just ended
+    fn heredoc_bodies(&mut self) {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            loop {
+                if self.peek(0).is_none() {
+                    self.incomplete = true;
+                    return;
+                }
+                let start = self.pos;
+                while self.peek(0).is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+                let line: String = self.chars[start..self.pos].iter().collect();
+                self.pos = (self.pos + 1).min(self.chars.len());
+
+                let line = if strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    &line
+                };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> Word {
+        let mut word = Word::default();
+
+        while let Some(c) = self.peek(0) {
+            match c {
+                ' ' | '\t' | '\n' | '|' | '&' | ';' | '(' | ')' => break,
+                '<' | '>' if self.peek(1) != Some('(') => break,
+                '<' | '>' => {
+                    word.text.push(c);
+                    word.text.push('(');
+                    self.pos += 2;
+                    self.substitution(&mut word, ')');
+                },
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek(0) {
+                        Some('\n') => {},
+                        Some(c) => word.text.push(c),
+                        None => {},
+                    }
+                    self.pos += 1;
+                },
+                '\'' => {
+                    self.pos += 1;
+                    let mut closed = false;
+                    while let Some(c) = self.peek(0) {
+                        self.pos += 1;
+                        if c == '\'' {
+                            closed = true;
+                            break;
+                        }
+                        word.text.push(c);
+                    }
+                    if !closed {
+                        self.incomplete = true;
+                    }
+                },
+                '"' => {
+                    self.pos += 1;
+                    self.double_quoted(&mut word);
+                },
+                '$' => self.dollar(&mut word),
+                '`' => {
+                    self.pos += 1;
+                    word.text.push('`');
+                    self.substitution(&mut word, '`');
+                },
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+
+        word
+    }
+
+    fn double_quoted(&mut self, word: &mut Word) {
+        while let Some(c) = self.peek(0) {
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return;
+                },
+                '\\' => {
+                    match self.peek(1) {
+                        Some(escaped @ ('$' | '`' | '"' | '\\')) => word.text.push(escaped),
+                        Some('\n') => {},
+                        Some(other) => {
+                            word.text.push('\\');
+                            word.text.push(other);
+                        },
+                        None => word.text.push('\\'),
+                    }
+                    self.pos += 2;
+                },
+                '$' => self.dollar(word),
+                '`' => {
+                    self.pos += 1;
+                    word.text.push('`');
+                    self.substitution(word, '`');
+                },
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+        self.incomplete = true;
+    }
+
+    /// Reads an expansion starting with `$`
+    fn dollar(&mut self, word: &mut Word) {
+        match self.peek(1) {
+            Some('(') if self.peek(2) == Some('(') => {
+                // Arithmetic expansion, there are no commands in it
+                word.dynamic = true;
+                let start = self.pos;
+                self.pos += 2;
+                self.matching(')');
+                word.text.extend(&self.chars[start..self.pos]);
+            },
+            Some('(') => {
+                word.text.push_str("$(");
+                self.pos += 2;
+                self.substitution(word, ')');
+            },
+            Some('{') => {
+                word.dynamic = true;
+                let start = self.pos;
+                self.pos += 2;
+                self.matching('}');
+                word.text.extend(&self.chars[start..self.pos]);
+            },
+            Some(c) if c.is_alphanumeric() || "_@*#?$!-".contains(c) => {
+                word.dynamic = true;
+                word.text.push('$');
+                self.pos += 1;
+                if c.is_alphabetic() || c == '_' {
+                    while self.peek(0).is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                        word.text.push(self.chars[self.pos]);
+                        self.pos += 1;
+                    }
+                } else {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            },
+            // `$'...'` quotes are read as single quotes
+            Some('\'') => self.pos += 1,
+            _ => {
+                word.text.push('$');
+                self.pos += 1;
+            },
+        }
+    }
+
+    /// Reads a command substitution up to `close` and parses the commands in it, the opening
+    /// bracket or backtick was already read
+    fn substitution(&mut self, word: &mut Word, close: char) {
+        word.dynamic = true;
+        let start = self.pos;
+        let closed = if close == '`' {
+            loop {
+                match self.peek(0) {
+                    Some('\\') => self.pos += 2,
+                    Some('`') => {
+                        self.pos += 1;
+                        break true;
+                    },
+                    Some(_) => self.pos += 1,
+                    None => break false,
+                }
+            }
+        } else {
+            self.matching(close)
+        };
+        self.pos = self.pos.min(self.chars.len());
+
+        let end = if closed { self.pos - 1 } else { self.pos };
+        let source: String = self.chars[start..end].iter().collect();
+        let script = parse(&source.replace("\\`", "`"));
+        if !closed || script.incomplete {
+            self.incomplete = true;
+        }
+        word.text.extend(&self.chars[start..self.pos]);
+        word.substitutions.push(script);
+    }
+
+    /// Moves past the bracket matching the one that was just read, skipping quoted text
+    fn matching(&mut self, close: char) -> bool {
+        let open = if close == ')' { '(' } else { '{' };
+        let mut depth = 1;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                '\\' => self.pos += 1,
+                '\'' => {
+                    while self.peek(0).is_some_and(|c| c != '\'') {
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                },
+                '"' => {
+                    while let Some(c) = self.peek(0) {
+                        self.pos += if c == '\\' { 2 } else { 1 };
+                        if c == '"' {
+                            break;
+                        }
+                    }
+                },
+                c if c == open => depth += 1,
+                c if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return true;
+                    }
+                },
+                _ => {},
+            }
+        }
+        self.pos = self.pos.min(self.chars.len());
+        self.incomplete = true;
+        false
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// `case` statements the parser is in, their patterns end with `)`
+    case_depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(&word.text),
+            _ => None,
+        }
+    }
+
+    /// Parses pipelines until the end of the input or `close`
+    fn script(&mut self, close: Option<&str>) -> Script {
+        let mut script = Script::default();
+
+        loop {
+            match self.peek() {
+                None => {
+                    if close.is_some() {
+                        script.incomplete = true;
+                    }
+                    break;
+                },
+                Some(Token::Op(op)) if *op != "(" => {
+                    let op = *op;
+                    self.pos += 1;
+                    match op {
+                        ")" if close == Some(")") => break,
+                        "&" => {
+                            if let Some(pipeline) = script.pipelines.last_mut() {
+                                pipeline.background = true;
+                            }
+                        },
+                        // Separators, and brackets of constructs that aren't modeled
+                        _ => {},
+                    }
+                },
+                Some(Token::Word(word)) if close == Some("}") && word.text == "}" => {
+                    self.pos += 1;
+                    break;
+                },
+                _ => {
+                    let pipeline = self.pipeline();
+                    if !pipeline.commands.is_empty() {
+                        script.pipelines.push(pipeline);
+                    }
+                },
+            }
+        }
+
+        script
+    }
+
+    fn pipeline(&mut self) -> Pipeline {
+        let mut pipeline = Pipeline::default();
+        loop {
+            if let Some(command) = self.command() {
+                pipeline.commands.push(command);
+            }
+            match self.peek() {
+                Some(Token::Op("|" | "|&")) => self.pos += 1,
+                _ => break,
+            }
+        }
+        pipeline
+    }
+
+    fn command(&mut self) -> Option<Command> {
+        // Keywords that start or continue a compound command
+        while let Some(word) = self.peek_word().map(str::to_owned) {
+            match word.as_str() {
+                "if" | "then" | "else" | "elif" | "fi" | "do" | "done" | "while" | "until" | "!" | "time" | "esac"
+                | "}" => {
+                    if word == "esac" {
+                        self.case_depth = self.case_depth.saturating_sub(1);
+                    }
+                    self.pos += 1;
+                },
+                "for" | "select" | "case" => {
+                    if word == "case" {
+                        self.case_depth += 1;
+                    }
+                    let end = if word == "case" { "in" } else { "do" };
+                    while let Some(token) = self.peek() {
+                        match token {
+                            Token::Word(word) if word.text == end => {
+                                self.pos += 1;
+                                break;
+                            },
+                            Token::Op(";" | "\n") => {
+                                self.pos += 1;
+                                if end == "do" && self.peek_word() == Some("do") {
+                                    self.pos += 1;
+                                    break;
+                                }
+                            },
+                            _ => self.pos += 1,
+                        }
+                    }
+                },
+                "function" => {
+                    self.pos += 1;
+                    let name = self.peek_word().unwrap_or_default().to_owned();
+                    self.pos += 1;
+                    if matches!(self.peek(), Some(Token::Op("("))) {
+                        self.pos += 2;
+                    }
+                    return Some(self.function(name));
+                },
+                _ if self.case_depth > 0 && self.pattern() => {},
+                _ => break,
+            }
+        }
+
+        match self.peek()? {
+            Token::Op("(") => {
+                self.pos += 1;
+                let body = self.script(Some(")"));
+                let redirects = self.redirects();
+                Some(Command::Compound { body, redirects })
+            },
+            Token::Word(word) if word.text == "{" => {
+                self.pos += 1;
+                let body = self.script(Some("}"));
+                let redirects = self.redirects();
+                Some(Command::Compound { body, redirects })
+            },
+            Token::Op(_) => None,
+            _ => self.simple(),
+        }
+    }
+
+    /// Skips a `case` pattern like `a|b)`, if the parser is at one
+    fn pattern(&mut self) -> bool {
+        let mut end = self.pos;
+        if matches!(self.tokens.get(end), Some(Token::Op("("))) {
+            end += 1;
+        }
+        loop {
+            match (self.tokens.get(end), self.tokens.get(end + 1)) {
+                (Some(Token::Word(_)), Some(Token::Op(")"))) => {
+                    self.pos = end + 2;
+                    return true;
+                },
+                (Some(Token::Word(_)), Some(Token::Op("|"))) => end += 2,
+                _ => return false,
+            }
+        }
+    }
+
+    fn simple(&mut self) -> Option<Command> {
+        let mut assignments = vec![];
+        let mut words: Vec<Word> = vec![];
+        let mut redirects = vec![];
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) => {
+                    let word = word.clone();
+                    self.pos += 1;
+                    if words.is_empty() && is_assignment(&word.text) {
+                        assignments.push(word);
+                    } else {
+                        words.push(word);
+                    }
+                },
+                Some(Token::Redirect { .. }) => redirects.extend(self.redirect()),
+                // `name() { ...; }` defines a function
+                Some(Token::Op("("))
+                    if words.len() == 1 && matches!(self.tokens.get(self.pos + 1), Some(Token::Op(")"))) =>
+                {
+                    self.pos += 2;
+                    let name = words.pop().map(|word| word.text).unwrap_or_default();
+                    return Some(self.function(name));
+                },
+                _ => break,
+            }
+        }
+
+        Some(Command::Simple {
+            assignments,
+            words,
+            redirects,
+        })
+    }
+
+    fn function(&mut self, name: String) -> Command {
+        while matches!(self.peek(), Some(Token::Op("\n"))) {
+            self.pos += 1;
+        }
+        let body = match self.command() {
+            Some(Command::Compound { body, .. }) => body,
+            Some(command) => Script {
+                pipelines: vec![Pipeline {
+                    commands: vec![command],
+                    background: false,
+                }],
+                incomplete: false,
+            },
+            None => Script::default(),
+        };
+        Command::Function { name, body }
+    }
+
+    fn redirects(&mut self) -> Vec<Redirect> {
+        let mut redirects = vec![];
+        while matches!(self.peek(), Some(Token::Redirect { .. })) {
+            redirects.extend(self.redirect());
+        }
+        redirects
+    }
+
+    fn redirect(&mut self) -> Option<Redirect> {
+        let Some(Token::Redirect { op, fd }) = self.peek().cloned() else {
+            return None;
+        };
+        self.pos += 1;
+        match self.peek() {
+            Some(Token::Word(target)) => {
+                let target = target.clone();
+                self.pos += 1;
+                Some(Redirect { op, fd, target })
+            },
+            _ => None,
+        }
+    }
+}
+
+fn is_assignment(text: &str) -> bool {
+    match text.split_once('=') {
+        Some((name, _)) => {
+            let name = name.strip_suffix('+').unwrap_or(name);
+            let name = name.split_once('[').map_or(name, |(name, _)| name);
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &Command) -> Vec<&str> {
+        match command {
+            Command::Simple { words, .. } => words.iter().map(|word| word.text.as_str()).collect(),
+            _ => panic!("not a simple command: {command:?}"),
+        }
+    }
+
+    fn commands(script: &Script) -> Vec<Vec<&str>> {
+        script
+            .pipelines
+            .iter()
+            .flat_map(|pipeline| &pipeline.commands)
+            .map(words)
+            .collect()
+    }
+
+    #[test]
+    fn test_lists_and_pipelines() {
+        let script = parse("cd dir && ls -la | grep foo; echo done &\nsleep 1 || true");
+        assert_eq!(commands(&script), vec![
+            vec!["cd", "dir"],
+            vec!["ls", "-la"],
+            vec!["grep", "foo"],
+            vec!["echo", "done"],
+            vec!["sleep", "1"],
+            vec!["true"]
+        ]);
+        assert_eq!(script.pipelines[1].commands.len(), 2);
+        assert!(script.pipelines[2].background);
+        assert!(!script.incomplete);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let script = parse(r#"echo 'a  b' "c $HOME \"d\"" e\ f"#);
+        let Command::Simple { words, .. } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(words[1].text, "a  b");
+        assert!(!words[1].dynamic);
+        assert_eq!(words[2].text, "c $HOME \"d\"");
+        assert!(words[2].dynamic);
+        assert_eq!(words[3].text, "e f");
+
+        assert!(parse("echo 'unterminated").incomplete);
+        assert!(parse("echo \"unterminated").incomplete);
+    }
+
+    #[test]
+    fn test_substitutions() {
+        let script = parse("echo $(rm -rf \"$(pwd)\") `ls` <(cat file)");
+        let Command::Simple { words, .. } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(commands(&words[1].substitutions[0]), vec![vec!["rm", "-rf", "$(pwd)"]]);
+        assert_eq!(commands(&words[2].substitutions[0]), vec![vec!["ls"]]);
+        assert_eq!(commands(&words[3].substitutions[0]), vec![vec!["cat", "file"]]);
+        assert!(words.iter().skip(1).all(|word| word.dynamic));
+
+        // Arithmetic isn't a substitution
+        let script = parse("echo $((1 + 2))");
+        let Command::Simple { words, .. } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(words[1].text, "$((1 + 2))");
+        assert!(words[1].substitutions.is_empty());
+    }
+
+    #[test]
+    fn test_redirects() {
+        let script = parse("cmd 2>&1 >out.txt < in >> log 2>/dev/null");
+        let Command::Simple { words, redirects, .. } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(words.len(), 1);
+        let redirects: Vec<_> = redirects
+            .iter()
+            .map(|redirect| {
+                (
+                    redirect.op,
+                    redirect.fd,
+                    redirect.target.text.as_str(),
+                    redirect.writes(),
+                )
+            })
+            .collect();
+        assert_eq!(redirects, vec![
+            (">&", Some(2), "1", false),
+            (">", None, "out.txt", true),
+            ("<", None, "in", false),
+            (">>", None, "log", true),
+            (">", Some(2), "/dev/null", true),
+        ]);
+    }
+
+    #[test]
+    fn test_heredoc() {
+        let script = parse("cat <<EOF > file\nrm -rf /\nEOF\necho done");
+        assert_eq!(commands(&script), vec![vec!["cat"], vec!["echo", "done"]]);
+        assert!(parse("cat <<EOF\nno end").incomplete);
+    }
+
+    #[test]
+    fn test_compound() {
+        let script = parse("(cd dir; make) > log; { echo a; echo b; }");
+        let Command::Compound { body, redirects } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(commands(body), vec![vec!["cd", "dir"], vec!["make"]]);
+        assert_eq!(redirects[0].target.text, "log");
+        let Command::Compound { body, .. } = &script.pipelines[1].commands[0] else {
+            panic!();
+        };
+        assert_eq!(commands(body), vec![vec!["echo", "a"], vec!["echo", "b"]]);
+
+        let script = parse("if [ -f x ]; then rm x; else touch x; fi; for f in *.txt; do cat \"$f\"; done");
+        assert_eq!(commands(&script), vec![
+            vec!["[", "-f", "x", "]"],
+            vec!["rm", "x"],
+            vec!["touch", "x"],
+            vec!["cat", "$f"]
+        ]);
+
+        let script = parse("case $1 in a|b) echo ab;; *) rm c;; esac");
+        assert_eq!(commands(&script), vec![vec!["echo", "ab"], vec!["rm", "c"]]);
+    }
+
+    #[test]
+    fn test_function() {
+        let script = parse(":(){ :|:& };:");
+        let Command::Function { name, body } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(name, ":");
+        assert_eq!(body.pipelines[0].commands.len(), 2);
+        assert!(body.pipelines[0].background);
+        assert_eq!(
+            commands(&Script {
+                pipelines: script.pipelines[1..].to_vec(),
+                incomplete: false
+            }),
+            vec![vec![":"]]
+        );
+    }
+
+    #[test]
+    fn test_assignments() {
+        let script = parse("FOO=1 BAR+=x env");
+        let Command::Simple { assignments, words, .. } = &script.pipelines[0].commands[0] else {
+            panic!();
+        };
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(words[0].text, "env");
+    }
+}
@@ -23,6 +23,10 @@ use crate::history::{
     HistorySender,
 };
 use crate::inline::on_prompt;
+use crate::notifier::{
+    self,
+    NotifierConfig,
+};
 use crate::{
     INSERT_ON_NEW_CMD,
     MainLoopEvent,
@@ -34,6 +38,7 @@ pub struct EventHandler {
     history_sender: HistorySender,
     main_loop_sender: Sender<MainLoopEvent>,
     csi_u_enabled: bool,
+    notifier: NotifierConfig,
 }
 
 impl EventHandler {
@@ -47,6 +52,7 @@ impl EventHandler {
             history_sender,
             main_loop_sender,
             csi_u_enabled: fig_settings::settings::get_bool_or("qterm.csi-u.enabled", false),
+            notifier: NotifierConfig::load(),
         }
     }
 }
@@ -126,6 +132,8 @@ impl EventListener for EventHandler {
                 if let Err(err) = self.history_sender.send(HistoryCommand::Insert(command_info.clone())) {
                     error!(%err, "Sender error");
                 }
+
+                notifier::on_command_finished(&self.notifier, command_info, &self.main_loop_sender);
             },
             Event::ShellChanged => {
                 // let shell = &shell_state.local_context.shell;
@@ -12,6 +12,13 @@ use tracing::{
 
 use crate::HOSTNAME;
 
+/// Command output can contain secrets, so it is only stored in history when the user opts in
+const CAPTURE_OUTPUT_KEY: &str = "history.captureOutput";
+
+fn capture_output(settings: &fig_settings::Settings) -> bool {
+    settings.get_bool_or(CAPTURE_OUTPUT_KEY, false)
+}
+
 #[derive(Debug)]
 pub struct HistoryQueryParams {
     pub limit: usize,
@@ -51,6 +58,9 @@ pub async fn spawn_history_task() -> HistorySender {
                             .as_deref()
                             .and_then(|username| HOSTNAME.as_deref().map(|hostname| format!("{username}@{hostname}"))),
                         exit_code: command.exit_code,
+                        output: command
+                            .output
+                            .filter(|_| capture_output(&fig_settings::Settings::new())),
                     };
 
                     if let Err(err) = history.insert_command_history(&command_info, true) {
@@ -83,3 +93,21 @@ pub async fn spawn_history_task() -> HistorySender {
 
     sender
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_output() {
+        assert!(!capture_output(&fig_settings::Settings::new_fake()));
+        assert!(capture_output(&fig_settings::Settings::from_slice(&[(
+            CAPTURE_OUTPUT_KEY,
+            true.into()
+        )])));
+        assert!(!capture_output(&fig_settings::Settings::from_slice(&[(
+            CAPTURE_OUTPUT_KEY,
+            false.into()
+        )])));
+    }
+}
@@ -0,0 +1,293 @@
+//! Inline suggestions drawn by figterm for shells we have no line editor widgets for
+//!
+//! Zsh shows suggestions with its own widgets, in bash and fish figterm requests a suggestion when
+//! the edit buffer changes and draws it dimmed after the cursor. The suggestion is erased before
+//! any more output from the shell is written, accepting it types the accepted text into the shell.
+
+use alacritty_terminal::Term;
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::Point;
+use alacritty_terminal::term::TextBuffer;
+use fig_util::shell_words::AcceptKind;
+
+use crate::input::{
+    KeyCode,
+    KeyEvent,
+    Modifiers,
+};
+
+/// Erases from the cursor to the end of the line
+const ERASE: &[u8] = b"\x1b[K";
+
+/// A suggestion that was accepted and typed into the shell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accepted {
+    pub buffer: String,
+    pub suggestion: String,
+    pub kind: AcceptKind,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct GhostText {
+    /// The buffer the suggestion is shown after
+    buffer: String,
+    suggestion: String,
+    drawn: bool,
+}
+
+impl GhostText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If figterm draws the suggestions for `shell`
+    pub fn supported(shell: Option<&str>) -> bool {
+        let shell = shell.map(|shell| shell.strip_suffix(" (figterm)").unwrap_or(shell));
+        matches!(shell, Some("bash" | "fish"))
+    }
+
+    /// Updates the suggestion for the current edit buffer, returns the buffer to request a new
+    /// suggestion for
+    ///
+    /// Typing the start of the suggestion keeps the rest of it, anything else clears it.
+    pub fn update(&mut self, buffer: Option<&TextBuffer>) -> Option<String> {
+        let Some(buffer) = buffer.filter(|buffer| {
+            !buffer.buffer.trim().is_empty() && buffer.cursor_idx == Some(buffer.buffer.chars().count())
+        }) else {
+            self.clear();
+            return None;
+        };
+        let buffer = &buffer.buffer;
+
+        if *buffer == self.buffer {
+            return None;
+        }
+
+        let typed = buffer.strip_prefix(self.buffer.as_str());
+        match typed.and_then(|typed| self.suggestion.strip_prefix(typed)) {
+            Some(rest) if !rest.is_empty() => {
+                self.suggestion = rest.to_owned();
+                self.buffer.clone_from(buffer);
+                None
+            },
+            _ => {
+                self.suggestion.clear();
+                self.buffer.clone_from(buffer);
+                Some(buffer.clone())
+            },
+        }
+    }
+
+    /// Sets the suggestion requested for `buffer` if the buffer has not changed since
+    pub fn set_suggestion(&mut self, buffer: &str, suggestion: String) {
+        if buffer == self.buffer && self.suggestion.is_empty() {
+            self.suggestion = suggestion;
+        }
+    }
+
+    /// The bytes that draw the suggestion after the cursor, if there is room for it on the line
+    pub fn draw<T: EventListener>(&mut self, term: &Term<T>) -> Option<Vec<u8>> {
+        if self.drawn || self.suggestion.is_empty() {
+            return None;
+        }
+
+        // Nothing can be after the cursor, fish draws its own autosuggestions there
+        let cursor = term.grid().cursor.point;
+        let end = Point::new(cursor.line, term.last_column());
+        if !term.bounds_to_string(cursor, end).trim().is_empty() {
+            return None;
+        }
+
+        let columns = term.columns().saturating_sub(cursor.column.0);
+        self.render(columns)
+    }
+
+    fn render(&mut self, columns: usize) -> Option<Vec<u8>> {
+        // Leave the last column free so the terminal doesn't wrap
+        let visible: String = self.suggestion.chars().take(columns.saturating_sub(1)).collect();
+        if visible.is_empty() {
+            return None;
+        }
+
+        self.drawn = true;
+        // Save the cursor, draw in bright black like zsh-autosuggestions, then restore the cursor
+        Some(format!("\x1b7\x1b[90m{visible}\x1b[0m\x1b8").into_bytes())
+    }
+
+    /// The bytes that erase the suggestion, this must be written before anything else moves the
+    /// cursor
+    pub fn erase(&mut self) -> Option<&'static [u8]> {
+        std::mem::take(&mut self.drawn).then_some(ERASE)
+    }
+
+    /// Forgets that the suggestion is drawn without erasing it, the terminal reflows on resize so
+    /// the suggestion is no longer where it was drawn
+    pub fn forget_drawn(&mut self) {
+        self.drawn = false;
+    }
+
+    /// Accepts the suggestion if the key accepts it, the accepted text should be written to the
+    /// shell
+    pub fn accept(&mut self, event: &KeyEvent) -> Option<Accepted> {
+        self.accept_as(accept_kind(event)?)
+    }
+
+    /// Accepts `kind` of the suggestion, e.g. for a keybinding that accepts the next word
+    pub fn accept_as(&mut self, kind: AcceptKind) -> Option<Accepted> {
+        if self.suggestion.is_empty() {
+            return None;
+        }
+
+        let text = kind.accepted(&self.buffer, &self.suggestion);
+        if text.is_empty() {
+            return None;
+        }
+
+        Some(Accepted {
+            buffer: self.buffer.clone(),
+            suggestion: self.suggestion.clone(),
+            kind,
+            text: text.to_owned(),
+        })
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.suggestion.clear();
+    }
+}
+
+/// The keys that accept a suggestion, the same keys that move forward in bash and fish
+fn accept_kind(event: &KeyEvent) -> Option<AcceptKind> {
+    match (event.key, event.modifiers) {
+        (KeyCode::RightArrow | KeyCode::End, Modifiers::NONE) => Some(AcceptKind::Full),
+        (KeyCode::Char('e' | 'f'), Modifiers::CTRL) => Some(AcceptKind::Full),
+        (KeyCode::Char('f'), Modifiers::ALT) => Some(AcceptKind::Word),
+        (KeyCode::RightArrow, Modifiers::ALT) => Some(AcceptKind::Argument),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(buffer: &str) -> TextBuffer {
+        TextBuffer {
+            buffer: buffer.into(),
+            cursor_idx: Some(buffer.chars().count()),
+        }
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent { key, modifiers }
+    }
+
+    #[test]
+    fn test_update() {
+        let mut ghost = GhostText::new();
+        assert_eq!(ghost.update(Some(&buffer("git"))), Some("git".into()));
+        // The buffer didn't change
+        assert_eq!(ghost.update(Some(&buffer("git"))), None);
+
+        ghost.set_suggestion("git", " commit -m 'fix'".into());
+        assert_eq!(
+            ghost.render(80),
+            Some(b"\x1b7\x1b[90m commit -m 'fix'\x1b[0m\x1b8".to_vec())
+        );
+        assert_eq!(ghost.erase(), Some(ERASE));
+        assert_eq!(ghost.erase(), None);
+
+        // Typing the suggestion keeps the rest of it
+        assert_eq!(ghost.update(Some(&buffer("git co"))), None);
+        assert_eq!(ghost.suggestion, "mmit -m 'fix'");
+
+        // Typing something else requests a new one
+        assert_eq!(ghost.update(Some(&buffer("git cl"))), Some("git cl".into()));
+        assert_eq!(ghost.suggestion, "");
+
+        // A suggestion for an old buffer is ignored
+        ghost.set_suggestion("git", " commit".into());
+        assert_eq!(ghost.suggestion, "");
+
+        // The cursor has to be at the end of the buffer
+        ghost.set_suggestion("git cl", "one".into());
+        assert_eq!(
+            ghost.update(Some(&TextBuffer {
+                buffer: "git cl".into(),
+                cursor_idx: Some(2),
+            })),
+            None
+        );
+        assert_eq!(ghost.suggestion, "");
+    }
+
+    #[test]
+    fn test_render_truncates() {
+        let mut ghost = GhostText::new();
+        ghost.update(Some(&buffer("echo")));
+        ghost.set_suggestion("echo", " hello".into());
+        assert_eq!(ghost.render(1), None);
+        assert_eq!(ghost.render(4), Some(b"\x1b7\x1b[90m he\x1b[0m\x1b8".to_vec()));
+    }
+
+    #[test]
+    fn test_accept() {
+        let mut ghost = GhostText::new();
+        assert_eq!(ghost.accept(&key(KeyCode::RightArrow, Modifiers::NONE)), None);
+
+        ghost.update(Some(&buffer("git commit")));
+        ghost.set_suggestion("git commit", " -m 'fix the bug'".into());
+
+        assert_eq!(ghost.accept(&key(KeyCode::Char('a'), Modifiers::NONE)), None);
+        assert_eq!(
+            ghost.accept(&key(KeyCode::RightArrow, Modifiers::NONE)).unwrap().text,
+            " -m 'fix the bug'"
+        );
+        assert_eq!(
+            ghost.accept(&key(KeyCode::Char('f'), Modifiers::ALT)).unwrap().text,
+            " -m"
+        );
+
+        // The shell echoes the accepted text, the rest of the suggestion stays
+        ghost.update(Some(&buffer("git commit -m")));
+        let accepted = ghost.accept(&key(KeyCode::RightArrow, Modifiers::ALT)).unwrap();
+        assert_eq!(accepted, Accepted {
+            buffer: "git commit -m".into(),
+            suggestion: " 'fix the bug'".into(),
+            kind: AcceptKind::Argument,
+            text: " 'fix the bug'".into(),
+        });
+    }
+
+    #[test]
+    fn test_accept_as() {
+        let mut ghost = GhostText::new();
+        assert_eq!(ghost.accept_as(AcceptKind::Word), None);
+
+        ghost.update(Some(&buffer("cargo")));
+        ghost.set_suggestion("cargo", " test --workspace".into());
+        assert_eq!(
+            ghost.accept_as(AcceptKind::Word),
+            Some(Accepted {
+                buffer: "cargo".into(),
+                suggestion: " test --workspace".into(),
+                kind: AcceptKind::Word,
+                text: " test".into(),
+            })
+        );
+
+        ghost.update(Some(&buffer("cargo test")));
+        assert_eq!(ghost.accept_as(AcceptKind::Word).unwrap().text, " --workspace");
+    }
+
+    #[test]
+    fn test_supported() {
+        assert!(GhostText::supported(Some("bash")));
+        assert!(GhostText::supported(Some("fish (figterm)")));
+        assert!(!GhostText::supported(Some("zsh")));
+        assert!(!GhostText::supported(None));
+    }
+}
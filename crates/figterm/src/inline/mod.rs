@@ -1,4 +1,5 @@
 mod completion_cache;
+pub mod ghost_text;
 mod validate;
 
 use std::fmt::Write;
@@ -30,7 +31,7 @@ use fig_telemetry::{
     AppTelemetryEvent,
     SuggestionState,
 };
-use fig_util::Shell;
+use fig_util::shell_words::AcceptKind;
 use fig_util::terminal::{
     current_terminal,
     current_terminal_version,
@@ -55,7 +56,10 @@ use crate::history::{
 const HISTORY_COUNT_DEFAULT: usize = 49;
 const DEBOUNCE_DURATION_DEFAULT: Duration = Duration::from_millis(300);
 
-static INLINE_ENABLED: Mutex<bool> = Mutex::const_new(true);
+const INLINE_ENABLED_SETTINGS_KEY: &str = "inline.enabled";
+
+static INLINE_ENABLED: LazyLock<Mutex<bool>> =
+    LazyLock::new(|| Mutex::new(fig_settings::settings::get_bool_or(INLINE_ENABLED_SETTINGS_KEY, true)));
 
 static LAST_RECEIVED: Mutex<Option<SystemTime>> = Mutex::const_new(None);
 
@@ -78,6 +82,10 @@ static DEBOUNCE_DURATION: LazyLock<Duration> = LazyLock::new(|| {
         .map_or(DEBOUNCE_DURATION_DEFAULT, Duration::from_millis)
 });
 
+pub async fn enabled() -> bool {
+    *INLINE_ENABLED.lock().await
+}
+
 pub async fn on_prompt() {
     COMPLETION_CACHE.lock().await.clear();
     TELEMETRY_QUEUE.lock().await.send_all_items(None).await;
@@ -110,8 +118,11 @@ impl TelemetryQueue {
                 suggestion_state,
                 edit_buffer_len,
                 suggested_chars_len,
+                accepted_chars_len,
+                accept_kind,
                 number_of_recommendations,
                 latency,
+                shell,
                 ..
             } = item;
 
@@ -125,12 +136,13 @@ impl TelemetryQueue {
                         suggestion_state,
                         edit_buffer_len,
                         suggested_chars_len,
+                        accepted_chars_len,
+                        accept_kind,
                         number_of_recommendations,
                         latency,
                         terminal: current_terminal().map(|s| s.internal_id().into_owned()),
                         terminal_version: current_terminal_version().map(Into::into),
-                        // The only supported shell currently is Zsh
-                        shell: Some(Shell::Zsh.as_str().into()),
+                        shell,
                         shell_version: None,
                     },
                 })
@@ -144,6 +156,30 @@ impl TelemetryQueue {
     }
 }
 
+impl TelemetryQueue {
+    /// `suggestion` is what was left of the suggestion, after a partial accept the accepted text is
+    /// part of the buffer the next accept is for
+    fn accept(&mut self, buffer: &str, suggestion: &str, kind: AcceptKind, accepted: &str) {
+        let buffer = buffer.trim_start();
+
+        for item in self.items.iter_mut() {
+            let Some(earlier) = buffer.strip_prefix(item.buffer.as_str()) else {
+                continue;
+            };
+            if item.suggestion.strip_prefix(earlier) != Some(suggestion) {
+                continue;
+            }
+
+            item.suggestion_state = SuggestionState::Accept;
+            item.accepted_chars_len = (earlier.chars().count() + accepted.chars().count()).try_into().ok();
+            item.accept_kind = match item.accept_kind {
+                Some(last) if last.is_partial() && !kind.is_partial() => Some(last),
+                _ => Some(kind),
+            };
+        }
+    }
+}
+
 struct TelemetryQueueItem {
     buffer: String,
     suggestion: String,
@@ -155,38 +191,49 @@ struct TelemetryQueueItem {
     suggestion_state: SuggestionState,
     edit_buffer_len: Option<i64>,
     suggested_chars_len: i32,
+    accepted_chars_len: Option<i32>,
+    accept_kind: Option<AcceptKind>,
     number_of_recommendations: i32,
     latency: Duration,
+    shell: Option<String>,
 }
 
 pub async fn handle_request(
     figterm_request: InlineShellCompletionRequest,
     _session_id: String,
+    shell: Option<String>,
     response_tx: Sender<FigtermResponseMessage>,
     history_sender: HistorySender,
 ) {
-    if !*INLINE_ENABLED.lock().await {
+    if !enabled().await {
         return;
     }
 
-    let buffer = figterm_request.buffer.trim_start();
+    let insert_text = complete(&figterm_request.buffer, shell, &history_sender).await;
+
+    if let Err(err) = response_tx
+        .send_async(FigtermResponseMessage {
+            response: Some(FigtermResponse::InlineShellCompletion(InlineShellCompletionResponse {
+                insert_text,
+            })),
+        })
+        .await
+    {
+        // This means the user typed something else before we got a response
+        error!(%err, "Failed to send inline_shell_completion completion");
+    }
+}
+
+/// Gets the text to insert after `buffer`, `None` if there is no suggestion or if another
+/// completion was requested while this one was debounced
+pub async fn complete(buffer: &str, shell: Option<String>, history_sender: &HistorySender) -> Option<String> {
+    let buffer = buffer.trim_start();
 
     if *CACHE_ENABLED {
         // use cached completion if available
         if let Some(insert_text) = COMPLETION_CACHE.lock().await.get_insert_text(buffer) {
             let trimmed_insert = insert_text.strip_prefix(buffer).unwrap_or(insert_text);
-
-            if let Err(err) = response_tx
-                .send_async(FigtermResponseMessage {
-                    response: Some(FigtermResponse::InlineShellCompletion(InlineShellCompletionResponse {
-                        insert_text: Some(trimmed_insert.to_owned()),
-                    })),
-                })
-                .await
-            {
-                error!(%err, "Failed to send inline_shell_completion completion");
-            }
-            return;
+            return Some(trimmed_insert.to_owned());
         }
     }
 
@@ -195,7 +242,7 @@ pub async fn handle_request(
     LAST_RECEIVED.lock().await.replace(now);
 
     let Ok(client) = Client::new().await else {
-        return;
+        return None;
     };
 
     for _ in 0..3 {
@@ -205,18 +252,7 @@ pub async fn handle_request(
             *LAST_RECEIVED.lock().await = Some(SystemTime::now());
         } else {
             warn!("Received another inline_shell_completion completion request, aborting");
-            if let Err(err) = response_tx
-                .send_async(FigtermResponseMessage {
-                    response: Some(FigtermResponse::InlineShellCompletion(InlineShellCompletionResponse {
-                        insert_text: None,
-                    })),
-                })
-                .await
-            {
-                error!(%err, "Failed to send inline_shell_completion completion");
-            }
-
-            return;
+            return None;
         }
 
         info!("Sending inline_shell_completion completion request");
@@ -240,9 +276,7 @@ pub async fn handle_request(
             },
         };
 
-        let Some(prompt) = prompt(&history, buffer) else {
-            return;
-        };
+        let prompt = prompt(&history, buffer)?;
 
         let input = RecommendationsInput {
             file_context: FileContext {
@@ -284,7 +318,7 @@ pub async fn handle_request(
                 // skips the first one which we will recommend, we only cache the rest
                 for completion in completions.iter().skip(1) {
                     let full_text = format!("{buffer}{completion}");
-                    if !completion.is_empty() && validate(&full_text) {
+                    if !completion.is_empty() && validate(buffer, &full_text) {
                         completion_cache.insert(full_text, 1.0);
                     }
                 }
@@ -292,7 +326,7 @@ pub async fn handle_request(
                 // now deals with the first recommendation
                 if let Some(completion) = completions.first_mut() {
                     let full_text = format!("{buffer}{completion}");
-                    let valid = validate(&full_text);
+                    let valid = validate(buffer, &full_text);
                     let is_empty = completion.is_empty();
 
                     if valid && !is_empty {
@@ -308,6 +342,7 @@ pub async fn handle_request(
                     tokio::spawn({
                         let completion = completion.clone();
                         let buffer = buffer.to_owned();
+                        let shell = shell.clone();
                         async move {
                             let mut queue = TELEMETRY_QUEUE.lock().await;
                             queue.items.push(TelemetryQueueItem {
@@ -320,7 +355,10 @@ pub async fn handle_request(
                                 latency: start_instant.elapsed(),
                                 suggestion_state,
                                 edit_buffer_len: buffer.chars().count().try_into().ok(),
+                                accepted_chars_len: None,
+                                accept_kind: None,
                                 buffer,
+                                shell,
                             });
                             // flush all but 4 messages, this is to retain messages that might have
                             // an accept waiting
@@ -333,6 +371,10 @@ pub async fn handle_request(
                     None
                 }
             },
+            Err(err) if err.is_rate_limited() => {
+                warn!(%err, "Not requesting inline_shell_completion completion while rate limited");
+                None
+            },
             Err(err) => {
                 error!(%err, "Failed to get inline_shell_completion completion");
                 None
@@ -341,35 +383,33 @@ pub async fn handle_request(
 
         info!(?insert_text, "Got inline_shell_completion completion");
 
-        match response_tx
-            .send_async(FigtermResponseMessage {
-                response: Some(FigtermResponse::InlineShellCompletion(InlineShellCompletionResponse {
-                    insert_text,
-                })),
-            })
-            .await
-        {
-            Ok(()) => {},
-            Err(err) => {
-                // This means the user typed something else before we got a response
-                // We want to bump the debounce timer
-
-                error!(%err, "Failed to send inline_shell_completion completion");
-            },
-        }
-
-        break;
+        return insert_text;
     }
+
+    None
 }
 
 pub async fn handle_accept(figterm_request: InlineShellCompletionAcceptRequest, _session_id: String) {
+    let kind = AcceptKind::from(figterm_request.kind());
+    let accepted = match figterm_request.accepted {
+        Some(accepted) => accepted,
+        None => kind
+            .accepted(&figterm_request.buffer, &figterm_request.suggestion)
+            .to_owned(),
+    };
+    record_accept(&figterm_request.buffer, &figterm_request.suggestion, kind, &accepted).await;
+}
+
+/// Marks the suggestion shown after `buffer` as accepted
+///
+/// Partial accepts are sent with the next prompt since more of the suggestion may still be
+/// accepted.
+pub async fn record_accept(buffer: &str, suggestion: &str, kind: AcceptKind, accepted: &str) {
     let mut queue = TELEMETRY_QUEUE.lock().await;
-    for item in queue.items.iter_mut() {
-        if item.buffer == figterm_request.buffer.trim_start() && item.suggestion == figterm_request.suggestion {
-            item.suggestion_state = SuggestionState::Accept;
-        }
+    queue.accept(buffer, suggestion, kind, accepted);
+    if !kind.is_partial() || accepted == suggestion {
+        queue.send_all_items(None).await;
     }
-    queue.send_all_items(None).await;
 }
 
 pub async fn handle_set_enabled(figterm_request: InlineShellCompletionSetEnabledRequest, _session_id: String) {
@@ -468,6 +508,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_partial_accept() {
+        let mut queue = TelemetryQueue::new();
+        queue.items.push(TelemetryQueueItem {
+            buffer: "git".into(),
+            suggestion: " commit -m 'fix the bug'".into(),
+            timestamp: SystemTime::now(),
+            session_id: "session".into(),
+            request_id: "request".into(),
+            suggestion_state: SuggestionState::Discard,
+            edit_buffer_len: Some(3),
+            suggested_chars_len: 24,
+            accepted_chars_len: None,
+            accept_kind: None,
+            number_of_recommendations: 1,
+            latency: Duration::ZERO,
+            shell: Some("bash".into()),
+        });
+
+        // A different suggestion is not accepted
+        queue.accept("git", " status", AcceptKind::Full, " status");
+        assert_eq!(queue.items[0].suggestion_state, SuggestionState::Discard);
+
+        queue.accept("git", " commit -m 'fix the bug'", AcceptKind::Word, " commit");
+        assert_eq!(queue.items[0].suggestion_state, SuggestionState::Accept);
+        assert_eq!(queue.items[0].accepted_chars_len, Some(7));
+        assert_eq!(queue.items[0].accept_kind, Some(AcceptKind::Word));
+
+        queue.accept("git commit", " -m 'fix the bug'", AcceptKind::Argument, " -m");
+        assert_eq!(queue.items[0].accepted_chars_len, Some(10));
+        assert_eq!(queue.items[0].accept_kind, Some(AcceptKind::Argument));
+
+        // Accepting the rest keeps the last partial kind
+        queue.accept("git commit -m", " 'fix the bug'", AcceptKind::Full, " 'fix the bug'");
+        assert_eq!(queue.items[0].accepted_chars_len, Some(24));
+        assert_eq!(queue.items[0].accept_kind, Some(AcceptKind::Argument));
+    }
+
     #[test]
     fn too_long_prompt() {
         let history = vec![CommandInfo {
@@ -1,16 +1,35 @@
 use std::path::Path;
 
-pub(super) fn validate(command: &str) -> bool {
-    validate_with_context(
-        command,
-        || {
-            std::env::current_dir()
-                .ok()
-                .and_then(|s| s.to_str().map(ToOwned::to_owned))
-        },
-        || fig_util::directories::home_dir_utf8().ok(),
-        |key| std::env::var(key).map(Some),
-    )
+use fig_util::command_risk::{
+    self,
+    Risk,
+};
+
+/// If `command`, the `buffer` the user typed followed by a suggestion, is worth suggesting
+pub(super) fn validate(buffer: &str, command: &str) -> bool {
+    !makes_destructive(buffer, command)
+        && validate_with_context(
+            command,
+            || {
+                std::env::current_dir()
+                    .ok()
+                    .and_then(|s| s.to_str().map(ToOwned::to_owned))
+            },
+            || fig_util::directories::home_dir_utf8().ok(),
+            |key| std::env::var(key).map(Some),
+        )
+}
+
+/// Suggestions are accepted with a single key, so one that turns what the user typed into a
+/// destructive command is never shown
+fn makes_destructive(buffer: &str, command: &str) -> bool {
+    let destructive = |command: &str| {
+        command_risk::analyze(command)
+            .findings
+            .iter()
+            .any(|finding| finding.risk == Risk::Destructive)
+    };
+    destructive(command) && !destructive(buffer)
 }
 
 fn validate_with_context<CwdStr, Cwd, HdStr, Hd, CtxStr, Ctx, E>(
@@ -62,6 +81,15 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_makes_destructive() {
+        assert!(makes_destructive("git re", "git reset --hard HEAD~3"));
+        assert!(makes_destructive("ls ", "ls | xargs rm"));
+        assert!(!makes_destructive("git re", "git restore --staged file"));
+        // The user already typed a destructive command
+        assert!(!makes_destructive("rm -rf ", "rm -rf node_modules"));
+    }
+
     #[test]
     fn test_validate() {
         let tempdir = tempfile::tempdir().unwrap();
@@ -104,6 +104,8 @@ pub enum InputEvent {
     // PixelMouse(PixelMouseEvent),
     /// Detected that the user has resized the terminal
     Resized,
+    /// The terminal gained or lost focus, reported when focus reporting (`CSI ? 1004 h`) is enabled
+    Focus(bool),
     /// For terminals that support Bracketed Paste mode,
     /// pastes are collected and reported as this variant.
     Paste(String),
@@ -1518,6 +1520,9 @@ impl InputParser {
             }),
         );
 
+        map.insert(b"\x1b[I", InputEvent::Focus(true));
+        map.insert(b"\x1b[O", InputEvent::Focus(false));
+
         map.insert(
             PASTE_START.as_bytes(),
             InputEvent::Key(KeyEvent {
@@ -2032,6 +2037,19 @@ mod test {
         );
     }
 
+    #[test]
+    fn focus_events() {
+        let mut p = InputParser::new();
+        assert_eq!(p.parse_as_vec(b"\x1b[I"), vec![InputEvent::Focus(true)]);
+        assert_eq!(p.parse_as_vec(b"\x1b[Oa"), vec![
+            InputEvent::Focus(false),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('a'),
+                modifiers: Modifiers::NONE
+            })
+        ]);
+    }
+
     #[test]
     fn test_parse_general() {
         let mut p = InputParser::new();
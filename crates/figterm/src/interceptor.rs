@@ -1,5 +1,6 @@
 use std::sync::LazyLock;
 
+use alacritty_terminal::term::TextBuffer;
 use anyhow::Result;
 use dashmap::DashMap;
 use fig_proto::figterm::Action;
@@ -7,7 +8,11 @@ use fig_settings::keybindings::{
     KeyBinding,
     KeyBindings,
 };
-use tracing::trace;
+use fig_util::consts::CLI_BINARY_NAME;
+use tracing::{
+    trace,
+    warn,
+};
 
 use crate::input::{
     KeyCode,
@@ -20,6 +25,9 @@ const GLOBAL_ACTIONS: &[&str] = &["toggleAutocomplete", "showAutocomplete"];
 
 const IGNORE_ACTION: &str = "ignore";
 
+/// Keybindings figterm handles itself are set as `qterm.keybindings.<binding>`
+const LOCAL_KEYBINDINGS_NAMESPACE: &str = "qterm";
+
 static ONLY_SHOW_ON_TAB: LazyLock<bool> =
     LazyLock::new(|| fig_settings::settings::get_bool_or("autocomplete.onlyShowOnTab", false));
 
@@ -86,6 +94,69 @@ pub fn key_from_text(text: impl AsRef<str>) -> Option<KeyEvent> {
     Some(KeyEvent { key, modifiers })
 }
 
+/// An action figterm runs itself when a user defined keybinding is pressed at the prompt, these
+/// work without the desktop app running
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalAction {
+    /// Types the text into the shell, a trailing newline runs it. Set as `insert:<text>`
+    Insert(String),
+    /// Replaces the edit buffer with `q chat` asking about it
+    Chat,
+    /// Replaces the edit buffer with `q translate` of it
+    Translate,
+    /// Accepts the next word of the inline suggestion, figterm accepts the suggestions it draws
+    /// itself, the [input](Self::input) accepts the shell's own
+    AcceptWord,
+}
+
+impl LocalAction {
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        match identifier {
+            "chat" => Some(Self::Chat),
+            "translate" => Some(Self::Translate),
+            "acceptWord" => Some(Self::AcceptWord),
+            _ => identifier
+                .strip_prefix("insert:")
+                .map(|text| Self::Insert(text.to_owned())),
+        }
+    }
+
+    /// The input to write to the shell to run the action
+    pub fn input(&self, buffer: Option<&TextBuffer>) -> Vec<u8> {
+        let text = buffer.map(|buffer| buffer.buffer.trim()).unwrap_or_default();
+        let subcommand = |name: &str| match text {
+            "" => format!("{CLI_BINARY_NAME} {name}"),
+            text => format!("{CLI_BINARY_NAME} {name} {}", shell_quote(text)),
+        };
+
+        match self {
+            LocalAction::Insert(text) => text.replace("\r\n", "\r").replace('\n', "\r").into_bytes(),
+            LocalAction::Chat => replace_buffer(buffer, &subcommand("chat")),
+            LocalAction::Translate => replace_buffer(buffer, &subcommand("translate")),
+            // Alt+f is forward-word, which partially accepts suggestions in both zsh-autosuggestions
+            // and fish
+            LocalAction::AcceptWord => b"\x1bf".to_vec(),
+        }
+    }
+}
+
+/// Quotes `text` as a single argument for POSIX shells and fish
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\"'\"'"))
+}
+
+/// Erases the edit buffer and runs `command` in its place
+pub fn replace_buffer(buffer: Option<&TextBuffer>, command: &str) -> Vec<u8> {
+    let erase = buffer.map_or(0, |TextBuffer { buffer, cursor_idx }| {
+        buffer.chars().count().max(cursor_idx.unwrap_or(0))
+    });
+
+    let mut input = vec![b'\x08'; erase];
+    input.extend(command.as_bytes());
+    input.push(b'\r');
+    input
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct KeyInterceptor {
     intercept_global: bool,
@@ -97,6 +168,7 @@ pub struct KeyInterceptor {
     _global_actions: Vec<Action>,
 
     mappings: DashMap<KeyEvent, String, fnv::FnvBuildHasher>,
+    local_mappings: DashMap<KeyEvent, LocalAction, fnv::FnvBuildHasher>,
 }
 
 impl KeyInterceptor {
@@ -111,9 +183,29 @@ impl KeyInterceptor {
                 self.insert_binding(binding, identifier);
             }
         }
+
+        match KeyBindings::load_from_settings(LOCAL_KEYBINDINGS_NAMESPACE) {
+            Ok(key_bindings) => self.set_local_bindings(key_bindings),
+            Err(err) => warn!(%err, "Failed to load keybindings"),
+        }
+
         Ok(())
     }
 
+    pub fn set_local_bindings(&mut self, key_bindings: impl IntoIterator<Item = KeyBinding>) {
+        self.local_mappings.clear();
+        for KeyBinding { identifier, binding } in key_bindings {
+            match (key_from_text(&binding), LocalAction::from_identifier(&identifier)) {
+                (Some(key), Some(action)) => {
+                    for key in binding_variants(key) {
+                        self.local_mappings.insert(key, action.clone());
+                    }
+                },
+                _ => warn!(%binding, %identifier, "Invalid keybinding"),
+            }
+        }
+    }
+
     pub fn set_intercept_global(&mut self, intercept_global: bool) {
         trace!("Setting intercept global to {intercept_global}");
         self.intercept_global = intercept_global;
@@ -144,46 +236,9 @@ impl KeyInterceptor {
     }
 
     fn insert_binding(&mut self, binding: KeyEvent, identifier: String) {
-        if let Some(key) = match binding.key {
-            KeyCode::UpArrow => Some(KeyCode::ApplicationUpArrow),
-            KeyCode::DownArrow => Some(KeyCode::ApplicationDownArrow),
-            KeyCode::LeftArrow => Some(KeyCode::ApplicationLeftArrow),
-            KeyCode::RightArrow => Some(KeyCode::ApplicationRightArrow),
-            _ => None,
-        } {
-            self.mappings.insert(
-                KeyEvent {
-                    key,
-                    modifiers: binding.modifiers,
-                },
-                identifier.clone(),
-            );
-        };
-
-        if let KeyCode::Char(key) = binding.key {
-            // Fill in other case if there is a ctrl or alt, i.e. ctrl+r is the same as ctrl+R
-            //
-            // This will prevent ctrl+shift+r from being the same as ctrl+r but that is probably
-            // fine since we lose context due to parsing ambiguity in the original xterm spec
-            // when other modifiers are present
-            if (binding.modifiers.contains(Modifiers::CTRL) || binding.modifiers.contains(Modifiers::ALT))
-                && key.is_ascii_alphabetic()
-            {
-                self.mappings.insert(
-                    KeyEvent {
-                        key: KeyCode::Char(if key.is_ascii_uppercase() {
-                            key.to_ascii_lowercase()
-                        } else {
-                            key.to_ascii_uppercase()
-                        }),
-                        modifiers: binding.modifiers,
-                    },
-                    identifier.clone(),
-                );
-            }
+        for key in binding_variants(binding) {
+            self.mappings.insert(key, identifier.clone());
         }
-
-        self.mappings.insert(binding, identifier);
     }
 
     pub fn reset(&mut self) {
@@ -192,6 +247,12 @@ impl KeyInterceptor {
         self.intercept = false;
     }
 
+    /// The user defined action for a key, these are handled whether or not the desktop app is
+    /// intercepting keys
+    pub fn intercept_local(&self, key_event: &KeyEvent) -> Option<LocalAction> {
+        self.local_mappings.get(key_event).map(|action| action.value().clone())
+    }
+
     pub fn intercept_key(&self, key_event: &KeyEvent) -> Option<String> {
         trace!(?key_event, "Intercepting key");
 
@@ -226,6 +287,46 @@ impl KeyInterceptor {
     }
 }
 
+/// The key events that should trigger a binding
+fn binding_variants(binding: KeyEvent) -> Vec<KeyEvent> {
+    let mut variants = vec![binding.clone()];
+
+    if let Some(key) = match binding.key {
+        KeyCode::UpArrow => Some(KeyCode::ApplicationUpArrow),
+        KeyCode::DownArrow => Some(KeyCode::ApplicationDownArrow),
+        KeyCode::LeftArrow => Some(KeyCode::ApplicationLeftArrow),
+        KeyCode::RightArrow => Some(KeyCode::ApplicationRightArrow),
+        _ => None,
+    } {
+        variants.push(KeyEvent {
+            key,
+            modifiers: binding.modifiers,
+        });
+    };
+
+    if let KeyCode::Char(key) = binding.key {
+        // Fill in other case if there is a ctrl or alt, i.e. ctrl+r is the same as ctrl+R
+        //
+        // This will prevent ctrl+shift+r from being the same as ctrl+r but that is probably
+        // fine since we lose context due to parsing ambiguity in the original xterm spec
+        // when other modifiers are present
+        if (binding.modifiers.contains(Modifiers::CTRL) || binding.modifiers.contains(Modifiers::ALT))
+            && key.is_ascii_alphabetic()
+        {
+            variants.push(KeyEvent {
+                key: KeyCode::Char(if key.is_ascii_uppercase() {
+                    key.to_ascii_lowercase()
+                } else {
+                    key.to_ascii_uppercase()
+                }),
+                modifiers: binding.modifiers,
+            });
+        }
+    }
+
+    variants
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,4 +378,65 @@ mod tests {
             Some("navigateDown".into())
         );
     }
+
+    #[test]
+    fn test_local_bindings() {
+        let mut interceptor = KeyInterceptor::new();
+        interceptor.set_local_bindings([
+            KeyBinding {
+                identifier: "chat".into(),
+                binding: "ctrl+g".into(),
+            },
+            KeyBinding {
+                identifier: "insert:git status\n".into(),
+                binding: "alt+s".into(),
+            },
+            KeyBinding {
+                identifier: "unknown".into(),
+                binding: "ctrl+u".into(),
+            },
+        ]);
+
+        // Local bindings don't depend on the desktop app intercepting keys
+        assert_eq!(
+            interceptor.intercept_local(&KeyEvent {
+                key: KeyCode::Char('G'),
+                modifiers: Modifiers::CTRL
+            }),
+            Some(LocalAction::Chat)
+        );
+        assert_eq!(
+            interceptor.intercept_local(&KeyEvent {
+                key: KeyCode::Char('s'),
+                modifiers: Modifiers::ALT
+            }),
+            Some(LocalAction::Insert("git status\n".into()))
+        );
+        assert_eq!(
+            interceptor.intercept_local(&KeyEvent {
+                key: KeyCode::Char('u'),
+                modifiers: Modifiers::CTRL
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_local_action_input() {
+        let buffer = TextBuffer {
+            buffer: "it's broken ".into(),
+            cursor_idx: Some(12),
+        };
+
+        assert_eq!(
+            LocalAction::Insert("ls -la\n".into()).input(Some(&buffer)),
+            b"ls -la\r".to_vec()
+        );
+        assert_eq!(
+            LocalAction::Chat.input(Some(&buffer)),
+            [&[b'\x08'; 12][..], b"q chat 'it'\"'\"'s broken'\r"].concat()
+        );
+        assert_eq!(LocalAction::Translate.input(None), b"q translate\r".to_vec());
+        assert_eq!(LocalAction::AcceptWord.input(None), b"\x1bf".to_vec());
+    }
 }
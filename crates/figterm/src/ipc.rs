@@ -2,6 +2,10 @@
 
 use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
 use std::task::{
     Context,
     Poll,
@@ -63,6 +67,13 @@ use tracing::{
 
 use crate::MainLoopEvent;
 
+static DESKTOP_CONNECTED: AtomicBool = AtomicBool::new(false);
+
+/// If figterm is connected to the desktop app
+pub fn desktop_connected() -> bool {
+    DESKTOP_CONNECTED.load(Ordering::Relaxed)
+}
+
 #[allow(dead_code)]
 #[pin_project(project = MessageSourceProj)]
 enum MessageSource {
@@ -144,7 +155,9 @@ async fn get_forwarded_stream() -> Result<(MessageSource, MessageSink, Option<Jo
     }
 
     let socket = directories::remote_socket_path()?;
-    let stream = fig_ipc::socket_connect_timeout(&socket, Duration::from_secs(5)).await?;
+    // A socket left behind by a multiplexer that exited is removed, so the next attempt looks for
+    // another one rather than connecting to it again
+    let stream = fig_ipc::socket_connect_or_remove_stale(&socket, Duration::from_secs(5)).await?;
     let (reader, writer) = tokio::io::split(stream);
     Ok((MessageSource::UnixStream(reader), MessageSink::UnixStream(writer), None))
 }
@@ -297,6 +310,7 @@ pub async fn spawn_remote_ipc(
                         continue;
                     }
                     info!("Handshake succeeded");
+                    DESKTOP_CONNECTED.store(true, Ordering::Relaxed);
 
                     // send outgoing messages
                     outgoing_rx.drain();
@@ -358,6 +372,7 @@ pub async fn spawn_remote_ipc(
                     } else {
                         let _ = join!(outgoing_task, incoming_task);
                     }
+                    DESKTOP_CONNECTED.store(false, Ordering::Relaxed);
                 }
             }
         }
@@ -9,7 +9,10 @@ pub mod interceptor;
 pub mod ipc;
 pub mod logger;
 mod message;
+mod notifier;
+mod popup;
 pub mod pty;
+mod recorder;
 pub mod term;
 pub mod update;
 
@@ -19,7 +22,6 @@ use std::ffi::{
     CString,
     OsStr,
 };
-use std::iter::repeat;
 use std::sync::{
     LazyLock,
     Mutex,
@@ -37,7 +39,6 @@ use alacritty_terminal::grid::Dimensions;
 use alacritty_terminal::term::{
     ShellState,
     SizeInfo,
-    TextBuffer,
 };
 use anyhow::{
     Context as _,
@@ -73,12 +74,15 @@ use fig_util::env_var::{
     Q_PARENT,
     Q_SHELL,
     Q_TERM,
+    Q_TERM_RECORD,
+    Q_TERM_RECORD_INPUT,
     QTERM_SESSION_ID,
 };
 use fig_util::process_info::{
     Pid,
     PidExt,
 };
+use fig_util::shell_words::AcceptKind;
 use fig_util::{
     PRODUCT_NAME,
     PTY_BINARY_NAME,
@@ -102,14 +106,20 @@ use tokio::{
     select,
 };
 use tracing::{
+    Instrument,
     debug,
     error,
     info,
+    info_span,
     trace,
     warn,
 };
 
 use crate::event_handler::EventHandler;
+use crate::inline::ghost_text::{
+    Accepted,
+    GhostText,
+};
 use crate::input::{
     InputEvent,
     KeyCode,
@@ -117,8 +127,12 @@ use crate::input::{
     KeyboardEncoding,
     Modifiers,
 };
-use crate::interceptor::KeyInterceptor;
+use crate::interceptor::{
+    KeyInterceptor,
+    LocalAction,
+};
 use crate::ipc::{
+    desktop_connected,
     spawn_figterm_ipc,
     spawn_remote_ipc,
 };
@@ -126,6 +140,12 @@ use crate::message::{
     process_figterm_message,
     process_remote_message,
 };
+use crate::popup::{
+    KeyAction,
+    Popup,
+    Renderer,
+    Specs,
+};
 #[cfg(unix)]
 use crate::pty::unix::open_pty;
 #[cfg(windows)]
@@ -175,6 +195,15 @@ pub enum MainLoopEvent {
     },
     SetCsiU,
     UnsetCsiU,
+    /// Write directly to the user's terminal
+    WriteTerminal(Vec<u8>),
+    /// An inline suggestion figterm requested to draw itself
+    InlineSuggestion {
+        buffer: String,
+        suggestion: String,
+    },
+    /// A spec the autocomplete popup was waiting for was loaded
+    PopupSpecLoaded,
 }
 
 fn shell_state_to_context(shell_state: &ShellState) -> local::ShellContext {
@@ -363,7 +392,8 @@ async fn send_edit_buffer<T>(
 where
     T: EventListener,
 {
-    if !*AUTOCOMPLETE_ENABLED {
+    // The desktop app would draw autocomplete over the popup figterm draws
+    if !*AUTOCOMPLETE_ENABLED || Renderer::load() == Renderer::Terminal {
         return Ok(());
     }
 
@@ -390,6 +420,15 @@ where
     }
 }
 
+/// Types the accepted part of an inline suggestion into the shell
+fn accept_inline(write_buffer: &mut BytesMut, accepted: Accepted) {
+    debug!(?accepted, "Accepted inline suggestion");
+    write_buffer.extend(accepted.text.as_bytes());
+    tokio::spawn(async move {
+        inline::record_accept(&accepted.buffer, &accepted.suggestion, accepted.kind, &accepted.text).await;
+    });
+}
+
 fn get_parent_shell() -> Result<String> {
     match env::var(Q_SHELL).ok().filter(|s| !s.is_empty()) {
         Some(v) => Ok(v),
@@ -444,6 +483,8 @@ fn build_shell_command(command: Option<&[String]>) -> Result<CommandBuilder> {
     builder.env_remove("Q_START_TEXT");
     builder.env_remove("Q_SHELL_EXTRA_ARGS");
     builder.env_remove("Q_EXECUTION_STRING");
+    builder.env_remove(Q_TERM_RECORD);
+    builder.env_remove(Q_TERM_RECORD_INPUT);
 
     if let Ok(dir) = std::env::current_dir() {
         builder.cwd(dir);
@@ -500,6 +541,7 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
     };
 
     let pty = open_pty(&pty_size).context("Failed to open pty")?;
+    let default_shell = command.is_none().then(get_parent_shell).and_then(Result::ok);
     let command = build_shell_command(command)?;
 
     let pty_name = pty.slave.get_name().unwrap_or_else(|| session_id.clone());
@@ -530,7 +572,8 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
     }
 
     let mut child = pty.slave.spawn_command(command)?;
-    info!("Shell: {:?}", child.process_id());
+    let child_pid = child.process_id();
+    info!("Shell: {:?}", child_pid);
     if let Some(pid) = child.process_id() {
         logger::stdio_debug_log(format!("Child pid: {pid}"));
     }
@@ -580,13 +623,32 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
         #[cfg(target_os = "windows")]
         term.set_windows_delay_end_prompt(true);
 
+        // Shells that only emit the standard OSC 133 and OSC 7 sequences never report their context,
+        // fill in what we know, our shell integration overrides it
+        let shell_state = term.shell_state_mut();
+        shell_state.hostname = HOSTNAME.clone();
+        let shell_context = shell_state.get_mut_context();
+        shell_context.pid = child_pid.and_then(|pid| pid.try_into().ok());
+        shell_context.tty = Some(pty_name.clone());
+        shell_context.username = env::var("USER").ok();
+        if let Some(shell) = &default_shell {
+            let path = std::path::Path::new(shell);
+            shell_context.shell = path.file_name().and_then(|name| name.to_str()).map(str::to_owned);
+            shell_context.shell_path = Some(path.to_path_buf());
+        }
+
         let mut write_buffer: Vec<u8> = vec![0; BUFFER_SIZE];
 
+        let mut recorder = recorder::Recorder::from_env(pty_size.cols as usize, pty_size.rows as usize);
+
         let mut key_interceptor = KeyInterceptor::new();
         key_interceptor.load_key_intercepts()?;
 
         let mut edit_buffer_interval = tokio::time::interval(Duration::from_millis(16));
 
+        let mut ghost_text = GhostText::new();
+        let mut popup = Popup::new(Specs::new(main_loop_tx.clone()));
+
         let mut first_time = true;
 
         let input_rx = terminal.read_input()?;
@@ -613,6 +675,14 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
 
         let mut csi_u_set = false;
 
+        // Track focus for long running command notifications
+        let focus_reporting = notifier::NotifierConfig::load().enabled;
+        let _focus_reporting_guard = match focus_reporting {
+            true => Some(notifier::FocusReportingGuard::enable(std::io::stdout())?),
+            false => None,
+        };
+        let mut shell_focus_reporting = false;
+
         let result: Result<()> = 'select_loop: loop {
             if first_time && term.shell_state().has_seen_prompt {
                 trace!("Has seen prompt and first time");
@@ -675,6 +745,36 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                                     stdout.flush().await?;
                                     csi_u_set = false;
                                 },
+                                MainLoopEvent::WriteTerminal(bytes) => {
+                                    if let Some(erase) = popup.erase(&term) {
+                                        stdout.write_all(&erase).await?;
+                                    }
+                                    if let Some(erase) = ghost_text.erase() {
+                                        stdout.write_all(erase).await?;
+                                    }
+                                    stdout.write_all(&bytes).await?;
+                                    stdout.flush().await?;
+                                },
+                                MainLoopEvent::InlineSuggestion { buffer, suggestion } => {
+                                    ghost_text.set_suggestion(&buffer, suggestion);
+                                    if can_send_edit_buffer(&term) {
+                                        if let Some(bytes) = ghost_text.draw(&term) {
+                                            stdout.write_all(&bytes).await?;
+                                            stdout.flush().await?;
+                                        }
+                                    }
+                                },
+                                MainLoopEvent::PopupSpecLoaded => {
+                                    if *AUTOCOMPLETE_ENABLED && can_send_edit_buffer(&term) && popup::enabled(desktop_connected()) {
+                                        let cwd = term.shell_state().get_context().current_working_directory.clone();
+                                        popup.invalidate();
+                                        popup.update(term.get_current_buffer().as_ref(), cwd.as_deref());
+                                        if let Some(bytes) = popup.draw(&term) {
+                                            stdout.write_all(&bytes).await?;
+                                            stdout.flush().await?;
+                                        }
+                                    }
+                                },
                                 MainLoopEvent::PromptSSH { uuid: _, remote_host: _ } => {
                                     // let should_install = should_install_remote_ssh_integration(
                                     //     uuid,
@@ -724,26 +824,53 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
 
                                         debug!(?event, ?raw, %preexec,  "Got key event");
 
+                                        if !preexec {
+                                            if let Some(action) = key_interceptor.intercept_local(&event) {
+                                                debug!(?action, "Running local action");
+                                                // Suggestions figterm draws are accepted here, the shell accepts its own
+                                                let accepted = match action {
+                                                    LocalAction::AcceptWord => ghost_text.accept_as(AcceptKind::Word),
+                                                    _ => None,
+                                                };
+                                                match accepted {
+                                                    Some(accepted) => accept_inline(&mut write_buffer, accepted),
+                                                    None => write_buffer.extend(action.input(term.get_current_buffer().as_ref())),
+                                                }
+                                                continue;
+                                            }
+
+                                            if let Some(action) = popup.on_key(&event) {
+                                                debug!(?action, "Popup handled key");
+                                                let bytes = match action {
+                                                    KeyAction::Redraw => popup.draw(&term),
+                                                    KeyAction::Hide => popup.erase(&term),
+                                                    KeyAction::Insert(input) => {
+                                                        write_buffer.extend(input);
+                                                        None
+                                                    },
+                                                };
+                                                if let Some(bytes) = bytes {
+                                                    stdout.write_all(&bytes).await?;
+                                                    stdout.flush().await?;
+                                                }
+                                                continue;
+                                            }
+
+                                            if let Some(accepted) = ghost_text.accept(&event) {
+                                                accept_inline(&mut write_buffer, accepted);
+                                                continue;
+                                            }
+                                        }
+
                                         if !preexec && ai_enabled && event.key == KeyCode::Enter && event.modifiers == input::Modifiers::NONE {
-                                            if let Some(TextBuffer { buffer, cursor_idx }) = term.get_current_buffer() {
-                                                let buffer = buffer.trim();
+                                            if let Some(text_buffer) = term.get_current_buffer() {
+                                                let buffer = text_buffer.buffer.trim();
                                                 if buffer.len() > 1 && buffer.starts_with('#') && term.columns() > buffer.len() {
-                                                    write_buffer.extend(
-                                                        &repeat(b'\x08')
-                                                            .take(buffer.len()
-                                                            .max(cursor_idx.unwrap_or(0)))
-                                                            .collect::<Vec<_>>()
-                                                    );
-                                                    write_buffer.extend(
-                                                        format!(
-                                                            "{} translate '{}'\r",
-                                                            CLI_BINARY_NAME,
-                                                            buffer
-                                                                .trim_start_matches('#')
-                                                                .trim()
-                                                                .replace('\'', "'\"'\"'")
-                                                            ).as_bytes()
+                                                    let command = format!(
+                                                        "{CLI_BINARY_NAME} translate {}",
+                                                        interceptor::shell_quote(buffer.trim_start_matches('#').trim())
                                                     );
+                                                    write_buffer.extend(interceptor::replace_buffer(Some(&text_buffer), &command));
                                                     master.write_all(&write_buffer).await?;
                                                     continue 'select_loop;
                                                 }
@@ -806,9 +933,24 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                                         };
 
                                         master.resize(pty_size)?;
+                                        ghost_text.forget_drawn();
+                                        popup.forget_drawn();
                                         let window_size = SizeInfo::new(size.rows, size.cols);
                                         debug!("Window size changed: {window_size:?}");
                                         term.resize(window_size);
+
+                                        if let Some(recorder) = &mut recorder {
+                                            recorder.resize(size.cols, size.rows);
+                                        }
+                                    }
+                                    Ok((raw, InputEvent::Focus(focused))) => {
+                                        notifier::set_focused(focused);
+                                        // Only pass focus events through if the shell asked for them
+                                        if term.mode().contains(alacritty_terminal::term::TermMode::FOCUS_IN_OUT) {
+                                            if let Some(raw) = raw {
+                                                write_buffer.extend(&raw);
+                                            }
+                                        }
                                     }
                                     Ok((None, InputEvent::Paste(string))) => {
                                         // Pass through bracketed pastes.
@@ -836,6 +978,10 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                                 };
                             }
                             master.write_all(&write_buffer).await?;
+
+                            if let Some(recorder) = &mut recorder {
+                                recorder.input(&write_buffer);
+                            }
                         }
                         Err(err) => {
                             warn!("Failed recv: {err}");
@@ -854,6 +1000,9 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                         Ok(size) => {
                             trace!("Read {size} bytes from master");
 
+                            // The popup is erased from the grid as it was before the output
+                            let popup_erase = popup.erase(&term);
+
                             let old_delayed_count = term.get_delayed_events_count();
                             for byte in &write_buffer[..size] {
                                 processor.advance(&mut term, *byte);
@@ -867,9 +1016,30 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                                 term.flush_delayed_events();
                             }
 
+                            if let Some(erase) = popup_erase {
+                                stdout.write_all(&erase).await?;
+                            }
+                            if let Some(erase) = ghost_text.erase() {
+                                stdout.write_all(erase).await?;
+                            }
                             stdout.write_all(&write_buffer[..size]).await?;
+
+                            // Programs that used focus reporting turn it off when they exit
+                            let focus_mode = term.mode().contains(alacritty_terminal::term::TermMode::FOCUS_IN_OUT);
+                            if focus_reporting && shell_focus_reporting && !focus_mode {
+                                stdout.write_all(notifier::ENABLE_FOCUS_REPORTING).await?;
+                            }
+                            shell_focus_reporting = focus_mode;
+
                             stdout.flush().await?;
 
+                            if let Some(recorder) = &mut recorder {
+                                if let Some(command) = &term.shell_state().command_info {
+                                    recorder.command(command);
+                                }
+                                recorder.output(&write_buffer[..size]);
+                            }
+
                             if write_buffer.capacity() == write_buffer.len() {
                                 write_buffer.reserve(write_buffer.len());
                             }
@@ -879,6 +1049,38 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
                                 if let Err(err) = send_edit_buffer(&term, &remote_sender, cursor_coordinates).await {
                                     warn!("Failed to send edit buffer: {err}");
                                 }
+
+                                let shell = term.shell_state().get_context().shell.clone();
+                                if GhostText::supported(shell.as_deref()) && inline::enabled().await {
+                                    if let Some(buffer) = ghost_text.update(term.get_current_buffer().as_ref()) {
+                                        tokio::spawn({
+                                            let main_loop_tx = main_loop_tx.clone();
+                                            let history_sender = history_sender.clone();
+                                            async move {
+                                                if let Some(suggestion) = inline::complete(&buffer, shell, &history_sender).await {
+                                                    main_loop_tx
+                                                        .send_async(MainLoopEvent::InlineSuggestion { buffer, suggestion })
+                                                        .await
+                                                        .ok();
+                                                }
+                                            }
+                                        });
+                                    }
+
+                                    if let Some(bytes) = ghost_text.draw(&term) {
+                                        stdout.write_all(&bytes).await?;
+                                        stdout.flush().await?;
+                                    }
+                                }
+
+                                if *AUTOCOMPLETE_ENABLED && popup::enabled(desktop_connected()) {
+                                    let cwd = term.shell_state().get_context().current_working_directory.clone();
+                                    popup.update(term.get_current_buffer().as_ref(), cwd.as_deref());
+                                    if let Some(bytes) = popup.draw(&term) {
+                                        stdout.write_all(&bytes).await?;
+                                        stdout.flush().await?;
+                                    }
+                                }
                             }
 
                             Ok(())
@@ -956,7 +1158,7 @@ fn figterm_main(command: Option<&[String]>) -> Result<()> {
         fig_telemetry::finish_telemetry().await;
 
         result
-    });
+    }.instrument(info_span!("session", session_id = %session_id)));
 
     // Reading from stdin is a blocking task on a separate thread:
     // https://github.com/tokio-rs/tokio/issues/2466
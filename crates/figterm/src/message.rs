@@ -313,8 +313,15 @@ pub async fn process_figterm_message(
         Some(FigtermRequest::InlineShellCompletion(request)) => {
             let history_sender = history_sender.clone();
             let session_id = session_id.to_owned();
-
-            tokio::spawn(inline::handle_request(request, session_id, response_tx, history_sender));
+            let shell = term.shell_state().get_context().shell.clone();
+
+            tokio::spawn(inline::handle_request(
+                request,
+                session_id,
+                shell,
+                response_tx,
+                history_sender,
+            ));
         },
         Some(FigtermRequest::InlineShellCompletionAccept(request)) => {
             tokio::spawn(inline::handle_accept(request, session_id.to_owned()));
@@ -0,0 +1,290 @@
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use alacritty_terminal::term::CommandInfo;
+use flume::Sender;
+use tracing::{
+    debug,
+    warn,
+};
+
+use crate::MainLoopEvent;
+
+const ENABLED_KEY: &str = "qterm.notifications.enabled";
+const THRESHOLD_KEY: &str = "qterm.notifications.threshold";
+const WATCH_KEY: &str = "qterm.notifications.watch";
+const METHOD_KEY: &str = "qterm.notifications.method";
+
+const DEFAULT_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Enables focus reporting in the terminal, focus changes are sent as `CSI I` and `CSI O`
+pub const ENABLE_FOCUS_REPORTING: &[u8] = b"\x1b[?1004h";
+pub const DISABLE_FOCUS_REPORTING: &[u8] = b"\x1b[?1004l";
+
+/// Disables focus reporting when dropped, so the terminal isn't left sending focus events to
+/// whatever runs after figterm exits, even when it exits on an error
+#[must_use]
+pub struct FocusReportingGuard<W: Write>(W);
+
+impl<W: Write> FocusReportingGuard<W> {
+    /// Enables focus reporting on `stdout`, it is disabled on the same writer when dropped
+    pub fn enable(mut stdout: W) -> std::io::Result<Self> {
+        stdout.write_all(ENABLE_FOCUS_REPORTING)?;
+        stdout.flush()?;
+        Ok(Self(stdout))
+    }
+}
+
+impl<W: Write> Drop for FocusReportingGuard<W> {
+    fn drop(&mut self) {
+        self.0.write_all(DISABLE_FOCUS_REPORTING).ok();
+        self.0.flush().ok();
+    }
+}
+
+/// If the terminal is focused, `None` until the terminal reports a focus change
+static FOCUSED: Mutex<Option<bool>> = Mutex::new(None);
+
+pub fn set_focused(focused: bool) {
+    *FOCUSED.lock().unwrap() = Some(focused);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Desktop notifications where available, otherwise [`Method::Osc9`]
+    Auto,
+    Desktop,
+    /// `OSC 9`, supported by iTerm2, kitty, WezTerm, Ghostty and Windows Terminal
+    Osc9,
+    /// `OSC 777`, supported by foot, rxvt-unicode, WezTerm and Ghostty
+    Osc777,
+    Bell,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifierConfig {
+    pub enabled: bool,
+    /// Commands running longer than this notify when the terminal isn't focused
+    pub threshold: Duration,
+    /// Commands that always notify when they finish
+    pub watch: Vec<String>,
+    pub method: Method,
+}
+
+impl NotifierConfig {
+    pub fn load() -> Self {
+        let method = match fig_settings::settings::get_string_opt(METHOD_KEY).as_deref() {
+            Some("desktop") => Method::Desktop,
+            Some("osc9") => Method::Osc9,
+            Some("osc777") => Method::Osc777,
+            Some("bell") => Method::Bell,
+            _ => Method::Auto,
+        };
+
+        Self {
+            // Off by default as it turns on focus reporting in the terminal
+            enabled: fig_settings::settings::get_bool_or(ENABLED_KEY, false),
+            threshold: fig_settings::settings::get_int(THRESHOLD_KEY)
+                .ok()
+                .flatten()
+                .and_then(|secs| u64::try_from(secs).ok())
+                .map_or(DEFAULT_THRESHOLD, Duration::from_secs),
+            watch: fig_settings::settings::get(WATCH_KEY)
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            method,
+        }
+    }
+
+    /// If a notification should be sent for the finished command
+    fn should_notify(&self, command: &CommandInfo, focused: Option<bool>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        let Some(duration) = duration(command) else {
+            return false;
+        };
+
+        let program = command
+            .command
+            .as_deref()
+            .and_then(|command| command.split_whitespace().next())
+            .map(|program| program.rsplit('/').next().unwrap_or(program));
+        if program.is_some_and(|program| self.watch.iter().any(|watched| watched == program)) {
+            return true;
+        }
+
+        // Only notify when we know the user isn't looking at the terminal
+        duration >= self.threshold && focused == Some(false)
+    }
+}
+
+fn duration(command: &CommandInfo) -> Option<Duration> {
+    command.end_time?.duration_since(command.start_time?).ok()
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+fn message(command: &CommandInfo) -> (String, String) {
+    let summary = match command.exit_code {
+        Some(0) | None => "Command finished".to_owned(),
+        Some(code) => format!("Command failed with exit code {code}"),
+    };
+    let body = format!(
+        "{} ({})",
+        command.command.as_deref().unwrap_or_default(),
+        duration(command).map(format_duration).unwrap_or_default()
+    );
+    (summary, body)
+}
+
+/// The sequence written to the terminal to show a notification, notification text can not contain
+/// control characters or `;` for `OSC 777`
+fn terminal_notification(method: Method, summary: &str, body: &str) -> Vec<u8> {
+    let clean = |text: &str| text.chars().filter(|c| !c.is_control()).collect::<String>();
+    match method {
+        Method::Osc777 => format!(
+            "\x1b]777;notify;{};{}\x07",
+            clean(summary).replace(';', ","),
+            clean(body).replace(';', ",")
+        )
+        .into_bytes(),
+        Method::Bell => b"\x07".to_vec(),
+        Method::Auto | Method::Desktop | Method::Osc9 => {
+            format!("\x1b]9;{}: {}\x07", clean(summary), clean(body)).into_bytes()
+        },
+    }
+}
+
+/// Desktop notifications are only used when they would show up on the machine the user is at
+#[cfg(target_os = "linux")]
+fn desktop_available() -> bool {
+    std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+        && std::env::var_os("SSH_CONNECTION").is_none()
+        && std::env::var_os("SSH_TTY").is_none()
+}
+
+/// Sends a notification for a finished command if it ran long enough or is watched
+pub fn on_command_finished(config: &NotifierConfig, command: &CommandInfo, main_loop_sender: &Sender<MainLoopEvent>) {
+    let focused = *FOCUSED.lock().unwrap();
+    if !config.should_notify(command, focused) {
+        return;
+    }
+
+    let (summary, body) = message(command);
+    debug!(%summary, %body, ?focused, "Sending command notification");
+
+    let method = config.method;
+    let main_loop_sender = main_loop_sender.clone();
+    tokio::spawn(async move {
+        #[cfg(target_os = "linux")]
+        if method == Method::Desktop || (method == Method::Auto && desktop_available()) {
+            match dbus::notifications::send_notification(fig_util::PRODUCT_NAME, &summary, &body).await {
+                Ok(_) => return,
+                Err(err) => warn!(%err, "Failed to send desktop notification, falling back to the terminal"),
+            }
+        }
+
+        let sequence = terminal_notification(method, &summary, &body);
+        if let Err(err) = main_loop_sender
+            .send_async(MainLoopEvent::WriteTerminal(sequence))
+            .await
+        {
+            warn!(%err, "Failed to send notification");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    fn command(command: &str, secs: u64, exit_code: i32) -> CommandInfo {
+        let start_time = SystemTime::now();
+        CommandInfo {
+            command: Some(command.into()),
+            start_time: Some(start_time),
+            end_time: Some(start_time + Duration::from_secs(secs)),
+            exit_code: Some(exit_code),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_should_notify() {
+        let config = NotifierConfig {
+            enabled: true,
+            threshold: Duration::from_secs(30),
+            watch: vec!["make".into()],
+            method: Method::Auto,
+        };
+
+        assert!(config.should_notify(&command("cargo build", 60, 0), Some(false)));
+        assert!(!config.should_notify(&command("cargo build", 60, 0), Some(true)));
+        assert!(!config.should_notify(&command("cargo build", 60, 0), None));
+        assert!(!config.should_notify(&command("cargo build", 10, 0), Some(false)));
+
+        assert!(config.should_notify(&command("/usr/bin/make -j8", 1, 0), Some(true)));
+        assert!(!config.should_notify(&command("cmake .", 1, 0), None));
+
+        let config = NotifierConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(!config.should_notify(&command("make", 60, 0), Some(false)));
+    }
+
+    #[test]
+    fn test_focus_reporting_guard() {
+        let mut out = Vec::new();
+        let guard = FocusReportingGuard::enable(&mut out).unwrap();
+        drop(guard);
+        assert_eq!(out, [ENABLE_FOCUS_REPORTING, DISABLE_FOCUS_REPORTING].concat());
+
+        // Disabled even when the guard is dropped while unwinding
+        let mut out = Vec::new();
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = FocusReportingGuard::enable(&mut out).unwrap();
+            panic!("figterm exited on an error");
+        }))
+        .unwrap_err();
+        assert!(out.ends_with(DISABLE_FOCUS_REPORTING));
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(&command("cargo build", 83, 0)),
+            ("Command finished".into(), "cargo build (1m 23s)".into())
+        );
+        assert_eq!(
+            message(&command("make", 7300, 2)),
+            ("Command failed with exit code 2".into(), "make (2h 1m)".into())
+        );
+    }
+
+    #[test]
+    fn test_terminal_notification() {
+        assert_eq!(
+            terminal_notification(Method::Osc9, "Done", "ls\x1b"),
+            b"\x1b]9;Done: ls\x07".to_vec()
+        );
+        assert_eq!(
+            terminal_notification(Method::Osc777, "Done", "a; b"),
+            b"\x1b]777;notify;Done;a, b\x07".to_vec()
+        );
+        assert_eq!(terminal_notification(Method::Bell, "Done", "ls"), b"\x07".to_vec());
+    }
+}
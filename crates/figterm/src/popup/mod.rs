@@ -0,0 +1,402 @@
+//! Autocomplete drawn by figterm in the terminal
+//!
+//! The desktop app shows autocomplete in a window next to the cursor, which needs a display and
+//! doesn't work over SSH, in tmux on a server or in a bare TTY. When the desktop app isn't
+//! connected figterm draws the suggestions itself in the rows next to the prompt, from the same
+//! completion specs the desktop app loads.
+//!
+//! Only specs without generators and other functions can be read, see [`fig_specs::module`]. For
+//! commands with any other spec the popup says the spec isn't supported rather than suggesting
+//! part of it.
+
+mod render;
+mod specs;
+mod suggestions;
+
+use std::path::Path;
+
+use alacritty_terminal::Term;
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::term::TextBuffer;
+pub use specs::{
+    Spec,
+    Specs,
+};
+
+use self::render::Area;
+use self::suggestions::{
+    Kind,
+    Suggestion,
+};
+use crate::input::{
+    KeyCode,
+    KeyEvent,
+    Modifiers,
+};
+
+const RENDERER_SETTINGS_KEY: &str = "autocomplete.renderer";
+
+/// If figterm draws autocomplete, autocomplete has to be enabled and not drawn by the desktop app
+pub fn enabled(desktop_connected: bool) -> bool {
+    !fig_settings::settings::get_bool_or("autocomplete.disable", false)
+        && Renderer::load().in_terminal(desktop_connected)
+}
+
+/// Where autocomplete is drawn, set with `autocomplete.renderer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    /// In the desktop app when it is connected and in the terminal otherwise
+    Auto,
+    Desktop,
+    Terminal,
+}
+
+impl Renderer {
+    pub fn load() -> Self {
+        match fig_settings::settings::get_string(RENDERER_SETTINGS_KEY)
+            .ok()
+            .flatten()
+            .as_deref()
+        {
+            Some("desktop") => Renderer::Desktop,
+            Some("terminal") => Renderer::Terminal,
+            _ => Renderer::Auto,
+        }
+    }
+
+    /// If figterm draws autocomplete itself
+    pub fn in_terminal(self, desktop_connected: bool) -> bool {
+        match self {
+            Renderer::Auto => !desktop_connected,
+            Renderer::Desktop => false,
+            Renderer::Terminal => true,
+        }
+    }
+}
+
+/// What to do with a key the popup handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    /// The selection moved, the popup has to be drawn again
+    Redraw,
+    /// Write the bytes to the shell to insert the selected suggestion
+    Insert(Vec<u8>),
+    /// The popup was dismissed and has to be erased
+    Hide,
+}
+
+#[derive(Debug)]
+pub struct Popup {
+    specs: Specs,
+    /// The buffer the suggestions are for
+    buffer: String,
+    /// The token under the cursor as it was typed
+    token: String,
+    suggestions: Vec<Suggestion>,
+    selected: usize,
+    /// Enter only inserts a suggestion once one was picked with the arrow keys
+    navigated: bool,
+    /// Escape hides the popup until the buffer changes
+    hidden: bool,
+    drawn: Option<Area>,
+}
+
+impl Popup {
+    pub fn new(specs: Specs) -> Self {
+        Self {
+            specs,
+            buffer: String::new(),
+            token: String::new(),
+            suggestions: vec![],
+            selected: 0,
+            navigated: false,
+            hidden: false,
+            drawn: None,
+        }
+    }
+
+    /// Updates the suggestions for the edit buffer, the cursor has to be at the end of the buffer
+    pub fn update(&mut self, buffer: Option<&TextBuffer>, cwd: Option<&Path>) {
+        let Some(buffer) = buffer.filter(|buffer| {
+            !buffer.buffer.trim().is_empty() && buffer.cursor_idx == Some(buffer.buffer.chars().count())
+        }) else {
+            self.clear();
+            return;
+        };
+        let buffer = &buffer.buffer;
+
+        if *buffer == self.buffer {
+            return;
+        }
+        self.buffer.clone_from(buffer);
+        self.selected = 0;
+        self.navigated = false;
+        self.hidden = false;
+
+        let tokens = suggestions::tokenize(buffer);
+        let spec = match tokens.as_slice() {
+            // The command itself isn't completed
+            [command, _, ..] => self.specs.get(&command.text),
+            _ => None,
+        };
+
+        let current = tokens.last().map_or(0, |token| token.len);
+        self.token = buffer.chars().skip(buffer.chars().count() - current).collect();
+        self.suggestions = match spec {
+            Some(Spec::Unsupported) => vec![Suggestion {
+                name: "spec not supported".into(),
+                description: Some("Only the desktop app can complete this command".into()),
+                kind: Kind::Notice,
+                insert: String::new(),
+            }],
+            Some(Spec::Supported(spec)) => {
+                let current = tokens.last().map(|token| token.text.as_str()).unwrap_or_default();
+                suggestions::suggest(&spec, &tokens, cwd)
+                    .into_iter()
+                    // Nothing to complete for a token that is already typed out
+                    .filter(|suggestion| suggestion.kind == Kind::Folder || suggestion.name != current)
+                    .collect()
+            },
+            None => vec![],
+        };
+    }
+
+    /// Suggests again for the same buffer, after the spec for it was loaded
+    pub fn invalidate(&mut self) {
+        self.buffer.clear();
+    }
+
+    pub fn visible(&self) -> bool {
+        !self.hidden && !self.suggestions.is_empty()
+    }
+
+    /// The bytes that draw the popup next to the cursor, erasing it first if it was drawn
+    /// somewhere else
+    pub fn draw<T: EventListener>(&mut self, term: &Term<T>) -> Option<Vec<u8>> {
+        if !self.visible() {
+            return self.erase(term);
+        }
+
+        let cursor = term.grid().cursor.point;
+        let line = usize::try_from(cursor.line.0).ok()?;
+        let column = cursor.column.0.saturating_sub(self.token.chars().count());
+        let width = render::width(&self.suggestions);
+        let Some(area) = render::area(
+            term.screen_lines(),
+            term.columns(),
+            line,
+            column,
+            self.suggestions.len(),
+            width,
+        ) else {
+            return self.erase(term);
+        };
+
+        let mut bytes = match self.drawn {
+            Some(drawn) if drawn != area => render::restore(term, drawn),
+            _ => vec![],
+        };
+        bytes.extend(render::draw(area, &self.suggestions, self.selected));
+        self.drawn = Some(area);
+        Some(bytes)
+    }
+
+    /// The bytes that erase the popup, this has to be written before the grid changes since the
+    /// cells under the popup are written back from the grid
+    pub fn erase<T: EventListener>(&mut self, term: &Term<T>) -> Option<Vec<u8>> {
+        self.drawn.take().map(|area| render::restore(term, area))
+    }
+
+    /// Forgets that the popup is drawn without erasing it, the terminal reflows on resize so the
+    /// popup is no longer where it was drawn
+    pub fn forget_drawn(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Handles the keys that move through and insert suggestions while the popup is drawn
+    pub fn on_key(&mut self, event: &KeyEvent) -> Option<KeyAction> {
+        if self.drawn.is_none() || !self.visible() {
+            return None;
+        }
+        if self.suggestions[self.selected].kind == Kind::Notice {
+            return match (event.key, event.modifiers) {
+                (KeyCode::Escape, Modifiers::NONE) => {
+                    self.hidden = true;
+                    Some(KeyAction::Hide)
+                },
+                _ => None,
+            };
+        }
+
+        let last = self.suggestions.len() - 1;
+        let wrap = fig_settings::settings::get_bool_or("autocomplete.scrollWrapAround", false);
+
+        match (event.key, event.modifiers) {
+            (KeyCode::DownArrow, Modifiers::NONE) | (KeyCode::Char('n'), Modifiers::CTRL) => {
+                self.selected = match self.selected {
+                    selected if selected < last => selected + 1,
+                    _ if wrap => 0,
+                    selected => selected,
+                };
+                self.navigated = true;
+                Some(KeyAction::Redraw)
+            },
+            (KeyCode::UpArrow, Modifiers::NONE) | (KeyCode::Char('p'), Modifiers::CTRL) => {
+                self.selected = match self.selected {
+                    0 if wrap => last,
+                    selected => selected.saturating_sub(1),
+                };
+                self.navigated = true;
+                Some(KeyAction::Redraw)
+            },
+            (KeyCode::Tab, Modifiers::NONE) => Some(KeyAction::Insert(self.insert())),
+            (KeyCode::Enter, Modifiers::NONE) if self.navigated => Some(KeyAction::Insert(self.insert())),
+            (KeyCode::Escape, Modifiers::NONE) => {
+                self.hidden = true;
+                Some(KeyAction::Hide)
+            },
+            _ => None,
+        }
+    }
+
+    /// The input that replaces the token under the cursor with the selected suggestion
+    fn insert(&self) -> Vec<u8> {
+        let insert = &self.suggestions[self.selected].insert;
+        match insert.strip_prefix(self.token.as_str()) {
+            Some(rest) => rest.as_bytes().to_vec(),
+            None => {
+                let mut input = vec![b'\x08'; self.token.chars().count()];
+                input.extend(insert.as_bytes());
+                input
+            },
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.token.clear();
+        self.suggestions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popup(buffer: &str, token: &str, names: &[&str]) -> Popup {
+        let (main_loop_tx, _) = flume::unbounded();
+        let mut popup = Popup::new(Specs::new(main_loop_tx));
+        popup.buffer = buffer.into();
+        popup.token = token.into();
+        popup.suggestions = names
+            .iter()
+            .map(|name| Suggestion {
+                name: (*name).into(),
+                description: None,
+                kind: Kind::Subcommand,
+                insert: format!("{name} "),
+            })
+            .collect();
+        popup.drawn = Some(Area {
+            top: 1,
+            left: 0,
+            rows: names.len(),
+            width: 10,
+        });
+        popup
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent { key, modifiers }
+    }
+
+    #[test]
+    fn test_on_key() {
+        let mut popup = popup("git c", "c", &["checkout", "commit"]);
+
+        // Enter runs the command until a suggestion is picked
+        assert_eq!(popup.on_key(&key(KeyCode::Enter, Modifiers::NONE)), None);
+        assert_eq!(
+            popup.on_key(&key(KeyCode::Tab, Modifiers::NONE)),
+            Some(KeyAction::Insert(b"heckout ".to_vec()))
+        );
+
+        assert_eq!(
+            popup.on_key(&key(KeyCode::DownArrow, Modifiers::NONE)),
+            Some(KeyAction::Redraw)
+        );
+        assert_eq!(
+            popup.on_key(&key(KeyCode::Char('n'), Modifiers::CTRL)),
+            Some(KeyAction::Redraw)
+        );
+        assert_eq!(popup.selected, 1);
+        assert_eq!(
+            popup.on_key(&key(KeyCode::Enter, Modifiers::NONE)),
+            Some(KeyAction::Insert(b"ommit ".to_vec()))
+        );
+
+        assert_eq!(popup.on_key(&key(KeyCode::Char('a'), Modifiers::NONE)), None);
+        assert_eq!(
+            popup.on_key(&key(KeyCode::Escape, Modifiers::NONE)),
+            Some(KeyAction::Hide)
+        );
+        assert!(!popup.visible());
+        assert_eq!(popup.on_key(&key(KeyCode::Tab, Modifiers::NONE)), None);
+    }
+
+    #[test]
+    fn test_insert_replaces_token() {
+        // The typed token is quoted, the suggestion is escaped
+        let mut popup = popup("cat \"my f", "\"my f", &[]);
+        popup.suggestions = vec![Suggestion {
+            name: "my file.txt".into(),
+            description: None,
+            kind: Kind::File,
+            insert: "my\\ file.txt ".into(),
+        }];
+        assert_eq!(popup.insert(), b"\x08\x08\x08\x08\x08my\\ file.txt ".to_vec());
+    }
+
+    #[test]
+    fn test_unsupported_spec() {
+        let (main_loop_tx, _) = flume::unbounded();
+        let specs = Specs::new(main_loop_tx);
+        specs.insert("git", Some(Spec::Unsupported));
+        let mut popup = Popup::new(specs);
+
+        popup.update(
+            Some(&TextBuffer {
+                buffer: "git ch".into(),
+                cursor_idx: Some(6),
+            }),
+            None,
+        );
+        assert_eq!(popup.suggestions.len(), 1);
+        assert_eq!(popup.suggestions[0].name, "spec not supported");
+        assert!(popup.visible());
+
+        // Nothing is inserted, tab goes to the shell
+        popup.drawn = Some(Area {
+            top: 1,
+            left: 0,
+            rows: 1,
+            width: 20,
+        });
+        assert_eq!(popup.on_key(&key(KeyCode::Tab, Modifiers::NONE)), None);
+        assert_eq!(popup.on_key(&key(KeyCode::Enter, Modifiers::NONE)), None);
+        assert_eq!(popup.on_key(&key(KeyCode::DownArrow, Modifiers::NONE)), None);
+        assert_eq!(
+            popup.on_key(&key(KeyCode::Escape, Modifiers::NONE)),
+            Some(KeyAction::Hide)
+        );
+    }
+
+    #[test]
+    fn test_renderer() {
+        assert!(Renderer::Auto.in_terminal(false));
+        assert!(!Renderer::Auto.in_terminal(true));
+        assert!(Renderer::Terminal.in_terminal(true));
+        assert!(!Renderer::Desktop.in_terminal(false));
+    }
+}
@@ -0,0 +1,356 @@
+//! Draws the popup over the rows next to the cursor
+//!
+//! The popup is drawn straight to the user's terminal and never goes through figterm's grid, so the
+//! grid still has what was under the popup and erasing it writes those cells back.
+
+use std::fmt::Write;
+
+use alacritty_terminal::Term;
+use alacritty_terminal::ansi::{
+    Color,
+    NamedColor,
+};
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::index::{
+    Column,
+    Line,
+};
+use alacritty_terminal::term::cell::{
+    Cell,
+    ShellFlags,
+};
+use unicode_width::{
+    UnicodeWidthChar,
+    UnicodeWidthStr,
+};
+
+use super::suggestions::Suggestion;
+
+/// The most suggestions shown at once
+pub const MAX_ROWS: usize = 8;
+const MAX_NAME_WIDTH: usize = 30;
+const MAX_WIDTH: usize = 80;
+
+const STYLE: &str = "\x1b[0;97;100m";
+const SELECTED_STYLE: &str = "\x1b[0;30;46m";
+
+/// The cells the popup covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Area {
+    pub top: usize,
+    pub left: usize,
+    pub rows: usize,
+    pub width: usize,
+}
+
+/// Where to draw `count` suggestions `width` wide for the cursor at `line`, below the cursor if
+/// they fit and above it otherwise, like the desktop popup flips at the bottom of the screen
+pub fn area(
+    screen_lines: usize,
+    columns: usize,
+    line: usize,
+    column: usize,
+    count: usize,
+    width: usize,
+) -> Option<Area> {
+    let wanted = count.min(MAX_ROWS);
+    let below = screen_lines.saturating_sub(line + 1);
+    let above = line;
+
+    let (top, rows) = if below >= wanted || below >= above {
+        (line + 1, wanted.min(below))
+    } else {
+        let rows = wanted.min(above);
+        (line - rows, rows)
+    };
+
+    let width = width.min(columns);
+    if rows == 0 || width == 0 {
+        return None;
+    }
+
+    Some(Area {
+        top,
+        left: column.min(columns - width),
+        rows,
+        width,
+    })
+}
+
+/// The width the popup needs for `suggestions`
+pub fn width(suggestions: &[Suggestion]) -> usize {
+    let name = name_width(suggestions);
+    let description = suggestions
+        .iter()
+        .filter_map(|suggestion| suggestion.description.as_deref())
+        .map(UnicodeWidthStr::width)
+        .max();
+
+    // A column of padding on either side and two between the name and description
+    match description {
+        Some(description) => (name + description + 4).min(MAX_WIDTH),
+        None => name + 2,
+    }
+}
+
+fn name_width(suggestions: &[Suggestion]) -> usize {
+    suggestions
+        .iter()
+        .map(|suggestion| suggestion.name.width())
+        .max()
+        .unwrap_or_default()
+        .min(MAX_NAME_WIDTH)
+}
+
+/// The bytes that draw the suggestions in `area` with `selected` highlighted, the list scrolls to
+/// keep the selected suggestion visible
+pub fn draw(area: Area, suggestions: &[Suggestion], selected: usize) -> Vec<u8> {
+    let offset = (selected + 1).saturating_sub(area.rows);
+    let name_width = name_width(suggestions).min(area.width.saturating_sub(2));
+    let description_width = area.width.saturating_sub(name_width + 4);
+
+    let mut out = String::from("\x1b7");
+    for (row, (i, suggestion)) in suggestions.iter().enumerate().skip(offset).take(area.rows).enumerate() {
+        let style = if i == selected { SELECTED_STYLE } else { STYLE };
+        let mut text = format!(" {}", fit(&suggestion.name, name_width));
+        if description_width > 0 {
+            text.push_str("  ");
+            text.push_str(&fit(
+                suggestion.description.as_deref().unwrap_or_default(),
+                description_width,
+            ));
+        }
+        let text = fit(&text, area.width);
+
+        let _ = write!(out, "\x1b[{};{}H{style}{text}", area.top + row + 1, area.left + 1);
+    }
+    out.push_str("\x1b[0m\x1b8");
+
+    out.into_bytes()
+}
+
+/// Truncates or pads `text` to exactly `width` columns, truncated text ends with an ellipsis
+fn fit(text: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut fitted = text.replace(char::is_control, " ");
+    if fitted.width() > width {
+        let mut used = 0;
+        fitted = fitted
+            .chars()
+            .take_while(|c| {
+                used += c.width().unwrap_or_default();
+                used < width
+            })
+            .chain(['…'])
+            .collect();
+    }
+
+    let used = fitted.width();
+    fitted.extend(std::iter::repeat_n(' ', width.saturating_sub(used)));
+    fitted
+}
+
+/// The bytes that write the cells under `area` back from the grid
+pub fn restore<T: EventListener>(term: &Term<T>, area: Area) -> Vec<u8> {
+    let grid = term.grid();
+    let mut out = String::from("\x1b7");
+
+    for line in area.top..area.top + area.rows {
+        let _ = write!(out, "\x1b[{};{}H\x1b[0m", line + 1, area.left + 1);
+        let Ok(line) = i32::try_from(line) else {
+            break;
+        };
+        let row = &grid[Line(line)];
+
+        let mut style = None;
+        for column in area.left..area.left + area.width {
+            let cell = &row[Column(column)];
+
+            if cell.flags.contains(ShellFlags::WIDE_CHAR_SPACER) && column != area.left {
+                continue;
+            }
+
+            let cell_style = (cell.fg, cell.bg, cell.flags & STYLE_FLAGS);
+            if style != Some(cell_style) {
+                out.push_str(&sgr(cell));
+                style = Some(cell_style);
+            }
+
+            if cell
+                .flags
+                .intersects(ShellFlags::WIDE_CHAR_SPACER | ShellFlags::LEADING_WIDE_CHAR_SPACER)
+            {
+                out.push(' ');
+            } else {
+                out.push(cell.c);
+                out.extend(cell.zerowidth().unwrap_or_default());
+            }
+        }
+    }
+    out.push_str("\x1b[0m\x1b8");
+
+    out.into_bytes()
+}
+
+const STYLE_FLAGS: ShellFlags = ShellFlags::BOLD
+    .union(ShellFlags::DIM)
+    .union(ShellFlags::ITALIC)
+    .union(ShellFlags::UNDERLINE)
+    .union(ShellFlags::DOUBLE_UNDERLINE)
+    .union(ShellFlags::INVERSE)
+    .union(ShellFlags::HIDDEN)
+    .union(ShellFlags::STRIKEOUT);
+
+/// The SGR sequence that sets the attributes of `cell`
+fn sgr(cell: &Cell) -> String {
+    let mut params = vec!["0".to_owned()];
+
+    for (flag, param) in [
+        (ShellFlags::BOLD, "1"),
+        (ShellFlags::DIM, "2"),
+        (ShellFlags::ITALIC, "3"),
+        (ShellFlags::UNDERLINE, "4"),
+        (ShellFlags::DOUBLE_UNDERLINE, "21"),
+        (ShellFlags::INVERSE, "7"),
+        (ShellFlags::HIDDEN, "8"),
+        (ShellFlags::STRIKEOUT, "9"),
+    ] {
+        if cell.flags.contains(flag) {
+            params.push(param.to_owned());
+        }
+    }
+
+    params.extend(color(cell.fg, 30));
+    params.extend(color(cell.bg, 40));
+
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// The SGR parameter for `color`, `base` is 30 for the foreground and 40 for the background
+fn color(color: Color, base: u8) -> Option<String> {
+    match color {
+        Color::Named(named) => {
+            let index = named as usize;
+            match named {
+                _ if index < 8 => Some((base as usize + index).to_string()),
+                _ if index < 16 => Some((base as usize + 60 + index - 8).to_string()),
+                NamedColor::DimBlack
+                | NamedColor::DimRed
+                | NamedColor::DimGreen
+                | NamedColor::DimYellow
+                | NamedColor::DimBlue
+                | NamedColor::DimMagenta
+                | NamedColor::DimCyan
+                | NamedColor::DimWhite => Some((base as usize + named.to_bright() as usize).to_string()),
+                // The default colors
+                _ => None,
+            }
+        },
+        Color::Indexed(index) => Some(format!("{};5;{index}", base + 8)),
+        Color::Spec(rgb) => Some(format!("{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::ansi::Processor;
+    use alacritty_terminal::event::VoidListener;
+    use alacritty_terminal::term::SizeInfo;
+
+    use super::*;
+    use crate::popup::suggestions::Kind;
+
+    fn suggestion(name: &str, description: Option<&str>) -> Suggestion {
+        Suggestion {
+            name: name.into(),
+            description: description.map(str::to_owned),
+            kind: Kind::Subcommand,
+            insert: format!("{name} "),
+        }
+    }
+
+    #[test]
+    fn test_area() {
+        // Below the cursor when there is room
+        assert_eq!(
+            area(24, 80, 2, 10, 3, 20),
+            Some(Area {
+                top: 3,
+                left: 10,
+                rows: 3,
+                width: 20
+            })
+        );
+        // Above the cursor at the bottom of the screen
+        assert_eq!(
+            area(24, 80, 23, 10, 12, 20),
+            Some(Area {
+                top: 15,
+                left: 10,
+                rows: 8,
+                width: 20
+            })
+        );
+        // Shifted left at the right edge
+        assert_eq!(area(24, 80, 0, 75, 1, 20).unwrap().left, 60);
+        // A single line screen has no room
+        assert_eq!(area(1, 80, 0, 0, 1, 20), None);
+    }
+
+    #[test]
+    fn test_draw() {
+        let suggestions = vec![
+            suggestion("checkout", Some("Switch branches")),
+            suggestion("commit", None),
+            suggestion("cherry-pick", Some("Apply the changes of existing commits")),
+        ];
+        let width = width(&suggestions);
+        assert_eq!(width, 11 + 37 + 4);
+
+        let area = Area {
+            top: 1,
+            left: 4,
+            rows: 2,
+            width: 30,
+        };
+        let drawn = String::from_utf8(draw(area, &suggestions, 2)).unwrap();
+        // Scrolled to the selected suggestion
+        assert!(!drawn.contains("checkout"));
+        assert!(drawn.starts_with("\x1b7\x1b[2;5H\x1b[0;97;100m commit       "));
+        assert!(drawn.contains("\x1b[3;5H\x1b[0;30;46m cherry-pick  Apply the chan… "));
+        assert!(drawn.ends_with("\x1b[0m\x1b8"));
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("abc", 5), "abc  ");
+        assert_eq!(fit("abcdef", 4), "abc…");
+        assert_eq!(fit("日本語", 5), "日本…");
+        assert_eq!(fit("日本語", 4), "日… ");
+        assert_eq!(fit("", 2), "  ");
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut term = Term::new_test(SizeInfo::new(4, 10), VoidListener, 0);
+        let mut processor = Processor::new();
+        for byte in b"$ ls\r\na \x1b[31mred\x1b[0m b" {
+            processor.advance(&mut term, *byte);
+        }
+
+        let restored = String::from_utf8(restore(&term, Area {
+            top: 1,
+            left: 0,
+            rows: 2,
+            width: 8,
+        }))
+        .unwrap();
+        assert_eq!(
+            restored,
+            "\x1b7\x1b[2;1H\x1b[0m\x1b[0ma \x1b[0;31mred\x1b[0m b \x1b[3;1H\x1b[0m\x1b[0m        \x1b[0m\x1b8"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! The specs the popup completes with, loaded in the background the first time a command is typed
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use fig_specs::cdn::{
+    self,
+    SourceIndex,
+};
+use fig_specs::spec::Subcommand;
+use fig_specs::{
+    Refresh,
+    SpecCache,
+};
+use fig_util::directories;
+use flume::Sender;
+use parking_lot::Mutex;
+use tracing::{
+    debug,
+    error,
+};
+
+use crate::MainLoopEvent;
+
+/// Specs are loaded again after this long so edits to local specs are picked up
+const RELOAD_AFTER: Duration = Duration::from_secs(30);
+
+/// A spec that was found for a command
+#[derive(Debug, Clone)]
+pub enum Spec {
+    Supported(Arc<Subcommand>),
+    /// The spec has parts only the desktop app can complete, see [`fig_specs::module`]
+    Unsupported,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// `None` if there is no spec for the command
+    spec: Option<Spec>,
+    loaded_at: Instant,
+    loading: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Specs {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+    main_loop_tx: Sender<MainLoopEvent>,
+}
+
+impl Specs {
+    pub fn new(main_loop_tx: Sender<MainLoopEvent>) -> Self {
+        Self {
+            entries: Default::default(),
+            main_loop_tx,
+        }
+    }
+
+    /// The spec for the command `name`, if it isn't loaded yet it is loaded in the background and
+    /// [`MainLoopEvent::PopupSpecLoaded`] is sent once it is
+    pub fn get(&self, name: &str) -> Option<Spec> {
+        let mut entries = self.entries.lock();
+        match entries.get_mut(name) {
+            Some(entry) if entry.loading || entry.loaded_at.elapsed() < RELOAD_AFTER => return entry.spec.clone(),
+            // Keep the stale spec until the new one is loaded
+            Some(entry) => entry.loading = true,
+            None => {
+                entries.insert(name.to_owned(), Entry {
+                    spec: None,
+                    loaded_at: Instant::now(),
+                    loading: true,
+                });
+            },
+        }
+
+        let spec = entries.get(name).and_then(|entry| entry.spec.clone());
+        drop(entries);
+
+        let specs = self.clone();
+        let name = name.to_owned();
+        tokio::spawn(async move {
+            let loaded = match load(&name).await {
+                Ok(spec) => spec.map(|spec| Spec::Supported(Arc::new(spec))),
+                Err(err @ fig_specs::Error::Unsupported(_)) => {
+                    debug!(%err, %name, "Spec is not supported");
+                    Some(Spec::Unsupported)
+                },
+                Err(err) => {
+                    error!(%err, %name, "Failed to load spec");
+                    None
+                },
+            };
+            debug!(%name, found = loaded.is_some(), "Loaded spec");
+
+            specs.entries.lock().insert(name, Entry {
+                spec: loaded,
+                loaded_at: Instant::now(),
+                loading: false,
+            });
+            specs.main_loop_tx.send_async(MainLoopEvent::PopupSpecLoaded).await.ok();
+        });
+
+        spec
+    }
+
+    /// Sets the spec for `name` as if it was loaded
+    #[cfg(test)]
+    pub fn insert(&self, name: &str, spec: Option<Spec>) {
+        self.entries.lock().insert(name.to_owned(), Entry {
+            spec,
+            loaded_at: Instant::now(),
+            loading: false,
+        });
+    }
+}
+
+/// Loads the spec `name` from the local specs directory or the spec CDNs through the spec cache
+async fn load(name: &str) -> fig_specs::Result<Option<Subcommand>> {
+    let path = fig_specs::spec_path(name);
+
+    let source = match fig_specs::local::read(&directories::autocomplete_specs_dir()?, &path).await? {
+        Some(source) => source,
+        None => {
+            let Some(client) = fig_request::client() else {
+                return Ok(None);
+            };
+            let cache = SpecCache::new()?;
+            let indexes: Vec<SourceIndex> = cdn::indexes(&cache, client, Refresh::IfStale)
+                .await
+                .into_iter()
+                .flatten()
+                .collect();
+            if !indexes.iter().any(|index| index.index.contains(name)) {
+                return Ok(None);
+            }
+
+            let source = cdn::source_for(&indexes, name);
+            cache.get(client, source, &path, Refresh::IfStale).await?.bytes
+        },
+    };
+
+    Ok(Some(fig_specs::module::parse(&String::from_utf8_lossy(&source))?))
+}
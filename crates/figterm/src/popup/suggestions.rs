@@ -0,0 +1,422 @@
+//! Suggestions for the edit buffer from the static parts of a completion spec
+//!
+//! The buffer is walked through the spec like the autocomplete parser does, subcommands move into
+//! the subcommand, options consume their args and everything else fills the positional args. The
+//! token under the cursor is then completed with the subcommands, options or arg suggestions of
+//! where the walk ended.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use fig_specs::spec::{
+    Arg,
+    Opt,
+    Subcommand,
+    Template,
+};
+
+/// Directories with more entries than this are only partly suggested
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Subcommand,
+    Option,
+    Arg,
+    File,
+    Folder,
+    /// Shown in place of suggestions, it can't be inserted
+    Notice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: Kind,
+    /// The text that replaces the token under the cursor
+    pub insert: String,
+}
+
+/// A word of the buffer with quotes and escapes removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    /// The number of chars the token takes up in the buffer
+    pub len: usize,
+}
+
+/// Splits `buffer` into words, the last token is the word under the cursor and is empty if the
+/// buffer ends with a space
+pub fn tokenize(buffer: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut current = Token {
+        text: String::new(),
+        len: 0,
+    };
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in buffer.chars() {
+        if escaped {
+            current.text.push(c);
+            current.len += 1;
+            escaped = false;
+            continue;
+        }
+
+        match (c, quote) {
+            ('\\', None | Some('"')) => escaped = true,
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if current.len > 0 {
+                    tokens.push(std::mem::replace(&mut current, Token {
+                        text: String::new(),
+                        len: 0,
+                    }));
+                }
+                continue;
+            },
+            (c, _) => current.text.push(c),
+        }
+        current.len += 1;
+    }
+
+    tokens.push(current);
+    tokens
+}
+
+/// The suggestions for the last token of `tokens`, the first token is the command `spec` is for
+pub fn suggest(spec: &Subcommand, tokens: &[Token], cwd: Option<&Path>) -> Vec<Suggestion> {
+    let Some((current, words)) = tokens.split_last() else {
+        return vec![];
+    };
+    let current = current.text.as_str();
+    let position = walk(spec, words.iter().skip(1).map(|token| token.text.as_str()));
+
+    let mut suggestions = vec![];
+    let arg = match position.option_arg {
+        Some(arg) => Some(arg),
+        None => {
+            if position.args == 0 {
+                suggestions.extend(
+                    position
+                        .node
+                        .subcommands
+                        .iter()
+                        .filter(|subcommand| !subcommand.hidden)
+                        .flat_map(|subcommand| {
+                            subcommand.name.0.iter().map(|name| Suggestion {
+                                name: name.clone(),
+                                description: subcommand.description.clone(),
+                                kind: Kind::Subcommand,
+                                insert: format!("{name} "),
+                            })
+                        }),
+                );
+            }
+
+            if current.starts_with('-') && !position.options_ended {
+                suggestions.extend(
+                    position
+                        .options()
+                        .filter(|opt| !opt.hidden)
+                        .filter(|opt| opt.is_repeatable || !opt.name.0.iter().any(|name| position.used.contains(name)))
+                        .flat_map(|opt| {
+                            opt.name.0.iter().map(|name| Suggestion {
+                                name: name.clone(),
+                                description: opt.description.clone(),
+                                kind: Kind::Option,
+                                insert: if name.ends_with('=') {
+                                    name.clone()
+                                } else {
+                                    format!("{name} ")
+                                },
+                            })
+                        }),
+                );
+            }
+
+            let args = &position.node.args;
+            args.get(position.args)
+                .or_else(|| args.last().filter(|arg| arg.is_variadic))
+        },
+    };
+
+    if let Some(arg) = arg {
+        suggestions.extend(arg.suggestions.iter().map(|suggestion| Suggestion {
+            name: suggestion.clone(),
+            description: arg.description.clone(),
+            kind: Kind::Arg,
+            insert: format!("{} ", escape(suggestion)),
+        }));
+
+        if let Some(template) = arg.template {
+            suggestions.extend(paths(current, template, cwd));
+            // Paths are matched on the file name, the rest of the token is the directory
+            let file_name = current.rsplit('/').next().unwrap_or(current);
+            suggestions.retain(|suggestion| match suggestion.kind {
+                Kind::File | Kind::Folder => suggestion.name.starts_with(file_name),
+                _ => suggestion.name.starts_with(current),
+            });
+            return suggestions;
+        }
+    }
+
+    suggestions.retain(|suggestion| suggestion.name.starts_with(current));
+    suggestions
+}
+
+/// Where in the spec the words before the cursor end up
+struct Position<'a> {
+    node: &'a Subcommand,
+    /// Options of the parent commands that are also available to `node`
+    persistent: Vec<&'a Opt>,
+    /// The number of positional args given to `node`
+    args: usize,
+    /// The arg of an option that is still missing its value
+    option_arg: Option<&'a Arg>,
+    options_ended: bool,
+    used: Vec<String>,
+}
+
+impl<'a> Position<'a> {
+    fn options(&self) -> impl Iterator<Item = &'a Opt> + '_ {
+        self.node.options.iter().chain(self.persistent.iter().copied())
+    }
+}
+
+fn walk<'a, 'b>(spec: &'a Subcommand, words: impl Iterator<Item = &'b str>) -> Position<'a> {
+    let mut position = Position {
+        node: spec,
+        persistent: vec![],
+        args: 0,
+        option_arg: None,
+        options_ended: false,
+        used: vec![],
+    };
+    // The args an option still takes after the current word
+    let mut option_args: &[Arg] = &[];
+
+    for word in words {
+        if let Some((_, rest)) = option_args.split_first() {
+            option_args = rest;
+            continue;
+        }
+
+        if word == "--" && !position.options_ended {
+            position.options_ended = true;
+            continue;
+        }
+
+        if word.starts_with('-') && !position.options_ended {
+            let (name, value) = match word.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (word, false),
+            };
+            if let Some(opt) = position.options().find(|opt| opt.name.contains(name)) {
+                if !value {
+                    option_args = required_args(&opt.args);
+                }
+            }
+            position.used.push(name.to_owned());
+            continue;
+        }
+
+        if position.args == 0 {
+            if let Some(subcommand) = position.node.subcommands.iter().find(|s| s.name.contains(word)) {
+                position
+                    .persistent
+                    .extend(position.node.options.iter().filter(|opt| opt.is_persistent));
+                position.node = subcommand;
+                position.used.clear();
+                continue;
+            }
+        }
+
+        position.args += 1;
+    }
+
+    position.option_arg = option_args.first();
+    position
+}
+
+/// The args up to the first optional one, an option with only optional args doesn't need a value
+fn required_args(args: &[Arg]) -> &[Arg] {
+    let required = args.iter().take_while(|arg| !arg.is_optional).count();
+    &args[..required]
+}
+
+/// The files or folders in the directory the token `current` is in
+fn paths(current: &str, template: Template, cwd: Option<&Path>) -> Vec<Suggestion> {
+    let (dir, file_name) = match current.rfind('/') {
+        Some(i) => (&current[..=i], &current[i + 1..]),
+        None => ("", current),
+    };
+
+    let expanded = PathBuf::from(shellexpand::tilde(dir).into_owned());
+    let path = match cwd {
+        Some(cwd) if expanded.is_relative() => cwd.join(&expanded),
+        _ if dir.is_empty() && cwd.is_none() => return vec![],
+        _ => expanded,
+    };
+
+    let Ok(read_dir) = std::fs::read_dir(&path) else {
+        return vec![];
+    };
+
+    let mut suggestions: Vec<Suggestion> = read_dir
+        .flatten()
+        .take(MAX_ENTRIES)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files are only suggested once a `.` is typed
+            if name.starts_with('.') && !file_name.starts_with('.') {
+                return None;
+            }
+
+            let is_dir = entry.path().is_dir();
+            if template == Template::Folders && !is_dir {
+                return None;
+            }
+
+            Some(if is_dir {
+                Suggestion {
+                    insert: format!("{}{}/", escape(dir), escape(&name)),
+                    name: format!("{name}/"),
+                    description: None,
+                    kind: Kind::Folder,
+                }
+            } else {
+                Suggestion {
+                    insert: format!("{}{} ", escape(dir), escape(&name)),
+                    name,
+                    description: None,
+                    kind: Kind::File,
+                }
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| a.name.cmp(&b.name));
+    suggestions
+}
+
+/// Escapes the characters the shell would split or expand with backslashes
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_whitespace() || "\\'\"`$&|;<>()*?![]{}#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Subcommand {
+        fig_specs::module::parse(
+            r#"const completionSpec = {
+                name: "git",
+                options: [{ name: ["-C"], args: { name: "path", template: "folders" }, isPersistent: true }],
+                subcommands: [
+                    {
+                        name: ["checkout", "co"],
+                        description: "Switch branches",
+                        args: { name: "branch", suggestions: ["main", "dev"] },
+                        options: [{ name: "-b", args: { name: "new-branch" } }, { name: "--quiet" }],
+                    },
+                    { name: "commit", options: [{ name: ["-m", "--message"], args: { name: "message" } }] },
+                    { name: "internal", hidden: true },
+                    { name: "add", args: { name: "pathspec", isVariadic: true, template: "filepaths" } },
+                ],
+            };
+            export default completionSpec;"#,
+        )
+        .unwrap()
+    }
+
+    fn names(buffer: &str, cwd: Option<&Path>) -> Vec<String> {
+        suggest(&spec(), &tokenize(buffer), cwd)
+            .into_iter()
+            .map(|suggestion| suggestion.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(r#"git commit -m "fix the bug" a\ b "#);
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(texts, vec!["git", "commit", "-m", "fix the bug", "a b", ""]);
+        assert_eq!(tokens[3].len, 13);
+        assert_eq!(tokens[4].len, 4);
+
+        let tokens = tokenize("git ch");
+        assert_eq!(tokens.last().unwrap().text, "ch");
+    }
+
+    #[test]
+    fn test_subcommands() {
+        assert_eq!(names("git c", None), vec!["checkout", "co", "commit"]);
+        // Hidden subcommands are never suggested
+        assert_eq!(names("git i", None), Vec::<String>::new());
+        assert_eq!(names("git checkout ", None), vec!["main", "dev"]);
+        assert_eq!(names("git co d", None), vec!["dev"]);
+        // Once the arg is given nothing is left to suggest
+        assert_eq!(names("git checkout main ", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(names("git checkout -", None), vec!["-b", "--quiet", "-C"]);
+        // An option is suggested once, its arg is not a subcommand
+        assert_eq!(names("git checkout --quiet -", None), vec!["-b", "-C"]);
+        assert_eq!(names("git checkout -b ", None), Vec::<String>::new());
+        assert_eq!(names("git checkout -b new ", None), vec!["main", "dev"]);
+        assert_eq!(names("git commit --message=fix -", None), vec!["-C"]);
+        assert_eq!(names("git checkout -- -", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("my file.txt"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+
+        let suggestions = suggest(&spec(), &tokenize("git add "), Some(dir.path()));
+        assert_eq!(suggestions.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec![
+            "my file.txt",
+            "src/"
+        ]);
+        assert_eq!(suggestions[0].insert, "my\\ file.txt ");
+        assert_eq!(suggestions[1].insert, "src/");
+
+        assert_eq!(names("git add src/m", Some(dir.path())), vec!["main.rs"]);
+        assert_eq!(names("git add .", Some(dir.path())), vec![".hidden"]);
+        // Folders only
+        assert_eq!(names("git -C ", Some(dir.path())), vec!["src/"]);
+
+        let suggestions = suggest(&spec(), &tokenize("git add src/m"), Some(dir.path()));
+        assert_eq!(suggestions[0].insert, "src/main.rs ");
+        assert_eq!(suggestions[0].kind, Kind::File);
+    }
+
+    #[test]
+    fn test_required_args() {
+        let arg = |is_optional| Arg {
+            is_optional,
+            ..Default::default()
+        };
+        assert_eq!(required_args(&[arg(false), arg(true)]).len(), 1);
+        assert_eq!(required_args(&[arg(true)]).len(), 0);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{
+    BufWriter,
+    Write,
+};
+use std::path::Path;
+use std::time::{
+    Instant,
+    SystemTime,
+};
+
+use fig_util::asciicast::{
+    Event,
+    EventCode,
+    Header,
+    Utf8Decoder,
+};
+use fig_util::env_var::{
+    Q_TERM_RECORD,
+    Q_TERM_RECORD_INPUT,
+};
+use tracing::error;
+
+/// Records the session to an asciicast v2 file, enabled by `q term record`
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: Instant,
+    record_input: bool,
+    output_decoder: Utf8Decoder,
+    input_decoder: Utf8Decoder,
+    /// Start time of the last command a marker was written for
+    last_command: Option<SystemTime>,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Creates a recorder if [`Q_TERM_RECORD`] is set
+    pub fn from_env(width: usize, height: usize) -> Option<Self> {
+        let path = std::env::var_os(Q_TERM_RECORD).filter(|path| !path.is_empty())?;
+        let record_input = std::env::var(Q_TERM_RECORD_INPUT).is_ok_and(|value| value == "1");
+
+        match Self::create(Path::new(&path), width, height, record_input) {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                error!(%err, "Failed to start recording to {path:?}");
+                None
+            },
+        }
+    }
+
+    fn create(path: &Path, width: usize, height: usize, record_input: bool) -> std::io::Result<Self> {
+        let mut header = Header::new(width, height);
+        header.timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs() as i64);
+        header.env = ["SHELL", "TERM"]
+            .into_iter()
+            .filter_map(|key| Some((key.to_owned(), std::env::var(key).ok()?)))
+            .collect();
+
+        Self::new(BufWriter::new(File::create(path)?), &header, record_input)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W, header: &Header, record_input: bool) -> std::io::Result<Self> {
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            record_input,
+            output_decoder: Utf8Decoder::default(),
+            input_decoder: Utf8Decoder::default(),
+            last_command: None,
+        })
+    }
+
+    pub fn output(&mut self, bytes: &[u8]) {
+        let data = self.output_decoder.decode(bytes);
+        self.write_event(EventCode::Output, data);
+    }
+
+    pub fn input(&mut self, bytes: &[u8]) {
+        if self.record_input {
+            let data = self.input_decoder.decode(bytes);
+            self.write_event(EventCode::Input, data);
+        }
+    }
+
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.write_event(EventCode::Resize, format!("{columns}x{rows}"));
+    }
+
+    /// Writes a marker the first time a command is seen
+    pub fn command(&mut self, command: &alacritty_terminal::term::CommandInfo) {
+        if command.start_time.is_some() && command.start_time != self.last_command {
+            self.last_command = command.start_time;
+            let label = command.command.clone().unwrap_or_default();
+            self.write_event(EventCode::Marker, label);
+            // Flush at each command so the recording is usable while the session is still running
+            if let Err(err) = self.writer.flush() {
+                error!(%err, "Failed to flush recording");
+            }
+        }
+    }
+
+    fn write_event(&mut self, code: EventCode, data: String) {
+        if data.is_empty() && code != EventCode::Marker {
+            return;
+        }
+
+        let event = Event {
+            time: self.start.elapsed().as_secs_f64(),
+            code,
+            data,
+        };
+        let res = serde_json::to_writer(&mut self.writer, &event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"));
+        if let Err(err) = res {
+            error!(%err, "Failed to write to recording");
+        }
+    }
+}
+
+impl<W: Write> Drop for Recorder<W> {
+    fn drop(&mut self) {
+        self.writer.flush().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::term::CommandInfo;
+    use fig_util::asciicast;
+
+    use super::*;
+
+    #[test]
+    fn test_recorder() {
+        let mut buf = Vec::new();
+        {
+            let mut recorder = Recorder::new(&mut buf, &Header::new(80, 24), false).unwrap();
+            recorder.output(b"$ ");
+            recorder.input(b"ls\r");
+
+            let command = CommandInfo {
+                command: Some("ls".into()),
+                start_time: Some(SystemTime::now()),
+                ..Default::default()
+            };
+            recorder.command(&command);
+            recorder.command(&command);
+
+            recorder.output("a.txt \u{2713}".as_bytes());
+            recorder.resize(100, 30);
+        }
+
+        let (header, events) = asciicast::parse(std::str::from_utf8(&buf).unwrap()).unwrap();
+        assert_eq!(header.width, 80);
+        let events = events
+            .into_iter()
+            .map(|event| (event.code, event.data))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![
+            (EventCode::Output, "$ ".into()),
+            (EventCode::Marker, "ls".into()),
+            (EventCode::Output, "a.txt \u{2713}".into()),
+            (EventCode::Resize, "100x30".into()),
+        ]);
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::VecDeque;
 use std::env;
 use std::path::Path;
-use std::sync::LazyLock;
+use std::sync::{
+    Arc,
+    LazyLock,
+};
 
 use eyre::{
     Result,
@@ -24,6 +27,10 @@ use fig_api_client::model::{
     UserInputMessage,
     UserInputMessageContext,
 };
+use fig_os_shim::{
+    Command,
+    Context,
+};
 use fig_settings::history::{
     History,
     OrderBy,
@@ -64,9 +71,12 @@ const MAX_GIT_STATUS_LEN: usize = 4096;
 const MAX_SHELL_HISTORY_LIST_LEN: usize = 20;
 const MAX_SHELL_HISTORY_COMMAND_LEN: usize = 1024;
 const MAX_SHELL_HISTORY_DIRECTORY_LEN: usize = 256;
+const MAX_SHELL_HISTORY_OUTPUT_LEN: usize = 1024;
+const MAX_LAST_COMMAND_OUTPUT_LEN: usize = 8192;
 
-/// Regex for the context modifiers `@git`, `@env`, and `@history`
-static CONTEXT_MODIFIER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@(git|env|history) ?").unwrap());
+/// Regex for the context modifiers `@git`, `@env`, `@history`, `@output`, and `@last`
+static CONTEXT_MODIFIER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"@(git|env|history|output|last) ?").unwrap());
 
 /// Limit to send the number of messages as part of chat.
 const MAX_CONVERSATION_STATE_HISTORY_LEN: usize = 100;
@@ -74,6 +84,7 @@ const MAX_CONVERSATION_STATE_HISTORY_LEN: usize = 100;
 /// Tracks state related to an ongoing conversation.
 #[derive(Debug, Clone)]
 pub struct ConversationState {
+    ctx: Arc<Context>,
     /// Randomly generated on creation.
     conversation_id: String,
     /// The next user message to be sent as part of the conversation. Required to be [Some] before
@@ -84,10 +95,11 @@ pub struct ConversationState {
 }
 
 impl ConversationState {
-    pub fn new(tool_config: ToolConfiguration) -> Self {
+    pub fn new(ctx: Arc<Context>, tool_config: ToolConfiguration) -> Self {
         let conversation_id = Alphanumeric.sample_string(&mut rand::thread_rng(), 9);
         info!(?conversation_id, "Generated new conversation id");
         Self {
+            ctx,
             conversation_id,
             next_message: None,
             history: VecDeque::new(),
@@ -121,7 +133,7 @@ impl ConversationState {
         let history = History::new();
 
         let mut user_input_message_context = UserInputMessageContext {
-            shell_state: Some(build_shell_state(ctx.history, &history)),
+            shell_state: Some(build_shell_state(&ctx, &history)),
             env_state: Some(build_env_state(Some(&ctx))),
             tool_results: None,
             tools: if self.tools.is_empty() {
@@ -133,7 +145,7 @@ impl ConversationState {
         };
 
         if ctx.git {
-            if let Ok(git_state) = build_git_state(None).await {
+            if let Ok(git_state) = build_git_state(&self.ctx, None).await {
                 user_input_message_context.git_state = Some(git_state);
             }
         }
@@ -344,13 +356,17 @@ struct ContextModifiers {
     env: bool,
     history: bool,
     git: bool,
+    /// Shell history including the output of each command
+    output: bool,
+    /// Only the last command and its output
+    last: bool,
 }
 
 impl ContextModifiers {
     /// Returns `true` if any context modifiers are set
     #[allow(dead_code)]
     fn any(&self) -> bool {
-        self.env || self.history || self.git
+        self.env || self.history || self.git || self.output || self.last
     }
 }
 
@@ -365,6 +381,8 @@ fn input_to_modifiers(input: String) -> (ContextModifiers, String) {
             "git" => modifiers.git = true,
             "env" => modifiers.env = true,
             "history" => modifiers.history = true,
+            "output" => modifiers.output = true,
+            "last" => modifiers.last = true,
             _ => unreachable!(),
         }
     }
@@ -372,16 +390,14 @@ fn input_to_modifiers(input: String) -> (ContextModifiers, String) {
     (modifiers, input)
 }
 
-async fn build_git_state(dir: Option<&Path>) -> Result<GitState> {
-    // git status --porcelain=v1 -b
-    let mut command = tokio::process::Command::new("git");
-    command.args(["status", "--porcelain=v1", "-b"]);
+async fn build_git_state(ctx: &Context, dir: Option<&Path>) -> Result<GitState> {
+    let mut command = Command::new("git").args(["status", "--porcelain=v1", "-b"]);
     if let Some(dir) = dir {
-        command.current_dir(dir);
+        command = command.current_dir(dir);
     }
-    let output = command.output().await?;
+    let output = ctx.process().output(&command).await?;
 
-    if output.status.success() && !output.stdout.is_empty() {
+    if output.success() && !output.stdout.is_empty() {
         Ok(GitState {
             status: truncate_safe(&String::from_utf8_lossy(&output.stdout), MAX_GIT_STATUS_LEN).into(),
         })
@@ -420,7 +436,7 @@ fn build_env_state(modifiers: Option<&ContextModifiers>) -> EnvState {
     env_state
 }
 
-fn build_shell_state(shell_history: bool, history: &History) -> ShellState {
+fn build_shell_state(modifiers: &ContextModifiers, history: &History) -> ShellState {
     // Try to grab the shell from the parent process via the `Shell::current_shell`,
     // then try the `SHELL` env, finally just report bash
     let shell_name = Shell::current_shell()
@@ -436,14 +452,19 @@ fn build_shell_state(shell_history: bool, history: &History) -> ShellState {
         shell_history: None,
     };
 
-    if shell_history {
-        shell_state.shell_history = build_shell_history(history);
+    if modifiers.history || modifiers.output {
+        let output_len = modifiers.output.then_some(MAX_SHELL_HISTORY_OUTPUT_LEN);
+        shell_state.shell_history = build_shell_history(history, MAX_SHELL_HISTORY_LIST_LEN, output_len);
+    } else if modifiers.last {
+        shell_state.shell_history = build_shell_history(history, 1, Some(MAX_LAST_COMMAND_OUTPUT_LEN));
     }
 
     shell_state
 }
 
-fn build_shell_history(history: &History) -> Option<Vec<ShellHistoryEntry>> {
+/// Builds the last `limit` commands of the shell history, the output of each is included and
+/// truncated to `output_len` if it is [Some]
+fn build_shell_history(history: &History, limit: usize, output_len: Option<usize>) -> Option<Vec<ShellHistoryEntry>> {
     let mut shell_history = vec![];
 
     if let Ok(commands) = history.rows(
@@ -452,7 +473,7 @@ fn build_shell_history(history: &History) -> Option<Vec<ShellHistoryEntry>> {
             fig_settings::history::HistoryColumn::Id,
             fig_settings::history::Order::Desc,
         )],
-        MAX_SHELL_HISTORY_LIST_LEN,
+        limit,
         0,
     ) {
         for command in commands.into_iter().filter(|c| c.command.is_some()).rev() {
@@ -468,6 +489,12 @@ fn build_shell_history(history: &History) -> Option<Vec<ShellHistoryEntry>> {
                         }
                     }),
                     exit_code: command.exit_code,
+                    stdout: output_len.and_then(|output_len| {
+                        command
+                            .output
+                            .filter(|output| !output.is_empty())
+                            .map(|output| truncate_safe_start(&output, output_len).into())
+                    }),
                 });
             }
         }
@@ -498,12 +525,28 @@ fn truncate_safe(s: &str, max_bytes: usize) -> &str {
     &s[..byte_count]
 }
 
+/// Like [truncate_safe] but keeps the end of `s`, used for command output where errors are usually
+/// at the end
+fn truncate_safe_start(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+
+    let mut start = s.len() - max_bytes;
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+
+    &s[start..]
+}
+
 #[cfg(test)]
 mod tests {
     use fig_api_client::model::{
         AssistantResponseMessage,
         ToolResultStatus,
     };
+    use fig_os_shim::Output;
     use fig_settings::history::CommandInfo;
 
     use super::*;
@@ -515,6 +558,10 @@ mod tests {
         assert_eq!(truncate_safe("Hello ", 5), "Hello");
         assert_eq!(truncate_safe("Hello World", 11), "Hello World");
         assert_eq!(truncate_safe("Hello World", 15), "Hello World");
+
+        assert_eq!(truncate_safe_start("Hello World", 5), "World");
+        assert_eq!(truncate_safe_start("Hello World", 15), "Hello World");
+        assert_eq!(truncate_safe_start("héllo", 4), "llo");
     }
 
     #[test]
@@ -527,25 +574,32 @@ mod tests {
         assert_eq!(modifiers, ContextModifiers {
             env: true,
             history: true,
-            git: true
+            git: true,
+            ..Default::default()
         });
         assert_eq!(input, "@git @env @history How do I use git?");
 
         let (modifiers, input) = input_to_modifiers("@git How do I use git?".to_string());
         assert_eq!(modifiers, ContextModifiers {
-            env: false,
-            history: false,
-            git: true
+            git: true,
+            ..Default::default()
         });
         assert_eq!(input, "@git How do I use git?");
 
         let (modifiers, input) = input_to_modifiers("@env How do I use git?".to_string());
         assert_eq!(modifiers, ContextModifiers {
             env: true,
-            history: false,
-            git: false
+            ..Default::default()
         });
         assert_eq!(input, "@env How do I use git?");
+
+        let (modifiers, input) = input_to_modifiers("@last @output Why did this fail?".to_string());
+        assert_eq!(modifiers, ContextModifiers {
+            output: true,
+            last: true,
+            ..Default::default()
+        });
+        assert_eq!(input, "@last @output Why did this fail?");
     }
 
     #[test]
@@ -563,25 +617,90 @@ mod tests {
             )
             .unwrap();
 
-        let shell_state = build_shell_state(true, &history);
+        let shell_state = build_shell_state(
+            &ContextModifiers {
+                history: true,
+                ..Default::default()
+            },
+            &history,
+        );
 
         for ShellHistoryEntry {
             command,
             directory,
             exit_code,
+            stdout,
         } in shell_state.shell_history.unwrap()
         {
-            println!("{command} {directory:?} {exit_code:?}");
+            println!("{command} {directory:?} {exit_code:?} {stdout:?}");
         }
     }
 
+    #[test]
+    fn test_shell_state_output() {
+        let history = History::mock();
+        for (command, exit_code, output) in [("ls", 0, "a.txt"), ("cargo build", 101, "error: could not compile")] {
+            history
+                .insert_command_history(
+                    &CommandInfo {
+                        command: Some(command.into()),
+                        exit_code: Some(exit_code),
+                        output: Some(output.into()),
+                        ..Default::default()
+                    },
+                    false,
+                )
+                .unwrap();
+        }
+
+        // @history does not include the output
+        let shell_history = build_shell_state(
+            &ContextModifiers {
+                history: true,
+                ..Default::default()
+            },
+            &history,
+        )
+        .shell_history
+        .unwrap();
+        assert_eq!(shell_history.len(), 2);
+        assert!(shell_history.iter().all(|entry| entry.stdout.is_none()));
+
+        // @output includes the output of every command
+        let shell_history = build_shell_state(
+            &ContextModifiers {
+                output: true,
+                ..Default::default()
+            },
+            &history,
+        )
+        .shell_history
+        .unwrap();
+        assert_eq!(shell_history.len(), 2);
+        assert_eq!(shell_history[0].stdout.as_deref(), Some("a.txt"));
+
+        // @last only includes the last command
+        let shell_history = build_shell_state(
+            &ContextModifiers {
+                last: true,
+                ..Default::default()
+            },
+            &history,
+        )
+        .shell_history
+        .unwrap();
+        assert_eq!(shell_history.len(), 1);
+        assert_eq!(shell_history[0].command, "cargo build");
+        assert_eq!(shell_history[0].exit_code, Some(101));
+        assert_eq!(shell_history[0].stdout.as_deref(), Some("error: could not compile"));
+    }
+
     #[test]
     fn test_env_state() {
         // env: true
         let env_state = build_env_state(Some(&ContextModifiers {
             env: true,
-            history: false,
-            git: false,
+            ..Default::default()
         }));
         assert!(!env_state.environment_variables.is_empty());
         assert!(!env_state.current_working_directory.as_ref().unwrap().is_empty());
@@ -611,6 +730,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_git_state() {
+        let ctx = Context::new();
         let tempdir = tempfile::tempdir().unwrap();
         let dir_path = tempdir.path();
 
@@ -618,19 +738,40 @@ mod tests {
         let path = dir_path.join("test.txt");
         std::fs::write(path, "test").unwrap();
 
-        let git_state_err = build_git_state(Some(dir_path)).await.unwrap_err();
+        let git_state_err = build_git_state(&ctx, Some(dir_path)).await.unwrap_err();
         println!("git_state_err: {git_state_err}");
         assert!(git_state_err.to_string().contains("git status failed"));
 
         init_git_repo(dir_path).await;
 
-        let git_state = build_git_state(Some(dir_path)).await.unwrap();
+        let git_state = build_git_state(&ctx, Some(dir_path)).await.unwrap();
         println!("git_state: {git_state:?}");
     }
 
+    #[tokio::test]
+    async fn test_git_state_fake() {
+        let ctx = Context::new_fake();
+        ctx.process()
+            .push_output("git", Output::succeeded("## main...origin/main\n M src/lib.rs\n"));
+        ctx.process()
+            .push_output("git", Output::failed(128, "fatal: not a git repository"));
+
+        let git_state = build_git_state(&ctx, Some(Path::new("/repo"))).await.unwrap();
+        assert_eq!(git_state.status, "## main...origin/main\n M src/lib.rs\n");
+        assert!(build_git_state(&ctx, None).await.is_err());
+        // git isn't installed
+        assert!(build_git_state(&ctx, None).await.is_err());
+
+        let invocations = ctx.process().invocations();
+        assert_eq!(invocations.len(), 3);
+        assert_eq!(invocations[0].get_args(), ["status", "--porcelain=v1", "-b"]);
+        assert_eq!(invocations[0].get_current_dir(), Some(Path::new("/repo")));
+        assert_eq!(invocations[1].get_current_dir(), None);
+    }
+
     #[tokio::test]
     async fn test_conversation_state_history_handling() {
-        let mut conversation_state = ConversationState::new(load_tools().unwrap());
+        let mut conversation_state = ConversationState::new(Context::new_fake(), load_tools().unwrap());
 
         // First, build a large conversation history. We need to ensure that the order is always
         // User -> Assistant -> User -> Assistant ...and so on.
@@ -684,7 +825,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_conversation_state_history_handling_with_tool_results() {
-        let mut conversation_state = ConversationState::new(load_tools().unwrap());
+        let mut conversation_state = ConversationState::new(Context::new_fake(), load_tools().unwrap());
 
         // Build a long conversation history of tool use results.
         conversation_state.append_new_user_message("start".to_string()).await;
@@ -0,0 +1,151 @@
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+use eyre::{
+    Result,
+    bail,
+};
+use fig_settings::history::{
+    CommandInfo,
+    History,
+    HistoryColumn,
+    Order,
+    OrderBy,
+    WhereExpression,
+};
+use fig_util::env_var::QTERM_SESSION_ID;
+
+const MAX_OUTPUT_LEN: usize = 8192;
+
+/// Starts a chat explaining the last command that failed, preferring commands from the current
+/// terminal session
+pub async fn explain_last() -> Result<ExitCode> {
+    let session_id = std::env::var(QTERM_SESSION_ID).ok();
+    let Some(command) = last_failed_command(&History::new(), session_id.as_deref())? else {
+        bail!("No failed commands found in your shell history");
+    };
+
+    super::chat(Some(explain_prompt(&command))).await
+}
+
+fn last_failed_command(history: &History, session_id: Option<&str>) -> Result<Option<CommandInfo>> {
+    let failed = || {
+        WhereExpression::And(
+            Box::new(WhereExpression::NotNull(HistoryColumn::ExitCode)),
+            Box::new(WhereExpression::Ne(HistoryColumn::ExitCode, "0".into())),
+        )
+    };
+
+    // The where expression is not escaped, only use session ids that are safe to embed
+    let session_id =
+        session_id.filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+
+    let mut filters = vec![];
+    if let Some(session_id) = session_id {
+        filters.push(WhereExpression::And(
+            Box::new(failed()),
+            Box::new(WhereExpression::Eq(HistoryColumn::SessionId, session_id.into())),
+        ));
+    }
+    filters.push(failed());
+
+    for filter in filters {
+        let rows = history.rows(Some(filter), vec![OrderBy::new(HistoryColumn::Id, Order::Desc)], 1, 0)?;
+        if let Some(command) = rows.into_iter().next() {
+            return Ok(Some(command));
+        }
+    }
+
+    Ok(None)
+}
+
+fn explain_prompt(command: &CommandInfo) -> String {
+    let mut prompt = format!(
+        "My last command failed with exit code {}. Explain why it failed and how to fix it.\n\nCommand: `{}`\n",
+        command.exit_code.unwrap_or_default(),
+        command.command.as_deref().unwrap_or_default(),
+    );
+
+    if let Some(cwd) = &command.cwd {
+        writeln!(prompt, "Directory: {cwd}").ok();
+    }
+
+    match command.output.as_deref().filter(|output| !output.is_empty()) {
+        Some(output) => {
+            let mut start = output.len().saturating_sub(MAX_OUTPUT_LEN);
+            while !output.is_char_boundary(start) {
+                start += 1;
+            }
+            write!(prompt, "Output:\n```\n{}\n```\n", &output[start..]).ok();
+        },
+        None => prompt.push_str("The output of the command was not captured.\n"),
+    }
+
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(history: &History, command: &str, session_id: &str, exit_code: i32) {
+        history
+            .insert_command_history(
+                &CommandInfo {
+                    command: Some(command.into()),
+                    session_id: Some(session_id.into()),
+                    exit_code: Some(exit_code),
+                    output: Some(format!("output of {command}")),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_last_failed_command() {
+        let history = History::mock();
+        assert!(last_failed_command(&history, None).unwrap().is_none());
+
+        insert(&history, "make", "session-a", 2);
+        insert(&history, "cargo build", "session-b", 101);
+        insert(&history, "ls", "session-a", 0);
+
+        let command = last_failed_command(&history, Some("session-a")).unwrap().unwrap();
+        assert_eq!(command.command.as_deref(), Some("make"));
+
+        let command = last_failed_command(&history, None).unwrap().unwrap();
+        assert_eq!(command.command.as_deref(), Some("cargo build"));
+
+        // Falls back to every session
+        let command = last_failed_command(&history, Some("session-c")).unwrap().unwrap();
+        assert_eq!(command.command.as_deref(), Some("cargo build"));
+
+        // Unsafe session ids are ignored
+        let command = last_failed_command(&history, Some("' OR 1=1 --")).unwrap().unwrap();
+        assert_eq!(command.command.as_deref(), Some("cargo build"));
+    }
+
+    #[test]
+    fn test_explain_prompt() {
+        let prompt = explain_prompt(&CommandInfo {
+            command: Some("cargo build".into()),
+            cwd: Some("/home/user/project".into()),
+            exit_code: Some(101),
+            output: Some("error[E0308]: mismatched types".into()),
+            ..Default::default()
+        });
+        assert!(prompt.contains("exit code 101"));
+        assert!(prompt.contains("`cargo build`"));
+        assert!(prompt.contains("/home/user/project"));
+        assert!(prompt.contains("error[E0308]: mismatched types"));
+
+        let prompt = explain_prompt(&CommandInfo {
+            command: Some("make".into()),
+            exit_code: Some(2),
+            ..Default::default()
+        });
+        assert!(prompt.contains("was not captured"));
+    }
+}
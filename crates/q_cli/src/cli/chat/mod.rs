@@ -1,4 +1,5 @@
 mod conversation_state;
+mod explain;
 mod input_source;
 mod parse;
 mod parser;
@@ -16,6 +17,7 @@ use std::process::ExitCode;
 use std::sync::Arc;
 use std::time::Duration;
 
+use aws_types::request_id::RequestId;
 use conversation_state::ConversationState;
 use crossterm::style::{
     Attribute,
@@ -29,6 +31,7 @@ use crossterm::{
     style,
     terminal,
 };
+pub use explain::explain_last;
 use eyre::{
     Result,
     bail,
@@ -64,8 +67,11 @@ use tools::{
     ToolSpec,
 };
 use tracing::{
+    Instrument,
     debug,
     error,
+    info_span,
+    instrument,
     trace,
 };
 use winnow::Partial;
@@ -108,7 +114,7 @@ pub async fn chat(initial_input: Option<String>) -> Result<ExitCode> {
     };
 
     let mut output = stdio::StdioOutput::new(is_interactive);
-    let result = ChatContext::new(ChatArgs {
+    let mut chat = ChatContext::new(ChatArgs {
         output: &mut output,
         ctx,
         initial_input,
@@ -117,9 +123,12 @@ pub async fn chat(initial_input: Option<String>) -> Result<ExitCode> {
         tool_config,
         client,
         terminal_width_provider: || terminal::window_size().map(|s| s.columns.into()).ok(),
-    })
-    .try_chat()
-    .await;
+    });
+    let span = info_span!(
+        "conversation",
+        conversation_id = chat.conversation_state.conversation_id()
+    );
+    let result = chat.try_chat().instrument(span).await;
 
     if is_interactive {
         queue!(
@@ -286,14 +295,14 @@ where
     fn new(args: ChatArgs<'o, W>) -> Self {
         Self {
             output: args.output,
-            ctx: args.ctx,
+            ctx: Arc::clone(&args.ctx),
             initial_input: args.initial_input,
             input_source: args.input_source,
             is_interactive: args.is_interactive,
             client: args.client,
             terminal_width_provider: args.terminal_width_provider,
             spinner: None,
-            conversation_state: ConversationState::new(args.tool_config),
+            conversation_state: ConversationState::new(Arc::clone(&args.ctx), args.tool_config),
             tool_use_telemetry_events: HashMap::new(),
             tool_use_status: ToolUseStatus::Idle,
         }
@@ -307,6 +316,8 @@ where
 Hi, I'm <g>Amazon Q</g>. Ask me anything.
 
 <em>@history</em> to pass your shell history
+<em>@output</em> to pass your shell history with the output of each command
+<em>@last</em> to pass your last command and its output
 <em>@git</em> to pass information about your current git repository
 <em>@env</em> to pass your shell environment
 
@@ -380,16 +391,27 @@ Hi, I'm <g>Amazon Q</g>. Ask me anything.
                             execute!(self.output, style::Print("\n"))?;
                             tool_uses = inter;
                         },
-                        ChatError::Client(err) => {
-                            if let fig_api_client::Error::QuotaBreach(msg) = err {
+                        ChatError::Client(err) => match err {
+                            fig_api_client::Error::QuotaBreach(msg) => {
                                 print_error(self.output, msg, None)?;
-                            } else {
+                            },
+                            fig_api_client::Error::RateLimited { retry_after, .. } => {
+                                print_error(
+                                    self.output,
+                                    &format!(
+                                        "Amazon Q is receiving too many requests right now, please try again in {}s",
+                                        retry_after.as_secs().max(1)
+                                    ),
+                                    None,
+                                )?;
+                            },
+                            err => {
                                 print_error(
                                     self.output,
                                     "Amazon Q is having trouble responding right now",
                                     Some(err.into()),
                                 )?;
-                            }
+                            },
                         },
                         _ => {
                             print_error(
@@ -483,6 +505,12 @@ Hi, I'm <g>Amazon Q</g>. Ask me anything.
                     if user_input.contains("@history") {
                         queue!(self.output, style::Print("Using shell history\n"))?;
                     }
+                    if user_input.contains("@output") {
+                        queue!(self.output, style::Print("Using shell history with command output\n"))?;
+                    }
+                    if user_input.contains("@last") {
+                        queue!(self.output, style::Print("Using last command output\n"))?;
+                    }
                     if user_input.contains("@git") {
                         queue!(self.output, style::Print("Using git context\n"))?;
                     }
@@ -605,6 +633,7 @@ Hi, I'm <g>Amazon Q</g>. Ask me anything.
         ));
     }
 
+    #[instrument(skip_all, fields(request_id = response.request_id()))]
     async fn handle_response(&mut self, response: SendMessageOutput) -> Result<ChatState, ChatError> {
         let mut buf = String::new();
         let mut offset = 0;
@@ -22,7 +22,7 @@ use rustyline::{
 };
 use winnow::stream::AsChar;
 
-const MODIFIERS: &[&str] = &["@history", "@git", "@env"];
+const MODIFIERS: &[&str] = &["@history", "@git", "@env", "@output", "@last"];
 const COMMANDS: &[&str] = &["/clear"];
 
 pub struct ChatCompleater {}
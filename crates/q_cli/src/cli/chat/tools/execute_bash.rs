@@ -1,5 +1,4 @@
 use std::io::Write;
-use std::process::Stdio;
 
 use bstr::ByteSlice;
 use crossterm::style::{
@@ -14,7 +13,11 @@ use eyre::{
     Context as EyreContext,
     Result,
 };
-use fig_os_shim::Context;
+use fig_os_shim::{
+    Command,
+    Context,
+};
+use fig_util::command_risk;
 use serde::Deserialize;
 
 use super::{
@@ -30,7 +33,7 @@ pub struct ExecuteBash {
 }
 
 impl ExecuteBash {
-    pub async fn invoke(&self, mut updates: impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, ctx: &Context, mut updates: impl Write) -> Result<InvokeOutput> {
         queue!(
             updates,
             style::SetForegroundColor(Color::Green),
@@ -39,23 +42,17 @@ impl ExecuteBash {
             style::Print("\n"),
         )?;
 
-        let (stdout, stderr) = match self.interactive {
-            Some(true) => (Stdio::inherit(), Stdio::inherit()),
-            _ => (Stdio::piped(), Stdio::piped()),
-        };
+        let mut command = Command::new("bash").arg("-c").arg(&self.command).inherit_stdin();
+        if let Some(true) = self.interactive {
+            command = command.inherit_output();
+        }
 
-        let output = tokio::process::Command::new("bash")
-            .arg("-c")
-            .arg(&self.command)
-            .stdin(Stdio::inherit())
-            .stdout(stdout)
-            .stderr(stderr)
-            .spawn()
-            .wrap_err_with(|| format!("Unable to spawn command '{}'", &self.command))?
-            .wait_with_output()
+        let output = ctx
+            .process()
+            .output(&command)
             .await
-            .wrap_err_with(|| format!("Unable to wait on subprocess for command '{}'", &self.command))?;
-        let status = output.status.code().unwrap_or(0).to_string();
+            .wrap_err_with(|| format!("Unable to run command '{}'", &self.command))?;
+        let status = output.exit_code.unwrap_or(0).to_string();
         let stdout = output.stdout.to_str_lossy();
         let stderr = output.stderr.to_str_lossy();
 
@@ -102,12 +99,23 @@ impl ExecuteBash {
             queue!(updates, style::Print("\n"),)?;
         }
 
-        Ok(queue!(
+        queue!(
             updates,
             style::SetForegroundColor(Color::Green),
             style::Print(&self.command),
             style::ResetColor
-        )?)
+        )?;
+
+        for warning in command_risk::analyze(&self.command).warnings() {
+            queue!(
+                updates,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("\nWarning: {}", warning.reason)),
+                style::ResetColor
+            )?;
+        }
+
+        Ok(())
     }
 
     pub async fn validate(&mut self, _ctx: &Context) -> Result<()> {
@@ -118,10 +126,13 @@ impl ExecuteBash {
 
 #[cfg(test)]
 mod tests {
+    use fig_os_shim::Output;
+
     use super::*;
 
     #[tokio::test]
     async fn test_execute_bash_tool() {
+        let ctx = Context::new();
         let mut stdout = std::io::stdout();
 
         // Verifying stdout
@@ -131,7 +142,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteBash>(v)
             .unwrap()
-            .invoke(&mut stdout)
+            .invoke(&ctx, &mut stdout)
             .await
             .unwrap();
 
@@ -150,7 +161,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteBash>(v)
             .unwrap()
-            .invoke(&mut stdout)
+            .invoke(&ctx, &mut stdout)
             .await
             .unwrap();
 
@@ -169,7 +180,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteBash>(v)
             .unwrap()
-            .invoke(&mut stdout)
+            .invoke(&ctx, &mut stdout)
             .await
             .unwrap();
         if let OutputKind::Json(json) = out.output {
@@ -180,4 +191,32 @@ mod tests {
             panic!("Expected JSON output");
         }
     }
+
+    #[tokio::test]
+    async fn test_execute_bash_invocation() {
+        let ctx = Context::new_fake();
+        ctx.process().push_output("bash", Output {
+            exit_code: Some(2),
+            stdout: b"partial\n".to_vec(),
+            stderr: b"grep: missing\n".to_vec(),
+        });
+
+        let cmd = ExecuteBash {
+            command: "grep foo missing".into(),
+            interactive: Some(true),
+        };
+        let out = cmd.invoke(&ctx, &mut std::io::sink()).await.unwrap();
+        let OutputKind::Json(json) = out.output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json.get("exit_status").unwrap(), "2");
+        assert_eq!(json.get("stdout").unwrap(), "partial\n");
+        assert_eq!(json.get("stderr").unwrap(), "grep: missing\n");
+
+        let invocations = ctx.process().invocations();
+        assert_eq!(invocations, [Command::new("bash")
+            .args(["-c", "grep foo missing"])
+            .inherit_stdin()
+            .inherit_output()]);
+    }
 }
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::io::Write;
-use std::process::Stdio;
 
 use bstr::ByteSlice;
 use convert_case::{
@@ -15,7 +14,14 @@ use eyre::{
     Result,
     WrapErr,
 };
-use fig_os_shim::Context;
+use fig_os_shim::{
+    Command,
+    Context,
+};
+use fig_util::command_risk::{
+    self,
+    Risk,
+};
 use serde::Deserialize;
 
 use super::{
@@ -24,10 +30,6 @@ use super::{
     OutputKind,
 };
 
-const READONLY_OPS: [&str; 6] = ["get", "describe", "list", "ls", "search", "batch_get"];
-
-// TODO: we should perhaps composite this struct with an interface that we can use to mock the
-// actual cli with. That will allow us to more thoroughly test it.
 #[derive(Debug, Clone, Deserialize)]
 pub struct UseAws {
     pub service_name: String,
@@ -40,30 +42,26 @@ pub struct UseAws {
 
 impl UseAws {
     pub fn requires_consent(&self) -> bool {
-        !READONLY_OPS.iter().any(|op| self.operation_name.starts_with(op))
+        command_risk::aws_operation_risk(&self.operation_name) != Risk::ReadOnly
     }
 
-    pub async fn invoke(&self, _ctx: &Context, _updates: impl Write) -> Result<InvokeOutput> {
-        let mut command = tokio::process::Command::new("aws");
-        command.envs(std::env::vars()).arg("--region").arg(&self.region);
+    pub async fn invoke(&self, ctx: &Context, _updates: impl Write) -> Result<InvokeOutput> {
+        let mut command = Command::new("aws").arg("--region").arg(&self.region);
         if let Some(profile_name) = self.profile_name.as_deref() {
-            command.arg("--profile").arg(profile_name);
+            command = command.arg("--profile").arg(profile_name);
         }
-        command.arg(&self.service_name).arg(&self.operation_name);
+        command = command.arg(&self.service_name).arg(&self.operation_name);
         if let Some(parameters) = self.cli_parameters() {
             for (name, val) in parameters {
-                command.arg(name).arg(val);
+                command = command.arg(name).arg(val);
             }
         }
-        let output = command
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .wrap_err_with(|| format!("Unable to spawn command '{:?}'", self))?
-            .wait_with_output()
+        let output = ctx
+            .process()
+            .output(&command)
             .await
             .wrap_err_with(|| format!("Unable to spawn command '{:?}'", self))?;
-        let status = output.status.code().unwrap_or(0).to_string();
+        let status = output.exit_code.unwrap_or(0).to_string();
         let stdout = output.stdout.to_str_lossy();
         let stderr = output.stderr.to_str_lossy();
 
@@ -125,6 +123,15 @@ impl UseAws {
         if let Some(ref label) = self.label {
             queue!(updates, style::Print(format!("\nLabel: {}", label)))?;
         }
+
+        if command_risk::aws_operation_risk(&self.operation_name) == Risk::Destructive {
+            queue!(
+                updates,
+                style::SetForegroundColor(style::Color::Yellow),
+                style::Print(format!("\nWarning: `{}` deletes AWS resources", self.operation_name)),
+                style::ResetColor,
+            )?;
+        }
         Ok(())
     }
 
@@ -151,6 +158,8 @@ impl UseAws {
 
 #[cfg(test)]
 mod tests {
+    use fig_os_shim::Output;
+
     use super::*;
 
     macro_rules! use_aws {
@@ -218,7 +227,7 @@ mod tests {
     #[tokio::test]
     #[ignore = "not in ci"]
     async fn test_aws_read_only() {
-        let ctx = Context::new_fake();
+        let ctx = Context::new();
 
         let v = serde_json::json!({
             "service_name": "s3",
@@ -243,7 +252,7 @@ mod tests {
     #[tokio::test]
     #[ignore = "not in ci"]
     async fn test_aws_output() {
-        let ctx = Context::new_fake();
+        let ctx = Context::new();
 
         let v = serde_json::json!({
             "service_name": "s3",
@@ -272,4 +281,45 @@ mod tests {
             panic!("Expected JSON output");
         }
     }
+
+    #[tokio::test]
+    async fn test_aws_invocation() {
+        let ctx = Context::new_fake();
+        ctx.process()
+            .push_output("aws", Output::succeeded(r#"{"Buckets": []}"#));
+        ctx.process()
+            .push_output("aws", Output::failed(254, "An error occurred (AccessDenied)"));
+
+        let cmd = use_aws! {{
+            "service_name": "s3api",
+            "operation_name": "list-buckets",
+            "parameters": { "MaxBuckets": 10 },
+            "region": "us-west-2",
+            "profile_name": "dev",
+            "label": ""
+        }};
+        let out = cmd.invoke(&ctx, &mut std::io::stdout()).await.unwrap();
+        let OutputKind::Json(json) = out.output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json.get("exit_status").unwrap(), "0");
+        assert_eq!(json.get("stdout").unwrap(), r#"{"Buckets": []}"#);
+
+        let err = cmd.invoke(&ctx, &mut std::io::stdout()).await.unwrap_err();
+        assert!(err.to_string().contains("AccessDenied"));
+
+        let invocations = ctx.process().invocations();
+        assert_eq!(invocations.len(), 2);
+        assert_eq!(invocations[0].get_program(), "aws");
+        assert_eq!(invocations[0].get_args(), [
+            "--region",
+            "us-west-2",
+            "--profile",
+            "dev",
+            "s3api",
+            "list-buckets",
+            "--max-buckets",
+            "10"
+        ]);
+    }
 }
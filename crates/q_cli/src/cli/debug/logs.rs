@@ -0,0 +1,347 @@
+//! Tailing the logs of every component (qterm, the desktop app and the cli) in one place
+
+use std::collections::HashMap;
+use std::io::{
+    Read,
+    Seek,
+    SeekFrom,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crossterm::style::Stylize;
+use eyre::Result;
+use fig_util::{
+    PTY_BINARY_NAME,
+    directories,
+};
+use regex::Regex;
+use serde_json::Value;
+use tracing::Level;
+
+use crate::util::{
+    glob,
+    glob_dir,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+static ANSI_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+/// Which log lines to show
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    /// Only lines at this level or more severe
+    pub level: Option<Level>,
+    /// Only the logs of these components, all of them when empty
+    pub components: Vec<String>,
+    /// Only lines logged in this qterm session or chat conversation
+    pub session: Option<String>,
+}
+
+impl LogFilter {
+    fn includes_component(&self, component: &str) -> bool {
+        self.components.is_empty() || self.components.iter().any(|c| c == component)
+    }
+
+    fn matches(&self, line: &LogLine) -> bool {
+        let level = match (self.level, line.level) {
+            (Some(max), Some(level)) => level <= max,
+            _ => true,
+        };
+        let session = match &self.session {
+            Some(session) => match &line.json {
+                Some(json) => span_fields(json).any(|(key, value)| {
+                    matches!(key.as_str(), "session_id" | "conversation_id") && value.as_str() == Some(session)
+                }),
+                None => line.text.contains(session.as_str()),
+            },
+            None => true,
+        };
+        level && session
+    }
+}
+
+/// A line of a log file, written either as text or as JSON
+#[derive(Debug)]
+struct LogLine {
+    /// The line without escape codes
+    text: String,
+    json: Option<Value>,
+    /// [None] for lines that continue a multi-line message
+    level: Option<Level>,
+}
+
+impl LogLine {
+    fn parse(line: &str) -> Self {
+        let text = ANSI_REGEX.replace_all(line, "").into_owned();
+        match serde_json::from_str::<Value>(&text) {
+            Ok(json @ Value::Object(_)) => Self {
+                level: json.get("level").and_then(Value::as_str).and_then(parse_level),
+                json: Some(json),
+                text,
+            },
+            _ => Self {
+                // Text lines are `<timestamp> <level> <spans and target>: <message>`
+                level: text.split_whitespace().nth(1).and_then(parse_level),
+                json: None,
+                text,
+            },
+        }
+    }
+
+    /// Renders JSON lines like text lines so logs of both formats can be read together
+    fn display(&self) -> String {
+        let Some(json) = &self.json else {
+            return self.text.clone();
+        };
+
+        let field = |name: &str| json.get(name).and_then(Value::as_str).unwrap_or_default();
+        let mut out = format!("{} {:>5} ", field("timestamp"), field("level"));
+        for (name, value) in span_fields(json) {
+            out.push_str(&format!(
+                "{name}={} ",
+                value.as_str().map_or(value.to_string(), Into::into)
+            ));
+        }
+        out.push_str(&format!("{}: ", field("target")));
+        if let Some(Value::Object(fields)) = json.get("fields") {
+            if let Some(Value::String(message)) = fields.get("message") {
+                out.push_str(message);
+            }
+            for (name, value) in fields.iter().filter(|(name, _)| *name != "message") {
+                out.push_str(&format!(
+                    " {name}={}",
+                    value.as_str().map_or(value.to_string(), Into::into)
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// The level as tracing writes it, parsing a [`Level`] also accepts numbers and lowercase names
+/// which continuation lines could start with
+fn parse_level(level: &str) -> Option<Level> {
+    match level {
+        "TRACE" => Some(Level::TRACE),
+        "DEBUG" => Some(Level::DEBUG),
+        "INFO" => Some(Level::INFO),
+        "WARN" => Some(Level::WARN),
+        "ERROR" => Some(Level::ERROR),
+        _ => None,
+    }
+}
+
+/// The fields of the spans a JSON log line was logged in
+fn span_fields(json: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    json.get("spans")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .flatten()
+        .filter(|(name, _)| name.as_str() != "name")
+}
+
+/// The component a log file belongs to, every qterm session has its own log file
+fn component(path: &Path) -> String {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    if name.starts_with(PTY_BINARY_NAME) {
+        PTY_BINARY_NAME.into()
+    } else {
+        name.into_owned()
+    }
+}
+
+#[derive(Debug)]
+struct Tail {
+    component: String,
+    offset: u64,
+    /// The end of the file when it doesn't end with a newline yet
+    partial: String,
+    /// Whether the last line with a level was shown, which decides whether continuation lines are
+    show_continuation: bool,
+}
+
+impl Tail {
+    fn new(path: &Path) -> Self {
+        Self {
+            component: component(path),
+            offset: 0,
+            partial: String::new(),
+            show_continuation: false,
+        }
+    }
+
+    /// Reads what was appended to `path` since the last read, starting over if it was rotated
+    fn read(&mut self, path: &Path) -> Result<Vec<String>> {
+        let mut file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(vec![]);
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut buf)?;
+        self.offset = len;
+
+        self.partial.push_str(&String::from_utf8_lossy(&buf));
+        let mut lines = self.partial.split('\n').map(String::from).collect::<Vec<_>>();
+        self.partial = lines.pop().unwrap_or_default();
+        Ok(lines)
+    }
+
+    fn filter(&mut self, lines: Vec<String>, filter: &LogFilter) -> Vec<String> {
+        lines
+            .into_iter()
+            .filter_map(|line| {
+                let line = LogLine::parse(&line);
+                let show = match line.level {
+                    Some(_) => filter.matches(&line),
+                    None => self.show_continuation,
+                };
+                if line.level.is_some() {
+                    self.show_continuation = show;
+                }
+                show.then(|| format!("{} {}", format!("{}:", self.component).dark_grey(), line.display()))
+            })
+            .collect()
+    }
+}
+
+fn log_files(logs_dir: &Path, filter: &LogFilter) -> Result<Vec<PathBuf>> {
+    let pattern = logs_dir.join("*.log");
+    let globset = glob([pattern.to_str().unwrap()])?;
+    Ok(glob_dir(&globset, logs_dir)?
+        .into_iter()
+        .filter(|path| filter.includes_component(&component(path)))
+        .collect())
+}
+
+/// Prints the last `lines` lines of every matching log and then, when `follow` is set, every line
+/// that is logged until interrupted
+pub async fn tail(filter: LogFilter, lines: usize, follow: bool) -> Result<()> {
+    let logs_dir = directories::logs_dir()?;
+    let mut tails: HashMap<PathBuf, Tail> = HashMap::new();
+
+    for path in log_files(&logs_dir, &filter)? {
+        let mut tail = Tail::new(&path);
+        let read = tail.read(&path)?;
+        let shown = tail.filter(read, &filter);
+        for line in &shown[shown.len().saturating_sub(lines)..] {
+            println!("{line}");
+        }
+        tails.insert(path, tail);
+    }
+
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        // New qterm sessions start new log files
+        for path in log_files(&logs_dir, &filter)? {
+            tails.entry(path).or_insert_with_key(|path| Tail::new(path));
+        }
+
+        for (path, tail) in &mut tails {
+            let Ok(read) = tail.read(path) else {
+                continue;
+            };
+            for line in tail.filter(read, &filter) {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "\x1b[2m2026-10-19T03:15:40.393615Z\x1b[0m \x1b[33m WARN\x1b[0m \
+                        session{session_id=abc}: figterm: 12: slow write";
+
+    const JSON: &str = r#"{"timestamp":"2026-10-19T03:15:40.393615Z","level":"DEBUG","fields":{"message":"sending","len":3},"target":"q_cli::cli::chat","spans":[{"conversation_id":"xyz","name":"conversation"},{"request_id":"r1","name":"handle_response"}]}"#;
+
+    #[test]
+    fn test_parse_line() {
+        let text = LogLine::parse(TEXT);
+        assert_eq!(text.level, Some(Level::WARN));
+        assert!(text.json.is_none());
+        assert!(text.display().starts_with("2026-10-19T03:15:40.393615Z  WARN session"));
+
+        let json = LogLine::parse(JSON);
+        assert_eq!(json.level, Some(Level::DEBUG));
+        assert_eq!(
+            json.display(),
+            "2026-10-19T03:15:40.393615Z DEBUG conversation_id=xyz request_id=r1 q_cli::cli::chat: sending len=3"
+        );
+
+        assert_eq!(LogLine::parse("   at src/main.rs:12").level, None);
+        assert_eq!(LogLine::parse("  step 3 of 5").level, None);
+        assert_eq!(LogLine::parse("  was info about the request").level, None);
+        assert_eq!(LogLine::parse(r#"{"level":"warn"}"#).level, None);
+    }
+
+    #[test]
+    fn test_filter() {
+        let text = LogLine::parse(TEXT);
+        let json = LogLine::parse(JSON);
+
+        let filter = LogFilter {
+            level: Some(Level::INFO),
+            ..Default::default()
+        };
+        assert!(filter.matches(&text));
+        assert!(!filter.matches(&json));
+
+        let filter = |session: &str| LogFilter {
+            session: Some(session.into()),
+            ..Default::default()
+        };
+        assert!(filter("abc").matches(&text));
+        assert!(!filter("abc").matches(&json));
+        assert!(filter("xyz").matches(&json));
+        assert!(!filter("r1").matches(&json));
+    }
+
+    #[test]
+    fn test_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{PTY_BINARY_NAME}ttys001.log"));
+        assert_eq!(component(&path), PTY_BINARY_NAME);
+        assert_eq!(component(Path::new("/logs/fig_desktop.log")), "fig_desktop");
+
+        let mut tail = Tail::new(&path);
+        let filter = LogFilter {
+            level: Some(Level::WARN),
+            ..Default::default()
+        };
+
+        std::fs::write(&path, format!("{TEXT}\n  caused by: disk full\n{JSON}\npartial")).unwrap();
+        let read = tail.read(&path).unwrap();
+        assert_eq!(read.len(), 3);
+        let shown = tail.filter(read, &filter);
+        assert_eq!(shown.len(), 2);
+        assert!(shown[1].ends_with("  caused by: disk full"));
+
+        // Rotated files are read from the start
+        std::fs::write(&path, "2026-10-19T03:15:41Z ERROR figterm: failed\n").unwrap();
+        let read = tail.read(&path).unwrap();
+        assert_eq!(read, ["2026-10-19T03:15:41Z ERROR figterm: failed"]);
+        assert!(tail.read(&path).unwrap().is_empty());
+    }
+}
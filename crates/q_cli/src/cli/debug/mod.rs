@@ -1,4 +1,5 @@
 mod fix_permissions;
+mod logs;
 
 use std::fmt::Write as _;
 use std::io::{
@@ -49,7 +50,6 @@ use fig_util::{
     PRODUCT_NAME,
     PTY_BINARY_NAME,
     Shell,
-    directories,
 };
 use owo_colors::OwoColorize;
 use tempfile::{
@@ -62,8 +62,6 @@ use crate::cli::launch_fig_desktop;
 use crate::util::desktop::LaunchArgs;
 use crate::util::{
     get_app_info,
-    glob,
-    glob_dir,
     quit_fig,
 };
 
@@ -174,8 +172,19 @@ pub enum DebugSubcommand {
     },
     /// Show debug logs
     Logs {
+        /// Log level to set, only lines at this level or more severe are shown
         #[arg(long)]
-        level: Option<String>,
+        level: Option<tracing::Level>,
+        /// Only show lines from this qterm session or chat conversation
+        #[arg(long)]
+        session: Option<String>,
+        /// Number of existing lines to show from each log before following
+        #[arg(long, short = 'n', default_value_t = 0)]
+        lines: usize,
+        /// Exit after showing existing lines instead of following
+        #[arg(long)]
+        no_follow: bool,
+        /// Components to show logs for, e.g. fig_desktop, qterm, chat or cli
         files: Vec<String>,
     },
     /// Input method debugger
@@ -320,67 +329,50 @@ impl DebugSubcommand {
                     return result.map(|_| ExitCode::SUCCESS).map_err(eyre::Report::from);
                 }
             },
-            DebugSubcommand::Logs { level, files } => {
-                let level = std::sync::Arc::new(level.clone());
-                let files = std::sync::Arc::new(files.clone());
-
-                fig_settings::state::set_value("developer.logging", true)?;
-
-                if files.is_empty() || files.iter().any(|f| f == "fig_desktop") {
-                    if let Err(err) =
-                        fig_ipc::local::set_log_level(level.as_ref().clone().unwrap_or_else(|| "DEBUG".into())).await
-                    {
-                        println!("Could not set log level for fig_desktop: {err}");
-                    }
-                }
-
-                tokio::spawn(async move {
-                    tokio::signal::ctrl_c().await.unwrap();
-                    let code = match fig_settings::state::set_value("developer.logging", false) {
-                        Ok(_) => 0,
-                        Err(_) => 1,
-                    };
-
-                    if let Err(err) = fig_ipc::local::set_log_level("INFO".into()).await {
-                        println!("Could not restore log level for fig_desktop: {err}");
+            DebugSubcommand::Logs {
+                level,
+                session,
+                lines,
+                no_follow,
+                files,
+            } => {
+                let follow = !no_follow;
+                if follow {
+                    fig_settings::state::set_value("developer.logging", true)?;
+
+                    if files.is_empty() || files.iter().any(|f| f == "fig_desktop") {
+                        if let Err(err) =
+                            fig_ipc::local::set_log_level(level.unwrap_or(tracing::Level::DEBUG).to_string()).await
+                        {
+                            println!("Could not set log level for fig_desktop: {err}");
+                        }
                     }
 
-                    std::process::exit(code);
-                });
-
-                let logs_dir = directories::logs_dir()?;
-
-                let log_paths = if files.is_empty() {
-                    let pattern = logs_dir.join("*.log");
-                    let globset = glob([pattern.to_str().unwrap()])?;
-                    glob_dir(&globset, &logs_dir)?
-                } else {
-                    let mut files = files.as_ref().clone();
-                    let mut paths = Vec::new();
-
-                    if files.iter().any(|f| f == PTY_BINARY_NAME) {
-                        // Remove figterm from the list of files to open
-                        files.retain(|f| f != PTY_BINARY_NAME);
-
-                        // Add figterm*.log to the list of files to open
-                        let pattern = logs_dir.join(format!("{PTY_BINARY_NAME}*.log"));
-                        let globset = glob([pattern.to_str().unwrap()])?;
-                        let qterm_logs = glob_dir(&globset, &logs_dir)?;
-                        paths.extend(qterm_logs);
-                    }
+                    tokio::spawn(async move {
+                        tokio::signal::ctrl_c().await.unwrap();
+                        let code = match fig_settings::state::set_value("developer.logging", false) {
+                            Ok(_) => 0,
+                            Err(_) => 1,
+                        };
 
-                    // Push any remaining files to open
-                    paths.extend(files.iter().map(|file| logs_dir.join(format!("{file}.log"))));
+                        if let Err(err) = fig_ipc::local::set_log_level("INFO".into()).await {
+                            println!("Could not restore log level for fig_desktop: {err}");
+                        }
 
-                    paths
-                };
+                        std::process::exit(code);
+                    });
+                }
 
-                Command::new("tail")
-                    .arg("-n0")
-                    .arg("-qf")
-                    .args(log_paths)
-                    .spawn()?
-                    .wait()?;
+                logs::tail(
+                    logs::LogFilter {
+                        level: *level,
+                        components: files.clone(),
+                        session: session.clone(),
+                    },
+                    *lines,
+                    follow,
+                )
+                .await?;
             },
             #[cfg(target_os = "macos")]
             DebugSubcommand::InputMethod { action } => {
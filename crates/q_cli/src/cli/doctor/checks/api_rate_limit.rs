@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+use fig_api_client::governor::{
+    CircuitState,
+    GovernorStatus,
+};
+use fig_settings::State;
+use time::OffsetDateTime;
+
+use crate::cli::doctor::{
+    DoctorCheck,
+    DoctorError,
+};
+
+pub struct ApiRateLimitCheck;
+
+#[async_trait]
+impl DoctorCheck for ApiRateLimitCheck {
+    fn name(&self) -> Cow<'static, str> {
+        "API rate limiting".into()
+    }
+
+    async fn check(&self, _: &()) -> Result<(), DoctorError> {
+        check_statuses(&GovernorStatus::load_all(&State::new()), OffsetDateTime::now_utc())
+    }
+}
+
+fn check_statuses(statuses: &[GovernorStatus], now: OffsetDateTime) -> Result<(), DoctorError> {
+    let warnings = statuses
+        .iter()
+        .filter(|status| status.is_limited())
+        .map(|status| {
+            let seconds = status
+                .retry_at
+                .map_or(0, |retry_at| (retry_at - now).whole_seconds().max(0));
+            let mut warning = match status.circuit {
+                CircuitState::Open => format!(
+                    "Requests to {} are paused for {seconds}s after {} failed requests",
+                    status.endpoint, status.consecutive_failures
+                ),
+                _ => format!(
+                    "Requests to {} are throttled for {seconds}s by the service",
+                    status.endpoint
+                ),
+            };
+            if let Some(last_error) = &status.last_error {
+                warning.push_str(&format!(" (last error: {last_error})"));
+            }
+            warning
+        })
+        .collect::<Vec<_>>();
+
+    if warnings.is_empty() {
+        Ok(())
+    } else {
+        Err(DoctorError::Warning(warnings.join("\n").into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+
+    fn status(circuit: CircuitState, retry_at: Option<OffsetDateTime>) -> GovernorStatus {
+        GovernorStatus {
+            endpoint: "https://codewhisperer.us-east-1.amazonaws.com/".into(),
+            circuit,
+            consecutive_failures: 5,
+            retry_at,
+            last_error: Some("service error".into()),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn test_check_statuses() {
+        let now = OffsetDateTime::now_utc();
+
+        assert!(check_statuses(&[], now).is_ok());
+        assert!(check_statuses(&[status(CircuitState::Closed, None)], now).is_ok());
+        assert!(check_statuses(&[status(CircuitState::Closed, Some(now - Duration::minutes(1)))], now).is_ok());
+        // the cooldown has passed, the next request is let through as a probe
+        assert!(check_statuses(&[status(CircuitState::Open, Some(now - Duration::minutes(1)))], now).is_ok());
+
+        let Err(DoctorError::Warning(warning)) =
+            check_statuses(&[status(CircuitState::Open, Some(now + Duration::seconds(30)))], now)
+        else {
+            panic!("expected a warning");
+        };
+        assert!(warning.contains("paused for 30s after 5 failed requests"), "{warning}");
+        assert!(warning.contains("service error"), "{warning}");
+
+        let Err(DoctorError::Warning(warning)) =
+            check_statuses(&[status(CircuitState::Closed, Some(now + Duration::minutes(1)))], now)
+        else {
+            panic!("expected a warning");
+        };
+        assert!(warning.contains("throttled"), "{warning}");
+    }
+}
@@ -1,12 +1,15 @@
 use std::borrow::Cow;
 
 use async_trait::async_trait;
-use eyre::Context;
+use eyre::Context as _;
+use fig_os_shim::{
+    Command,
+    Context,
+};
 use semver::{
     Version,
     VersionReq,
 };
-use tokio::process::Command;
 
 use crate::cli::doctor::{
     DoctorCheck,
@@ -22,37 +25,39 @@ impl DoctorCheck for FishVersionCheck {
     }
 
     async fn check(&self, _: &()) -> Result<(), DoctorError> {
-        if which::which("fish").is_err() {
-            // fish is not installed, so we shouldn't check it
-            return Ok(());
-        }
-
-        let output = Command::new("fish")
-            .arg("--version")
-            .output()
-            .await
-            .context("failed getting fish version")?;
-
-        let version = Version::parse(
-            &String::from_utf8_lossy(&output.stdout)
-                .chars()
-                .filter(|char| char.is_numeric() || char == &'.')
-                .collect::<String>(),
-        )
-        .context("failed parsing fish version")?;
-
-        if !VersionReq::parse(">=3.3.0").unwrap().matches(&version) {
-            return Err(DoctorError::error(format!(
-                "your fish version is outdated (need at least 3.3.0, found {version})"
-            )));
-        }
-
-        Ok(())
+        check_fish_version(&Context::new()).await
     }
 }
 
+async fn check_fish_version(ctx: &Context) -> Result<(), DoctorError> {
+    let output = match ctx.process().output(&Command::new("fish").arg("--version")).await {
+        Ok(output) => output,
+        // fish is not installed, so we shouldn't check it
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(eyre::Report::from(err).wrap_err("failed getting fish version").into()),
+    };
+
+    let version = Version::parse(
+        &String::from_utf8_lossy(&output.stdout)
+            .chars()
+            .filter(|char| char.is_numeric() || char == &'.')
+            .collect::<String>(),
+    )
+    .context("failed parsing fish version")?;
+
+    if !VersionReq::parse(">=3.3.0").unwrap().matches(&version) {
+        return Err(DoctorError::error(format!(
+            "your fish version is outdated (need at least 3.3.0, found {version})"
+        )));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use fig_os_shim::Output;
+
     use super::*;
     use crate::cli::doctor::Platform;
 
@@ -64,4 +69,22 @@ mod tests {
         let result = check.check(&()).await;
         println!("{name}: {doctor_type:?} {result:?}");
     }
+
+    #[tokio::test]
+    async fn test_check_fish_version_fake() {
+        let ctx = Context::new_fake();
+        // fish isn't installed
+        assert!(check_fish_version(&ctx).await.is_ok());
+
+        ctx.process()
+            .push_output("fish", Output::succeeded("fish, version 3.7.1\n"));
+        assert!(check_fish_version(&ctx).await.is_ok());
+
+        ctx.process()
+            .push_output("fish", Output::succeeded("fish, version 3.1.2\n"));
+        let err = check_fish_version(&ctx).await.unwrap_err();
+        assert!(err.to_string().contains("found 3.1.2"), "{err}");
+
+        assert_eq!(ctx.process().invocations()[1].get_args(), ["--version"]);
+    }
 }
@@ -1,3 +1,4 @@
+mod api_rate_limit;
 mod bash_version;
 mod fish_version;
 #[cfg(target_os = "linux")]
@@ -5,6 +6,7 @@ pub mod linux;
 mod midway;
 mod sshd_config;
 
+pub use api_rate_limit::ApiRateLimitCheck;
 pub use bash_version::BashVersionCheck;
 pub use fish_version::FishVersionCheck;
 pub use midway::MidwayCheck;
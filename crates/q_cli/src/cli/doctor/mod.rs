@@ -24,6 +24,7 @@ use anstream::{
 };
 use async_trait::async_trait;
 use checks::{
+    ApiRateLimitCheck,
     BashVersionCheck,
     FishVersionCheck,
     MidwayCheck,
@@ -2190,6 +2191,7 @@ pub async fn doctor_cli(all: bool, strict: bool) -> Result<ExitCode> {
                 &AutocompleteHostCheck,
                 &MidwayCheck,
                 &InlineCheck,
+                &ApiRateLimitCheck,
             ],
             config,
             &mut spinner,
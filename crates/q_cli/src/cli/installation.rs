@@ -139,7 +139,7 @@ pub async fn install_cli(
                 eyre::bail!("You must run with --no-confirm if unattended");
             }
 
-            login_interactive().await?;
+            login_interactive(false).await?;
         } else {
             println!();
             println!("You must login before you can use {PRODUCT_NAME}'s features.");
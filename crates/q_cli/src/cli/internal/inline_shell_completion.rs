@@ -15,11 +15,13 @@ use fig_proto::figterm::figterm_response_message::Response;
 use fig_proto::figterm::{
     FigtermRequestMessage,
     FigtermResponseMessage,
+    InlineShellCompletionAcceptKind,
     InlineShellCompletionAcceptRequest,
     InlineShellCompletionRequest,
     InlineShellCompletionResponse,
 };
 use fig_util::env_var::QTERM_SESSION_ID;
+use fig_util::shell_words::AcceptKind;
 use tracing::error;
 
 macro_rules! unwrap_or_exit {
@@ -78,7 +80,18 @@ pub(super) async fn inline_shell_completion(buffer: String) -> ExitCode {
     }
 }
 
-pub(super) async fn inline_shell_completion_accept(buffer: String, suggestion: String) -> ExitCode {
+pub(super) async fn inline_shell_completion_accept(
+    buffer: String,
+    suggestion: String,
+    kind: AcceptKind,
+    accepted: Option<String>,
+) -> ExitCode {
+    // The shell widgets insert what is printed here, so print it before talking to figterm and even
+    // if figterm can't be reached
+    let accepted = accepted.unwrap_or_else(|| kind.accepted(&buffer, &suggestion).to_owned());
+    let _ = write!(stdout(), "{accepted}");
+    let _ = stdout().flush();
+
     let session_id = unwrap_or_exit!(std::env::var(QTERM_SESSION_ID), "Failed to get session ID");
 
     let figterm_socket_path = unwrap_or_exit!(
@@ -94,7 +107,12 @@ pub(super) async fn inline_shell_completion_accept(buffer: String, suggestion: S
     match conn
         .send_message(FigtermRequestMessage {
             request: Some(Request::InlineShellCompletionAccept(
-                InlineShellCompletionAcceptRequest { buffer, suggestion },
+                InlineShellCompletionAcceptRequest {
+                    buffer,
+                    suggestion,
+                    kind: InlineShellCompletionAcceptKind::from(kind).into(),
+                    accepted: Some(accepted),
+                },
             )),
         })
         .await
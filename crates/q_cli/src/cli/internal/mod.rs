@@ -64,6 +64,7 @@ use fig_util::directories::{
     logs_dir,
 };
 use fig_util::env_var::QTERM_SESSION_ID;
+use fig_util::shell_words::AcceptKind;
 use fig_util::{
     CLI_BINARY_NAME,
     directories,
@@ -280,11 +281,17 @@ pub enum InternalSubcommand {
         #[arg(long, allow_hyphen_values = true)]
         buffer: String,
     },
+    /// Reports an accepted inline suggestion and prints the text that was accepted
     InlineShellCompletionAccept {
         #[arg(long, allow_hyphen_values = true)]
         buffer: String,
         #[arg(long, allow_hyphen_values = true)]
         suggestion: String,
+        #[arg(long, value_enum, default_value_t = AcceptKind::Full)]
+        kind: AcceptKind,
+        /// The text that was accepted if the shell already split the suggestion itself
+        #[arg(long, allow_hyphen_values = true)]
+        accepted: Option<String>,
     },
     #[command(alias = "mux")]
     Multiplexer(MultiplexerArgs),
@@ -849,9 +856,12 @@ impl InternalSubcommand {
             },
             InternalSubcommand::GenerateSsh(args) => args.execute().await,
             InternalSubcommand::InlineShellCompletion { buffer } => Ok(inline_shell_completion(buffer).await),
-            InternalSubcommand::InlineShellCompletionAccept { buffer, suggestion } => {
-                Ok(inline_shell_completion_accept(buffer, suggestion).await)
-            },
+            InternalSubcommand::InlineShellCompletionAccept {
+                buffer,
+                suggestion,
+                kind,
+                accepted,
+            } => Ok(inline_shell_completion_accept(buffer, suggestion, kind, accepted).await),
             InternalSubcommand::Multiplexer(args) => match multiplexer::execute(args).await {
                 Ok(()) => Ok(ExitCode::SUCCESS),
                 Err(err) => {
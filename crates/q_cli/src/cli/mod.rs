@@ -13,8 +13,11 @@ mod installation;
 mod integrations;
 pub mod internal;
 mod issue;
+mod remote;
 mod settings;
+mod specs;
 mod telemetry;
+mod term;
 mod theme;
 mod translate;
 mod uninstall;
@@ -185,9 +188,20 @@ pub enum CliRootCommands {
         /// The first question to ask
         input: Option<String>,
     },
+    /// Ask Amazon Q to explain your last failed command
+    ExplainLast,
     /// Inline shell completions
     #[command(subcommand)]
     Inline(inline::InlineSubcommand),
+    /// Record and replay terminal sessions
+    #[command(subcommand)]
+    Term(term::TermSubcommand),
+    /// Manage the completion specs cached for autocomplete
+    #[command(subcommand, alias("spec"))]
+    Specs(specs::SpecsSubcommand),
+    /// Use autocomplete and inline suggestions on SSH hosts
+    #[command(subcommand)]
+    Remote(remote::RemoteSubcommand),
 }
 
 impl CliRootCommands {
@@ -203,7 +217,7 @@ impl CliRootCommands {
             CliRootCommands::Init(_) => "init",
             CliRootCommands::Theme(_) => "theme",
             CliRootCommands::Issue(_) => "issue",
-            CliRootCommands::RootUser(RootUserSubcommand::Login) => "login",
+            CliRootCommands::RootUser(RootUserSubcommand::Login { .. }) => "login",
             CliRootCommands::RootUser(RootUserSubcommand::Logout) => "logout",
             CliRootCommands::RootUser(RootUserSubcommand::Whoami { .. }) => "whoami",
             CliRootCommands::User(_) => "user",
@@ -219,7 +233,11 @@ impl CliRootCommands {
             CliRootCommands::Version => "version",
             CliRootCommands::Dashboard => "dashboard",
             CliRootCommands::Chat { .. } => "chat",
+            CliRootCommands::ExplainLast => "explain-last",
             CliRootCommands::Inline(_) => "inline",
+            CliRootCommands::Term(_) => "term",
+            CliRootCommands::Specs(_) => "specs",
+            CliRootCommands::Remote(_) => "remote",
         }
     }
 }
@@ -250,6 +268,9 @@ pub struct Cli {
     /// Increase logging verbosity
     #[arg(long, short = 'v', action = ArgAction::Count, global = true)]
     pub verbose: u8,
+    /// Use the named auth profile for this invocation
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
     /// Print help for all subcommands
     #[arg(long)]
     help_all: bool,
@@ -273,7 +294,7 @@ impl Cli {
             },
             log_to_stdout: std::env::var_os("Q_LOG_STDOUT").is_some() || self.verbose > 0,
             log_file_path: match self.subcommand {
-                Some(CliRootCommands::Chat { .. }) => Some("chat.log".to_owned()),
+                Some(CliRootCommands::Chat { .. } | CliRootCommands::ExplainLast) => Some("chat.log".to_owned()),
                 Some(CliRootCommands::Translate(..)) => Some("translate.log".to_owned()),
                 Some(CliRootCommands::Internal(InternalSubcommand::Multiplexer(_))) => Some("mux.log".to_owned()),
                 _ => match fig_log::get_log_level_max() >= Level::DEBUG {
@@ -293,6 +314,11 @@ impl Cli {
             return self.print_help_all();
         }
 
+        if let Some(profile) = &self.profile {
+            fig_auth::profile::set_override(profile)?;
+        }
+        fig_auth::profile::validate_active(&fig_settings::State::new())?;
+
         let cli_context = CliContext::new();
 
         match self.subcommand {
@@ -329,7 +355,11 @@ impl Cli {
                 CliRootCommands::Version => Self::print_version(),
                 CliRootCommands::Dashboard => launch_dashboard(false).await,
                 CliRootCommands::Chat { input } => chat::chat(input).await,
+                CliRootCommands::ExplainLast => chat::explain_last().await,
                 CliRootCommands::Inline(subcommand) => subcommand.execute(&cli_context).await,
+                CliRootCommands::Term(subcommand) => subcommand.execute().await,
+                CliRootCommands::Specs(subcommand) => subcommand.execute().await,
+                CliRootCommands::Remote(subcommand) => subcommand.execute().await,
             },
             // Root command
             None => launch_dashboard(true).await,
@@ -425,30 +455,76 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_telemetry() {
+        assert_parse!(
+            ["telemetry", "disable", "--category", "chatMetadata"],
+            CliRootCommands::Telemetry(telemetry::TelemetrySubcommand::Disable {
+                category: Some(fig_telemetry::TelemetryCategory::ChatMetadata)
+            })
+        );
+        assert_parse!(
+            ["telemetry", "log", "-c", "featureUsage", "-n", "5"],
+            CliRootCommands::Telemetry(telemetry::TelemetrySubcommand::Log {
+                name: None,
+                category: Some(fig_telemetry::TelemetryCategory::FeatureUsage),
+                limit: 5,
+                clear: false,
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_debug_logs() {
+        assert_parse!(
+            ["debug", "logs", "--level", "warn", "qterm"],
+            CliRootCommands::Debug(debug::DebugSubcommand::Logs {
+                level: Some(tracing::Level::WARN),
+                session: None,
+                lines: 0,
+                no_follow: false,
+                files: vec!["qterm".into()],
+            })
+        );
+        assert!(Cli::try_parse_from([CLI_BINARY_NAME, "debug", "logs", "--level", "loud"]).is_err());
+    }
+
     /// Test flag parsing for the top level [Cli]
     #[test]
     fn test_flags() {
         assert_eq!(Cli::parse_from([CLI_BINARY_NAME, "-v"]), Cli {
             subcommand: None,
             verbose: 1,
+            profile: None,
             help_all: false,
         });
 
         assert_eq!(Cli::parse_from([CLI_BINARY_NAME, "-vvv"]), Cli {
             subcommand: None,
             verbose: 3,
+            profile: None,
             help_all: false,
         });
 
         assert_eq!(Cli::parse_from([CLI_BINARY_NAME, "--help-all"]), Cli {
             subcommand: None,
             verbose: 0,
+            profile: None,
             help_all: true,
         });
 
         assert_eq!(Cli::parse_from([CLI_BINARY_NAME, "chat", "-vv"]), Cli {
             subcommand: Some(CliRootCommands::Chat { input: None },),
             verbose: 2,
+            profile: None,
+            help_all: false,
+        });
+
+        assert_eq!(Cli::parse_from([CLI_BINARY_NAME, "chat", "--profile", "work"]), Cli {
+            subcommand: Some(CliRootCommands::Chat { input: None },),
+            verbose: 0,
+            profile: Some("work".into()),
             help_all: false,
         });
     }
@@ -487,6 +563,7 @@ mod test {
 
     #[test]
     fn test_inline_shell_completion() {
+        use fig_util::shell_words::AcceptKind;
         use internal::InternalSubcommand;
 
         assert_parse!(
@@ -533,11 +610,46 @@ mod test {
             ],
             CliRootCommands::Internal(InternalSubcommand::InlineShellCompletionAccept {
                 buffer: "abc".to_string(),
-                suggestion: "def".to_string()
+                suggestion: "def".to_string(),
+                kind: AcceptKind::Full,
+                accepted: None,
+            })
+        );
+
+        assert_parse!(
+            [
+                "_",
+                "inline-shell-completion-accept",
+                "--buffer",
+                "git",
+                "--suggestion",
+                " commit -m 'fix'",
+                "--kind",
+                "word",
+                "--accepted",
+                " commit -m"
+            ],
+            CliRootCommands::Internal(InternalSubcommand::InlineShellCompletionAccept {
+                buffer: "git".to_string(),
+                suggestion: " commit -m 'fix'".to_string(),
+                kind: AcceptKind::Word,
+                accepted: Some(" commit -m".to_string()),
             })
         );
     }
 
+    #[test]
+    fn test_login() {
+        assert_parse!(
+            ["login"],
+            CliRootCommands::RootUser(RootUserSubcommand::Login { no_browser: false })
+        );
+        assert_parse!(
+            ["login", "--no-browser"],
+            CliRootCommands::RootUser(RootUserSubcommand::Login { no_browser: true })
+        );
+    }
+
     #[test]
     fn test_doctor() {
         assert_parse!(
@@ -569,4 +681,19 @@ mod test {
             })
         );
     }
+
+    #[test]
+    fn test_specs() {
+        assert_parse!(
+            ["specs", "update"],
+            CliRootCommands::Specs(specs::SpecsSubcommand::Update { specs: vec![] })
+        );
+        assert_parse!(
+            ["specs", "pin", "git", "cargo"],
+            CliRootCommands::Specs(specs::SpecsSubcommand::Pin {
+                specs: vec!["git".into(), "cargo".into()]
+            })
+        );
+        assert!(Cli::try_parse_from([CLI_BINARY_NAME, "specs", "unpin"]).is_err());
+    }
 }
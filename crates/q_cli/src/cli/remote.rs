@@ -0,0 +1,526 @@
+//! Remote sessions over SSH without the SSH integration
+//!
+//! `q remote attach <host>` starts the multiplexer on the remote host over an SSH channel. The
+//! multiplexer speaks `mux.proto` on its stdio, which is bridged to the local desktop app by
+//! connecting to its remote socket once per remote qterm session, as if that session were running
+//! locally.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::{
+    ExitCode,
+    Stdio,
+};
+use std::time::Duration;
+
+use anstream::eprintln;
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use eyre::{
+    Context,
+    ContextCompat,
+    Result,
+    bail,
+};
+use fig_ipc::{
+    Base64LineCodec,
+    BufferedReader,
+    RecvMessage,
+    SendMessage,
+    socket_connect_or_remove_stale,
+};
+use fig_proto::mux::{
+    self,
+    PacketOptions,
+    message_to_packet,
+    packet_to_message,
+};
+use fig_proto::remote::{
+    self,
+    clientbound,
+    hostbound,
+};
+use fig_util::{
+    CLI_BINARY_NAME,
+    directories,
+};
+use futures::{
+    SinkExt,
+    StreamExt,
+};
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio_util::codec::{
+    FramedRead,
+    FramedWrite,
+};
+use tracing::{
+    debug,
+    error,
+    info,
+    trace,
+    warn,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum RemoteSubcommand {
+    /// Forward autocomplete and inline suggestions from an SSH host to this machine
+    Attach {
+        /// The host to connect to, anything `ssh` accepts as a destination
+        host: String,
+        /// The path to the CLI on the remote host
+        #[arg(long, default_value = CLI_BINARY_NAME)]
+        remote_bin: String,
+        /// Extra arguments to pass to `ssh`
+        #[arg(last = true)]
+        ssh_args: Vec<String>,
+    },
+}
+
+impl RemoteSubcommand {
+    pub async fn execute(self) -> Result<ExitCode> {
+        match self {
+            RemoteSubcommand::Attach {
+                host,
+                remote_bin,
+                ssh_args,
+            } => {
+                let socket = directories::remote_socket_path()?;
+                // The socket file is left behind if the desktop app crashed
+                if socket_connect_or_remove_stale(&socket, Duration::from_secs(5))
+                    .await
+                    .is_err()
+                {
+                    bail!(
+                        "The desktop app is not running, launch it with {}",
+                        format!("{CLI_BINARY_NAME} launch").magenta()
+                    );
+                }
+
+                let mut child = tokio::process::Command::new("ssh")
+                    .args(["-T", "-o", "ServerAliveInterval=30"])
+                    .args(&ssh_args)
+                    .arg(&host)
+                    .args([&remote_bin, "_", "multiplexer"])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .context("Failed to run ssh")?;
+                let stdout = child.stdout.take().context("Failed to open ssh stdout")?;
+                let stdin = child.stdin.take().context("Failed to open ssh stdin")?;
+
+                eprintln!("Attached to {host}, press ctrl-c to detach");
+                let detached = tokio::select! {
+                    res = bridge(stdout, stdin, socket) => {
+                        res?;
+                        false
+                    },
+                    _ = tokio::signal::ctrl_c() => true,
+                };
+
+                if detached {
+                    child.start_kill().ok();
+                    child.wait().await?;
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                let status = child.wait().await?;
+                match status.code() {
+                    Some(0) => Ok(ExitCode::SUCCESS),
+                    // 255 is ssh failing to connect, the multiplexer's own errors are in its log
+                    Some(255) => bail!("Failed to connect to {host}"),
+                    _ => bail!("The multiplexer on {host} exited with {status}"),
+                }
+            },
+        }
+    }
+}
+
+/// Bridges the `mux.proto` packets of a multiplexer to the desktop app listening on `socket` until
+/// the multiplexer disconnects
+async fn bridge<R, W>(mux_reader: R, mux_writer: W, socket: PathBuf) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let codec = Base64LineCodec::<mux::Packet>::new();
+    let mut reader = FramedRead::new(mux_reader, codec.clone());
+    let mut writer = FramedWrite::new(mux_writer, codec);
+
+    let (clientbound_tx, mut clientbound_rx) = mpsc::unbounded_channel::<mux::Clientbound>();
+    tokio::spawn(async move {
+        while let Some(clientbound) = clientbound_rx.recv().await {
+            let packet = match message_to_packet(clientbound, &PacketOptions { gzip: true }) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    error!(?err, "error encoding packet");
+                    continue;
+                },
+            };
+            if let Err(err) = writer.send(packet).await {
+                error!(?err, "error sending packet to the multiplexer");
+                break;
+            }
+        }
+    });
+
+    let mut sessions: HashMap<String, mpsc::UnboundedSender<remote::Hostbound>> = HashMap::new();
+    while let Some(packet) = reader.next().await {
+        let hostbound: mux::Hostbound = match packet
+            .map_err(eyre::Report::from)
+            .and_then(|packet| packet_to_message(packet).map_err(eyre::Report::from))
+        {
+            Ok(hostbound) => hostbound,
+            Err(err) => {
+                error!(?err, "error decoding packet");
+                continue;
+            },
+        };
+
+        let Some((session_id, message)) = hostbound.submessage.and_then(hostbound_to_remote) else {
+            continue;
+        };
+
+        let sender = sessions
+            .entry(session_id.clone())
+            .and_modify(|sender| {
+                // Reconnect sessions whose connection to the desktop app was lost
+                if sender.is_closed() {
+                    *sender = spawn_session(&socket, &session_id, clientbound_tx.clone());
+                }
+            })
+            .or_insert_with(|| spawn_session(&socket, &session_id, clientbound_tx.clone()));
+        sender.send(message).ok();
+    }
+
+    info!("multiplexer disconnected");
+    Ok(())
+}
+
+/// Connects a remote session to the desktop app, returning where to send its messages to
+fn spawn_session(
+    socket: &Path,
+    session_id: &str,
+    clientbound_tx: mpsc::UnboundedSender<mux::Clientbound>,
+) -> mpsc::UnboundedSender<remote::Hostbound> {
+    let (hostbound_tx, hostbound_rx) = mpsc::unbounded_channel();
+    let socket = socket.to_owned();
+    let session_id = session_id.to_owned();
+    tokio::spawn(async move {
+        if let Err(err) = run_session(&socket, &session_id, hostbound_rx, clientbound_tx).await {
+            error!(%err, %session_id, "remote session disconnected");
+        }
+    });
+    hostbound_tx
+}
+
+async fn run_session(
+    socket: &Path,
+    session_id: &str,
+    mut hostbound_rx: mpsc::UnboundedReceiver<remote::Hostbound>,
+    clientbound_tx: mpsc::UnboundedSender<mux::Clientbound>,
+) -> Result<()> {
+    let stream = UnixStream::connect(socket).await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufferedReader::new(reader);
+
+    writer
+        .send_message(remote::Hostbound {
+            packet: Some(hostbound::Packet::Handshake(hostbound::Handshake {
+                id: session_id.to_owned(),
+                secret: Uuid::new_v4().simple().to_string(),
+                parent_id: None,
+            })),
+        })
+        .await?;
+    match reader.recv_message::<remote::Clientbound>().await? {
+        Some(remote::Clientbound {
+            packet: Some(clientbound::Packet::HandshakeResponse(clientbound::HandshakeResponse { success: true })),
+        }) => info!(%session_id, "remote session connected"),
+        response => bail!("handshake failed: {response:?}"),
+    }
+
+    loop {
+        tokio::select! {
+            hostbound = hostbound_rx.recv() => match hostbound {
+                Some(hostbound) => writer.send_message(hostbound).await?,
+                None => return Ok(()),
+            },
+            clientbound = reader.recv_message::<remote::Clientbound>() => match clientbound? {
+                Some(clientbound) => match clientbound_to_mux(session_id, clientbound) {
+                    FromDesktop::Forward(clientbound) => clientbound_tx.send(clientbound)?,
+                    FromDesktop::Reply(hostbound) => writer.send_message(*hostbound).await?,
+                    FromDesktop::Ignore => {},
+                },
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Converts a message from a remote session to what that session would have sent the desktop app
+/// directly, along with the session's id
+fn hostbound_to_remote(submessage: mux::hostbound::Submessage) -> Option<(String, remote::Hostbound)> {
+    use hostbound::request::Request;
+    use mux::hostbound::request::Inner;
+
+    match submessage {
+        mux::hostbound::Submessage::Request(mux::hostbound::Request {
+            session_id,
+            inner: Some(inner),
+            ..
+        }) => {
+            let request = match inner {
+                Inner::EditBuffer(hook) => Request::EditBuffer(hook),
+                Inner::Prompt(hook) => Request::Prompt(hook),
+                Inner::PreExec(hook) => Request::PreExec(hook),
+                Inner::PostExec(hook) => Request::PostExec(hook),
+                Inner::InterceptedKey(hook) => Request::InterceptedKey(hook),
+            };
+            Some((session_id, remote::Hostbound {
+                packet: Some(hostbound::Packet::Request(hostbound::Request {
+                    nonce: None,
+                    request: Some(request),
+                })),
+            }))
+        },
+        mux::hostbound::Submessage::Response(mux::hostbound::Response {
+            session_id,
+            message_id,
+            inner: Some(mux::hostbound::response::Inner::RunProcess(response)),
+        }) => Some((session_id, remote::Hostbound {
+            packet: Some(hostbound::Packet::Response(hostbound::Response {
+                nonce: message_id.parse().ok(),
+                response: Some(hostbound::response::Response::RunProcess(response)),
+            })),
+        })),
+        mux::hostbound::Submessage::Pong(pong) => {
+            trace!(?pong, "received pong");
+            None
+        },
+        submessage => {
+            warn!(?submessage, "received malformed message");
+            None
+        },
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum FromDesktop {
+    /// Forward to the session on the remote host
+    Forward(mux::Clientbound),
+    /// Answer the desktop app without involving the remote host
+    Reply(Box<remote::Hostbound>),
+    Ignore,
+}
+
+fn clientbound_to_mux(session_id: &str, clientbound: remote::Clientbound) -> FromDesktop {
+    use clientbound::request::Request;
+    use mux::clientbound::request::Inner;
+
+    match clientbound.packet {
+        Some(clientbound::Packet::Ping(())) => FromDesktop::Reply(Box::new(remote::Hostbound {
+            packet: Some(hostbound::Packet::Pong(())),
+        })),
+        Some(clientbound::Packet::Request(clientbound::Request {
+            nonce,
+            request: Some(request),
+        })) => {
+            let inner = match request {
+                Request::Intercept(request) => Inner::Intercept(request),
+                Request::InsertText(request) => Inner::InsertText(request),
+                Request::SetBuffer(request) => Inner::SetBuffer(request),
+                Request::RunProcess(request) => Inner::RunProcess(request),
+                request => {
+                    debug!(?request, "request is not supported by the multiplexer");
+                    return match nonce {
+                        Some(nonce) => FromDesktop::Reply(Box::new(remote::Hostbound {
+                            packet: Some(hostbound::Packet::Response(hostbound::Response {
+                                nonce: Some(nonce),
+                                response: Some(hostbound::response::Response::Error(
+                                    "Not supported in remote sessions".into(),
+                                )),
+                            })),
+                        })),
+                        None => FromDesktop::Ignore,
+                    };
+                },
+            };
+            FromDesktop::Forward(mux::Clientbound {
+                submessage: Some(mux::clientbound::Submessage::Request(mux::clientbound::Request {
+                    session_id: session_id.to_owned(),
+                    message_id: nonce.map_or_else(|| Uuid::new_v4().to_string(), |nonce| nonce.to_string()),
+                    inner: Some(inner),
+                })),
+            })
+        },
+        _ => FromDesktop::Ignore,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fig_proto::fig::RunProcessResponse;
+    use fig_proto::figterm::{
+        DiagnosticsRequest,
+        SetBufferRequest,
+    };
+    use fig_proto::local::PromptHook;
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    fn prompt_request(session_id: &str) -> mux::Hostbound {
+        mux::Hostbound {
+            submessage: Some(mux::hostbound::Submessage::Request(mux::hostbound::Request {
+                session_id: session_id.into(),
+                message_id: Uuid::new_v4().to_string(),
+                inner: Some(mux::hostbound::request::Inner::Prompt(PromptHook::default())),
+            })),
+        }
+    }
+
+    fn set_buffer(nonce: Option<u64>) -> remote::Clientbound {
+        remote::Clientbound {
+            packet: Some(clientbound::Packet::Request(clientbound::Request {
+                nonce,
+                request: Some(clientbound::request::Request::SetBuffer(SetBufferRequest {
+                    text: "ls".into(),
+                    cursor_position: None,
+                })),
+            })),
+        }
+    }
+
+    #[test]
+    fn test_hostbound_to_remote() {
+        let (session_id, hostbound) = hostbound_to_remote(prompt_request("abc").submessage.unwrap()).unwrap();
+        assert_eq!(session_id, "abc");
+        assert!(matches!(
+            hostbound.packet,
+            Some(hostbound::Packet::Request(hostbound::Request {
+                request: Some(hostbound::request::Request::Prompt(_)),
+                ..
+            }))
+        ));
+
+        let response = mux::hostbound::Submessage::Response(mux::hostbound::Response {
+            session_id: "abc".into(),
+            message_id: "7".into(),
+            inner: Some(mux::hostbound::response::Inner::RunProcess(
+                RunProcessResponse::default(),
+            )),
+        });
+        let (_, hostbound) = hostbound_to_remote(response).unwrap();
+        assert!(matches!(
+            hostbound.packet,
+            Some(hostbound::Packet::Response(hostbound::Response { nonce: Some(7), .. }))
+        ));
+
+        let pong = mux::hostbound::Submessage::Pong(mux::Pong { message_id: "1".into() });
+        assert!(hostbound_to_remote(pong).is_none());
+    }
+
+    #[test]
+    fn test_clientbound_to_mux() {
+        let FromDesktop::Forward(forward) = clientbound_to_mux("abc", set_buffer(Some(7))) else {
+            panic!("expected the request to be forwarded");
+        };
+        let Some(mux::clientbound::Submessage::Request(request)) = forward.submessage else {
+            panic!("expected a request");
+        };
+        assert_eq!(request.session_id, "abc");
+        assert_eq!(request.message_id, "7");
+        assert!(matches!(
+            request.inner,
+            Some(mux::clientbound::request::Inner::SetBuffer(_))
+        ));
+
+        let ping = remote::Clientbound {
+            packet: Some(clientbound::Packet::Ping(())),
+        };
+        assert_eq!(
+            clientbound_to_mux("abc", ping),
+            FromDesktop::Reply(Box::new(remote::Hostbound {
+                packet: Some(hostbound::Packet::Pong(())),
+            }))
+        );
+
+        let diagnostics = |nonce| remote::Clientbound {
+            packet: Some(clientbound::Packet::Request(clientbound::Request {
+                nonce,
+                request: Some(clientbound::request::Request::Diagnostics(DiagnosticsRequest {})),
+            })),
+        };
+        assert!(matches!(
+            clientbound_to_mux("abc", diagnostics(Some(1))),
+            FromDesktop::Reply(_)
+        ));
+        assert_eq!(clientbound_to_mux("abc", diagnostics(None)), FromDesktop::Ignore);
+    }
+
+    #[tokio::test]
+    async fn test_bridge() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("remote.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let (mux_side, bridge_side) = tokio::io::duplex(1024 * 64);
+        let (bridge_reader, bridge_writer) = tokio::io::split(bridge_side);
+        let bridge = tokio::spawn(bridge(bridge_reader, bridge_writer, socket));
+
+        let (mux_reader, mux_writer) = tokio::io::split(mux_side);
+        let codec = Base64LineCodec::<mux::Packet>::new();
+        let mut mux_reader = FramedRead::new(mux_reader, codec.clone());
+        let mut mux_writer = FramedWrite::new(mux_writer, codec);
+        let options = PacketOptions { gzip: true };
+        mux_writer
+            .send(message_to_packet(prompt_request("abc"), &options).unwrap())
+            .await
+            .unwrap();
+
+        // The desktop app sees a session handshake followed by the hook
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufferedReader::new(reader);
+        let handshake = reader.recv_message::<remote::Hostbound>().await.unwrap().unwrap();
+        assert!(matches!(
+            handshake.packet,
+            Some(hostbound::Packet::Handshake(hostbound::Handshake { ref id, .. })) if id == "abc"
+        ));
+        writer
+            .send_message(remote::Clientbound {
+                packet: Some(clientbound::Packet::HandshakeResponse(clientbound::HandshakeResponse {
+                    success: true,
+                })),
+            })
+            .await
+            .unwrap();
+        let hook = reader.recv_message::<remote::Hostbound>().await.unwrap().unwrap();
+        assert!(matches!(hook.packet, Some(hostbound::Packet::Request(_))));
+
+        // Requests from the desktop app reach the multiplexer
+        writer.send_message(set_buffer(Some(3))).await.unwrap();
+        let packet = mux_reader.next().await.unwrap().unwrap();
+        let clientbound: mux::Clientbound = packet_to_message(packet).unwrap();
+        assert!(matches!(
+            clientbound.submessage,
+            Some(mux::clientbound::Submessage::Request(mux::clientbound::Request { ref session_id, .. }))
+                if session_id == "abc"
+        ));
+
+        // The bridge ends when the multiplexer disconnects
+        drop((mux_reader, mux_writer));
+        bridge.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,398 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anstream::println;
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use fig_os_shim::{
+    Command,
+    Context,
+    Process,
+};
+use fig_specs::spec::{
+    Arg,
+    Names,
+    Opt,
+    Subcommand,
+};
+use fig_util::directories;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::help;
+
+/// How long a command gets to print its help
+const HELP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Args, PartialEq, Eq)]
+pub struct GenerateArgs {
+    /// The command to generate a spec for
+    command: String,
+    /// Read the command from a JSON dump of its clap `Command` instead of running it
+    #[arg(long, value_name = "PATH")]
+    from_json: Option<PathBuf>,
+    /// How many levels of subcommands to run `<command> <subcommand> --help` for. Off by default
+    /// as some commands run the subcommand instead of printing its help
+    #[arg(long, default_value_t = 0)]
+    depth: usize,
+    /// Where to write the spec, `-` for stdout, defaults to the local specs directory autocomplete
+    /// loads specs from
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+impl GenerateArgs {
+    pub async fn execute(&self) -> Result<ExitCode> {
+        let name = Path::new(&self.command)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.command)
+            .to_owned();
+
+        let spec = match &self.from_json {
+            Some(path) => {
+                let json = tokio::fs::read(path)
+                    .await
+                    .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+                let mut spec: Subcommand = serde_json::from_slice::<ClapCommand>(&json)?.into();
+                spec.name = name.clone().into();
+                spec
+            },
+            None => {
+                let process = Context::new().process().clone();
+                let mut spec = match help_spec(&process, &self.command, &[]).await {
+                    Ok(mut spec) => {
+                        generate_subcommands(&process, &self.command, &mut spec, &mut vec![], self.depth).await;
+                        spec
+                    },
+                    Err(err) => {
+                        debug!(%err, "Falling back to the man page");
+                        man_spec(&process, &self.command).await?
+                    },
+                };
+                spec.name = name.clone().into();
+                spec
+            },
+        };
+
+        let module = spec.to_module()?;
+        match self.output.as_deref() {
+            Some(path) if path == Path::new("-") => {
+                println!("{module}");
+            },
+            output => {
+                let path = match output {
+                    Some(path) => path.to_owned(),
+                    None => directories::autocomplete_specs_dir()?.join(fig_specs::spec_path(&name)),
+                };
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&path, module).await?;
+
+                println!(
+                    "Generated a spec for {} with {} subcommands and {} options at {}",
+                    name.as_str().bold(),
+                    count(&spec, |s| s.subcommands.len()),
+                    count(&spec, |s| s.options.len()),
+                    path.display()
+                );
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn count(spec: &Subcommand, f: fn(&Subcommand) -> usize) -> usize {
+    f(spec) + spec.subcommands.iter().map(|s| count(s, f)).sum::<usize>()
+}
+
+/// Runs `--help` for every subcommand in `spec` and fills them in, `path` is the subcommand the
+/// spec is for
+///
+/// The subcommands are the ones listed in the `--help` output of their parent, names that aren't a
+/// plain word are skipped so nothing from the help output ends up as another kind of argument.
+async fn generate_subcommands(
+    process: &Process,
+    command: &str,
+    spec: &mut Subcommand,
+    path: &mut Vec<String>,
+    depth: usize,
+) {
+    if depth == 0 {
+        return;
+    }
+
+    for subcommand in &mut spec.subcommands {
+        let Some(name) = subcommand.name.first().map(str::to_owned) else {
+            continue;
+        };
+        if name == "help" || !is_plain_word(&name) {
+            continue;
+        }
+
+        path.push(name.clone());
+        match help_spec(process, command, path).await {
+            Ok(generated) => {
+                subcommand.options = generated.options;
+                subcommand.args = generated.args;
+                subcommand.subcommands = generated.subcommands;
+                if subcommand.description.is_none() {
+                    subcommand.description = generated.description;
+                }
+                Box::pin(generate_subcommands(process, command, subcommand, path, depth - 1)).await;
+            },
+            Err(err) => debug!(%err, ?path, "Failed to get help"),
+        }
+        path.pop();
+    }
+}
+
+fn is_plain_word(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// The spec for `command` parsed from its `--help`
+///
+/// Only `--help` is run, `-h` means something else to plenty of commands, e.g. `df -h`.
+async fn help_spec(process: &Process, command: &str, subcommands: &[String]) -> Result<Subcommand> {
+    let args = subcommands.iter().map(String::as_str).chain(["--help"]);
+    if let Some(help) = run(process, Command::new(command).args(args)).await {
+        let spec = help::parse(command, &help);
+        if !spec.options.is_empty() || !spec.subcommands.is_empty() {
+            return Ok(spec);
+        }
+    }
+
+    bail!("{command} didn't print any help")
+}
+
+/// The spec for `command` parsed from its man page
+async fn man_spec(process: &Process, command: &str) -> Result<Subcommand> {
+    match run(process, Command::new("man").args(["-P", "cat", command])).await {
+        Some(help) => Ok(help::parse(command, &help)),
+        None => bail!("{command} didn't print any help and has no man page"),
+    }
+}
+
+/// The output of the command, help is printed to stderr by some commands
+async fn run(process: &Process, command: Command) -> Option<String> {
+    let command = command.env("MANWIDTH", "120").env("NO_COLOR", "1").kill_on_drop();
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let output = match tokio::time::timeout(HELP_TIMEOUT, process.output(&command)).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => {
+            debug!(%err, program, "Failed to run");
+            return None;
+        },
+        Err(_) => {
+            debug!(program, "Timed out");
+            return None;
+        },
+    };
+
+    [output.stdout, output.stderr]
+        .into_iter()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .find(|text| !text.trim().is_empty())
+}
+
+/// A clap `Command` dumped to JSON, the fields are named after the `Command` and `Arg` getters
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct ClapCommand {
+    name: String,
+    #[serde(default)]
+    about: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    args: Vec<ClapArg>,
+    #[serde(default)]
+    subcommands: Vec<ClapCommand>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct ClapArg {
+    id: String,
+    #[serde(default)]
+    short: Option<char>,
+    #[serde(default)]
+    long: Option<String>,
+    #[serde(default)]
+    help: Option<String>,
+    #[serde(default)]
+    value_names: Vec<String>,
+    #[serde(default)]
+    possible_values: Vec<String>,
+    #[serde(default)]
+    required: bool,
+    /// If the arg takes a value, flags don't
+    #[serde(default)]
+    takes_value: bool,
+    /// If the arg can be given more than once or takes more than one value
+    #[serde(default)]
+    multiple: bool,
+}
+
+impl ClapArg {
+    fn value(&self) -> Arg {
+        Arg {
+            description: None,
+            is_variadic: self.multiple,
+            suggestions: self.possible_values.clone(),
+            ..Arg::new(self.value_names.first().unwrap_or(&self.id).clone())
+        }
+    }
+}
+
+impl From<ClapCommand> for Subcommand {
+    fn from(command: ClapCommand) -> Self {
+        let mut options = vec![];
+        let mut args = vec![];
+
+        for arg in command.args {
+            let names: Vec<String> = arg
+                .short
+                .map(|short| format!("-{short}"))
+                .into_iter()
+                .chain(arg.long.as_ref().map(|long| format!("--{long}")))
+                .collect();
+
+            if names.is_empty() {
+                args.push(Arg {
+                    description: arg.help.clone(),
+                    is_optional: !arg.required,
+                    ..arg.value()
+                });
+            } else {
+                options.push(Opt {
+                    name: Names(names),
+                    description: arg.help.clone(),
+                    args: if arg.takes_value { vec![arg.value()] } else { vec![] },
+                    is_required: arg.required,
+                    is_repeatable: arg.multiple && !arg.takes_value,
+                    ..Default::default()
+                });
+            }
+        }
+
+        Subcommand {
+            name: Names([command.name].into_iter().chain(command.aliases).collect()),
+            description: command.about,
+            subcommands: command.subcommands.into_iter().map(Into::into).collect(),
+            options,
+            args,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fig_os_shim::Output;
+
+    use super::*;
+
+    #[test]
+    fn test_from_clap_json() {
+        let json = r#"{
+            "name": "tool",
+            "about": "A tool",
+            "args": [
+                { "id": "verbose", "short": "v", "long": "verbose", "multiple": true },
+                { "id": "config", "long": "config", "value_names": ["FILE"], "takes_value": true },
+                { "id": "target", "help": "What to build", "required": true, "possible_values": ["debug", "release"] }
+            ],
+            "subcommands": [{ "name": "build", "aliases": ["b"] }]
+        }"#;
+
+        let spec: Subcommand = serde_json::from_str::<ClapCommand>(json).unwrap().into();
+        assert_eq!(spec.description.as_deref(), Some("A tool"));
+        assert_eq!(spec.options.len(), 2);
+        assert_eq!(spec.options[0].name, Names(vec!["-v".into(), "--verbose".into()]));
+        assert!(spec.options[0].is_repeatable);
+        assert!(spec.options[0].args.is_empty());
+        assert_eq!(spec.options[1].args[0].name, "FILE");
+        assert_eq!(spec.args.len(), 1);
+        assert!(!spec.args[0].is_optional);
+        assert_eq!(spec.args[0].suggestions, vec!["debug", "release"]);
+        assert_eq!(spec.subcommands[0].name, Names(vec!["build".into(), "b".into()]));
+    }
+
+    const HELP: &str = "\
+Usage: tool [OPTIONS] <COMMAND>
+
+Commands:
+  build      Build the project
+  rm;reboot  Not a subcommand anyone should run
+  help       Print this message
+
+Options:
+  -v, --verbose  Print more
+";
+
+    const BUILD_HELP: &str = "\
+Usage: tool build [OPTIONS]
+
+Options:
+  --release  Build with optimizations
+";
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_help_spec() {
+        let process = Process::new_fake();
+        process.push_output("tool", Output::succeeded(HELP));
+        process.push_output("tool", Output::succeeded(BUILD_HELP));
+
+        let mut spec = help_spec(&process, "tool", &[]).await.unwrap();
+        assert!(spec.options.iter().any(|opt| opt.name.contains("--verbose")));
+        generate_subcommands(&process, "tool", &mut spec, &mut vec![], 1).await;
+        assert_eq!(spec.subcommands[0].options[0].name, Names::from("--release"));
+
+        // Only `--help`, and only for the subcommands listed that are plain words
+        let invocations = process.invocations();
+        assert_eq!(invocations.len(), 2);
+        assert_eq!(args(&invocations[0]), ["--help"]);
+        assert_eq!(args(&invocations[1]), ["build", "--help"]);
+    }
+
+    #[tokio::test]
+    async fn test_help_spec_without_help() {
+        let process = Process::new_fake();
+        process.push_output("tool", Output::failed(1, "unknown option --help"));
+        process.push_output("man", Output::succeeded("NAME\n       tool - does things\n"));
+
+        assert!(help_spec(&process, "tool", &[]).await.is_err());
+        let spec = man_spec(&process, "tool").await.unwrap();
+        assert_eq!(spec.description.as_deref(), Some("does things"));
+
+        let invocations = process.invocations();
+        assert_eq!(invocations.len(), 2);
+        assert_eq!(args(&invocations[0]), ["--help"]);
+        assert_eq!(args(&invocations[1]), ["-P", "cat", "tool"]);
+    }
+}
@@ -0,0 +1,683 @@
+//! Infers a spec from the `--help` output or man page of a command
+//!
+//! Help output is split into sections by their headers, `Options:`, `Commands:`, `positional
+//! arguments:` or `OPTIONS` in a man page, and every entry in a section is a name followed by a
+//! description after a gap of two or more spaces. This covers the layouts of clap, argparse, cobra,
+//! GNU `--help` and man pages well enough to be edited by hand afterwards.
+
+use fig_specs::spec::{
+    Arg,
+    Names,
+    Opt,
+    Subcommand,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Intro,
+    Name,
+    Usage,
+    Commands,
+    Options,
+    Args,
+    Other,
+}
+
+impl Section {
+    fn from_header(header: &str) -> Self {
+        let header = header.trim_end_matches(':').to_lowercase();
+        if header.starts_with("usage") || header == "synopsis" {
+            Section::Usage
+        } else if header == "name" {
+            Section::Name
+        } else if header.contains("optional arguments") {
+            Section::Options
+        } else if header.contains("positional") {
+            Section::Args
+        } else if header.contains("command") {
+            Section::Commands
+        } else if header.contains("option") || header.contains("flag") {
+            Section::Options
+        } else if header.contains("argument") || header == "args" {
+            Section::Args
+        } else {
+            Section::Other
+        }
+    }
+}
+
+/// The entry descriptions are appended to as continuation lines are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    None,
+    Command,
+    Option,
+    Arg,
+}
+
+#[derive(Debug)]
+struct Parser {
+    spec: Subcommand,
+    section: Section,
+    /// The indent of the first entry in the current section
+    entry_indent: Option<usize>,
+    last: Last,
+    usage: Option<String>,
+    /// The choices of an argparse `{a,b,c}` positional, the entries after it describe them
+    choices: Vec<String>,
+}
+
+/// Parses the help output of the command `name`
+pub fn parse(name: &str, help: &str) -> Subcommand {
+    let mut parser = Parser {
+        spec: Subcommand {
+            name: name.into(),
+            ..Default::default()
+        },
+        section: Section::Intro,
+        entry_indent: None,
+        last: Last::None,
+        usage: None,
+        choices: vec![],
+    };
+
+    for line in strip_formatting(help).lines() {
+        parser.line(line.trim_end());
+    }
+
+    parser.finish()
+}
+
+impl Parser {
+    fn line(&mut self, line: &str) {
+        let text = line.trim_start();
+        let indent = line.len() - text.len();
+
+        if text.is_empty() {
+            self.last = Last::None;
+            return;
+        }
+
+        if indent == 0 {
+            if let Some((header, rest)) = split_header(text) {
+                self.section = Section::from_header(header);
+                self.entry_indent = None;
+                self.last = Last::None;
+                self.choices.clear();
+                if self.section == Section::Usage && !rest.is_empty() {
+                    self.usage.get_or_insert_with(|| rest.to_owned());
+                }
+                return;
+            }
+        }
+
+        // The description often follows the usage without a header of its own
+        if self.section == Section::Usage && indent == 0 && self.usage.is_some() {
+            self.section = Section::Intro;
+        }
+
+        match self.section {
+            Section::Usage => {
+                self.usage.get_or_insert_with(|| text.to_owned());
+            },
+            Section::Name => {
+                if let Some((_, description)) = text.split_once(" - ") {
+                    self.spec.description = Some(description.trim().to_owned());
+                }
+            },
+            Section::Intro if indent == 0 => {
+                self.spec.description.get_or_insert_with(|| text.to_owned());
+            },
+            _ => self.entry(indent, text),
+        }
+    }
+
+    fn entry(&mut self, indent: usize, text: &str) {
+        let entry_indent = *self.entry_indent.get_or_insert(indent);
+        let (left, description) = split_columns(text);
+
+        // Lines indented past the entries continue the description of the last entry, except for
+        // options indented a little further like clap does for options without a short name
+        let continuation = indent > entry_indent + 2
+            && self.last != Last::None
+            && !(text.starts_with('-') && indent <= entry_indent + 6);
+        if continuation {
+            self.append_description(text);
+            return;
+        }
+
+        if left.starts_with('-') {
+            if let Some(mut opt) = parse_option(left) {
+                opt.description = description.map(str::to_owned);
+                extract_possible_values(&mut opt);
+                self.spec.options.push(opt);
+                self.last = Last::Option;
+                return;
+            }
+        }
+
+        match self.section {
+            Section::Commands => self.command(left, description),
+            Section::Args => {
+                if let Some(choices) = left.strip_prefix('{').and_then(|left| left.strip_suffix('}')) {
+                    self.choices = choices.split(',').map(|choice| choice.trim().to_owned()).collect();
+                    for choice in self.choices.clone() {
+                        self.command(&choice, None);
+                    }
+                } else if self.choices.iter().any(|choice| choice == left) {
+                    self.command(left, description);
+                } else if let Some(mut arg) = parse_placeholder(left) {
+                    arg.description = description.map(str::to_owned);
+                    self.spec.args.push(arg);
+                    self.last = Last::Arg;
+                } else {
+                    self.last = Last::None;
+                }
+            },
+            _ => self.last = Last::None,
+        }
+    }
+
+    fn command(&mut self, left: &str, description: Option<&str>) {
+        let mut names: Vec<String> = left
+            .split([',', '|'])
+            .map(str::trim)
+            .filter(|name| is_command_name(name))
+            .map(str::to_owned)
+            .collect();
+        if names.is_empty() || names.len() != left.split([',', '|']).count() {
+            self.last = Last::None;
+            return;
+        }
+
+        let mut description = description.map(str::to_owned);
+        if let Some(aliases) = description.as_mut().and_then(|d| take_bracketed(d, "aliases:")) {
+            names.extend(aliases.split(',').map(|alias| alias.trim().to_owned()));
+        }
+
+        // argparse lists the choices first and describes them after
+        if let Some(existing) = self.spec.subcommands.iter_mut().find(|s| s.name.contains(&names[0])) {
+            if existing.description.is_none() {
+                existing.description = description;
+            }
+        } else {
+            self.spec.subcommands.push(Subcommand {
+                name: Names(names),
+                description,
+                ..Default::default()
+            });
+        }
+        self.last = Last::Command;
+    }
+
+    fn append_description(&mut self, text: &str) {
+        let description = match self.last {
+            Last::Command => self.spec.subcommands.last_mut().map(|s| &mut s.description),
+            Last::Option => self.spec.options.last_mut().map(|o| &mut o.description),
+            Last::Arg => self.spec.args.last_mut().map(|a| &mut a.description),
+            Last::None => None,
+        };
+
+        match description {
+            Some(Some(description)) => {
+                description.push(' ');
+                description.push_str(text);
+            },
+            Some(description) => *description = Some(text.to_owned()),
+            None => {},
+        }
+
+        if self.last == Last::Option {
+            if let Some(opt) = self.spec.options.last_mut() {
+                extract_possible_values(opt);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Subcommand {
+        if self.spec.args.is_empty() {
+            if let Some(usage) = &self.usage {
+                self.spec.args = usage_args(usage);
+            }
+        }
+
+        let mut seen = Vec::new();
+        self.spec.options.retain(|opt| {
+            let first = opt.name.first().unwrap_or_default().to_owned();
+            let new = !seen.contains(&first);
+            seen.push(first);
+            new
+        });
+
+        self.spec
+    }
+}
+
+/// Removes ANSI escapes and the backspace overstrikes man uses for bold and underline
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x08' => {
+                out.pop();
+            },
+            '\x1b' => {
+                if chars.next() == Some('[') {
+                    for c in chars.by_ref() {
+                        if c.is_ascii_alphabetic() {
+                            break;
+                        }
+                    }
+                }
+            },
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Splits `Usage: tool [OPTIONS]` into its header and the rest of the line, man page headers are
+/// in upper case without a colon
+fn split_header(text: &str) -> Option<(&str, &str)> {
+    if text.to_lowercase().starts_with("usage:") {
+        return Some((&text[..6], text[6..].trim()));
+    }
+
+    if let Some(header) = text.strip_suffix(':') {
+        return (header.split_whitespace().count() <= 4 && !header.starts_with('-')).then_some((header, ""));
+    }
+
+    let is_man_header = text.len() > 1
+        && text.chars().any(|c| c.is_ascii_alphabetic())
+        && text.chars().all(|c| c.is_ascii_uppercase() || c == ' ');
+    is_man_header.then_some((text, ""))
+}
+
+/// Splits an entry into the name column and the description after a gap of two or more spaces
+fn split_columns(text: &str) -> (&str, Option<&str>) {
+    let gap = text.find("  ").or_else(|| text.find('\t'));
+    match gap {
+        Some(i) => {
+            let description = text[i..].trim();
+            (&text[..i], (!description.is_empty()).then_some(description))
+        },
+        None => (text, None),
+    }
+}
+
+fn is_command_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+fn is_option_name(name: &str) -> bool {
+    let flag = name.trim_start_matches('-');
+    name.len() - flag.len() <= 2
+        && !flag.is_empty()
+        && flag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '?' | '#'))
+}
+
+/// Parses the name column of an option like `-o, --output <FILE>` or `-n NUM, --lines=NUM`
+fn parse_option(left: &str) -> Option<Opt> {
+    let mut opt = Opt::default();
+
+    for part in split_option_names(left) {
+        let end = part.find([' ', '=', '[', '<']).unwrap_or(part.len());
+        let (name, rest) = part.split_at(end);
+
+        let name = match name.strip_suffix("...") {
+            Some(name) => {
+                opt.is_repeatable = true;
+                name
+            },
+            None => name,
+        };
+        if !is_option_name(name) {
+            return None;
+        }
+        opt.name.0.push(name.to_owned());
+
+        let rest = rest.trim();
+        if opt.args.is_empty() && !rest.is_empty() {
+            let optional = rest.starts_with("[=") || rest.starts_with('[');
+            let rest = rest.trim_start_matches(['[', '=']).trim_end_matches(']');
+            let mut arg = parse_placeholder(rest.split_whitespace().next().unwrap_or(rest))?;
+            arg.is_optional |= optional;
+            opt.args.push(arg);
+        }
+    }
+
+    (!opt.name.0.is_empty()).then_some(opt)
+}
+
+/// Splits `-h, --help` into its names, a comma inside a placeholder like `<a,b>` doesn't split
+fn split_option_names(left: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, _) in left.match_indices(',') {
+        if left[i + 1..].trim_start().starts_with('-') {
+            parts.push(left[start..i].trim());
+            start = i + 1;
+        }
+    }
+    parts.push(left[start..].trim());
+    parts
+}
+
+/// Parses a placeholder like `FILE`, `<path>...`, `[NAME]` or `{a,b}` into an arg
+fn parse_placeholder(token: &str) -> Option<Arg> {
+    let mut token = token.trim();
+    let mut is_variadic = false;
+    let mut is_optional = false;
+
+    if let Some(rest) = token.strip_suffix("...") {
+        is_variadic = true;
+        token = rest;
+    }
+    if let Some(rest) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        is_optional = true;
+        token = rest;
+    }
+    if let Some(rest) = token.strip_suffix("...") {
+        is_variadic = true;
+        token = rest;
+    }
+    if let Some(rest) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        token = rest;
+    }
+    if let Some(rest) = token.strip_suffix("...") {
+        is_variadic = true;
+        token = rest;
+    }
+
+    if let Some(choices) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        return Some(Arg {
+            is_optional,
+            is_variadic,
+            suggestions: choices.split(',').map(|choice| choice.trim().to_owned()).collect(),
+            ..Arg::new("value")
+        });
+    }
+
+    let valid = !token.is_empty()
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+    valid.then(|| Arg {
+        is_optional,
+        is_variadic,
+        ..Arg::new(token)
+    })
+}
+
+/// Moves clap's `[possible values: a, b]` from the description into suggestions for the arg
+fn extract_possible_values(opt: &mut Opt) {
+    let Some(description) = &mut opt.description else {
+        return;
+    };
+    let Some(values) = take_bracketed(description, "possible values:") else {
+        return;
+    };
+
+    if let Some(arg) = opt.args.first_mut() {
+        arg.suggestions = values.split(',').map(|value| value.trim().to_owned()).collect();
+    }
+}
+
+/// Removes `[prefix ...]` from `text` and returns what was in the brackets after the prefix
+fn take_bracketed(text: &mut String, prefix: &str) -> Option<String> {
+    let start = text.find(&format!("[{prefix}"))?;
+    let end = start + text[start..].find(']')?;
+    let inner = text[start + prefix.len() + 1..end].trim().to_owned();
+
+    text.replace_range(start..=end, "");
+    *text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(inner)
+}
+
+/// Positional args from the usage line, `tool [OPTIONS] <SRC> [DEST]...` gives `SRC` and `DEST`
+fn usage_args(usage: &str) -> Vec<Arg> {
+    const NOT_ARGS: [&str; 7] = [
+        "OPTIONS",
+        "OPTION",
+        "COMMAND",
+        "COMMANDS",
+        "SUBCOMMAND",
+        "FLAGS",
+        "ARGS",
+    ];
+
+    let mut tokens = vec![];
+    let mut depth = 0_i32;
+    let mut start = 0;
+    for (i, c) in usage.char_indices() {
+        match c {
+            '[' | '<' | '{' | '(' => depth += 1,
+            ']' | '>' | '}' | ')' => depth -= 1,
+            c if c.is_whitespace() && depth <= 0 => {
+                tokens.push(&usage[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    tokens.push(&usage[start..]);
+
+    tokens
+        .into_iter()
+        .skip(1)
+        .filter(|token| !token.is_empty() && !token.trim_start_matches('[').starts_with('-'))
+        .filter_map(|token| {
+            let placeholder = token.contains('<')
+                || token
+                    .trim_matches(['[', ']', '.'])
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c == '_' || c == '-');
+            if !placeholder {
+                return None;
+            }
+
+            let arg = parse_placeholder(token)?;
+            (!NOT_ARGS.contains(&arg.name.to_uppercase().as_str())).then_some(arg)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use fig_specs::spec::Template;
+
+    use super::*;
+
+    fn names(names: &[&str]) -> Names {
+        Names(names.iter().map(|name| (*name).to_owned()).collect())
+    }
+
+    const CLAP: &str = "\
+A tool for deploying things
+
+Usage: deploy [OPTIONS] <COMMAND>
+
+Commands:
+  up      Bring the stack up [aliases: u]
+  down    Tear the stack down
+  help    Print this message or the help of the given subcommand(s)
+
+Options:
+  -c, --config <FILE>    Path to the config file
+      --color <WHEN>     When to use color [default: auto] [possible values: auto, always, never]
+  -v, --verbose...       More output per occurrence
+  -h, --help             Print help
+";
+
+    #[test]
+    fn test_clap() {
+        let spec = parse("deploy", CLAP);
+        assert_eq!(spec.description.as_deref(), Some("A tool for deploying things"));
+        assert_eq!(spec.subcommands.len(), 3);
+        assert_eq!(spec.subcommands[0].name, names(&["up", "u"]));
+        assert_eq!(spec.subcommands[0].description.as_deref(), Some("Bring the stack up"));
+        assert_eq!(spec.subcommands[1].name, "down".into());
+
+        assert_eq!(spec.options.len(), 4);
+        assert_eq!(spec.options[0].name, names(&["-c", "--config"]));
+        assert_eq!(spec.options[0].args[0].name, "FILE");
+        assert_eq!(spec.options[0].args[0].template, Some(Template::Filepaths));
+        assert_eq!(spec.options[1].args[0].suggestions, vec!["auto", "always", "never"]);
+        assert_eq!(
+            spec.options[1].description.as_deref(),
+            Some("When to use color [default: auto]")
+        );
+        assert!(spec.options[2].is_repeatable);
+        assert!(spec.options[3].args.is_empty());
+
+        // `<COMMAND>` in the usage is not an arg
+        assert!(spec.args.is_empty());
+    }
+
+    #[test]
+    fn test_clap_long_help() {
+        let help = "\
+Usage: tool [OPTIONS] <SRC> [DEST]...
+
+Arguments:
+  <SRC>
+          The source file
+
+  [DEST]...
+          Where to copy it
+
+Options:
+  -f, --force
+          Overwrite existing files
+          without asking
+";
+        let spec = parse("tool", help);
+        assert_eq!(spec.args.len(), 2);
+        assert_eq!(spec.args[0].name, "SRC");
+        assert_eq!(spec.args[0].description.as_deref(), Some("The source file"));
+        assert!(spec.args[1].is_optional && spec.args[1].is_variadic);
+        assert_eq!(
+            spec.options[0].description.as_deref(),
+            Some("Overwrite existing files without asking")
+        );
+    }
+
+    #[test]
+    fn test_argparse() {
+        let help = "\
+usage: manage.py [-h] [--level {debug,info}] [-n N] {run,test} ... file
+
+Manage the project
+
+positional arguments:
+  {run,test}
+    run                 Run the server
+    test                Run the tests
+  file                  The file to manage
+
+options:
+  -h, --help            show this help message and exit
+  --level {debug,info}  The log level
+  -n N, --count N       How many times
+";
+        let spec = parse("manage.py", help);
+        assert_eq!(spec.description.as_deref(), Some("Manage the project"));
+        assert_eq!(spec.subcommands.len(), 2);
+        assert_eq!(spec.subcommands[0].description.as_deref(), Some("Run the server"));
+        assert_eq!(spec.args.len(), 1);
+        assert_eq!(spec.args[0].name, "file");
+        assert_eq!(spec.options[1].args[0].suggestions, vec!["debug", "info"]);
+        assert_eq!(spec.options[2].name, names(&["-n", "--count"]));
+        assert_eq!(spec.options[2].args[0].name, "N");
+    }
+
+    #[test]
+    fn test_cobra() {
+        let help = "\
+Work with clusters
+
+Usage:
+  kube [command]
+
+Available Commands:
+  apply       Apply a configuration
+  completion  Generate the autocompletion script
+
+Flags:
+  -h, --help               help for kube
+  -n, --namespace string   The namespace
+";
+        let spec = parse("kube", help);
+        assert_eq!(spec.subcommands.len(), 2);
+        assert_eq!(spec.options[1].args[0].name, "string");
+        assert!(spec.args.is_empty());
+    }
+
+    #[test]
+    fn test_gnu() {
+        let help = "\
+Usage: ls [OPTION]... [FILE]...
+List information about the FILEs (the current directory by default).
+
+Mandatory arguments to long options are mandatory for short options too.
+  -a, --all                  do not ignore entries starting with .
+      --color[=WHEN]         color the output WHEN; more info below
+  -w, --width=COLS           set output width to COLS.  0 means no limit
+";
+        let spec = parse("ls", help);
+        assert_eq!(
+            spec.description.as_deref(),
+            Some("List information about the FILEs (the current directory by default).")
+        );
+        assert_eq!(spec.options.len(), 3);
+        assert!(spec.options[1].args[0].is_optional);
+        assert_eq!(spec.options[1].args[0].name, "WHEN");
+        assert_eq!(spec.options[2].args[0].name, "COLS");
+        assert_eq!(spec.args.len(), 1);
+        assert_eq!(spec.args[0].name, "FILE");
+        assert!(spec.args[0].is_optional && spec.args[0].is_variadic);
+    }
+
+    #[test]
+    fn test_man() {
+        let help = "\
+L\x08LS\x08S(1)                 User Commands                 LS(1)
+
+N\x08NA\x08AM\x08ME\x08E
+       ls - list directory contents
+
+O\x08OP\x08PT\x08TI\x08IO\x08ON\x08NS\x08S
+       -\x08-a\x08a, -\x08--\x08-a\x08al\x08ll\x08l
+              do not ignore entries starting with .
+
+       -\x08-B\x08B
+              do not list implied entries ending with ~
+";
+        let spec = parse("ls", help);
+        assert_eq!(spec.description.as_deref(), Some("list directory contents"));
+        assert_eq!(spec.options.len(), 2);
+        assert_eq!(spec.options[0].name, names(&["-a", "--all"]));
+        assert_eq!(
+            spec.options[0].description.as_deref(),
+            Some("do not ignore entries starting with .")
+        );
+    }
+
+    #[test]
+    fn test_parse_placeholder() {
+        let arg = parse_placeholder("[<dir>...]").unwrap();
+        assert_eq!(arg.name, "dir");
+        assert!(arg.is_optional && arg.is_variadic);
+        assert_eq!(arg.template, Some(Template::Folders));
+        assert!(parse_placeholder("a b").is_none());
+        assert!(parse_placeholder("").is_none());
+    }
+}
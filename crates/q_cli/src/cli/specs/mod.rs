@@ -0,0 +1,252 @@
+mod generate;
+mod help;
+
+use std::fmt::Write;
+use std::process::ExitCode;
+
+use anstream::{
+    eprintln,
+    println,
+};
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    eyre,
+};
+use fig_ipc::local::send_hook_to_socket;
+use fig_proto::hooks;
+use fig_request::reqwest::Client;
+use fig_specs::cdn::{
+    indexes,
+    source_for,
+};
+use fig_specs::{
+    CDNS,
+    CacheEntry,
+    CdnSource,
+    Refresh,
+    SourceIndex,
+    SpecCache,
+    spec_path,
+};
+use time::OffsetDateTime;
+use tracing::debug;
+
+use super::OutputFormat;
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum SpecsSubcommand {
+    /// List the completion specs cached for offline use
+    List {
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Update cached specs to their latest version, pinned specs are skipped
+    Update {
+        /// The specs to update, every cached spec is updated if none are given
+        specs: Vec<String>,
+    },
+    /// Keep specs at their cached version, specs that aren't cached yet are downloaded first
+    Pin {
+        #[arg(required = true)]
+        specs: Vec<String>,
+    },
+    /// Let pinned specs be updated again
+    Unpin {
+        #[arg(required = true)]
+        specs: Vec<String>,
+    },
+    /// Generate a spec for a command from its help output
+    Generate(generate::GenerateArgs),
+}
+
+impl SpecsSubcommand {
+    pub async fn execute(&self) -> Result<ExitCode> {
+        let cache = SpecCache::new()?;
+
+        let exit_code = match self {
+            SpecsSubcommand::List { format } => {
+                let entries: Vec<_> = cache
+                    .list()
+                    .await?
+                    .into_iter()
+                    .filter(|entry| entry.spec_name().is_some())
+                    .collect();
+
+                format.print(|| list_text(&entries, OffsetDateTime::now_utc()), || &entries);
+                return Ok(ExitCode::SUCCESS);
+            },
+            // The desktop app watches the local specs directory and reloads generated specs itself
+            SpecsSubcommand::Generate(args) => return args.execute().await,
+            SpecsSubcommand::Update { specs } => {
+                let client = client()?;
+                let indexes = source_indexes(&cache, client, Refresh::Always).await;
+                let entries = cache.list().await?;
+
+                let targets: Vec<(String, &CdnSource)> = if specs.is_empty() {
+                    entries
+                        .iter()
+                        .filter_map(|entry| {
+                            let source = CDNS.iter().find(|source| source.name() == entry.source)?;
+                            Some((entry.spec_name()?.to_owned(), source))
+                        })
+                        .collect()
+                } else {
+                    specs
+                        .iter()
+                        .map(|name| (name.clone(), source_for(&indexes, name)))
+                        .collect()
+                };
+
+                let mut failed = false;
+                for (name, source) in targets {
+                    let path = spec_path(&name);
+                    let pinned = entries
+                        .iter()
+                        .any(|entry| entry.source == source.name() && entry.path == path && entry.meta.pinned);
+                    if pinned {
+                        println!("{} {name} is pinned", "-".dark_grey());
+                        continue;
+                    }
+
+                    match cache.get(client, source, &path, Refresh::Always).await {
+                        Ok(_) => println!("{} {name}", "✓".green()),
+                        Err(err) => {
+                            failed = true;
+                            eprintln!("{} {name}: {err}", "✗".red());
+                        },
+                    }
+                }
+
+                if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+            },
+            SpecsSubcommand::Pin { specs } => {
+                let client = client()?;
+                let indexes = source_indexes(&cache, client, Refresh::IfStale).await;
+
+                for name in specs {
+                    let source = source_for(&indexes, name);
+                    let path = spec_path(name);
+                    cache.get(client, source, &path, Refresh::IfStale).await?;
+                    cache.set_pinned(source.name(), &path, true).await?;
+                    println!("Pinned {}", name.as_str().bold());
+                }
+                ExitCode::SUCCESS
+            },
+            SpecsSubcommand::Unpin { specs } => {
+                let entries = cache.list().await?;
+
+                let mut exit_code = ExitCode::SUCCESS;
+                for name in specs {
+                    let mut found = false;
+                    for entry in entries.iter().filter(|entry| entry.spec_name() == Some(name)) {
+                        found |= cache.set_pinned(&entry.source, &entry.path, false).await?;
+                    }
+
+                    if found {
+                        println!("Unpinned {}", name.as_str().bold());
+                    } else {
+                        eprintln!("{name} is not cached");
+                        exit_code = ExitCode::FAILURE;
+                    }
+                }
+                exit_code
+            },
+        };
+
+        // Let a running desktop app load the changed specs
+        if let Err(err) = send_hook_to_socket(hooks::new_clear_autocomplete_cache(vec![])).await {
+            debug!(%err, "Failed to clear the autocomplete cache");
+        }
+
+        Ok(exit_code)
+    }
+}
+
+fn client() -> Result<&'static Client> {
+    fig_request::client().ok_or_else(|| eyre!("Failed to create an http client"))
+}
+
+async fn source_indexes(cache: &SpecCache, client: &Client, refresh: Refresh) -> Vec<SourceIndex> {
+    indexes(cache, client, refresh).await.into_iter().flatten().collect()
+}
+
+fn list_text(entries: &[CacheEntry], now: OffsetDateTime) -> String {
+    if entries.is_empty() {
+        return "No specs are cached".into();
+    }
+
+    let width = entries
+        .iter()
+        .filter_map(|entry| entry.spec_name())
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+
+    let mut text = String::new();
+    for entry in entries {
+        let name = entry.spec_name().unwrap_or(&entry.path);
+        let age = format_age(now - entry.meta.fetched_at);
+        write!(text, "{name:<width$}  updated {age}").ok();
+        if entry.meta.pinned {
+            text.push_str(" (pinned)");
+        }
+        text.push('\n');
+    }
+
+    text.trim_end().to_owned()
+}
+
+fn format_age(age: time::Duration) -> String {
+    let age = age.max(time::Duration::ZERO);
+    let (count, unit) = if age.whole_days() > 0 {
+        (age.whole_days(), "day")
+    } else if age.whole_hours() > 0 {
+        (age.whole_hours(), "hour")
+    } else if age.whole_minutes() > 0 {
+        (age.whole_minutes(), "minute")
+    } else {
+        return "just now".into();
+    };
+
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use fig_specs::CacheMeta;
+    use time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::seconds(10)), "just now");
+        assert_eq!(format_age(Duration::minutes(1)), "1 minute ago");
+        assert_eq!(format_age(Duration::hours(5)), "5 hours ago");
+        assert_eq!(format_age(Duration::days(2) + Duration::hours(3)), "2 days ago");
+        assert_eq!(format_age(Duration::seconds(-10)), "just now");
+    }
+
+    #[test]
+    fn test_list_text() {
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let entry = |path: &str, pinned| CacheEntry {
+            source: CDNS[0].name().into(),
+            path: path.into(),
+            size: 0,
+            meta: CacheMeta {
+                etag: None,
+                content_type: None,
+                fetched_at: now - Duration::hours(2),
+                pinned,
+            },
+        };
+
+        assert_eq!(list_text(&[], now), "No specs are cached");
+
+        let text = list_text(&[entry("cargo.js", true), entry("git.js", false)], now);
+        assert_eq!(text, "cargo  updated 2 hours ago (pinned)\ngit    updated 2 hours ago");
+    }
+}
@@ -3,7 +3,14 @@ use std::process::ExitCode;
 use clap::Subcommand;
 use crossterm::style::Stylize;
 use eyre::Result;
+use fig_settings::telemetry_log::{
+    TelemetryLog,
+    TelemetryRecord,
+};
+use fig_telemetry::TelemetryCategory;
+use fig_util::CLI_BINARY_NAME;
 use serde_json::json;
+use strum::IntoEnumIterator;
 
 use super::OutputFormat;
 
@@ -11,43 +18,154 @@ const TELEMETRY_ENABLED_KEY: &str = "telemetry.enabled";
 
 #[derive(Debug, PartialEq, Eq, Subcommand)]
 pub enum TelemetrySubcommand {
-    Enable,
-    Disable,
+    Enable {
+        /// Allow this category of telemetry, e.g. featureUsage, as long as telemetry is enabled
+        #[arg(long, short)]
+        category: Option<TelemetryCategory>,
+    },
+    Disable {
+        /// Only deny this category of telemetry, e.g. chatMetadata
+        #[arg(long, short)]
+        category: Option<TelemetryCategory>,
+    },
     Status {
         /// Format of the output
         #[arg(long, short, value_enum, default_value_t)]
         format: OutputFormat,
     },
+    /// Show the telemetry events that were sent, most recent last
+    Log {
+        /// Only events whose name contains this
+        #[arg(long)]
+        name: Option<String>,
+        /// Only events of this category
+        #[arg(long, short)]
+        category: Option<TelemetryCategory>,
+        /// How many events to show
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+        /// Delete the log instead of showing it
+        #[arg(long, conflicts_with_all = ["name", "category", "limit"])]
+        clear: bool,
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
 }
 
 impl TelemetrySubcommand {
     pub async fn execute(&self) -> Result<ExitCode> {
         match self {
-            TelemetrySubcommand::Enable => {
-                fig_settings::settings::set_value(TELEMETRY_ENABLED_KEY, true)?;
+            TelemetrySubcommand::Enable { category } => {
+                let key = category.map_or(TELEMETRY_ENABLED_KEY.into(), |c| c.setting_key());
+                fig_settings::settings::set_value(key, true)?;
                 Ok(ExitCode::SUCCESS)
             },
-            TelemetrySubcommand::Disable => {
-                fig_settings::settings::set_value(TELEMETRY_ENABLED_KEY, false)?;
+            TelemetrySubcommand::Disable { category } => {
+                let key = category.map_or(TELEMETRY_ENABLED_KEY.into(), |c| c.setting_key());
+                fig_settings::settings::set_value(key, false)?;
                 Ok(ExitCode::SUCCESS)
             },
             TelemetrySubcommand::Status { format } => {
                 let status = fig_settings::settings::get_bool_or(TELEMETRY_ENABLED_KEY, true);
+                let categories = TelemetryCategory::iter()
+                    .map(|category| (category, fig_telemetry::telemetry_category_enabled(category)))
+                    .collect::<Vec<_>>();
                 format.print(
                     || {
-                        format!(
+                        let mut out = format!(
                             "Telemetry status: {}",
                             if status { "enabled" } else { "disabled" }.bold()
-                        )
+                        );
+                        if status {
+                            for (category, enabled) in &categories {
+                                out.push_str(&format!(
+                                    "\n  {category}: {}",
+                                    if *enabled { "enabled" } else { "disabled" }
+                                ));
+                            }
+                        }
+                        out
                     },
                     || {
                         json!({
                             TELEMETRY_ENABLED_KEY: status,
+                            "categories": categories
+                                .iter()
+                                .map(|(category, enabled)| (category.to_string(), json!(enabled)))
+                                .collect::<serde_json::Map<_, _>>(),
                         })
                     },
                 );
                 Ok(ExitCode::SUCCESS)
             },
+            TelemetrySubcommand::Log {
+                name,
+                category,
+                limit,
+                clear,
+                format,
+            } => {
+                let log = TelemetryLog::new();
+                if *clear {
+                    log.clear()?;
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                let category = category.map(|c| c.to_string());
+                let records = log.search(name.as_deref(), category.as_deref(), *limit)?;
+                if records.is_empty() && *format == OutputFormat::Plain {
+                    eprintln!(
+                        "No telemetry events were sent yet, see {} for what is collected",
+                        format!("{CLI_BINARY_NAME} telemetry status").magenta()
+                    );
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                format.print(
+                    || records.iter().rev().map(display_record).collect::<Vec<_>>().join("\n"),
+                    || {
+                        records
+                            .iter()
+                            .map(|record| {
+                                json!({
+                                    "name": record.name,
+                                    "category": record.category,
+                                    "time": time::OffsetDateTime::from(record.time)
+                                        .format(&time::format_description::well_known::Rfc3339)
+                                        .ok(),
+                                    "fields": record.fields,
+                                })
+                            })
+                            .collect::<Vec<_>>()
+                    },
+                );
+                Ok(ExitCode::SUCCESS)
+            },
         }
     }
 }
+
+fn display_record(record: &TelemetryRecord) -> String {
+    let offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
+    let time = time::OffsetDateTime::from(record.time)
+        .to_offset(offset)
+        .format(time::macros::format_description!(
+            "[month repr:short] [day] [hour]:[minute]:[second]"
+        ))
+        .unwrap_or_default();
+
+    let mut out = format!(
+        "{} {} {}",
+        time.dark_grey(),
+        record.name.clone().bold(),
+        format!("({})", record.category).dark_grey()
+    );
+    for (key, value) in &record.fields {
+        out.push_str(&format!(
+            "\n  {key}: {}",
+            value.as_str().map_or(value.to_string(), Into::into)
+        ));
+    }
+    out
+}
@@ -0,0 +1,224 @@
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anstream::{
+    eprintln,
+    println,
+};
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use eyre::{
+    Context,
+    Result,
+    bail,
+};
+use fig_util::asciicast::{
+    self,
+    Event,
+    EventCode,
+};
+use fig_util::env_var::{
+    Q_TERM_RECORD,
+    Q_TERM_RECORD_INPUT,
+};
+use fig_util::{
+    PTY_BINARY_NAME,
+    directories,
+};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum TermSubcommand {
+    /// Record a terminal session to an asciicast file
+    Record {
+        /// The file to write the recording to, defaults to a timestamped file in the current
+        /// directory
+        file: Option<PathBuf>,
+        /// Also record keyboard input, this may capture passwords typed into the terminal
+        #[arg(long)]
+        input: bool,
+        /// The command to record instead of your shell
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    /// Replay a recorded terminal session
+    Replay {
+        /// The asciicast file to replay
+        file: PathBuf,
+        /// Playback speed multiplier
+        #[arg(long, short, default_value_t = 1.0)]
+        speed: f64,
+        /// Limit pauses between output to this many seconds
+        #[arg(long, short)]
+        idle_limit: Option<f64>,
+        /// List the commands in the recording instead of replaying it
+        #[arg(long)]
+        markers: bool,
+    },
+}
+
+impl TermSubcommand {
+    pub async fn execute(self) -> Result<ExitCode> {
+        match self {
+            TermSubcommand::Record { file, input, command } => {
+                let file = file.unwrap_or_else(|| {
+                    let now = OffsetDateTime::now_utc();
+                    PathBuf::from(format!(
+                        "q-session-{}{:02}{:02}-{:02}{:02}{:02}.cast",
+                        now.year(),
+                        now.month() as u8,
+                        now.day(),
+                        now.hour(),
+                        now.minute(),
+                        now.second()
+                    ))
+                });
+                record(&file, input, &command).await
+            },
+            TermSubcommand::Replay {
+                file,
+                speed,
+                idle_limit,
+                markers,
+            } => {
+                if speed <= 0.0 {
+                    bail!("Speed must be greater than 0");
+                }
+
+                let recording = tokio::fs::read_to_string(&file)
+                    .await
+                    .with_context(|| format!("Failed to read {}", file.display()))?;
+                let (header, events) = asciicast::parse(&recording).context("Invalid asciicast recording")?;
+
+                if markers {
+                    for event in events.iter().filter(|event| event.code == EventCode::Marker) {
+                        println!("{:>8.1}s  {}", event.time, event.data);
+                    }
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                if let Ok((columns, rows)) = crossterm::terminal::size() {
+                    if (columns as usize) < header.width || (rows as usize) < header.height {
+                        eprintln!(
+                            "{}",
+                            format!(
+                                "The recording is {}x{} but your terminal is {columns}x{rows}, the replay may not render correctly",
+                                header.width, header.height
+                            )
+                            .yellow()
+                        );
+                    }
+                }
+
+                replay(&events, speed, idle_limit).await
+            },
+        }
+    }
+}
+
+async fn record(file: &Path, input: bool, command: &[String]) -> Result<ExitCode> {
+    let qterm = which::which(PTY_BINARY_NAME)
+        .ok()
+        .or_else(|| {
+            directories::home_local_bin()
+                .ok()
+                .map(|bin| bin.join(PTY_BINARY_NAME))
+                .filter(|path| path.exists())
+        })
+        .ok_or_else(|| eyre::eyre!("Could not find {PTY_BINARY_NAME}, make sure shell integrations are installed"))?;
+
+    // The recording is created by qterm relative to our working directory
+    let file = std::path::absolute(file)?;
+
+    let mut cmd = tokio::process::Command::new(qterm);
+    cmd.env(Q_TERM_RECORD, &file);
+    if input {
+        cmd.env(Q_TERM_RECORD_INPUT, "1");
+    }
+    if !command.is_empty() {
+        cmd.arg("--").args(command);
+    }
+
+    println!(
+        "Recording to {}, exit the shell to stop recording",
+        file.display().to_string().bold()
+    );
+    let status = cmd.status().await.context("Failed to start the recording")?;
+    println!("Saved recording to {}", file.display().to_string().bold());
+
+    Ok(if status.success() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+async fn replay(events: &[Event], speed: f64, idle_limit: Option<f64>) -> Result<ExitCode> {
+    let mut stdout = std::io::stdout();
+    for (delay, data) in schedule(events, speed, idle_limit) {
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        stdout.write_all(data.as_bytes())?;
+        stdout.flush()?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The output of the recording along with the delay before writing it
+fn schedule(events: &[Event], speed: f64, idle_limit: Option<f64>) -> Vec<(Duration, &str)> {
+    let mut last_time = 0.0;
+    events
+        .iter()
+        .filter(|event| event.code == EventCode::Output)
+        .map(|event| {
+            let mut delay = (event.time - last_time).max(0.0);
+            if let Some(idle_limit) = idle_limit {
+                delay = delay.min(idle_limit.max(0.0));
+            }
+            last_time = event.time;
+            (Duration::from_secs_f64(delay / speed), event.data.as_str())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(time: f64, code: EventCode, data: &str) -> Event {
+        Event {
+            time,
+            code,
+            data: data.into(),
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let events = [
+            event(0.5, EventCode::Output, "$ "),
+            event(1.0, EventCode::Input, "l"),
+            event(1.5, EventCode::Marker, "ls"),
+            event(2.0, EventCode::Output, "ls\r\n"),
+            event(12.0, EventCode::Output, "a.txt\r\n"),
+        ];
+
+        assert_eq!(schedule(&events, 1.0, None), vec![
+            (Duration::from_millis(500), "$ "),
+            (Duration::from_millis(1500), "ls\r\n"),
+            (Duration::from_secs(10), "a.txt\r\n"),
+        ]);
+
+        assert_eq!(schedule(&events, 2.0, Some(2.0)), vec![
+            (Duration::from_millis(250), "$ "),
+            (Duration::from_millis(750), "ls\r\n"),
+            (Duration::from_secs(1), "a.txt\r\n"),
+        ]);
+    }
+}
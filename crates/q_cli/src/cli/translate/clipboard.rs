@@ -0,0 +1,62 @@
+use std::io::Write;
+
+use arboard::Clipboard;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use eyre::Result;
+use tracing::warn;
+
+/// Copies `text` to the clipboard of the machine the user is sitting at
+///
+/// Over SSH the native clipboard belongs to the remote host (if there is one at all), so the text
+/// is sent to the local terminal with an OSC 52 escape sequence instead. Locally the native
+/// clipboard is preferred, falling back to OSC 52 when it isn't available, e.g. without a display
+/// server.
+pub fn copy(text: &str) -> Result<()> {
+    if !fig_util::system_info::in_ssh() {
+        match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+            Ok(()) => return Ok(()),
+            Err(err) => warn!(%err, "Failed to use the native clipboard, falling back to OSC 52"),
+        }
+    }
+
+    let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+    // Write to the tty directly so the sequence reaches the terminal even if stdout is redirected
+    match std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => {
+            tty.write_all(sequence.as_bytes())?;
+            tty.flush()?;
+        },
+        Err(_) => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(sequence.as_bytes())?;
+            stdout.flush()?;
+        },
+    }
+    Ok(())
+}
+
+/// The escape sequence that asks the terminal to set the clipboard to `text`, tmux only forwards it
+/// to the outer terminal when wrapped in a passthrough sequence
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else {
+        osc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("ls -la", false), "\x1b]52;c;bHMgLWxh\x07");
+        assert_eq!(
+            osc52_sequence("ls -la", true),
+            "\x1bPtmux;\x1b\x1b]52;c;bHMgLWxh\x07\x1b\\"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+mod clipboard;
+mod context;
+
 use std::fmt::Display;
 use std::io::{
     IsTerminal,
@@ -5,13 +8,15 @@ use std::io::{
 };
 use std::process::ExitCode;
 use std::sync::LazyLock;
-use std::time::Instant;
+use std::time::{
+    Instant,
+    SystemTime,
+};
 
 use anstream::{
     eprintln,
     println,
 };
-use arboard::Clipboard;
 use clap::Args;
 use color_eyre::owo_colors::OwoColorize;
 use crossterm::style::Stylize;
@@ -31,9 +36,16 @@ use fig_ipc::{
     BufferedUnixStream,
     SendMessage,
 };
+use fig_settings::translate_history::{
+    TranslateHistory,
+    Translation,
+};
 use fig_telemetry::SuggestionState;
-use fig_util::CLI_BINARY_NAME;
 use fig_util::env_var::QTERM_SESSION_ID;
+use fig_util::{
+    CLI_BINARY_NAME,
+    command_risk,
+};
 use regex::{
     Captures,
     Regex,
@@ -42,7 +54,9 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use tracing::error;
 
+use self::context::Context;
 use crate::util::region_check;
 use crate::util::spinner::{
     Spinner,
@@ -50,6 +64,7 @@ use crate::util::spinner::{
 };
 
 const SEEN_ONBOARDING_KEY: &str = "ai.seen-onboarding";
+const HISTORY_LIMIT: usize = 25;
 
 #[derive(Debug, Args, Default, PartialEq, Eq)]
 pub struct TranslateArgs {
@@ -57,6 +72,9 @@ pub struct TranslateArgs {
     /// Number of completions to generate (must be <=5)
     #[arg(short, long, hide = true)]
     n: Option<i32>,
+    /// Show previous translations, only those matching the query if one is given
+    #[arg(long, value_name = "QUERY", num_args = 0..=1, default_missing_value = "", conflicts_with = "input")]
+    history: Option<String>,
 }
 
 impl TranslateArgs {
@@ -82,19 +100,10 @@ struct CompleteResponse {
 
 #[derive(Debug, Clone)]
 enum DialogActions {
-    Execute {
-        command: String,
-        display: bool,
-    },
-    Edit {
-        command: String,
-        display: bool,
-    },
-    #[allow(dead_code)]
-    Copy {
-        command: String,
-        display: bool,
-    },
+    Execute { command: String, display: bool },
+    Edit { command: String, display: bool },
+    Copy { command: String, display: bool },
+    Refine,
     Regenerate,
     Ask,
     Cancel,
@@ -124,6 +133,7 @@ impl Display for DialogActions {
                     write!(f, "📋 Copy to clipboard")
                 }
             },
+            DialogActions::Refine => write!(f, "💬 Refine answer"),
             DialogActions::Regenerate => write!(f, "🔄 Regenerate answer"),
             DialogActions::Ask => write!(f, "❓ Ask another question"),
             DialogActions::Cancel => write!(f, "❌ Cancel"),
@@ -159,7 +169,30 @@ struct CwResponse {
     completions: Vec<String>,
 }
 
-async fn generate_response(question: &str, n: i32) -> Result<CwResponse> {
+/// A follow up asking to change the previous suggestion, e.g. "make it recursive"
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Refinement {
+    /// The command that was suggested before the follow up
+    command: String,
+    request: String,
+}
+
+/// The comment lines the model completes, each refinement feeds the previous suggestion back so the
+/// next one is an edit of it rather than a fresh answer
+fn question_prompt(question: &str, refinements: &[Refinement]) -> String {
+    let mut prompt = format!("{question}\n");
+    for Refinement { command, request } in refinements {
+        prompt.push_str(&format!("{command}\n# The command above, but {request}\n"));
+    }
+    prompt
+}
+
+async fn generate_response(
+    question: &str,
+    refinements: &[Refinement],
+    n: i32,
+    context: Option<&Context>,
+) -> Result<CwResponse> {
     let os = match std::env::consts::OS {
         "macos" => "macOS",
         "linux" => fig_util::system_info::linux::get_os_release()
@@ -186,7 +219,13 @@ i=`wc -l $FILENAME|cut -d ' ' -f1`; cat $FILENAME| echo "scale=2;(`paste -sd+`)/
 
     let mut input = RecommendationsInput {
         file_context: FileContext {
-            left_file_content: format!("{prompt_comment}\n\n{prompt}{question}\n"),
+            left_file_content: format!(
+                "{prompt_comment}\n\n{}{prompt}{}",
+                context
+                    .map(|context| format!("{}\n", context.to_prompt()))
+                    .unwrap_or_default(),
+                question_prompt(question, refinements)
+            ),
             right_file_content: "".into(),
             filename: "commands.sh".into(),
             programming_language: ProgrammingLanguage {
@@ -211,59 +250,23 @@ i=`wc -l $FILENAME|cut -d ' ' -f1`; cat $FILENAME| echo "scale=2;(`paste -sd+`)/
             _ => break,
         }
     }
+    // Prefer the suggestions that only run executables that exist
+    completions.sort_by_cached_key(|completion| context::missing_executables(completion).len());
+
     Ok(CwResponse { completions })
 }
 
 fn warning_message(content: &str) {
-    #[allow(clippy::type_complexity)]
-    let warnings: &[(Regex, fn(&Captures<'_>) -> String)] = &[
-        (Regex::new(r"\bsudo\b").unwrap(), |_m| {
-            "⚠️ Warning: this command contains sudo which will run the command as admin, please make sure you know what you are doing before you run this...".into()
-        }),
-        (
-            Regex::new(r"\s+(--hard|--force|-rf|--no-preserve-root)\b").unwrap(),
-            |m| {
-                format!(
-                    "⚠️ Warning: this command contains an irreversible flag ({}), please make sure you know what you are doing before you run this...",
-                    &m[0]
-                )
-            },
-        ),
-        (Regex::new(r"(\s*\/dev\/(\w*)(\s|$))").unwrap(), |_m| {
-            "⚠️ Warning: this command may override one of your disks, please make sure you know what you are doing before you run this...".into()
-        }),
-        (
-            Regex::new(r":\s*\(\s*\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:").unwrap(),
-            |_m| "⚠️ Warning: this command is a fork bomb".into(),
-        ),
-        (Regex::new(r"\bdd\b").unwrap(), |_m| {
-            "⚠️ Warning: dd is a dangerous command, please make sure you know what you are doing before you run it..."
-                .into()
-        }),
-        (Regex::new(r"\|\s*(bash|sh|zsh|fish)/").unwrap(), |m| {
-            format!(
-                "⚠️ Warning: piping into {} can be dangerous, please make sure you know what you are doing before you run it...",
-                &m[0]
-            )
-        }),
-        (Regex::new(r"(\bsudoedit|\bsu|\/etc\/sudoers)\b/").unwrap(), |m| {
-            format!(
-                "⚠️ Warning: you might be altering root/sudo files with ${}`, please make sure you know what you are doing before you run it...",
-                &m[0]
-            )
-        }),
-        (Regex::new(r"(\/dev\/(u?random)|(zero))").unwrap(), |m| {
+    for warning in command_risk::analyze(content).warnings() {
+        println!(
+            "{}\n",
             format!(
-                "⚠️ Warning: {} can be dangerous, please make sure you know what you are doing before you run this...",
-                &m[0]
+                "⚠️ Warning: {}, please make sure you know what you are doing before you run this...",
+                warning.reason
             )
-        }),
-    ];
-
-    for (re, warning) in warnings {
-        if let Some(capture) = re.captures(content) {
-            println!("{}\n", warning(&capture).yellow().bold());
-        }
+            .yellow()
+            .bold()
+        );
     }
 }
 
@@ -282,6 +285,61 @@ fn highlighter(s: &str) -> String {
         .into_owned()
 }
 
+/// Saves the translation so it can be found later with `--history`
+fn record(question: &str, refinements: &[Refinement], command: &str, action: Option<&str>) {
+    let translation = Translation {
+        question: question.into(),
+        refinements: refinements
+            .iter()
+            .map(|refinement| refinement.request.clone())
+            .collect(),
+        command: command.into(),
+        action: action.map(Into::into),
+        cwd: std::env::current_dir()
+            .ok()
+            .map(|cwd| cwd.to_string_lossy().into_owned()),
+        time: SystemTime::now(),
+    };
+
+    if let Err(err) = TranslateHistory::new().insert(&translation) {
+        error!(%err, "Failed to save translation to history");
+    }
+}
+
+fn print_history(query: Option<&str>) -> Result<ExitCode> {
+    let translations = TranslateHistory::new().search(query, HISTORY_LIMIT)?;
+    if translations.is_empty() {
+        match query {
+            Some(query) => eprintln!("No translations match {}", query.bold()),
+            None => eprintln!(
+                "No translations yet, run {} to make one",
+                format!("{CLI_BINARY_NAME} translate").magenta()
+            ),
+        }
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
+    // Oldest first so the most recent translation ends up right above the prompt
+    for translation in translations.iter().rev() {
+        let time = time::OffsetDateTime::from(translation.time)
+            .to_offset(offset)
+            .format(time::macros::format_description!(
+                "[month repr:short] [day] [hour]:[minute]"
+            ))
+            .unwrap_or_default();
+
+        println!("{} {}", time.dark_grey(), translation.question.clone().bold());
+        for refinement in &translation.refinements {
+            println!("  {} {refinement}", "↳".dark_grey());
+        }
+        println!("  {}", highlighter(&translation.command).bright_magenta());
+        println!();
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
 #[cfg(unix)]
 fn clear_stdin() -> Result<()> {
     use std::io::Read;
@@ -324,6 +382,10 @@ fn change_blocking_fd(fd: std::os::unix::io::RawFd, is_blocking: bool) -> Result
 
 impl TranslateArgs {
     pub async fn execute(self) -> Result<ExitCode> {
+        if let Some(query) = &self.history {
+            return print_history(Some(query.as_str()).filter(|query| !query.is_empty()));
+        }
+
         if !fig_util::system_info::in_cloudshell() && !fig_auth::is_logged_in().await {
             bail!(
                 "You are not logged in. Run {} to login.",
@@ -350,7 +412,7 @@ impl TranslateArgs {
             eprintln!();
         }
 
-        let Self { input, n } = self;
+        let Self { input, n, .. } = self;
         let mut input = if input.is_empty() { None } else { Some(input.join(" ")) };
 
         let n = match n {
@@ -374,9 +436,12 @@ impl TranslateArgs {
                 },
             };
 
-            match &generate_response(&question, 1).await?.completions[..] {
+            let question = question.trim();
+            let context = Context::load().await;
+            match &generate_response(question, &[], 1, context.as_ref()).await?.completions[..] {
                 [] => eyre::bail!("no valid completions were generated"),
                 [res, ..] => {
+                    record(question, &[], res, None);
                     println!("{res}");
                     return Ok(ExitCode::SUCCESS);
                 },
@@ -404,6 +469,8 @@ impl TranslateArgs {
             };
 
             let question = question.trim().replace('\n', " ");
+            let context = Context::load().await;
+            let mut refinements: Vec<Refinement> = vec![];
 
             'generate_loop: loop {
                 let spinner_text = format!("  {} {} ", "Shell".bold(), "·".grey());
@@ -414,7 +481,7 @@ impl TranslateArgs {
                 ]);
 
                 let response_time_start = Instant::now();
-                let res = match generate_response(&question, n).await {
+                let res = match generate_response(&question, &refinements, n, context.as_ref()).await {
                     Ok(res) => res,
                     Err(err) => {
                         spinner.stop_with_message("".into());
@@ -442,12 +509,20 @@ impl TranslateArgs {
                         spinner.stop_with_message(format!("{spinner_text}{}", highlighter(choice)));
                         println!();
                         warning_message(choice);
+                        for name in context::missing_executables(choice) {
+                            println!(
+                                "{}\n",
+                                format!("⚠️ Warning: `{name}` isn't installed or isn't on your PATH")
+                                    .yellow()
+                                    .bold()
+                            );
+                        }
 
                         let actions: Vec<DialogActions> = fig_settings::settings::get("ai.menu-actions")
                             .ok()
                             .flatten()
                             .unwrap_or_else(|| {
-                                ["execute", "edit", "regenerate", "ask", "cancel"]
+                                ["execute", "edit", "copy", "refine", "regenerate", "ask", "cancel"]
                                     .map(String::from)
                                     .to_vec()
                             })
@@ -465,6 +540,7 @@ impl TranslateArgs {
                                     command: choice.to_string(),
                                     display: false,
                                 }),
+                                "refine" => Some(DialogActions::Refine),
                                 "regenerate" => Some(DialogActions::Regenerate),
                                 "ask" => Some(DialogActions::Ask),
                                 "cancel" => Some(DialogActions::Cancel),
@@ -485,6 +561,17 @@ impl TranslateArgs {
                         })
                         .await;
 
+                        let recorded_action = match action {
+                            Some(DialogActions::Execute { .. }) => Some("execute"),
+                            Some(DialogActions::Edit { .. }) => Some("edit"),
+                            Some(DialogActions::Copy { .. }) => Some("copy"),
+                            Some(DialogActions::Refine | DialogActions::Regenerate) => None,
+                            Some(DialogActions::Ask | DialogActions::Cancel) | None => Some("cancel"),
+                        };
+                        if let Some(recorded_action) = recorded_action {
+                            record(&question, &refinements, choice, Some(recorded_action));
+                        }
+
                         match action {
                             Some(DialogActions::Execute { command, .. }) => {
                                 // let command = PARAM_REGEX
@@ -518,14 +605,20 @@ impl TranslateArgs {
                                 break 'ask_loop;
                             },
                             Some(DialogActions::Copy { command, .. }) => {
-                                if let Ok(mut clipboard) = Clipboard::new() {
-                                    match clipboard.set_text(command.to_string()) {
-                                        Ok(_) => println!("Copied!"),
-                                        Err(err) => eyre::bail!(err),
-                                    }
-                                }
+                                clipboard::copy(command)?;
+                                println!("Copied!");
                                 break 'ask_loop;
                             },
+                            Some(DialogActions::Refine) => {
+                                let request: String = dialoguer::Input::with_theme(&theme())
+                                    .with_prompt("Refine")
+                                    .interact_text()?;
+                                refinements.push(Refinement {
+                                    command: choice.clone(),
+                                    request: request.trim().replace('\n', " "),
+                                });
+                                continue 'generate_loop;
+                            },
                             Some(DialogActions::Regenerate) => {
                                 continue 'generate_loop;
                             },
@@ -632,16 +725,35 @@ mod test {
         ];
 
         for prompt in prompts {
-            let res = generate_response(prompt, 1).await.unwrap();
+            let res = generate_response(prompt, &[], 1, None).await.unwrap();
             let first = res.completions.first().unwrap();
             std::println!("{prompt},{first}");
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
     }
 
+    #[test]
+    fn test_question_prompt() {
+        assert_eq!(question_prompt("list files", &[]), "list files\n");
+        assert_eq!(
+            question_prompt("list files", &[
+                Refinement {
+                    command: "ls".into(),
+                    request: "make it recursive".into(),
+                },
+                Refinement {
+                    command: "ls -R".into(),
+                    request: "only .rs files".into(),
+                },
+            ]),
+            "list files\nls\n# The command above, but make it recursive\nls -R\n# The command above, but only .rs files\n"
+        );
+    }
+
     #[test]
     fn test_lints() {
-        warning_message("sudo dd if=/dev/sda");
+        warning_message("sudo dd if=/dev/sda of=/dev/sdb");
+        warning_message("ls -la");
     }
 
     #[test]
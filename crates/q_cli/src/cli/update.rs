@@ -7,6 +7,7 @@ use crossterm::style::Stylize;
 use eyre::Result;
 use fig_install::index::UpdatePackage;
 use fig_install::{
+    Mirror,
     UpdateOptions,
     UpdateStatus,
 };
@@ -37,6 +38,7 @@ use tracing::{
     error,
     info,
 };
+use url::Url;
 
 use crate::util::dialoguer_theme;
 use crate::util::spinner::{
@@ -55,21 +57,54 @@ pub struct UpdateArgs {
     /// Uses rollout
     #[arg(long)]
     rollout: bool,
+    /// Download the update and verify its hash and signature without installing it
+    #[arg(long)]
+    verify_only: bool,
+    /// Update from a mirror instead of the release url, either its url, a local directory or (on
+    /// Linux) a bundle archive of one. Only builds that verify update signatures can use a mirror
+    #[arg(long, value_name = "DIR|FILE|URL")]
+    from: Option<String>,
+    /// Switch back to the version installed before the last update
+    #[arg(long, conflicts_with_all = ["from", "verify_only"])]
+    rollback: bool,
 }
 
 impl UpdateArgs {
     pub async fn execute(&self) -> Result<ExitCode> {
-        let ctx = Context::new();
-        if ctx.platform().os() == Os::Linux && manifest().variant == Variant::Full {
-            return try_linux_update().await;
-        }
-
         let UpdateArgs {
             non_interactive,
             relaunch_dashboard,
             rollout,
+            verify_only,
+            from,
+            rollback,
         } = &self;
 
+        if *rollback {
+            let version = fig_install::rollback()?;
+            println!("Rolled back to {}", version.bold());
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let mirror = match from {
+            Some(from) => {
+                let from = from.clone();
+                Some(tokio::task::spawn_blocking(move || Mirror::open(&from)).await??)
+            },
+            None => None,
+        };
+        let release_url = mirror.as_ref().map(|mirror| mirror.release_url().clone());
+
+        if *verify_only {
+            return verify_update(release_url.as_ref(), !rollout).await;
+        }
+
+        let ctx = Context::new();
+        // The desktop app can only update from the configured release url
+        if ctx.platform().os() == Os::Linux && manifest().variant == Variant::Full && release_url.is_none() {
+            return try_linux_update().await;
+        }
+
         let res = fig_install::update(
             Context::new(),
             Some(Box::new(|mut recv| {
@@ -100,6 +135,7 @@ impl UpdateArgs {
                 ignore_rollout: !rollout,
                 interactive: !non_interactive,
                 relaunch_dashboard: *relaunch_dashboard,
+                release_url,
             },
         )
         .await;
@@ -121,6 +157,46 @@ impl UpdateArgs {
     }
 }
 
+async fn verify_update(release_url: Option<&Url>, ignore_rollout: bool) -> Result<ExitCode> {
+    // Until signing keys are pinned only the hash in the index can be checked
+    let checked = if fig_install::signature::keys_pinned() {
+        "The index signature is valid\n"
+    } else {
+        "This build has no update signing keys, signatures were not checked\n"
+    };
+
+    let mut spinner = Spinner::new(vec![
+        SpinnerComponent::Spinner,
+        SpinnerComponent::Text(" Verifying update...".into()),
+    ]);
+    let release_url = release_url.unwrap_or(fig_install::index::release_url());
+    let result = fig_install::verify_update(release_url, ignore_rollout).await;
+    spinner.stop();
+
+    match result {
+        Ok(Some(pkg)) => {
+            let valid = if fig_install::signature::keys_pinned() {
+                "hash and signature are"
+            } else {
+                "hash is"
+            };
+            println!(
+                "{checked}Version {} was downloaded and its {valid} valid",
+                pkg.version.to_string().bold()
+            );
+            Ok(ExitCode::SUCCESS)
+        },
+        Ok(None) => {
+            println!(
+                "{checked}No updates available, {} is the latest version.",
+                env!("CARGO_PKG_VERSION").bold()
+            );
+            Ok(ExitCode::SUCCESS)
+        },
+        Err(err) => eyre::bail!("{err}"),
+    }
+}
+
 async fn try_linux_update() -> Result<ExitCode> {
     match (fig_install::check_for_updates(true).await, bundle_metadata().await) {
         (ref update_result @ Ok(Some(ref pkg)), Some(file_type)) => {
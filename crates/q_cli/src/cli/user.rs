@@ -3,7 +3,10 @@ use std::fmt::Display;
 use std::process::ExitCode;
 use std::time::Duration;
 
-use anstream::println;
+use anstream::{
+    eprintln,
+    println,
+};
 use clap::Subcommand;
 use crossterm::style::Stylize;
 use eyre::{
@@ -12,21 +15,31 @@ use eyre::{
 };
 use fig_auth::builder_id::{
     PollCreateToken,
+    TokenExport,
     TokenType,
     poll_create_token,
     start_device_authorization,
 };
 use fig_auth::pkce::start_pkce_authorization;
+use fig_auth::profile::{
+    Profile,
+    ProfileEndpoint,
+    ProfileService,
+    active_name,
+};
 use fig_auth::secret_store::SecretStore;
 use fig_ipc::local::{
     login_command,
     logout_command,
 };
+use fig_settings::State;
 use fig_util::system_info::is_remote;
 use fig_util::{
     CLI_BINARY_NAME,
     PRODUCT_NAME,
 };
+use qrcode::QrCode;
+use qrcode::render::unicode;
 use serde_json::json;
 use tracing::error;
 
@@ -43,7 +56,12 @@ use crate::util::{
 #[derive(Subcommand, Debug, PartialEq, Eq)]
 pub enum RootUserSubcommand {
     /// Login
-    Login,
+    Login {
+        /// Don't open a browser on this machine, instead print a URL and QR code to open on
+        /// another device and paste the URL it redirects to back into the terminal
+        #[arg(long)]
+        no_browser: bool,
+    },
     /// Logout
     Logout,
     /// Prints details about the current user
@@ -74,7 +92,7 @@ impl Display for AuthMethod {
 impl RootUserSubcommand {
     pub async fn execute(self) -> Result<ExitCode> {
         match self {
-            Self::Login => {
+            Self::Login { no_browser } => {
                 if fig_auth::is_logged_in().await {
                     eyre::bail!(
                         "Already logged in, please logout with {} first",
@@ -82,12 +100,12 @@ impl RootUserSubcommand {
                     );
                 }
 
-                login_interactive().await?;
+                login_interactive(no_browser).await?;
 
                 Ok(ExitCode::SUCCESS)
             },
             Self::Logout => {
-                let logout_join = logout_command();
+                let logout_join = logout_command(Some(active_name(&State::new())));
 
                 let (_, _) = tokio::join!(logout_join, fig_auth::logout());
 
@@ -99,6 +117,7 @@ impl RootUserSubcommand {
                 Ok(ExitCode::SUCCESS)
             },
             Self::Whoami { format } => {
+                let profile = active_name(&State::new());
                 let builder_id = fig_auth::builder_id_token().await;
 
                 match builder_id {
@@ -121,13 +140,14 @@ impl RootUserSubcommand {
                                     },
                                     "startUrl": token.start_url,
                                     "region": token.region,
+                                    "profile": profile,
                                 })
                             },
                         );
                         Ok(ExitCode::SUCCESS)
                     },
                     _ => {
-                        format.print(|| "Not logged in", || json!({ "account": null }));
+                        format.print(|| "Not logged in", || json!({ "account": null, "profile": profile }));
                         Ok(ExitCode::FAILURE)
                     },
                 }
@@ -140,28 +160,214 @@ impl RootUserSubcommand {
 pub enum UserSubcommand {
     #[command(flatten)]
     Root(RootUserSubcommand),
+    /// Manage named auth profiles
+    #[command(subcommand)]
+    Profile(ProfileSubcommand),
+    /// Print the current login so it can be imported on another machine
+    ExportToken,
+    /// Login with a token printed by `export-token` on another machine
+    ImportToken {
+        /// The exported token, read from stdin if not set
+        token: Option<String>,
+    },
 }
 
 impl UserSubcommand {
     pub async fn execute(self) -> Result<ExitCode> {
         match self {
             Self::Root(cmd) => cmd.execute().await,
+            Self::Profile(cmd) => cmd.execute().await,
+            Self::ExportToken => {
+                let secret_store = SecretStore::new().await?;
+                let Some(export) = TokenExport::load(&secret_store).await? else {
+                    bail!(
+                        "Not logged in, please login with {} first",
+                        format!("{CLI_BINARY_NAME} login").magenta()
+                    );
+                };
+
+                eprintln!(
+                    "{}",
+                    "Warning: this token grants access to your account, only share it with machines you trust".yellow()
+                );
+                println!("{}", export.encode()?);
+                Ok(ExitCode::SUCCESS)
+            },
+            Self::ImportToken { token } => {
+                let token = match token {
+                    Some(token) => token,
+                    None => {
+                        let mut token = String::new();
+                        std::io::stdin().read_line(&mut token)?;
+                        token
+                    },
+                };
+
+                let export = TokenExport::decode(&token)?;
+                let secret_store = SecretStore::new().await?;
+                export.save(&secret_store).await?;
+
+                // Make sure the imported token is usable, refreshing it if it is expired
+                if fig_auth::builder_id_token().await?.is_none() {
+                    bail!("The imported token is expired, export it again");
+                }
+
+                if let Err(err) = login_command(Some(active_name(&State::new()))).await {
+                    error!(%err, "Failed to send login command");
+                }
+
+                println!("Logged in successfully");
+                Ok(ExitCode::SUCCESS)
+            },
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum ProfileSubcommand {
+    /// List the auth profiles
+    #[command(alias("ls"))]
+    List {
+        /// Output format to use
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Switch the profile used by default
+    Use {
+        /// The name of the profile
+        name: String,
+    },
+    /// Add a new profile, log in to it with `q login --profile <NAME>`
+    Add {
+        /// The name of the profile
+        name: String,
+        /// The IAM Identity Center start URL, Builder ID is used if not set
+        #[arg(long, requires = "region")]
+        start_url: Option<String>,
+        /// The IAM Identity Center region
+        #[arg(long, requires = "start_url")]
+        region: Option<String>,
+        /// Override the CodeWhisperer API endpoint used with this profile
+        #[arg(long, requires = "codewhisperer_endpoint_region")]
+        codewhisperer_endpoint_url: Option<String>,
+        /// The region of the CodeWhisperer API endpoint
+        #[arg(long, requires = "codewhisperer_endpoint_url")]
+        codewhisperer_endpoint_region: Option<String>,
+        /// Override the Q API endpoint used with this profile
+        #[arg(long, requires = "q_endpoint_region")]
+        q_endpoint_url: Option<String>,
+        /// The region of the Q API endpoint
+        #[arg(long, requires = "q_endpoint_url")]
+        q_endpoint_region: Option<String>,
+    },
+    /// Remove a profile and log out of it
+    #[command(alias("remove"))]
+    Rm {
+        /// The name of the profile
+        name: String,
+    },
+}
+
+impl ProfileSubcommand {
+    pub async fn execute(self) -> Result<ExitCode> {
+        let state = State::new();
+        match self {
+            Self::List { format } => {
+                let active = active_name(&state);
+                let profiles = Profile::list(&state)?;
+                format.print(
+                    || {
+                        profiles
+                            .iter()
+                            .map(|profile| {
+                                let marker = if profile.name == active { "*" } else { " " };
+                                let account = match &profile.start_url {
+                                    Some(start_url) => format!("IAM Identity Center ({start_url})"),
+                                    None => "Builder ID".into(),
+                                };
+                                format!("{marker} {} {}", profile.name.clone().bold(), account.dark_grey())
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    },
+                    || {
+                        json!({
+                            "active": active,
+                            "profiles": profiles,
+                        })
+                    },
+                );
+                Ok(ExitCode::SUCCESS)
+            },
+            Self::Use { name } => {
+                Profile::select(&state, &name)?;
+                println!("Switched to profile {}", name.bold());
+                Ok(ExitCode::SUCCESS)
+            },
+            Self::Add {
+                name,
+                start_url,
+                region,
+                codewhisperer_endpoint_url,
+                codewhisperer_endpoint_region,
+                q_endpoint_url,
+                q_endpoint_region,
+            } => {
+                let endpoints = [
+                    (
+                        ProfileService::Codewhisperer,
+                        codewhisperer_endpoint_url.zip(codewhisperer_endpoint_region),
+                    ),
+                    (ProfileService::Q, q_endpoint_url.zip(q_endpoint_region)),
+                ]
+                .into_iter()
+                .filter_map(|(service, endpoint)| Some((service, endpoint?)))
+                .map(|(service, (url, region))| (service, ProfileEndpoint { url, region }))
+                .collect();
+                let profile = Profile {
+                    name,
+                    start_url,
+                    region,
+                    endpoints,
+                };
+                profile.add(&state)?;
+                println!("Added profile {}", profile.name.clone().bold());
+                println!(
+                    "Run {} to log in to it",
+                    format!("{CLI_BINARY_NAME} login --profile {}", profile.name).magenta()
+                );
+                Ok(ExitCode::SUCCESS)
+            },
+            Self::Rm { name } => {
+                let secret_store = SecretStore::new().await?;
+                Profile::remove(&state, &secret_store, &name).await?;
+                println!("Removed profile {}", name.bold());
+                Ok(ExitCode::SUCCESS)
+            },
         }
     }
 }
 
-pub async fn login_interactive() -> Result<()> {
-    let options = [AuthMethod::BuilderId, AuthMethod::IdentityCenter];
-    let i = match choose("Select login method", &options)? {
-        Some(i) => i,
-        None => bail!("No login method selected"),
+pub async fn login_interactive(no_browser: bool) -> Result<()> {
+    // Named profiles already know which account they are for
+    let profile = Profile::load_active(&State::new())?;
+    let login_method = if profile.is_default() {
+        let options = [AuthMethod::BuilderId, AuthMethod::IdentityCenter];
+        let i = match choose("Select login method", &options)? {
+            Some(i) => i,
+            None => bail!("No login method selected"),
+        };
+        Some(options[i])
+    } else {
+        None
     };
-    let login_method = options[i];
+
     match login_method {
-        AuthMethod::BuilderId | AuthMethod::IdentityCenter => {
+        None | Some(AuthMethod::BuilderId | AuthMethod::IdentityCenter) => {
             let (start_url, region) = match login_method {
-                AuthMethod::BuilderId => (None, None),
-                AuthMethod::IdentityCenter => {
+                None => (profile.start_url, profile.region),
+                Some(AuthMethod::BuilderId) => (None, None),
+                Some(AuthMethod::IdentityCenter) => {
                     let default_start_url = fig_settings::state::get_string("auth.idc.start-url").ok().flatten();
                     let default_region = fig_settings::state::get_string("auth.idc.region").ok().flatten();
 
@@ -176,9 +382,12 @@ pub async fn login_interactive() -> Result<()> {
             };
             let secret_store = SecretStore::new().await?;
 
+            if no_browser {
+                try_callback_url_authorization(&secret_store, start_url.clone(), region.clone()).await?;
+            }
             // Remote machine won't be able to handle browser opening and redirects,
             // hence always use device code flow.
-            if is_remote() {
+            else if is_remote() {
                 try_device_authorization(&secret_store, start_url.clone(), region.clone()).await?;
             } else {
                 let (client, registration) = start_pkce_authorization(start_url.clone(), region.clone()).await?;
@@ -207,7 +416,7 @@ pub async fn login_interactive() -> Result<()> {
         },
     };
 
-    if let Err(err) = login_command().await {
+    if let Err(err) = login_command(Some(active_name(&State::new()))).await {
         error!(%err, "Failed to send login command");
     }
 
@@ -230,6 +439,7 @@ async fn try_device_authorization(
 
     if is_remote() {
         print_open_url();
+        print_qr_code(&device_auth.verification_uri_complete);
     } else if let Err(err) = fig_util::open_url_async(&device_auth.verification_uri_complete).await {
         error!(%err, "Failed to open URL with browser");
         print_open_url();
@@ -264,3 +474,48 @@ async fn try_device_authorization(
     }
     Ok(())
 }
+
+/// PKCE flow for when the browser is on another device, the redirect to the local server will
+/// fail so the user pastes the URL from the browser's address bar instead.
+async fn try_callback_url_authorization(
+    secret_store: &SecretStore,
+    start_url: Option<String>,
+    region: Option<String>,
+) -> Result<()> {
+    let (client, registration) = start_pkce_authorization(start_url, region).await?;
+
+    println!();
+    println!("Open this URL in a browser on any device:");
+    println!("{}", registration.url);
+    print_qr_code(&registration.url);
+    println!("After approving, the browser will fail to load a page on 127.0.0.1.");
+    println!("Copy the full URL from its address bar and paste it here:");
+
+    let mut callback_url = String::new();
+    std::io::stdin().read_line(&mut callback_url)?;
+
+    registration
+        .finish_with_callback_url(&client, &callback_url, Some(secret_store))
+        .await?;
+    fig_telemetry::send_user_logged_in().await;
+    println!("Logged in successfully");
+
+    Ok(())
+}
+
+/// Prints `url` as a QR code so it can be opened with a phone
+fn print_qr_code(url: &str) {
+    match QrCode::new(url) {
+        Ok(code) => {
+            // Inverted so the code is readable on the more common dark terminal backgrounds
+            let qr_code = code
+                .render::<unicode::Dense1x2>()
+                .dark_color(unicode::Dense1x2::Light)
+                .light_color(unicode::Dense1x2::Dark)
+                .quiet_zone(true)
+                .build();
+            println!("{qr_code}");
+        },
+        Err(err) => error!(%err, "Failed to render QR code"),
+    }
+}
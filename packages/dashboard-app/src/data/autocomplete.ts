@@ -100,6 +100,15 @@ const autocompleteSettings: PrefSection[] = [
         options: ["most recent", "alphabetical"],
         popular: false,
       },
+      {
+        id: "autocomplete.renderer",
+        title: "Renderer",
+        description: `Where ${PRODUCT_NAME} draws suggestions. "auto" draws them in the terminal when the desktop app isn't running, e.g. over SSH or in a TTY. Suggestions drawn in the terminal don't include those from generators, like git branches.`,
+        type: "select",
+        default: "auto",
+        options: ["auto", "desktop", "terminal"],
+        popular: false,
+      },
       {
         id: "autocomplete.scriptTimeout",
         title: "Script timeout",
@@ -242,7 +251,7 @@ const autocompleteSettings: PrefSection[] = [
       {
         id: "autocomplete.devCompletionsFolder",
         title: "Specs folder",
-        description: `When Developer Mode is enabled, ${PRODUCT_NAME} loads completion specs from the specified directory.`,
+        description: `When Developer Mode is enabled, ${PRODUCT_NAME} loads completion specs from the specified directory and reloads them when they change.`,
         type: "text",
         default: null,
         popular: false,
@@ -8,7 +8,8 @@ const inlineShellCompletionSettings: PrefSection[] = [
       {
         id: "inline.enabled",
         title: "Enable Inline completion",
-        description: "This setting only applies in new shell sessions.",
+        description:
+          "Accept a suggestion with right arrow, its next word with alt+f or its next argument with alt+right. This setting only applies in new shell sessions.",
         type: "boolean",
         default: true,
       },
@@ -39,6 +39,45 @@ const integrationOptions = [
       },
     ],
   },
+  {
+    title: "Notifications",
+    properties: [
+      {
+        id: "qterm.notifications.enabled",
+        title: "Long running commands",
+        description:
+          "Send a notification when a long running command finishes while the terminal isn't focused.",
+        type: "boolean",
+        default: false,
+      },
+      {
+        id: "qterm.notifications.threshold",
+        title: "Threshold",
+        description:
+          "How many seconds a command must run before a notification is sent.",
+        type: "number",
+        default: 30,
+      },
+      {
+        id: "qterm.notifications.watch",
+        title: "Watched commands",
+        description:
+          "A comma separated list of commands that always send a notification when they finish.",
+        example: "e.g. `make,terraform`",
+        type: "textlist",
+        default: [],
+      },
+      {
+        id: "qterm.notifications.method",
+        title: "Method",
+        description:
+          "How to send notifications, auto uses desktop notifications when available and otherwise asks the terminal to show one.",
+        type: "select",
+        options: ["auto", "desktop", "osc9", "osc777", "bell"],
+        default: "auto",
+      },
+    ],
+  },
   // {
   //   title: "Experimental",
   //   properties: [
@@ -72,6 +72,15 @@ const generalPreferences = [
         default: false,
         popular: false,
       },
+      {
+        id: "install.releaseUrl",
+        title: "Update mirror",
+        description:
+          "Check for updates from a mirror of the release bucket instead of the default. Accepts an https:// url or a file:// url of a local directory.",
+        type: "text",
+        default: null,
+        popular: false,
+      },
       // {
       //   id: "cli.tips.disabled",
       //   title: "Terminal Tips",
@@ -12,13 +12,21 @@ const translateSettings: PrefSection[] = [
         type: "boolean",
         default: true,
       },
+      {
+        id: "ai.translate-context",
+        title: "Include context",
+        description:
+          "Include the current directory, git state, recent commands and installed tools in translate requests, so suggestions use what is on your machine. Secrets in recent commands are redacted.",
+        type: "boolean",
+        default: true,
+      },
       // {
       //   id: "ai.menu-actions",
       //   title: "Menu Actions",
       //   description: "The actions that will be available in the AI menu.",
       //   type: "multiselect",
-      //   options: ["execute", "edit", "copy", "regenerate", "ask", "cancel"],
-      //   default: ["execute", "edit", "regenerate", "ask", "cancel"],
+      //   options: ["execute", "edit", "copy", "refine", "regenerate", "ask", "cancel"],
+      //   default: ["execute", "edit", "copy", "refine", "regenerate", "ask", "cancel"],
       //   inverted: true,
       // },
     ],
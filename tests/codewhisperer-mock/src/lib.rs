@@ -0,0 +1,251 @@
+//! # CodeWhisperer mock
+//!
+//! A local server implementing the parts of the CodeWhisperer and Q Developer APIs used by the
+//! CLI, with responses read from a [Script]. Point the CLI at it with the
+//! `api.codewhisperer.service` and `api.q.service` settings to run chat, translate and inline
+//! completions end to end without network access.
+//!
+//! All of the APIs use the `awsJson1_0` protocol, the operation is selected with the
+//! `x-amz-target` header and chat responses are sent as `application/vnd.amazon.eventstream`.
+
+mod script;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+use std::time::Duration;
+
+use aws_smithy_eventstream::frame::write_message_to;
+use aws_smithy_types::event_stream::{
+    Header,
+    HeaderValue,
+    Message,
+};
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{
+    BodyExt,
+    Full,
+    StreamBody,
+};
+use hyper::body::{
+    Frame,
+    Incoming,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{
+    Request,
+    Response,
+};
+use hyper_util::rt::TokioIo;
+pub use script::{
+    ChatResponse,
+    CompletionsResponse,
+    ErrorResponse,
+    Script,
+};
+use serde_json::{
+    Value,
+    json,
+};
+use tokio::net::TcpListener;
+use tracing::{
+    debug,
+    error,
+    info,
+};
+
+type Body = BoxBody<Bytes, Infallible>;
+
+pub struct MockServer {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+struct State {
+    script: Script,
+    request_count: AtomicU64,
+}
+
+impl MockServer {
+    pub async fn bind(addr: SocketAddr, script: Script) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            state: Arc::new(State {
+                script,
+                request_count: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The URL to set as the `endpoint` of the `api.codewhisperer.service` and `api.q.service`
+    /// settings
+    pub fn endpoint_url(&self) -> std::io::Result<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    /// Serves connections until the future is dropped
+    pub async fn serve(self) -> std::io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let state = Arc::clone(&self.state);
+            tokio::spawn(async move {
+                let service = service_fn(move |req| handle(Arc::clone(&state), req));
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    error!(?err, "Error serving connection");
+                }
+            });
+        }
+    }
+}
+
+async fn handle(state: Arc<State>, req: Request<Incoming>) -> Result<Response<Body>, Infallible> {
+    let request_id = format!("mock-request-{}", state.request_count.fetch_add(1, Ordering::Relaxed));
+    let target = req
+        .headers()
+        .get("x-amz-target")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let operation = target.rsplit('.').next().unwrap_or_default().to_owned();
+
+    let body = match req.into_body().collect().await {
+        Ok(body) => serde_json::from_slice::<Value>(&body.to_bytes()).unwrap_or(Value::Null),
+        Err(err) => {
+            error!(?err, "Failed to read request body");
+            Value::Null
+        },
+    };
+    info!(%request_id, %target, "Handling request");
+    debug!(%body, "Request body");
+
+    let mut response = match operation.as_str() {
+        "GenerateAssistantResponse" | "SendMessage" => {
+            let message = body
+                .pointer("/conversationState/currentMessage/userInputMessage/content")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            chat_response(state.script.chat_response(message))
+        },
+        "GenerateCompletions" | "GenerateRecommendations" => {
+            let left_file_content = body
+                .pointer("/fileContext/leftFileContent")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let response = state.script.completions_response(left_file_content);
+            match response.error {
+                Some(error) => error_response(&error),
+                None => {
+                    // CodeWhisperer calls them completions, Consolas calls them recommendations
+                    let key = if operation == "GenerateCompletions" {
+                        "completions"
+                    } else {
+                        "recommendations"
+                    };
+                    let items = response
+                        .completions
+                        .into_iter()
+                        .map(|content| json!({ "content": content }))
+                        .collect::<Vec<_>>();
+                    json_response(&json!({ key: items }))
+                },
+            }
+        },
+        "ListAvailableCustomizations" => json_response(&json!({ "customizations": [] })),
+        "ListCustomizations" => json_response(&json!({ "customizations": [] })),
+        "SendTelemetryEvent" => json_response(&json!({})),
+        _ => error_response(&ErrorResponse {
+            status: 400,
+            error_type: "UnknownOperationException".into(),
+            message: format!("The mock does not implement {target:?}"),
+        }),
+    };
+
+    response
+        .headers_mut()
+        .insert("x-amzn-requestid", request_id.parse().expect("valid header value"));
+    Ok(response)
+}
+
+fn chat_response(response: ChatResponse) -> Response<Body> {
+    if let Some(error) = response.error {
+        return error_response(&error);
+    }
+
+    let delay = Duration::from_millis(response.delay_ms);
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Result<Frame<Bytes>, Infallible>>(1);
+    tokio::spawn(async move {
+        for event in response.events {
+            for (event_type, payload) in event {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                if tx
+                    .send(Ok(Frame::data(encode_event(&event_type, &payload))))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    });
+
+    let stream = futures::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/vnd.amazon.eventstream")
+        .body(BodyExt::boxed(StreamBody::new(stream)))
+        .expect("valid response")
+}
+
+/// Encodes an event in the `vnd.amazon.eventstream` framing used by the streaming clients
+fn encode_event(event_type: &str, payload: &Value) -> Bytes {
+    let message = Message::new(serde_json::to_vec(payload).expect("value is serializable"))
+        .add_header(Header::new(":message-type", HeaderValue::String("event".into())))
+        .add_header(Header::new(
+            ":event-type",
+            HeaderValue::String(event_type.to_owned().into()),
+        ))
+        .add_header(Header::new(
+            ":content-type",
+            HeaderValue::String("application/json".into()),
+        ));
+
+    let mut buf = Vec::new();
+    write_message_to(&message, &mut buf).expect("message is valid");
+    buf.into()
+}
+
+fn json_response(value: &Value) -> Response<Body> {
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/x-amz-json-1.0")
+        .body(Full::new(Bytes::from(value.to_string())).boxed())
+        .expect("valid response")
+}
+
+fn error_response(error: &ErrorResponse) -> Response<Body> {
+    let body = json!({
+        "__type": error.error_type,
+        "message": error.message,
+    });
+    Response::builder()
+        .status(error.status)
+        .header("content-type", "application/x-amz-json-1.0")
+        .header("x-amzn-errortype", &error.error_type)
+        .body(Full::new(Bytes::from(body.to_string())).boxed())
+        .expect("valid response")
+}
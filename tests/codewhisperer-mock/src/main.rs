@@ -0,0 +1,54 @@
+use std::net::{
+    Ipv4Addr,
+    SocketAddr,
+};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Parser;
+use codewhisperer_mock::{
+    MockServer,
+    Script,
+};
+use tracing_subscriber::EnvFilter;
+
+/// Local mock of the CodeWhisperer and Q Developer APIs
+#[derive(Parser)]
+struct Cli {
+    /// Path to a JSON script of responses, every chat message gets an echo response if not set
+    #[arg(long, short)]
+    script: Option<PathBuf>,
+    /// Port to listen on, a random port is used if not set
+    #[arg(long, short, default_value_t = 0)]
+    port: u16,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
+    let cli = Cli::parse();
+    let script = match &cli.script {
+        Some(path) => {
+            let script = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&script).with_context(|| format!("parsing {}", path.display()))?
+        },
+        None => Script::default(),
+    };
+
+    let server = MockServer::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, cli.port)), script).await?;
+    let endpoint = serde_json::json!({
+        "endpoint": server.endpoint_url()?,
+        "region": "us-east-1",
+    });
+
+    println!("Listening on {}", server.local_addr()?);
+    println!("Point the CLI at the mock with:");
+    println!("  q settings api.codewhisperer.service '{endpoint}'");
+    println!("  q settings api.q.service '{endpoint}'");
+
+    server.serve().await?;
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+
+/// The scripted responses served by the mock
+///
+/// ```json
+/// {
+///   "chat": [
+///     {
+///       "match": "hello",
+///       "events": [
+///         { "messageMetadataEvent": { "conversationId": "conversation-1" } },
+///         { "assistantResponseEvent": { "content": "Hello! How can I help?" } }
+///       ]
+///     },
+///     { "error": { "status": 429, "type": "ThrottlingError", "message": "slow down" } }
+///   ],
+///   "completions": [
+///     { "match": "git ", "completions": ["git status"] }
+///   ]
+/// }
+/// ```
+///
+/// The first response whose `match` is contained in the request (the user message for chat, the
+/// left file content for completions) is used, responses without a `match` always match.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Script {
+    #[serde(default)]
+    pub chat: Vec<ChatResponse>,
+    #[serde(default)]
+    pub completions: Vec<CompletionsResponse>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatResponse {
+    #[serde(default, rename = "match")]
+    pub matches: Option<String>,
+    /// Events in the wire format, each is an object with a single key of the event type, e.g.
+    /// `{ "assistantResponseEvent": { "content": "Hi" } }`
+    #[serde(default)]
+    pub events: Vec<Map<String, Value>>,
+    /// Delay between each event to exercise the streaming code paths
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default)]
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionsResponse {
+    #[serde(default, rename = "match")]
+    pub matches: Option<String>,
+    #[serde(default)]
+    pub completions: Vec<String>,
+    #[serde(default)]
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub status: u16,
+    /// The error shape name, e.g. `ThrottlingError` or `AccessDeniedError`
+    #[serde(rename = "type")]
+    pub error_type: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl Script {
+    pub fn chat_response(&self, message: &str) -> ChatResponse {
+        find(&self.chat, |r| r.matches.as_deref(), message)
+            .cloned()
+            .unwrap_or_else(|| ChatResponse {
+                events: vec![event(
+                    "assistantResponseEvent",
+                    serde_json::json!({
+                        "content": format!("Mock response to: {message}")
+                    }),
+                )],
+                ..Default::default()
+            })
+    }
+
+    pub fn completions_response(&self, left_file_content: &str) -> CompletionsResponse {
+        find(&self.completions, |r| r.matches.as_deref(), left_file_content)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn find<'a, T>(responses: &'a [T], matches: impl Fn(&T) -> Option<&str>, text: &str) -> Option<&'a T> {
+    responses
+        .iter()
+        .find(|response| matches(response).is_none_or(|m| text.contains(m)))
+}
+
+fn event(event_type: &str, payload: Value) -> Map<String, Value> {
+    Map::from_iter([(event_type.to_owned(), payload)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_matching() {
+        let script: Script = serde_json::from_value(serde_json::json!({
+            "chat": [
+                { "match": "hello", "events": [{ "assistantResponseEvent": { "content": "Hi" } }] },
+                { "error": { "status": 429, "type": "ThrottlingError" } }
+            ],
+            "completions": [
+                { "match": "git ", "completions": ["git status"] }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(script.chat_response("well hello there").events.len(), 1);
+        assert_eq!(script.chat_response("bye").error.unwrap().status, 429);
+
+        assert_eq!(script.completions_response("git ").completions, vec!["git status"]);
+        assert!(script.completions_response("ls").completions.is_empty());
+
+        let default = Script::default().chat_response("hey");
+        assert_eq!(
+            default.events[0]["assistantResponseEvent"]["content"],
+            "Mock response to: hey"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Drives the mock with the generated SDK clients to make sure it speaks the same protocol
+
+use std::net::{
+    Ipv4Addr,
+    SocketAddr,
+};
+
+use amzn_codewhisperer_streaming_client::types::{
+    ChatMessage,
+    ChatResponseStream,
+    ChatTriggerType,
+    ConversationState,
+    UserInputMessage,
+};
+use aws_types::request_id::RequestId;
+use codewhisperer_mock::{
+    MockServer,
+    Script,
+};
+
+async fn start(script: serde_json::Value) -> String {
+    let script: Script = serde_json::from_value(script).unwrap();
+    let server = MockServer::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), script)
+        .await
+        .unwrap();
+    let url = server.endpoint_url().unwrap();
+    tokio::spawn(server.serve());
+    url
+}
+
+fn streaming_client(url: &str) -> amzn_codewhisperer_streaming_client::Client {
+    use amzn_codewhisperer_streaming_client::config::{
+        BehaviorVersion,
+        Config,
+        Token,
+    };
+
+    amzn_codewhisperer_streaming_client::Client::from_conf(
+        Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .endpoint_url(url)
+            .bearer_token_resolver(Token::new("mock-token", None))
+            .build(),
+    )
+}
+
+fn client(url: &str) -> amzn_codewhisperer_client::Client {
+    use amzn_codewhisperer_client::config::{
+        BehaviorVersion,
+        Config,
+        Token,
+    };
+
+    amzn_codewhisperer_client::Client::from_conf(
+        Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .endpoint_url(url)
+            .bearer_token_resolver(Token::new("mock-token", None))
+            .build(),
+    )
+}
+
+fn conversation_state(content: &str) -> ConversationState {
+    ConversationState::builder()
+        .current_message(ChatMessage::UserInputMessage(
+            UserInputMessage::builder().content(content).build().unwrap(),
+        ))
+        .chat_trigger_type(ChatTriggerType::Manual)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_generate_assistant_response() {
+    let url = start(serde_json::json!({
+        "chat": [{
+            "match": "hello",
+            "delayMs": 1,
+            "events": [
+                { "messageMetadataEvent": { "conversationId": "conversation-1" } },
+                { "assistantResponseEvent": { "content": "Hello!" } },
+                { "assistantResponseEvent": { "content": " How can I help?" } }
+            ]
+        }]
+    }))
+    .await;
+
+    let mut output = streaming_client(&url)
+        .generate_assistant_response()
+        .conversation_state(conversation_state("hello"))
+        .send()
+        .await
+        .unwrap();
+
+    let mut conversation_id = None;
+    let mut content = String::new();
+    while let Some(event) = output.generate_assistant_response_response.recv().await.unwrap() {
+        match event {
+            ChatResponseStream::MessageMetadataEvent(event) => conversation_id = event.conversation_id,
+            ChatResponseStream::AssistantResponseEvent(event) => content.push_str(&event.content),
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    assert_eq!(conversation_id.as_deref(), Some("conversation-1"));
+    assert_eq!(content, "Hello! How can I help?");
+}
+
+#[tokio::test]
+async fn test_generate_assistant_response_error() {
+    let url = start(serde_json::json!({
+        "chat": [{ "error": { "status": 429, "type": "ThrottlingError", "message": "slow down" } }]
+    }))
+    .await;
+
+    let err = streaming_client(&url)
+        .generate_assistant_response()
+        .conversation_state(conversation_state("hello"))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.raw_response().unwrap().status().as_u16(), 429);
+    assert!(err.into_service_error().is_throttling_error());
+}
+
+#[tokio::test]
+async fn test_generate_completions() {
+    use amzn_codewhisperer_client::types::{
+        FileContext,
+        ProgrammingLanguage,
+    };
+
+    let url = start(serde_json::json!({
+        "completions": [{ "match": "git ", "completions": ["git status"] }]
+    }))
+    .await;
+
+    let file_context = |left: &str| {
+        FileContext::builder()
+            .left_file_content(left)
+            .right_file_content("")
+            .filename("history.sh")
+            .programming_language(ProgrammingLanguage::builder().language_name("shell").build().unwrap())
+            .build()
+            .unwrap()
+    };
+
+    let client = client(&url);
+    let output = client
+        .generate_completions()
+        .file_context(file_context("git "))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.completions()[0].content(), "git status");
+    assert_eq!(output.request_id(), Some("mock-request-0"));
+
+    let output = client
+        .generate_completions()
+        .file_context(file_context("ls "))
+        .send()
+        .await
+        .unwrap();
+    assert!(output.completions().is_empty());
+}
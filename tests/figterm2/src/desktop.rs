@@ -0,0 +1,142 @@
+use std::path::Path;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+use fig_proto::local::{
+    EditBufferHook,
+    InterceptedKeyHook,
+    PostExecHook,
+    PreExecHook,
+    PromptHook,
+};
+use fig_proto::remote::clientbound;
+use fig_remote_ipc::RemoteHookHandler;
+use fig_remote_ipc::figterm::FigtermState;
+use tokio::net::UnixListener;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// A hook figterm sent to the desktop app
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hook {
+    EditBuffer(EditBufferHook),
+    Prompt(PromptHook),
+    PreExec(PreExecHook),
+    PostExec(PostExecHook),
+    InterceptedKey(InterceptedKeyHook),
+}
+
+#[derive(Debug, Clone)]
+struct RecordingHook {
+    hooks: Arc<Mutex<Vec<Hook>>>,
+}
+
+impl RecordingHook {
+    fn record(&self, hook: Hook) -> Result<Option<clientbound::response::Response>> {
+        self.hooks.lock().unwrap().push(hook);
+        Ok(None)
+    }
+}
+
+#[async_trait::async_trait]
+impl RemoteHookHandler for RecordingHook {
+    type Error = anyhow::Error;
+
+    async fn edit_buffer(
+        &mut self,
+        edit_buffer_hook: &EditBufferHook,
+        _session_id: Uuid,
+        _figterm_state: &Arc<FigtermState>,
+    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
+        self.record(Hook::EditBuffer(edit_buffer_hook.clone()))
+    }
+
+    async fn prompt(
+        &mut self,
+        prompt_hook: &PromptHook,
+        _session_id: Uuid,
+        _figterm_state: &Arc<FigtermState>,
+    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
+        self.record(Hook::Prompt(prompt_hook.clone()))
+    }
+
+    async fn pre_exec(
+        &mut self,
+        pre_exec_hook: &PreExecHook,
+        _session_id: Uuid,
+        _figterm_state: &Arc<FigtermState>,
+    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
+        self.record(Hook::PreExec(pre_exec_hook.clone()))
+    }
+
+    async fn post_exec(
+        &mut self,
+        post_exec_hook: &PostExecHook,
+        _session_id: Uuid,
+        _figterm_state: &Arc<FigtermState>,
+    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
+        self.record(Hook::PostExec(post_exec_hook.clone()))
+    }
+
+    async fn intercepted_key(
+        &mut self,
+        intercepted_key: InterceptedKeyHook,
+        _session_id: Uuid,
+    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
+        self.record(Hook::InterceptedKey(intercepted_key))
+    }
+}
+
+/// Stands in for the desktop app, serving the remote socket figterm connects to and recording every
+/// hook it sends
+pub struct FakeDesktop {
+    hooks: Arc<Mutex<Vec<Hook>>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeDesktop {
+    /// Binds `socket` before returning so figterm can connect as soon as it starts
+    pub fn start(socket: &Path) -> Result<Self> {
+        let listener = UnixListener::bind(socket).with_context(|| format!("Failed to bind {}", socket.display()))?;
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let figterm_state = Arc::new(FigtermState::new());
+
+        let hook = RecordingHook { hooks: hooks.clone() };
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(fig_remote_ipc::remote::handle_remote_ipc(
+                    stream,
+                    figterm_state.clone(),
+                    hook.clone(),
+                ));
+            }
+        });
+
+        Ok(Self { hooks, task })
+    }
+
+    /// All hooks received so far, oldest first
+    pub fn hooks(&self) -> Vec<Hook> {
+        self.hooks.lock().unwrap().clone()
+    }
+
+    /// The text of the most recent edit buffer hook
+    pub fn buffer(&self) -> Option<String> {
+        self.hooks.lock().unwrap().iter().rev().find_map(|hook| match hook {
+            Hook::EditBuffer(edit_buffer) => Some(edit_buffer.text.clone()),
+            _ => None,
+        })
+    }
+}
+
+impl Drop for FakeDesktop {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
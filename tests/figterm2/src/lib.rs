@@ -1,2 +1,342 @@
+//! A harness for running figterm headlessly in integration tests
+//!
+//! [`Figterm::spawn`] starts the figterm binary with a shell over a PTY, the output is parsed into
+//! a [`Screen`] and the hooks figterm sends are recorded by a [`FakeDesktop`], tests drive the
+//! session with keystrokes and wait on the results.
+
+mod desktop;
+mod screen;
 mod shell;
-pub use shell::Shell;
+
+use std::io::{
+    Read,
+    Write,
+};
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use alacritty_terminal::term::{
+    CommandInfo,
+    ShellState,
+};
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+pub use desktop::{
+    FakeDesktop,
+    Hook,
+};
+use fig_util::RUNTIME_DIR_NAME;
+pub use fig_util::Shell;
+use portable_pty::{
+    Child,
+    CommandBuilder,
+    MasterPty,
+    PtySize,
+    native_pty_system,
+};
+pub use screen::{
+    Screen,
+    ShellEvent,
+};
+use tempfile::TempDir;
+
+/// Overrides where the figterm binary is found, it defaults to the target directory the tests
+/// were built in
+pub const FIGTERM_PATH_ENV: &str = "FIGTERM_TEST_BINARY";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const KEYSTROKE_DELAY: Duration = Duration::from_millis(10);
+
+const COLUMNS: u16 = 80;
+const ROWS: u16 = 24;
+
+/// A figterm session running a shell
+pub struct Figterm {
+    shell: Shell,
+    desktop: FakeDesktop,
+    screen: Arc<Mutex<Screen>>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    timeout: Duration,
+    _home: TempDir,
+}
+
+impl Figterm {
+    /// If `shell` is installed and figterm has been built, tests should be skipped otherwise
+    pub fn available(shell: Shell) -> bool {
+        shell::executable(shell).is_some() && figterm_path().is_ok()
+    }
+
+    /// Starts figterm running `shell` in a temporary home directory, returns once the first prompt
+    /// has been shown
+    pub async fn spawn(shell: Shell) -> Result<Figterm> {
+        Self::spawn_with_settings(shell, serde_json::Map::new()).await
+    }
+
+    /// Like [`Figterm::spawn`] with `settings` written to the settings file
+    pub async fn spawn_with_settings(
+        shell: Shell,
+        settings: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Figterm> {
+        let figterm_path = figterm_path()?;
+        let shell_path = shell::executable(shell).with_context(|| format!("{shell} is not installed"))?;
+
+        let home = TempDir::new()?;
+        let data_dir = if cfg!(target_os = "macos") {
+            home.path().join("Library").join("Application Support")
+        } else {
+            home.path().join(".local").join("share")
+        }
+        .join("amazon-q");
+        std::fs::create_dir_all(&data_dir)?;
+        std::fs::write(data_dir.join("settings.json"), serde_json::to_vec_pretty(&settings)?)?;
+
+        let runtime_dir = home.path().join("run");
+        let sockets_dir = runtime_dir.join(RUNTIME_DIR_NAME);
+        std::fs::create_dir_all(&sockets_dir)?;
+        std::fs::set_permissions(&runtime_dir, std::fs::Permissions::from_mode(0o700))?;
+        std::fs::set_permissions(&sockets_dir, std::fs::Permissions::from_mode(0o700))?;
+
+        let desktop = FakeDesktop::start(&sockets_dir.join("remote.sock"))?;
+        let config = shell::configure(shell, shell_path, home.path())?;
+
+        let session_id = uuid::Uuid::new_v4().simple().to_string();
+
+        let mut cmd = CommandBuilder::new(figterm_path);
+        cmd.arg("--");
+        cmd.arg(&config.path);
+        cmd.args(&config.args);
+        cmd.cwd(home.path());
+
+        // Only pass through what the shell needs to run, everything else comes from the temporary home
+        cmd.env_clear();
+        for key in ["PATH", "USER", "LANG"] {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+        cmd.env("HOME", home.path());
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("TMPDIR", &runtime_dir);
+        cmd.env("XDG_RUNTIME_DIR", &runtime_dir);
+        cmd.env("MOCK_QTERM_SESSION_ID", &session_id);
+        for (key, value) in &config.env {
+            cmd.env(key, value);
+        }
+
+        let pty_pair = native_pty_system().openpty(PtySize {
+            cols: COLUMNS,
+            rows: ROWS,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let child = pty_pair.slave.spawn_command(cmd).context("Failed to spawn figterm")?;
+        drop(pty_pair.slave);
+
+        let writer = pty_pair.master.take_writer()?;
+        let mut reader = pty_pair.master.try_clone_reader()?;
+
+        let screen = Arc::new(Mutex::new(Screen::new(COLUMNS.into(), ROWS.into(), session_id)));
+        std::thread::spawn({
+            let screen = screen.clone();
+            move || {
+                let mut buf = [0; 4096];
+                while let Ok(size @ 1..) = reader.read(&mut buf) {
+                    screen.lock().unwrap().advance(&buf[..size]);
+                }
+            }
+        });
+
+        let mut figterm = Figterm {
+            shell,
+            desktop,
+            screen,
+            master: pty_pair.master,
+            writer,
+            child,
+            timeout: DEFAULT_TIMEOUT,
+            _home: home,
+        };
+        figterm.wait_for_prompt().await?;
+
+        Ok(figterm)
+    }
+
+    pub fn shell(&self) -> Shell {
+        self.shell
+    }
+
+    /// How long the `wait_for_*` functions wait before failing
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn desktop(&self) -> &FakeDesktop {
+        &self.desktop
+    }
+
+    /// Writes `data` to figterm as if it was pasted
+    pub fn write(&mut self, data: &str) -> Result<()> {
+        self.writer.write_all(data.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes `text` one character at a time as if it was typed
+    pub async fn typed(&mut self, text: &str) -> Result<()> {
+        for c in text.chars() {
+            self.write(c.encode_utf8(&mut [0; 4]))?;
+            tokio::time::sleep(KEYSTROKE_DELAY).await;
+        }
+        Ok(())
+    }
+
+    /// Types `command`, presses enter and waits for the next prompt, returns the finished command
+    pub async fn run(&mut self, command: &str) -> Result<CommandInfo> {
+        self.typed(command).await?;
+
+        let seen = self.with_screen(|screen| screen.events().len());
+        self.write("\r")?;
+        // The command info is sent along with the prompt that follows the command
+        self.wait_for(&format!("`{command}` to finish"), |figterm| {
+            figterm.with_screen(|screen| {
+                screen.events().into_iter().skip(seen).find_map(|event| match event {
+                    ShellEvent::CommandFinished(command_info) => Some(command_info),
+                    _ => None,
+                })
+            })
+        })
+        .await
+    }
+
+    pub fn resize(&mut self, columns: u16, rows: u16) -> Result<()> {
+        self.master.resize(PtySize {
+            cols: columns,
+            rows,
+            ..Default::default()
+        })?;
+        self.with_screen(|screen| screen.resize(columns.into(), rows.into()));
+        Ok(())
+    }
+
+    /// The visible text of the terminal
+    pub fn text(&self) -> String {
+        self.with_screen(|screen| screen.text())
+    }
+
+    pub fn shell_state(&self) -> ShellState {
+        self.with_screen(|screen| screen.shell_state().clone())
+    }
+
+    /// Waits until the shell is showing a prompt and waiting for input
+    pub async fn wait_for_prompt(&mut self) -> Result<()> {
+        self.wait_for("a prompt", |figterm| {
+            figterm
+                .with_screen(|screen| {
+                    !screen.shell_state().preexec
+                        && screen.events().iter().any(|event| matches!(event, ShellEvent::Prompt))
+                })
+                .then_some(())
+        })
+        .await
+    }
+
+    pub async fn wait_for_state(&mut self, description: &str, f: impl Fn(&ShellState) -> bool) -> Result<()> {
+        self.wait_for(description, |figterm| {
+            figterm.with_screen(|screen| f(screen.shell_state())).then_some(())
+        })
+        .await
+    }
+
+    pub async fn wait_for_text(&mut self, text: &str) -> Result<()> {
+        self.wait_for(&format!("{text:?} on screen"), |figterm| {
+            figterm.text().contains(text).then_some(())
+        })
+        .await
+    }
+
+    /// Waits for figterm to send the desktop an edit buffer with `text`
+    pub async fn wait_for_buffer(&mut self, text: &str) -> Result<()> {
+        self.wait_for(&format!("edit buffer {text:?}"), |figterm| {
+            (figterm.desktop.buffer().as_deref() == Some(text)).then_some(())
+        })
+        .await
+    }
+
+    /// Waits for a hook sent to the desktop after the first `skip` hooks
+    pub async fn wait_for_hook<T>(&mut self, skip: usize, f: impl Fn(&Hook) -> Option<T>) -> Result<T> {
+        self.wait_for("a hook", |figterm| {
+            figterm.desktop.hooks().iter().skip(skip).find_map(&f)
+        })
+        .await
+    }
+
+    /// Polls `f` until it returns a value, the error includes the screen to make failures easier to
+    /// debug
+    async fn wait_for<T>(&mut self, description: &str, f: impl Fn(&Self) -> Option<T>) -> Result<T> {
+        let start = Instant::now();
+        loop {
+            if let Some(value) = f(self) {
+                return Ok(value);
+            }
+            if let Ok(Some(status)) = self.child.try_wait() {
+                bail!(
+                    "figterm exited with {status:?} while waiting for {description}, screen:\n{}",
+                    self.text()
+                );
+            }
+            if start.elapsed() > self.timeout {
+                bail!(
+                    "Timed out waiting for {description} in {}, screen:\n{}",
+                    self.shell,
+                    self.text()
+                );
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn with_screen<T>(&self, f: impl FnOnce(&mut Screen) -> T) -> T {
+        f(&mut self.screen.lock().unwrap())
+    }
+}
+
+impl Drop for Figterm {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn figterm_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os(FIGTERM_PATH_ENV) {
+        return Ok(path.into());
+    }
+
+    // Test binaries are built in `target/<profile>/deps`
+    let path = std::env::current_exe()?
+        .parent()
+        .and_then(|deps| deps.parent())
+        .map(|profile| profile.join("figterm"))
+        .context("Failed to find the target directory")?;
+    if !path.exists() {
+        bail!(
+            "{} does not exist, build it with `cargo build -p figterm` or set {FIGTERM_PATH_ENV}",
+            path.display()
+        );
+    }
+    Ok(path)
+}
@@ -0,0 +1,111 @@
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use alacritty_terminal::Term;
+use alacritty_terminal::ansi::Processor;
+use alacritty_terminal::event::{
+    Event,
+    EventListener,
+};
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{
+    Column,
+    Line,
+    Point,
+};
+use alacritty_terminal::term::{
+    CommandInfo,
+    ShellState,
+    SizeInfo,
+};
+
+/// A shell event parsed from figterm's output
+#[derive(Debug, Clone)]
+pub enum ShellEvent {
+    Prompt,
+    PreExec,
+    CommandFinished(CommandInfo),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Listener {
+    events: Arc<Mutex<Vec<ShellEvent>>>,
+}
+
+impl EventListener for Listener {
+    fn send_event(&self, event: Event<'_>, _shell_state: &ShellState) {
+        let event = match event {
+            Event::Prompt => ShellEvent::Prompt,
+            Event::PreExec => ShellEvent::PreExec,
+            Event::CommandInfo(command_info) => ShellEvent::CommandFinished(command_info.clone()),
+            Event::ShellChanged => return,
+        };
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// What the user would see in the terminal figterm is running in
+///
+/// Figterm passes the shell's output through unchanged, so running it through the same parser
+/// figterm uses gives us both the rendered grid and the [`ShellState`] figterm sees.
+pub struct Screen {
+    term: Term<Listener>,
+    processor: Processor,
+    events: Arc<Mutex<Vec<ShellEvent>>>,
+}
+
+impl Screen {
+    pub fn new(columns: usize, rows: usize, session_id: String) -> Self {
+        let listener = Listener::default();
+        let events = listener.events.clone();
+        Self {
+            term: Term::new(SizeInfo::new(rows, columns), listener, 1000, session_id),
+            processor: Processor::new(),
+            events,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.processor.advance(&mut self.term, *byte);
+        }
+    }
+
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.term.resize(SizeInfo::new(rows, columns));
+    }
+
+    pub fn shell_state(&self) -> &ShellState {
+        self.term.shell_state()
+    }
+
+    /// All shell events seen so far, oldest first
+    pub fn events(&self) -> Vec<ShellEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// The visible rows of the terminal with trailing whitespace removed
+    pub fn rows(&self) -> Vec<String> {
+        let last_column = Column(self.term.columns() - 1);
+        (0..self.term.screen_lines())
+            .map(|line| {
+                let line = Line(line as i32);
+                let row = self
+                    .term
+                    .bounds_to_string(Point::new(line, Column(0)), Point::new(line, last_column));
+                row.trim_end().to_owned()
+            })
+            .collect()
+    }
+
+    /// The visible text of the terminal without trailing empty rows
+    pub fn text(&self) -> String {
+        let mut rows = self.rows();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows.join("\n")
+    }
+}
@@ -1,238 +1,118 @@
-use std::io::{
-    Write,
-    stdout,
-};
 use std::os::unix::fs::PermissionsExt as _;
-use std::sync::Arc;
-use std::time::Duration;
+use std::path::{
+    Path,
+    PathBuf,
+};
 
 use anyhow::{
     Context,
     Result,
 };
-use fig_proto::local::{
-    EditBufferHook,
-    InterceptedKeyHook,
-    PostExecHook,
-    PreExecHook,
-    PromptHook,
-    ShellContext,
-};
-use fig_proto::remote::clientbound;
-use fig_remote_ipc::RemoteHookHandler;
-use fig_remote_ipc::figterm::FigtermState;
-use fig_util::RUNTIME_DIR_NAME;
-use portable_pty::{
-    Child,
-    CommandBuilder,
-    PtyPair,
-    PtySize,
-    native_pty_system,
+use fig_integrations::shell::{
+    ShellExt,
+    When,
 };
-use tempfile::TempDir;
-use tokio::net::UnixListener;
-use tokio::sync::Mutex;
-use uuid::Uuid;
-
-#[derive(Debug, Clone)]
-struct RemoteHook {
-    buffer: Arc<Mutex<Option<String>>>,
-    shell_context: Arc<Mutex<Option<ShellContext>>>,
+use fig_util::Shell;
+
+/// How to launch a shell so it loads our post integration with a plain `$ ` prompt and nothing
+/// from the user's own dotfiles
+#[derive(Debug)]
+pub struct ShellConfig {
+    pub path: PathBuf,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
 }
 
-#[async_trait::async_trait]
-impl RemoteHookHandler for RemoteHook {
-    type Error = anyhow::Error;
-
-    async fn edit_buffer(
-        &mut self,
-        edit_buffer_hook: &EditBufferHook,
-        _session_id: Uuid,
-        _figterm_state: &Arc<FigtermState>,
-    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
-        *self.buffer.lock().await = Some(edit_buffer_hook.text.clone());
-        Ok(None)
-    }
-
-    async fn prompt(
-        &mut self,
-        _prompt_hook: &PromptHook,
-        _session_id: Uuid,
-        _figterm_state: &Arc<FigtermState>,
-    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
-        Ok(None)
-    }
-
-    async fn pre_exec(
-        &mut self,
-        _pre_exec_hook: &PreExecHook,
-        _session_id: Uuid,
-        _figterm_state: &Arc<FigtermState>,
-    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
-        Ok(None)
-    }
-
-    async fn post_exec(
-        &mut self,
-        _post_exec_hook: &PostExecHook,
-        _session_id: Uuid,
-        _figterm_state: &Arc<FigtermState>,
-    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
-        Ok(None)
-    }
-
-    async fn intercepted_key(
-        &mut self,
-        _intercepted_key: InterceptedKeyHook,
-        _session_id: Uuid,
-    ) -> Result<Option<clientbound::response::Response>, Self::Error> {
-        Ok(None)
-    }
-
-    async fn shell_context(&mut self, context: &ShellContext, _session_id: Uuid) {
-        *self.shell_context.lock().await = Some(context.clone());
-    }
+/// The path of the shell if it is installed
+pub fn executable(shell: Shell) -> Option<PathBuf> {
+    which::which(shell.as_str()).ok()
 }
 
-pub struct Shell {
-    // pub remote_socket: UnixListener,
-    pub desktop_socket: UnixListener,
-
-    pub pty_pair: PtyPair,
-    pub writer: Box<dyn std::io::Write + Send>,
-    pub child: Box<dyn Child + Send + Sync>,
-    pub tempdir: TempDir,
-
-    pub buffer: Arc<Mutex<Option<String>>>,
-    pub shell_context: Arc<Mutex<Option<ShellContext>>>,
-}
-
-impl Shell {
-    pub async fn init(shell: &str) -> Result<Shell> {
-        let tempdir = TempDir::new()?;
-        println!("{tempdir:?}");
-
-        let figterm_state = Arc::new(FigtermState::new());
-
-        let runtime_dir = tempdir.path().join(RUNTIME_DIR_NAME);
-        tokio::fs::create_dir_all(&runtime_dir).await?;
-        tokio::fs::set_permissions(&runtime_dir, std::fs::Permissions::from_mode(0o700)).await?;
-
-        println!("{runtime_dir:?} {}", runtime_dir.to_str().unwrap().len());
-
-        let path = runtime_dir.join("remote.sock");
-        let buffer = Arc::new(Mutex::new(None));
-        let shell_context = Arc::new(Mutex::new(None));
-        tokio::spawn({
-            let buffer = buffer.clone();
-            let shell_context = shell_context.clone();
-            async move {
-                fig_remote_ipc::remote::start_remote_ipc(path, figterm_state.clone(), RemoteHook {
-                    buffer,
-                    shell_context,
-                })
-                .await
-                .unwrap();
-            }
-        });
-
-        let desktop_socket =
-            UnixListener::bind(runtime_dir.join("desktop.sock")).context("Failed to make desktop.socket")?;
-
-        let pty_system = native_pty_system();
-
-        // Create a new pty
-        let pty_pair = pty_system.openpty(PtySize {
-            cols: 80,
-            rows: 24,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
-
-        // Spawn a shell into the pty
-        let mut cmd = CommandBuilder::new(shell);
-
-        let session_id = "1234";
-        cmd.env("Q_NEW_SESSION", "1");
-        cmd.env("MOCK_QTERM_SESSION_ID", session_id);
-        cmd.env("TMPDIR", tempdir.path());
-        cmd.env("XDG_RUNTIME_DIR", tempdir.path());
-
-        let child = pty_pair.slave.spawn_command(cmd)?;
-        let writer = pty_pair.master.take_writer()?;
-
-        let mut res = pty_pair.master.try_clone_reader().unwrap();
-        std::thread::spawn(move || {
-            let mut buf = [0; 1024];
-            while let Ok(a) = res.read(&mut buf) {
-                if a == 0 {
-                    break;
-                }
-                stdout().write_all(&buf[..a]).unwrap();
-                stdout().flush().unwrap();
-            }
-        });
-
-        // give time for shell to spawn
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
-        Ok(Shell {
-            desktop_socket,
-
-            pty_pair,
-            writer,
-            child,
-            tempdir,
-
-            buffer,
-            shell_context,
-        })
-    }
-
-    pub fn write(&mut self, data: &str) -> Result<()> {
-        self.writer.write_all(data.as_bytes())?;
-        self.writer.flush()?;
-
-        Ok(())
-    }
-
-    pub async fn typed(&mut self, text: &str) -> Result<()> {
-        println!("tying: {text}");
-        for c in text.chars() {
-            self.writer.write_all(c.to_string().as_bytes())?;
-            self.writer.flush()?;
-
-            tokio::time::sleep(Duration::from_millis(10)).await;
-        }
-
-        tokio::time::sleep(Duration::from_millis(100)).await;
-
-        Ok(())
-    }
-
-    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
-        self.pty_pair.master.resize(PtySize {
-            cols,
-            rows,
-            ..Default::default()
-        })?;
-        Ok(())
-    }
-
-    pub async fn reset(&mut self) -> Result<()> {
-        self.typed("\n").await?;
-        self.resize(80, 24)?;
-        Ok(())
-    }
-
-    pub async fn buffer(&mut self) -> Option<String> {
-        self.buffer.lock().await.clone()
-    }
+/// Writes the dotfiles for `shell` into `home`
+pub fn configure(shell: Shell, path: PathBuf, home: &Path) -> Result<ShellConfig> {
+    write_q_stub(&path, home)?;
+
+    let post = shell.get_fig_integration_source(&When::Post);
+    let home_str = home.to_string_lossy().into_owned();
+    let (args, env) = match shell {
+        Shell::Bash => {
+            let rcfile = home.join(".bashrc");
+            // The post integration queues the bash-preexec install itself, without delaying the
+            // install bash-preexec queues it a second time when sourced
+            std::fs::write(
+                &rcfile,
+                format!("PS1='$ '\nunset PROMPT_COMMAND\n__bp_delay_install=1\n{post}\n"),
+            )?;
+            (
+                vec![
+                    "--noprofile".into(),
+                    "--rcfile".into(),
+                    rcfile.to_string_lossy().into_owned(),
+                    "-i".into(),
+                ],
+                vec![],
+            )
+        },
+        Shell::Zsh => {
+            std::fs::write(
+                home.join(".zshrc"),
+                format!("PROMPT='$ '\nRPROMPT=''\nunsetopt PROMPT_SP\n{post}\n"),
+            )?;
+            (vec!["-i".into()], vec![("ZDOTDIR".into(), home_str)])
+        },
+        Shell::Fish => {
+            let config_dir = home.join(".config").join("fish");
+            std::fs::create_dir_all(&config_dir)?;
+            std::fs::write(
+                config_dir.join("config.fish"),
+                format!(
+                    "set -g fish_greeting\nfunction fish_prompt\n    printf '$ '\nend\nfunction fish_right_prompt\nend\n{post}\n"
+                ),
+            )?;
+            (vec!["-i".into()], vec![(
+                "XDG_CONFIG_HOME".into(),
+                home.join(".config").to_string_lossy().into_owned(),
+            )])
+        },
+        Shell::Nu => {
+            let env_config = home.join("env.nu");
+            let config = home.join("config.nu");
+            std::fs::write(
+                &env_config,
+                "let-env PROMPT_COMMAND = { \"\" }\nlet-env PROMPT_COMMAND_RIGHT = { \"\" }\nlet-env PROMPT_INDICATOR = { \"$ \" }\n",
+            )?;
+            std::fs::write(&config, format!("let-env config = {{ show_banner: false }}\n{post}\n"))?;
+            (
+                vec![
+                    "--env-config".into(),
+                    env_config.to_string_lossy().into_owned(),
+                    "--config".into(),
+                    config.to_string_lossy().into_owned(),
+                ],
+                vec![],
+            )
+        },
+    };
+
+    Ok(ShellConfig { path, args, env })
 }
 
-impl Drop for Shell {
-    fn drop(&mut self) {
-        self.child.kill().unwrap();
-        self.child.wait().unwrap();
-    }
+/// The integrations call out to `q`, a stub is put on the `PATH` so they work the same whether or
+/// not `q` is installed on the machine running the tests
+fn write_q_stub(shell_path: &Path, home: &Path) -> Result<()> {
+    let bin = home.join(".local").join("bin");
+    std::fs::create_dir_all(&bin)?;
+
+    let stub = bin.join("q");
+    std::fs::write(
+        &stub,
+        format!(
+            "#!/bin/sh\nif [ \"$1\" = \"_\" ] && [ \"$2\" = \"get-shell\" ]; then\n  echo \"{}\"\nfi\nexit 0\n",
+            shell_path.display()
+        ),
+    )
+    .context("Failed to write q stub")?;
+    std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755))?;
+
+    Ok(())
 }
@@ -0,0 +1,114 @@
+use figterm2::{
+    Figterm,
+    Hook,
+    Shell,
+};
+
+async fn spawn(shell: Shell) -> Option<Figterm> {
+    spawn_with_settings(shell, serde_json::json!({})).await
+}
+
+async fn spawn_with_settings(shell: Shell, settings: serde_json::Value) -> Option<Figterm> {
+    if !Figterm::available(shell) {
+        eprintln!("Skipping, {shell} is not installed or figterm has not been built");
+        return None;
+    }
+    let settings = settings.as_object().cloned().unwrap_or_default();
+    Some(Figterm::spawn_with_settings(shell, settings).await.unwrap())
+}
+
+async fn edit_buffer(shell: Shell) {
+    let Some(mut figterm) = spawn(shell).await else {
+        return;
+    };
+
+    figterm.typed("echo hello world").await.unwrap();
+    figterm.wait_for_buffer("echo hello world").await.unwrap();
+
+    figterm.typed("\x08\x08\x08\x08\x08").await.unwrap();
+    figterm.wait_for_buffer("echo hello ").await.unwrap();
+
+    figterm.resize(40, 30).unwrap();
+    figterm.typed("111").await.unwrap();
+    figterm.wait_for_buffer("echo hello 111").await.unwrap();
+}
+
+async fn run_command(shell: Shell) {
+    let Some(mut figterm) = spawn(shell).await else {
+        return;
+    };
+
+    let hooks = figterm.desktop().hooks().len();
+    let command = figterm.run("echo hello world").await.unwrap();
+    assert_eq!(command.command.as_deref(), Some("echo hello world"));
+    assert_eq!(command.exit_code, Some(0));
+    figterm.wait_for_text("\nhello world\n").await.unwrap();
+
+    let exit_code = figterm
+        .wait_for_hook(hooks, |hook| match hook {
+            Hook::PostExec(post_exec) => post_exec.exit_code,
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(exit_code, 0);
+
+    let command = figterm.run("false").await.unwrap();
+    assert_eq!(command.exit_code, Some(1));
+}
+
+async fn working_directory(shell: Shell) {
+    let Some(mut figterm) = spawn(shell).await else {
+        return;
+    };
+
+    figterm.run("cd /").await.unwrap();
+    figterm
+        .wait_for_state("the working directory to change", |state| {
+            state.get_context().current_working_directory.as_deref() == Some(std::path::Path::new("/"))
+        })
+        .await
+        .unwrap();
+}
+
+async fn keybindings(shell: Shell) {
+    let settings = serde_json::json!({ "qterm.keybindings.alt+s": "insert:echo from keybinding\n" });
+    let Some(mut figterm) = spawn_with_settings(shell, settings).await else {
+        return;
+    };
+
+    figterm.write("\x1bs").unwrap();
+    figterm.wait_for_text("\nfrom keybinding").await.unwrap();
+}
+
+macro_rules! shell_tests {
+    ($($name:ident: $shell:expr),*) => {
+        $(
+            mod $name {
+                use super::*;
+
+                #[tokio::test]
+                async fn edit_buffer() {
+                    super::edit_buffer($shell).await;
+                }
+
+                #[tokio::test]
+                async fn run_command() {
+                    super::run_command($shell).await;
+                }
+
+                #[tokio::test]
+                async fn working_directory() {
+                    super::working_directory($shell).await;
+                }
+
+                #[tokio::test]
+                async fn keybindings() {
+                    super::keybindings($shell).await;
+                }
+            }
+        )*
+    };
+}
+
+shell_tests!(bash: Shell::Bash, zsh: Shell::Zsh, fish: Shell::Fish, nu: Shell::Nu);